@@ -0,0 +1,63 @@
+use clap::ArgMatches;
+use pulser::simple::OperationResult;
+use serde::Serialize;
+
+/// The arguments which identify the object a command acts on, in order of preference
+const TARGET_ARGS: &[&str] = &[
+    "NAME|INDEX",
+    "FROM_NAME|FROM_INDEX",
+    "CARD_NAME|CARD_INDEX",
+    "sink",
+];
+
+/// What was attempted, which is included in the output of operations so that logs from many
+/// invocations (such as scripts running them in parallel) can be attributed
+#[derive(Debug, Default, Serialize)]
+pub struct Attempt {
+    /// The subcommand, such as "set-sink-volume"
+    pub command: Option<String>,
+    /// The object the subcommand acted on, as it was passed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+}
+
+impl Attempt {
+    pub fn new(matches: &ArgMatches) -> Attempt {
+        let (command, matches) = match matches.subcommand() {
+            Some(subcommand) => subcommand,
+            None => return Attempt::default(),
+        };
+
+        Attempt {
+            command: Some(command.to_string()),
+            target: TARGET_ARGS
+                .iter()
+                .find_map(|id| matches.try_get_one::<String>(id).ok().flatten())
+                .cloned(),
+        }
+    }
+
+    /// The same attempt, but acting on a different object; used when a command acts on many
+    pub fn with_target(&self, target: impl Into<String>) -> Attempt {
+        Attempt {
+            command: self.command.clone(),
+            target: Some(target.into()),
+        }
+    }
+
+    /// Adds this attempt to the result of an operation
+    pub fn result(&self, result: OperationResult) -> Attempted<'_> {
+        Attempted {
+            result,
+            attempt: self,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Attempted<'a> {
+    #[serde(flatten)]
+    result: OperationResult,
+    #[serde(flatten)]
+    attempt: &'a Attempt,
+}
@@ -0,0 +1,42 @@
+use std::error::Error;
+
+use pulser::api::{CardProfileRule, PAEvent, PAFacility, PAIdent, PAMask};
+use pulser::simple::{OperationResult, PulseAudio};
+use serde::Serialize;
+
+use crate::json_print;
+
+/// Printed each time a rule is applied to a card
+#[derive(Debug, Serialize)]
+struct Applied {
+    card: PAIdent,
+    #[serde(flatten)]
+    result: OperationResult,
+}
+
+/// Applies the rules to all current cards, and then to each new card as it appears. Runs until it's
+/// interrupted.
+pub fn card_profiles(pa: &PulseAudio, rules: &[CardProfileRule]) -> Result<(), Box<dyn Error>> {
+    // subscribe first, so no cards are missed between listing them and subscribing
+    let events = pa.events(PAMask::CARD)?;
+
+    for card in pa.get_card_info_list()? {
+        apply(pa, rules, PAIdent::Index(card.index))?;
+    }
+
+    for ev in events {
+        if let PAEvent::SubscriptionNew(PAFacility::Card, id) = ev {
+            apply(pa, rules, id)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn apply(pa: &PulseAudio, rules: &[CardProfileRule], card: PAIdent) -> Result<(), Box<dyn Error>> {
+    if let Some(result) = pa.apply_card_profile_rules(rules, card.clone())? {
+        json_print!(Applied { card, result });
+    }
+
+    Ok(())
+}
@@ -1,28 +1,126 @@
+use std::path::PathBuf;
 use std::str::FromStr;
+use std::time::Duration;
 
 use clap::{Args, Parser, Subcommand, ValueEnum};
-use pulser::api::{PAIdent, PAVol, VolumeSpec};
+use pulser::api::{
+    BluetoothProfile,
+    CardProfileRule,
+    EventFilter,
+    EventMatch,
+    FadeCurve,
+    PABufferAttr,
+    PAEncoding,
+    PAEvent,
+    PAFacility,
+    PAIdent,
+    PAMask,
+    PAVol,
+    RawFormat,
+    RawSpec,
+    RawStreamOptions,
+    SinkPreference,
+    StreamBuffer,
+    VolumeSpec,
+};
 use serde::Serialize;
 
+use crate::rotate::Rotation;
+
 #[derive(Debug, Parser)]
+#[command(after_help = "Exit codes:
+  0  success
+  1  failure (or a conflict)
+  3  the object doesn't exist
+  4  the server denied access
+  5  couldn't connect to the server, or the connection was lost
+  6  the server didn't respond in time")]
 pub struct Cli {
     #[command(subcommand)]
     pub command: Command,
+    /// Don't print the results of successful operations; check the exit code instead
+    #[clap(long, global = true)]
+    pub quiet: bool,
+    /// Print failed operations to stderr rather than stdout
+    #[clap(long, global = true)]
+    pub errors_to_stderr: bool,
+    /// Log to stderr; pass once for info, twice to also log each command sent to PulseAudio and
+    /// how long it took, or three times to also log each response
+    #[clap(short, long, global = true, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+    /// When to color human-readable output (JSON is never colored). `auto` only colors output to
+    /// a terminal, and honours `NO_COLOR`
+    #[clap(long, global = true, value_enum, default_value = "auto")]
+    pub color: ColorChoice,
+    /// The server to connect to (such as "tcp:host:4713"), otherwise the default server is used
+    #[clap(long, global = true)]
+    pub server: Option<String>,
+    /// A file containing the server's authentication cookie (a copy of its
+    /// `~/.config/pulse/cookie`), which remote servers usually require
+    #[clap(long, global = true)]
+    pub cookie: Option<PathBuf>,
+    /// After setting a volume or mute, fetch it again and include it in the result, since
+    /// PulseAudio may round or limit the volume it applies
+    #[clap(long, global = true)]
+    pub confirm: bool,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    Auto,
+    Always,
+    Never,
 }
 
 // TODO: think about a nice API for this... right now I'm just implementing things here
 // as a way to help me implement more commands in the crate's library
+// TODO: if these are ever served over a unix socket (as a daemon), use JSON-RPC with batch requests,
+// and an `rpc.discover` method listing the methods and their types (which needs a schema for the
+// `api` types first) so clients can adapt to whichever version is running. Requests should be able
+// to carry a deadline (see `PulseAudio::request_deadline`), so a wedged call can't stall the socket.
+// TODO: anything served over the network (HTTP, WebSockets, MQTT) must require a token, and should
+// have a read-only mode which only allows the getters, so a LAN client can't mute or kill streams.
 #[derive(Debug, Subcommand)]
 pub enum Command {
     /// Get server information
-    Info,
+    Info(InfoArgs),
     /// List objects from the server
     List(ListArgs),
+    /// Print a single field of an object, such as `get sink @DEFAULT_SINK@ mute`
+    Get(GetArgs),
+    /// Set a single property of an object, such as `set sink @DEFAULT_SINK@ volume 50%`
+    Set(SetArgs),
+    /// Compare two snapshots (created with `list`), or a snapshot with the current state
+    Diff(DiffArgs),
+    /// Check for common causes of "no sound", such as a missing or muted default sink
+    Doctor,
+    /// Show the streams which are playing or recording, along with their latency, until interrupted
+    Top,
+    /// Print the peak level of each stream which is playing as JSON, every 40ms, until interrupted
+    Meter(MeterArgs),
+    /// Print whether anything is recording from a microphone as JSON whenever that changes, and run
+    /// commands when recording starts and stops, until interrupted
+    MicIndicator(MicIndicatorArgs),
+    /// Print a readable summary of a sink, source or card, such as its state, volume, active port
+    /// and profile
+    Describe(DescribeArgs),
+    /// Set the description of a sink or source, which is the name shown to users (such as
+    /// "Desk Speakers"). Requires module-device-manager
+    Rename(RenameArgs),
+    /// Show the latency of every device and stream in a single report (in microseconds)
+    Latency,
+    /// Manage the volumes restored for each application's streams when they appear, such as
+    /// "Firefox = 60%". Requires module-stream-restore
+    AppVolume(AppVolumeArgs),
 
     /// Get the default sink (if any)
     GetDefaultSink,
     /// Get the default sink (if any)
     SetDefaultSink(BaseArgs),
+    /// Pick the default sink or source from a list which can be narrowed down by typing
+    Pick(PickArgs),
+    /// Switch the default sink to whichever of the two sinks isn't currently the default
+    ToggleDefaultSink(ToggleDefaultSinkArgs),
     /// Get the default source (if any)
     GetDefaultSource,
     /// Get the default source (if any)
@@ -32,8 +130,12 @@ pub enum Command {
     GetCardInfo(BaseArgs),
     /// Set the profile of a card
     SetCardProfile(SetProfileArgs),
+    /// Get the latency offset of a card port
+    GetPortLatencyOffset(CardPortArgs),
     /// Set the latency offset of a card port
     SetPortLatencyOffset(SetPortLatencyArgs),
+    /// Switch a Bluetooth card between A2DP and HFP, optionally choosing the codec
+    BtProfile(BtProfileArgs),
 
     /// Get information about a client
     GetClientInfo(BaseArgs),
@@ -41,11 +143,11 @@ pub enum Command {
     KillClient(BaseArgs),
 
     /// Get information about a module
-    GetModuleInfo(BaseArgs),
+    GetModuleInfo(GetModuleInfoArgs),
     /// Load a new module
     LoadModule(LoadModuleArgs),
-    /// Unload an existing module
-    UnloadModule(BaseArgs),
+    /// Unload an existing module, or every module with a given name
+    UnloadModule(UnloadModuleArgs),
 
     /// Get information about a sink
     GetSinkInfo(BaseArgs),
@@ -61,6 +163,15 @@ pub enum Command {
     SetSinkPort(SetPortArgs),
     /// Suspend a sink
     SuspendSink(SuspendArgs),
+    /// List the encodings a sink accepts, or check whether it accepts one (such as `--encoding ac3`
+    /// for passthrough)
+    Supports(SupportsArgs),
+    /// Gradually change the volume(s) of a sink
+    FadeSinkVolume(FadeVolumeArgs),
+    /// Raise the volume of a sink, by the sink's own volume steps if it has them (otherwise 5%)
+    VolumeUp(StepVolumeArgs),
+    /// Lower the volume of a sink, by the sink's own volume steps if it has them (otherwise 5%)
+    VolumeDown(StepVolumeArgs),
 
     /// Get information about a source
     GetSourceInfo(BaseArgs),
@@ -107,8 +218,28 @@ pub enum Command {
     /// Kill/terminate a source output
     KillSourceOutput(BaseArgs),
 
+    /// Mute every sink, source and stream (or only the given kinds) at once
+    MuteAll(MuteAllArgs),
+    /// Unmute every sink, source and stream (or only the given kinds) at once
+    UnmuteAll(MuteAllArgs),
+
+    /// Play an audio file, or raw audio read from stdin (like `pacat`)
+    Play(PlayArgs),
+    /// Record audio and write it to stdout until interrupted, like `parec`
+    Record(RecordArgs),
+    /// Play an audio file (such as a notification sound) once, and wait for it to finish
+    PlayFile(PlayFileArgs),
+
     /// Subscribe to server events
     Subscribe(SubscribeArgs),
+    /// Wait, then fade out a sink over the last minute and suspend it
+    SleepTimer(SleepTimerArgs),
+    /// When the default sink disappears, switch to the most preferred available sink instead of the
+    /// one the server picks. Runs until interrupted.
+    Prefer(PreferArgs),
+    /// Set the profile of cards as they appear (and of the current cards), such as forcing
+    /// `a2dp-sink` for a Bluetooth headset. Runs until interrupted.
+    CardProfiles(CardProfilesArgs),
     // TODO: others...
 }
 
@@ -119,19 +250,243 @@ pub enum Kind {
     Clients,
     Modules,
     Samples,
+    Server,
     Sinks,
     SinkInputs,
     Sources,
     SourceOutputs,
+    /// Every kind of object
+    All,
+}
+
+impl Kind {
+    /// Expands `All` (or an empty list) into every kind, sorting and removing any duplicates
+    pub fn expand(kinds: &[Kind]) -> Vec<Kind> {
+        let mut kinds = if kinds.is_empty() || kinds.contains(&Kind::All) {
+            Kind::value_variants().to_vec()
+        } else {
+            kinds.to_vec()
+        };
+
+        kinds.retain(|k| *k != Kind::All);
+        kinds.sort();
+        kinds.dedup();
+        kinds
+    }
+
+    /// The facility of this kind of object, or `None` for `All`
+    pub fn facility(&self) -> Option<PAFacility> {
+        match self {
+            Kind::Cards => Some(PAFacility::Card),
+            Kind::Clients => Some(PAFacility::Client),
+            Kind::Modules => Some(PAFacility::Module),
+            Kind::Samples => Some(PAFacility::SampleCache),
+            Kind::Server => Some(PAFacility::Server),
+            Kind::Sinks => Some(PAFacility::Sink),
+            Kind::SinkInputs => Some(PAFacility::SinkInput),
+            Kind::Sources => Some(PAFacility::Source),
+            Kind::SourceOutputs => Some(PAFacility::SourceOutput),
+            Kind::All => None,
+        }
+    }
+}
+
+impl From<Kind> for PAMask {
+    fn from(value: Kind) -> Self {
+        value.facility().map_or(PAMask::ALL, PAMask::from)
+    }
+}
+
+/// A single kind of object
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, ValueEnum)]
+pub enum ObjectKind {
+    Card,
+    Client,
+    Module,
+    Sink,
+    SinkInput,
+    Source,
+    SourceOutput,
+}
+
+/// The kinds of objects which can be described
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum DescribeKind {
+    Sink,
+    Source,
+    Card,
+}
+
+#[derive(Debug, Args)]
+pub struct AppVolumeArgs {
+    #[command(subcommand)]
+    pub command: AppVolumeCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AppVolumeCommand {
+    /// Print the saved volumes, as JSON which `load` accepts
+    List,
+    /// Save the current volume of each application which is playing
+    Save,
+    /// Save the volumes in a file, such as the output of `list`
+    Load { path: PathBuf },
+    /// Save the volume of a single application, in the same formats as `set-sink-volume`
+    Set {
+        /// The `application.name` property of its streams, such as "Firefox"
+        application: String,
+        #[clap(value_parser = vol_from_str)]
+        volume: PAVol,
+    },
+    /// Forget the saved volumes of the given applications
+    Forget {
+        #[clap(required = true)]
+        applications: Vec<String>,
+    },
+}
+
+#[derive(Debug, Args)]
+pub struct MicIndicatorArgs {
+    /// A command to run (with `sh -c`) when the first stream starts recording
+    #[clap(long)]
+    pub exec_on_start: Option<String>,
+    /// A command to run (with `sh -c`) when the last stream stops recording
+    #[clap(long)]
+    pub exec_on_stop: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct MeterArgs {
+    /// Group the streams by their application, and show the loudest of each
+    #[clap(long)]
+    pub per_app: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct DescribeArgs {
+    #[arg(value_enum)]
+    pub kind: DescribeKind,
+    #[clap(flatten)]
+    pub base_args: BaseArgs,
+}
+
+/// The kinds of devices which can be renamed
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum RenameKind {
+    Sink,
+    Source,
+}
+
+#[derive(Debug, Args)]
+pub struct RenameArgs {
+    #[arg(value_enum)]
+    pub kind: RenameKind,
+    #[clap(flatten)]
+    pub base_args: BaseArgs,
+    /// The new description
+    pub description: String,
+}
+
+#[derive(Debug, Args)]
+pub struct InfoArgs {
+    /// Print the protocol versions of the library and the server (and other details about the
+    /// connection) instead
+    #[clap(long)]
+    pub protocol: bool,
+    /// Print what the server supports (such as the message API, or which extensions are loaded)
+    /// instead
+    #[clap(long, conflicts_with = "protocol")]
+    pub capabilities: bool,
+    /// Keep running, and print the server's info again each time it changes (such as when the
+    /// default sink changes)
+    #[clap(long, conflicts_with_all = ["protocol", "capabilities"])]
+    pub watch: bool,
 }
 
 #[derive(Debug, Args)]
 pub struct ListArgs {
     // TODO: return CLI error if there are duplicates, currently not possible with clap
     // see: https://github.com/clap-rs/clap/discussions/4863
-    /// Which objects you want to list. If you pass none, all objects will be listed.
+    /// Which objects you want to list. Pass `all` (or nothing) to list all objects.
     #[arg(value_enum)]
     pub kinds: Vec<Kind>,
+    /// How to sort the objects of each kind
+    #[clap(long, value_enum, default_value = "index")]
+    pub sort: SortBy,
+    /// Hide corked (paused) sink inputs and source outputs
+    #[clap(long)]
+    pub only_active: bool,
+    /// Only list objects whose name contains this (case-insensitively), such as
+    /// `list modules --name loopback`
+    #[clap(long)]
+    pub name: Option<String>,
+}
+
+/// How to sort lists of objects; ties are broken by index
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum SortBy {
+    Index,
+    /// Alphabetically, objects without a name are last
+    Name,
+    /// Highest priority first (of the active port for sinks and sources, or the active profile
+    /// for cards)
+    Priority,
+    /// Lowest (average) volume first
+    Volume,
+}
+
+#[derive(Debug, Args)]
+pub struct GetArgs {
+    #[arg(value_enum)]
+    pub kind: ObjectKind,
+    #[clap(flatten)]
+    pub base_args: BaseArgs,
+    /// A dot-separated path to the field to print, such as `active_port.name`, `ports.0.name` or
+    /// `volume.percentage`. Numeric segments index into lists, other segments select that field
+    /// from each item in a list. A field an object doesn't have is looked up in its only list, so
+    /// `volume.percentage` prints the percentage of each channel
+    pub path: String,
+}
+
+#[derive(Debug, Args)]
+pub struct DiffArgs {
+    /// A snapshot, created by saving the output of `pulser-cli list`
+    pub a: PathBuf,
+    /// Another snapshot to compare against
+    #[clap(required_unless_present = "live")]
+    pub b: Option<PathBuf>,
+    /// Compare against the current state of the server, rather than another snapshot
+    #[clap(long, conflicts_with = "b")]
+    pub live: bool,
+}
+
+/// A property which can be changed with the `set` command
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, ValueEnum)]
+pub enum Property {
+    /// The volume(s) of a sink, source, sink-input or source-output
+    Volume,
+    /// Whether a sink, source, sink-input or source-output is muted
+    Mute,
+    /// The active port of a sink or source
+    Port,
+    /// The active profile of a card
+    Profile,
+    /// Make a sink or source the default (takes no value)
+    Default,
+}
+
+#[derive(Debug, Args)]
+pub struct SetArgs {
+    #[arg(value_enum)]
+    pub kind: ObjectKind,
+    #[clap(flatten)]
+    pub base_args: BaseArgs,
+    #[arg(value_enum)]
+    pub property: Property,
+    /// The new value, parsed the same way as the `set-*` subcommands, e.g.: a list of volumes for
+    /// `volume` or "yes"/"no" for `mute`
+    #[clap(num_args = 0.., allow_hyphen_values = true)]
+    pub values: Vec<String>,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
@@ -142,7 +497,7 @@ pub enum IdentKind {
 
 #[derive(Debug, Args)]
 pub struct BaseArgs {
-    /// Either a name or an index (number)
+    /// Either a name or an index (a number, optionally prefixed with "#")
     #[clap(name = "NAME|INDEX")]
     pub id: String,
     /// How to interpret the id; if not provided, it will be inferred
@@ -150,25 +505,24 @@ pub struct BaseArgs {
     pub r#type: Option<IdentKind>,
 }
 
-impl From<&BaseArgs> for PAIdent {
-    fn from(value: &BaseArgs) -> Self {
-        parse_id(value.r#type, &value.id)
+impl BaseArgs {
+    pub fn ident(&self) -> Result<PAIdent, String> {
+        parse_id(self.r#type, &self.id)
     }
 }
 
-fn parse_id(kind: Option<IdentKind>, input: impl AsRef<str>) -> PAIdent {
+// TODO: there's no config file yet, so there are no aliases to expand here. Once there is, resolve
+// aliases at runtime (to the current name or index) in one place, so every command which takes an
+// id accepts them, along with `subscribe`'s filters and the card profile rules.
+fn parse_id(kind: Option<IdentKind>, input: impl AsRef<str>) -> Result<PAIdent, String> {
     let input = input.as_ref();
     match kind {
         Some(kind) => match kind {
-            IdentKind::Index => PAIdent::Index(input.parse::<u32>().unwrap()),
-            IdentKind::Name => PAIdent::Name(input.to_string()),
-        },
-        None => match input.parse::<u32>() {
-            // if it's a number, then treat it as an index
-            Ok(idx) => PAIdent::Index(idx),
-            // otherwise, treat it as a name
-            Err(_) => PAIdent::Name(input.to_string()),
+            IdentKind::Index => PAIdent::parse_index(input),
+            IdentKind::Name => Ok(PAIdent::Name(input.to_string())),
         },
+        // numbers (or "#<number>") are treated as indices, anything else as a name
+        None => input.parse(),
     }
 }
 
@@ -192,6 +546,55 @@ impl From<Bool> for bool {
     }
 }
 
+#[derive(Debug, Args)]
+pub struct GetModuleInfoArgs {
+    /// Either a name or an index (a number, optionally prefixed with "#")
+    #[clap(name = "NAME|INDEX", required_unless_present = "by_argument")]
+    pub id: Option<String>,
+    /// How to interpret the id; if not provided, it will be inferred
+    #[clap(long)]
+    pub r#type: Option<IdentKind>,
+    /// Instead, list every module whose argument contains this (case-insensitively), such as
+    /// `--by-argument source=alsa_input.usb`
+    #[clap(long, conflicts_with = "NAME|INDEX")]
+    pub by_argument: Option<String>,
+}
+
+impl GetModuleInfoArgs {
+    pub fn ident(&self) -> Result<PAIdent, String> {
+        match self.id {
+            Some(ref id) => parse_id(self.r#type, id),
+            None => Err("Either a module or --by-argument is required".into()),
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct UnloadModuleArgs {
+    /// Either a name or an index (a number, optionally prefixed with "#")
+    #[clap(name = "NAME|INDEX", required_unless_present = "all_matching")]
+    pub id: Option<String>,
+    /// How to interpret the id; if not provided, it will be inferred
+    #[clap(long)]
+    pub r#type: Option<IdentKind>,
+    /// Instead, unload every module with this name (such as `module-loopback`), printing the
+    /// result for each
+    #[clap(long, conflicts_with = "NAME|INDEX")]
+    pub all_matching: Option<String>,
+    /// Only unload the matching modules whose argument contains this (case-insensitively)
+    #[clap(long, requires = "all_matching")]
+    pub argument_contains: Option<String>,
+}
+
+impl UnloadModuleArgs {
+    pub fn ident(&self) -> Result<PAIdent, String> {
+        match self.id {
+            Some(ref id) => parse_id(self.r#type, id),
+            None => Err("Either a module or --all-matching is required".into()),
+        }
+    }
+}
+
 #[derive(Debug, Args)]
 pub struct SetMuteArgs {
     #[clap(flatten)]
@@ -200,6 +603,52 @@ pub struct SetMuteArgs {
     pub mute: Bool,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum MuteKind {
+    Sinks,
+    Sources,
+    /// Both sink inputs and source outputs
+    Streams,
+}
+
+impl From<MuteKind> for PAMask {
+    fn from(value: MuteKind) -> Self {
+        match value {
+            MuteKind::Sinks => PAMask::SINK,
+            MuteKind::Sources => PAMask::SOURCE,
+            MuteKind::Streams => PAMask::SINK_INPUT | PAMask::SOURCE_OUTPUT,
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct MuteAllArgs {
+    /// The kinds of objects to change, if not provided then all of them are changed
+    #[arg(value_enum)]
+    pub kinds: Vec<MuteKind>,
+    /// Leave this object alone, either a name or an index (streams can only be matched by index)
+    #[clap(long, value_name = "NAME|INDEX")]
+    pub except: Option<String>,
+}
+
+impl MuteAllArgs {
+    pub fn mask(&self) -> PAMask {
+        match self.kinds.as_slice() {
+            [] => PAMask::SINK | PAMask::SOURCE | PAMask::SINK_INPUT | PAMask::SOURCE_OUTPUT,
+            kinds => kinds
+                .iter()
+                .fold(PAMask::NULL, |mask, kind| mask | PAMask::from(*kind)),
+        }
+    }
+
+    pub fn except(&self) -> Result<Option<PAIdent>, String> {
+        self.except
+            .as_ref()
+            .map(|id| parse_id(None, id))
+            .transpose()
+    }
+}
+
 #[derive(Debug, Args)]
 pub struct SuspendArgs {
     #[clap(flatten)]
@@ -208,11 +657,53 @@ pub struct SuspendArgs {
     pub suspend: Bool,
 }
 
+#[derive(Debug, Args)]
+pub struct SupportsArgs {
+    #[clap(flatten)]
+    pub base_args: BaseArgs,
+    /// The encoding to check for, one of: any, pcm, ac3, eac3, mpeg, dts, mpeg2_aac, truehd, dtshd
+    #[clap(long)]
+    pub encoding: Option<PAEncoding>,
+}
+
 #[derive(Debug, Args)]
 pub struct SetProfileArgs {
     #[clap(flatten)]
     pub base_args: BaseArgs,
-    pub profile: String,
+    #[clap(required_unless_present = "toggle")]
+    pub profile: Option<String>,
+    /// Instead, switch to the profile after the active one in this comma-separated list, such as
+    /// `--toggle a2dp-sink,headset-head-unit`
+    #[clap(long, value_delimiter = ',', conflicts_with = "profile")]
+    pub toggle: Vec<String>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum BtProfile {
+    /// High quality playback, without a microphone
+    A2dp,
+    /// Lower quality playback, with a microphone
+    Hfp,
+}
+
+impl From<BtProfile> for BluetoothProfile {
+    fn from(value: BtProfile) -> Self {
+        match value {
+            BtProfile::A2dp => BluetoothProfile::A2dp,
+            BtProfile::Hfp => BluetoothProfile::Hfp,
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct BtProfileArgs {
+    #[clap(flatten)]
+    pub base_args: BaseArgs,
+    #[arg(value_enum)]
+    pub profile: BtProfile,
+    /// The codec to use, such as "ldac" or "aac"
+    #[clap(long)]
+    pub codec: Option<String>,
 }
 
 #[derive(Debug, Args)]
@@ -233,32 +724,458 @@ pub struct SetVolumeArgs {
     /// "<INT>" (integer), "<INT|FLOAT>%" (percentage), "<FLOAT>dB" (decibels) or "<FLOAT>L" (linear)
     #[clap(required = true, num_args = 1.., value_parser = vol_from_str)]
     pub volumes: Vec<PAVol>,
+    /// Treat the volumes as left and right, and remap them onto the channels of the object. This
+    /// keeps 5.1 and 7.1 devices balanced (the LFE channel is left untouched).
+    #[clap(long)]
+    pub stereo: bool,
+    /// For streams, treat the volume as relative to the device it's playing to (or recording from),
+    /// the same as pavucontrol displays it. Only a single volume can be provided.
+    #[clap(long, conflicts_with = "stereo")]
+    pub relative: bool,
 }
 
-impl From<&SetVolumeArgs> for PAIdent {
-    fn from(value: &SetVolumeArgs) -> Self {
-        (&value.base_args).into()
+impl SetVolumeArgs {
+    pub fn volume_spec(&self) -> Result<VolumeSpec, String> {
+        if self.relative {
+            return match self.volumes.as_slice() {
+                [vol] => Ok(VolumeSpec::RelativeToDevice(vol.percentage())),
+                _ => Err("Expected a single volume with --relative".into()),
+            };
+        }
+
+        match (self.stereo, self.volumes.as_slice()) {
+            (_, []) => unreachable!(),
+            (true, [vol]) => Ok(VolumeSpec::stereo(*vol, *vol)),
+            (true, [left, right]) => Ok(VolumeSpec::stereo(*left, *right)),
+            (true, _) => Err("Expected at most two volumes (left and right) with --stereo".into()),
+            (false, [vol]) => Ok(VolumeSpec::All(*vol)),
+            (false, _) => Ok(VolumeSpec::Channels(self.volumes.clone())),
+        }
     }
 }
 
-impl From<&SetVolumeArgs> for VolumeSpec {
-    fn from(value: &SetVolumeArgs) -> VolumeSpec {
-        match value.volumes.len() {
-            0 => unreachable!(),
-            1 => VolumeSpec::All(value.volumes[0]),
-            _ => VolumeSpec::Channels(value.volumes.clone()),
+#[derive(Debug, Args)]
+pub struct StepVolumeArgs {
+    #[clap(flatten)]
+    pub base_args: BaseArgs,
+    /// How many steps to change the volume by
+    #[clap(default_value_t = 1)]
+    pub steps: u16,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum Curve {
+    Linear,
+    Exponential,
+}
+
+impl From<Curve> for FadeCurve {
+    fn from(value: Curve) -> Self {
+        match value {
+            Curve::Linear => FadeCurve::Linear,
+            Curve::Exponential => FadeCurve::Exponential,
         }
     }
 }
 
-fn vol_from_str(s: &str) -> Result<PAVol, String> {
+#[derive(Debug, Args)]
+pub struct FadeVolumeArgs {
+    #[clap(flatten)]
+    pub volume_args: SetVolumeArgs,
+    /// How long the fade should take.
+    /// Provide the duration in one of the following formats: "<INT>ms", "<INT>s", "<INT>m" or "<INT>h"
+    #[clap(long, value_parser = duration_from_str, default_value = "1s")]
+    pub duration: Duration,
+    /// How the volume should change over the course of the fade
+    #[clap(long, value_enum, default_value = "exponential")]
+    pub curve: Curve,
+}
+
+pub fn vol_from_str(s: &str) -> Result<PAVol, String> {
     PAVol::from_str(s).map_err(|e| e.to_string())
 }
 
+#[derive(Debug, Args)]
+pub struct RawArgs {
+    /// The sample format, one of: u8, s16le, s16be, s24le, s24be, s32le, s32be, f32le, f32be
+    #[clap(long, default_value = "s16le")]
+    pub format: RawFormat,
+    /// Samples per second
+    #[clap(long, default_value_t = 44100)]
+    pub rate: u32,
+    #[clap(long, default_value_t = 2)]
+    pub channels: u8,
+    /// Size the stream's buffer for this latency (in milliseconds), like `pacat --latency-msec`
+    #[clap(long, conflicts_with_all = ["tlength", "prebuf", "fragsize"])]
+    pub latency_msec: Option<u64>,
+    /// Playback only: the target length of the buffer, in bytes
+    #[clap(long)]
+    pub tlength: Option<u32>,
+    /// Playback only: how much must be buffered before playback starts, in bytes
+    #[clap(long)]
+    pub prebuf: Option<u32>,
+    /// Recording only: how much is read at once, in bytes
+    #[clap(long)]
+    pub fragsize: Option<u32>,
+}
+
+impl RawArgs {
+    pub fn spec(&self) -> RawSpec {
+        RawSpec {
+            format: self.format,
+            rate: self.rate,
+            channels: self.channels,
+        }
+    }
+
+    pub fn options(&self, device: Option<String>) -> RawStreamOptions {
+        RawStreamOptions {
+            buffer: self.buffer(),
+            ..RawStreamOptions::new(device, self.spec())
+        }
+    }
+
+    pub fn buffer(&self) -> StreamBuffer {
+        if let Some(ms) = self.latency_msec {
+            return StreamBuffer::Latency(Duration::from_millis(ms));
+        }
+
+        match (self.tlength, self.prebuf, self.fragsize) {
+            (None, None, None) => StreamBuffer::Default,
+            (tlength, prebuf, fragsize) => StreamBuffer::Attr(PABufferAttr {
+                tlength,
+                prebuf,
+                fragsize,
+                ..Default::default()
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct PlayArgs {
+    /// An audio file to play (WAV, or FLAC and Ogg Vorbis with the "decode" feature), instead of
+    /// raw PCM audio from stdin. Its format, rate and channels are read from the file.
+    #[clap(conflicts_with_all = ["raw", "format", "rate", "channels"])]
+    pub file: Option<PathBuf>,
+    /// Read raw PCM audio from stdin
+    #[clap(long, required_unless_present = "file")]
+    pub raw: bool,
+    /// The name of the sink to play to, if not provided then the default sink is used
+    #[clap(long)]
+    pub device: Option<String>,
+    #[clap(flatten)]
+    pub raw_args: RawArgs,
+    /// The sample format of stdin, if it differs from --format (it's converted as it's played)
+    #[cfg(feature = "resample")]
+    #[clap(long)]
+    pub input_format: Option<RawFormat>,
+    /// The rate of stdin, if it differs from --rate (it's resampled as it's played)
+    #[cfg(feature = "resample")]
+    #[clap(long)]
+    pub input_rate: Option<u32>,
+    /// The number of channels in stdin, if it differs from --channels (they're remixed as they're
+    /// played)
+    #[cfg(feature = "resample")]
+    #[clap(long)]
+    pub input_channels: Option<u8>,
+}
+
+impl PlayArgs {
+    #[cfg(not(feature = "resample"))]
+    pub fn options(&self) -> RawStreamOptions {
+        self.raw_args.options(self.device.clone())
+    }
+
+    #[cfg(feature = "resample")]
+    pub fn options(&self) -> RawStreamOptions {
+        let options = self.raw_args.options(self.device.clone());
+        match (self.input_format, self.input_rate, self.input_channels) {
+            (None, None, None) => options,
+            (format, rate, channels) => RawStreamOptions {
+                input: Some(RawSpec {
+                    format: format.unwrap_or(options.spec.format),
+                    rate: rate.unwrap_or(options.spec.rate),
+                    channels: channels.unwrap_or(options.spec.channels),
+                }),
+                ..options
+            },
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct RecordArgs {
+    /// Write raw PCM audio to stdout (currently the only supported format)
+    #[clap(long, required = true)]
+    pub raw: bool,
+    /// The name of the source to record from, if not provided then the default source is used
+    #[clap(long)]
+    pub device: Option<String>,
+    /// Record what's playing on this sink (by name or index) instead, using its monitor source
+    #[clap(long, conflicts_with_all = ["device", "latency_msec", "fragsize"])]
+    pub monitor_of: Option<String>,
+    #[clap(flatten)]
+    pub raw_args: RawArgs,
+}
+
+impl RecordArgs {
+    pub fn monitor_of(&self) -> Result<Option<PAIdent>, String> {
+        self.monitor_of
+            .as_ref()
+            .map(|id| parse_id(None, id))
+            .transpose()
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct PlayFileArgs {
+    /// The audio file to play (WAV, or FLAC and Ogg Vorbis with the "decode" feature)
+    pub path: PathBuf,
+    /// The sink to play to (by name or index), if not provided then the default sink is used
+    #[clap(long)]
+    pub sink: Option<String>,
+    /// The volume to play at, otherwise the server picks one.
+    /// Provide the volume, in one of the following formats:
+    /// "<INT>" (integer), "<INT|FLOAT>%" (percentage), "<FLOAT>dB" (decibels) or "<FLOAT>L" (linear)
+    #[clap(long, value_parser = vol_from_str)]
+    pub volume: Option<PAVol>,
+}
+
+impl PlayFileArgs {
+    pub fn sink(&self) -> Result<Option<PAIdent>, String> {
+        self.sink.as_ref().map(|id| parse_id(None, id)).transpose()
+    }
+}
+
 #[derive(Debug, Args)]
 pub struct SubscribeArgs {
+    /// Which objects you want to receive events for. Pass `all` (or nothing) to receive all events.
     #[arg(value_enum)]
     pub kinds: Vec<Kind>,
+    /// Emit an event once all sinks have been idle for this long (implies subscribing to sinks).
+    /// Provide the duration in one of the following formats: "<INT>ms", "<INT>s", "<INT>m" or "<INT>h"
+    #[clap(long, value_parser = duration_from_str)]
+    pub idle_timeout: Option<Duration>,
+    /// Ping the server this often, and emit an event if it stops responding. When run as a systemd
+    /// service with `WatchdogSec=`, this defaults to half of it, and the watchdog is only petted
+    /// while the server responds.
+    /// Provide the duration in one of the following formats: "<INT>ms", "<INT>s", "<INT>m" or "<INT>h"
+    #[clap(long, value_parser = duration_from_str)]
+    pub heartbeat: Option<Duration>,
+    /// Only emit events about the objects with these indices (events which aren't about a particular
+    /// object, such as "all_sinks_idle", are always emitted)
+    #[clap(long, num_args = 1..)]
+    pub index: Vec<u32>,
+    /// Only emit these events, by name, such as "new" or "sink_became_idle"
+    #[clap(long, num_args = 1.., value_parser = event_name_from_str)]
+    pub event: Vec<String>,
+    /// Append events to this file (as newline-delimited JSON) rather than printing them
+    #[clap(long)]
+    pub output: Option<PathBuf>,
+    /// Rotate the output file once it reaches this size.
+    /// Provide the size in one of the following formats: "<INT>" (bytes), "<INT>K", "<INT>M" or "<INT>G"
+    #[clap(long, requires = "output", value_parser = size_from_str)]
+    pub rotate_size: Option<u64>,
+    /// Rotate the output file once it has been written to for this long.
+    /// Provide the duration in one of the following formats: "<INT>ms", "<INT>s", "<INT>m" or "<INT>h"
+    #[clap(long, requires = "output", value_parser = duration_from_str)]
+    pub rotate_interval: Option<Duration>,
+    /// How many rotated output files to keep
+    #[clap(long, requires = "output", default_value_t = 5)]
+    pub rotate_keep: usize,
+    /// The most events which may wait to be written, such as when the output is a slow pipe.
+    /// Without this, events wait in memory for as long as they need to
+    #[clap(long, value_parser = buffer_from_str)]
+    pub buffer: Option<usize>,
+    /// What to do with an event when `--buffer` events are already waiting
+    #[clap(long, requires = "buffer", value_enum, default_value_t = Overflow::DropOldest)]
+    pub on_overflow: Overflow,
+}
+
+/// What to do when too many events are waiting to be written
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum Overflow {
+    /// Drop the event which has been waiting the longest
+    DropOldest,
+    /// Wait for room, holding up the connection to the server until there is some
+    Block,
+    /// Exit with an error
+    Exit,
+}
+
+impl SubscribeArgs {
+    /// The limit on waiting events for `--buffer` and `--on-overflow`, if there is one
+    pub fn bounds(&self) -> Option<(usize, Overflow)> {
+        self.buffer.map(|buffer| (buffer, self.on_overflow))
+    }
+
+    pub fn rotation(&self) -> Rotation {
+        Rotation {
+            max_size: self.rotate_size,
+            max_age: self.rotate_interval,
+            keep: self.rotate_keep,
+        }
+    }
+
+    /// The filter for `--index` and `--event`, if either was given
+    pub fn filter(&self) -> Option<EventFilter> {
+        let by_index = EventMatch {
+            indices: self.index.clone(),
+            ..Default::default()
+        };
+
+        match (self.index.is_empty(), self.event.is_empty()) {
+            (true, true) => None,
+            (false, true) => Some(by_index.into()),
+            (_, false) => {
+                let names = self.event.clone();
+                Some(EventFilter::Custom(Box::new(move |ev| {
+                    by_index.matches(ev) && names.iter().any(|name| name == ev.name())
+                })))
+            }
+        }
+    }
+}
+
+fn buffer_from_str(s: &str) -> Result<usize, String> {
+    match s.trim().parse::<usize>() {
+        Ok(0) => Err("The buffer must hold at least one event".into()),
+        Ok(n) => Ok(n),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn event_name_from_str(s: &str) -> Result<String, String> {
+    match PAEvent::NAMES.contains(&s) {
+        true => Ok(s.to_string()),
+        false => Err(format!(
+            "Unknown event, expected one of: {}",
+            PAEvent::NAMES.join(", ")
+        )),
+    }
+}
+
+/// The kinds of devices which can be picked
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum PickKind {
+    Sink,
+    Source,
+}
+
+#[derive(Debug, Args)]
+pub struct PickArgs {
+    #[arg(value_enum)]
+    pub kind: PickKind,
+    /// Print the name of the picked device instead of making it the default
+    #[clap(long)]
+    pub print: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ToggleDefaultSinkArgs {
+    /// Either a name, a description or an index (a number, optionally prefixed with "#")
+    pub a: String,
+    /// Either a name, a description or an index (a number, optionally prefixed with "#")
+    pub b: String,
+    /// Also move all streams to the new default sink
+    #[clap(long)]
+    pub move_streams: bool,
+}
+
+impl ToggleDefaultSinkArgs {
+    pub fn idents(&self) -> Result<(PAIdent, PAIdent), String> {
+        Ok((parse_id(None, &self.a)?, parse_id(None, &self.b)?))
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct PreferArgs {
+    /// Sink names, most preferred first. A `*` matches any number of characters, such as
+    /// `bluez_output.*` for any Bluetooth sink
+    #[clap(required = true)]
+    pub patterns: Vec<String>,
+    /// Also move all streams to the new default sink
+    #[clap(long)]
+    pub move_streams: bool,
+}
+
+impl From<PreferArgs> for SinkPreference {
+    fn from(value: PreferArgs) -> Self {
+        SinkPreference {
+            patterns: value.patterns,
+            move_streams: value.move_streams,
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct CardProfilesArgs {
+    /// Rules in the form "<CARD>=<PROFILE>"; the first rule which matches a card is used. A `*` in
+    /// the card name matches any number of characters, such as `bluez_card.*=a2dp-sink`
+    #[clap(required = true, value_parser = card_rule_from_str)]
+    pub rules: Vec<CardProfileRule>,
+}
+
+fn card_rule_from_str(s: &str) -> Result<CardProfileRule, String> {
+    match s.split_once('=') {
+        Some((card, profile)) if !card.is_empty() && !profile.is_empty() => Ok(CardProfileRule {
+            card: card.to_string(),
+            profile: profile.to_string(),
+        }),
+        _ => Err(format!("Expected \"<CARD>=<PROFILE>\", got \"{}\"", s)),
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct SleepTimerArgs {
+    /// How long until the sink is suspended.
+    /// Provide the duration in one of the following formats: "<INT>ms", "<INT>s", "<INT>m" or "<INT>h"
+    #[clap(value_parser = duration_from_str)]
+    pub duration: Duration,
+    /// Either a name or an index (number) of the sink; if not provided, the default sink is used
+    #[clap(long)]
+    pub sink: Option<String>,
+    /// How to interpret the sink id; if not provided, it will be inferred
+    #[clap(long)]
+    pub sink_type: Option<IdentKind>,
+}
+
+impl SleepTimerArgs {
+    pub fn sink_id(&self) -> Result<Option<PAIdent>, String> {
+        self.sink
+            .as_ref()
+            .map(|id| parse_id(self.sink_type, id))
+            .transpose()
+    }
+}
+
+fn size_from_str(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (value, unit) = s.split_at(split);
+    let value = value.parse::<u64>().map_err(|e| e.to_string())?;
+    match unit.trim() {
+        "" => Ok(value),
+        "K" => Ok(value * 1024),
+        "M" => Ok(value * 1024 * 1024),
+        "G" => Ok(value * 1024 * 1024 * 1024),
+        unit => Err(format!("Unknown size unit: {}", unit)),
+    }
+}
+
+fn duration_from_str(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (value, unit) = s.split_at(split);
+    let value = value.parse::<u64>().map_err(|e| e.to_string())?;
+    match unit.trim() {
+        "ms" => Ok(Duration::from_millis(value)),
+        "" | "s" => Ok(Duration::from_secs(value)),
+        "m" => Ok(Duration::from_secs(value * 60)),
+        "h" => Ok(Duration::from_secs(value * 60 * 60)),
+        unit => Err(format!("Unknown duration unit: {}", unit)),
+    }
 }
 
 #[derive(Debug, Args)]
@@ -269,6 +1186,9 @@ pub struct MoveArgs {
     /// How to interpret the id; if not provided, it will be inferred
     #[clap(long)]
     pub from_type: Option<IdentKind>,
+    /// Treat the id as an application name, and move all of its streams
+    #[clap(long, conflicts_with = "from_type")]
+    pub app: bool,
     /// Either a name or an index (number)
     #[clap(name = "TO_NAME|TO_INDEX")]
     pub to_id: String,
@@ -278,11 +1198,11 @@ pub struct MoveArgs {
 }
 
 impl MoveArgs {
-    pub fn from_id(&self) -> PAIdent {
+    pub fn from_id(&self) -> Result<PAIdent, String> {
         parse_id(self.from_type, &self.from_id)
     }
 
-    pub fn to_id(&self) -> PAIdent {
+    pub fn to_id(&self) -> Result<PAIdent, String> {
         parse_id(self.to_type, &self.to_id)
     }
 }
@@ -291,13 +1211,71 @@ impl MoveArgs {
 pub struct LoadModuleArgs {
     #[clap(required = true)]
     pub name: String,
-    /// Arguments to pass to the module
-    #[clap(required = true)]
-    pub args: String,
+    /// Arguments to pass to the module, exactly as PulseAudio expects them
+    pub args: Option<String>,
+    /// A single argument in the form "<KEY>=<VALUE>", which is quoted and escaped as needed (so
+    /// values may contain spaces and quotes). Can be repeated, and combined with the raw arguments
+    #[clap(long = "arg", value_parser = module_arg_from_str)]
+    pub arg: Vec<(String, String)>,
+}
+
+impl LoadModuleArgs {
+    /// The raw arguments followed by each `--arg`, as a single module argument string
+    pub fn argument(&self) -> String {
+        self.args
+            .iter()
+            .cloned()
+            .chain(
+                self.arg
+                    .iter()
+                    .map(|(key, value)| format!("{}={}", key, quote_module_arg(value))),
+            )
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+fn module_arg_from_str(s: &str) -> Result<(String, String), String> {
+    let (key, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("Expected \"<KEY>=<VALUE>\", got \"{}\"", s))?;
+
+    let valid = |c: char| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-');
+    if key.is_empty() || !key.chars().all(valid) {
+        return Err(format!(
+            "Invalid key \"{}\", keys may only contain letters, numbers, '_', '.' and '-'",
+            key
+        ));
+    }
+
+    Ok((key.to_string(), value.to_string()))
+}
+
+/// Module arguments are separated by whitespace, so values containing whitespace or quotes (or
+/// empty values) are wrapped in double quotes, escaping any quotes and backslashes within
+fn quote_module_arg(value: &str) -> String {
+    let needs_quotes = value.is_empty()
+        || value
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, '"' | '\'' | '\\'));
+    if !needs_quotes {
+        return value.to_string();
+    }
+
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        if matches!(c, '"' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
 }
 
 #[derive(Debug, Args)]
-pub struct SetPortLatencyArgs {
+pub struct CardPortArgs {
     /// Either a name or an index (number)
     #[clap(name = "CARD_NAME|CARD_INDEX")]
     pub card_id: String,
@@ -310,16 +1288,22 @@ pub struct SetPortLatencyArgs {
     /// How to interpret the id; if not provided, it will be inferred
     #[clap(long)]
     pub port_type: Option<IdentKind>,
-    /// The new latency offset
-    pub offset: i64,
 }
 
-impl SetPortLatencyArgs {
-    pub fn card_id(&self) -> PAIdent {
+impl CardPortArgs {
+    pub fn card_id(&self) -> Result<PAIdent, String> {
         parse_id(self.card_type, &self.card_id)
     }
 
-    pub fn port_id(&self) -> PAIdent {
+    pub fn port_id(&self) -> Result<PAIdent, String> {
         parse_id(self.port_type, &self.port_id)
     }
 }
+
+#[derive(Debug, Args)]
+pub struct SetPortLatencyArgs {
+    #[clap(flatten)]
+    pub port_args: CardPortArgs,
+    /// The new latency offset
+    pub offset: i64,
+}
@@ -0,0 +1,219 @@
+use std::error::Error;
+use std::fmt::Debug;
+
+use pulser::api::{HasVolume, PACardInfo, PADevice, PAIdent};
+use pulser::simple::PulseAudio;
+
+use crate::cli::DescribeKind;
+use crate::style::{self, Color, Style};
+
+/// A readable summary of an object, as a heading followed by aligned "label: value" lines
+struct Summary {
+    heading: String,
+    lines: Vec<(&'static str, String)>,
+}
+
+impl Summary {
+    fn new(kind: &str, index: u32, name: Option<&str>) -> Summary {
+        Summary {
+            heading: format!("{} #{}: {}", kind, index, name.unwrap_or("(no name)")),
+            lines: vec![],
+        }
+    }
+
+    fn line(&mut self, label: &'static str, value: impl Into<String>) {
+        self.lines.push((label, value.into()));
+    }
+
+    fn render(&self) -> String {
+        let width = self.lines.iter().map(|(l, _)| l.len()).max().unwrap_or(0);
+        let mut out = self.heading.clone();
+        for (label, value) in &self.lines {
+            out.push_str(&format!("\n  {:<width$}  {}", format!("{}:", label), value));
+        }
+
+        out
+    }
+}
+
+/// Prints a readable summary of a sink, source or card
+pub fn describe(
+    pa: &PulseAudio,
+    style: Style,
+    kind: DescribeKind,
+    id: PAIdent,
+) -> Result<(), Box<dyn Error>> {
+    let summary = match kind {
+        DescribeKind::Sink => {
+            let sink = pa.get_sink_info(id)?;
+            let mut summary = Summary::new("Sink", sink.index, sink.name.as_deref());
+            describe_device(style, &mut summary, &sink, lowercase(&sink.state));
+            summary.line(
+                "Active port",
+                match &sink.active_port {
+                    Some(p) => port(style, &p.name, &p.description, &p.available),
+                    None => "-".into(),
+                },
+            );
+            summary.line("Latency", latency(sink.latency, sink.configured_latency));
+            describe_card(pa, &mut summary, sink.card)?;
+            summary
+        }
+        DescribeKind::Source => {
+            let source = pa.get_source_info(id)?;
+            let mut summary = Summary::new("Source", source.index, source.name.as_deref());
+            describe_device(style, &mut summary, &source, lowercase(&source.state));
+            summary.line(
+                "Active port",
+                match &source.active_port {
+                    Some(p) => port(style, &p.name, &p.description, &p.available),
+                    None => "-".into(),
+                },
+            );
+            summary.line(
+                "Latency",
+                latency(source.latency, source.configured_latency),
+            );
+            if let Some(monitor) = source.monitor_of_sink_name {
+                summary.line("Monitor of", monitor);
+            }
+            describe_card(pa, &mut summary, source.card)?;
+            summary
+        }
+        DescribeKind::Card => describe_card_summary(style, &pa.get_card_info(id)?),
+    };
+
+    println!("{}", summary.render());
+    Ok(())
+}
+
+fn describe_device(
+    style: Style,
+    summary: &mut Summary,
+    device: &(impl PADevice + HasVolume),
+    state: String,
+) {
+    summary.line("Description", device.description().unwrap_or("-"));
+    summary.line(
+        "State",
+        match state.as_str() {
+            "running" => style.paint(Color::Green, &state),
+            "idle" => style.paint(Color::Yellow, &state),
+            "suspended" => style.paint(Color::Dim, &state),
+            _ => state,
+        },
+    );
+
+    // only show each channel's volume if they differ
+    let readings = device.volume_readings();
+    let mut volumes = readings.iter().map(|r| r.volume()).collect::<Vec<_>>();
+    volumes.dedup();
+    let loudest = volumes.iter().map(|v| v.percentage()).fold(0.0, f64::max);
+    let volume = volumes
+        .iter()
+        .map(style::volume)
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut volume = style.volume(volume, loudest, device.mute());
+    if device.mute() {
+        volume.push_str(&style.mute(" (muted)", true));
+    }
+    summary.line("Volume", volume);
+}
+
+fn describe_card(
+    pa: &PulseAudio,
+    summary: &mut Summary,
+    card: Option<u32>,
+) -> Result<(), Box<dyn Error>> {
+    let value = match card {
+        Some(idx) => {
+            let card = pa.get_card_info(PAIdent::Index(idx))?;
+            let profile = card
+                .active_profile
+                .and_then(|p| p.name)
+                .unwrap_or_else(|| "-".into());
+            format!(
+                "#{} {} (profile {})",
+                card.index,
+                card.name.as_deref().unwrap_or("-"),
+                profile
+            )
+        }
+        None => "-".into(),
+    };
+    summary.line("Card", value);
+    Ok(())
+}
+
+fn describe_card_summary(style: Style, card: &PACardInfo) -> Summary {
+    let mut summary = Summary::new("Card", card.index, card.name.as_deref());
+    summary.line(
+        "Description",
+        card.proplist
+            .get_str("device.description")
+            .unwrap_or_else(|| "-".into()),
+    );
+    summary.line("Driver", card.driver.as_deref().unwrap_or("-"));
+    summary.line(
+        "Active profile",
+        match &card.active_profile {
+            Some(profile) => named(profile.name.as_deref(), profile.description.as_deref()),
+            None => "-".into(),
+        },
+    );
+
+    let available = card.profiles.iter().filter(|p| p.available).count();
+    summary.line(
+        "Profiles",
+        format!("{} ({} available)", card.profiles.len(), available),
+    );
+    for p in &card.ports {
+        summary.line("Port", port(style, &p.name, &p.description, &p.available));
+    }
+
+    summary
+}
+
+/// Formats a port, such as "analog-output-speaker (Speakers), available"
+fn port(
+    style: Style,
+    name: &Option<String>,
+    description: &Option<String>,
+    available: &impl Debug,
+) -> String {
+    let (text, available) = match lowercase(available).as_str() {
+        "yes" => ("available", Some(true)),
+        "no" => ("unavailable", Some(false)),
+        _ => ("availability unknown", None),
+    };
+
+    format!(
+        "{}, {}",
+        named(name.as_deref(), description.as_deref()),
+        style.available(text, available)
+    )
+}
+
+/// Formats a name with its description, such as "analog-output-speaker (Speakers)"
+fn named(name: Option<&str>, description: Option<&str>) -> String {
+    match (name, description) {
+        (Some(name), Some(description)) => format!("{} ({})", name, description),
+        (Some(name), None) => name.to_string(),
+        (None, Some(description)) => description.to_string(),
+        (None, None) => "-".into(),
+    }
+}
+
+fn latency(latency: u64, configured: u64) -> String {
+    format!(
+        "{} (configured {})",
+        style::latency(latency),
+        style::latency(configured)
+    )
+}
+
+/// The states and availabilities are libpulse enums, which are only printable via `Debug`
+fn lowercase(value: &impl Debug) -> String {
+    format!("{:?}", value).to_lowercase()
+}
@@ -0,0 +1,218 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use pulser::api::PAIdent;
+use pulser::simple::PulseAudio;
+use serde::Serialize;
+use serde_json::{to_value, Map, Value};
+
+use crate::cli::{Kind, SortBy};
+use crate::list::list;
+
+/// Kinds of objects which have stable names (across restarts, etc), so they're matched by name.
+/// Everything else (streams, clients, modules) is matched by index.
+const MATCH_BY_NAME: &[&str] = &["cards", "samples", "sinks", "sources"];
+
+/// Fields which change constantly, and aren't interesting when comparing snapshots.
+const IGNORED_FIELDS: &[&str] = &[
+    "index",
+    "latency",
+    "configured_latency",
+    "buffer_usec",
+    "sink_usec",
+    "source_usec",
+];
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "change", rename_all = "lowercase")]
+pub enum Change {
+    /// An object exists in the second snapshot, but not the first
+    Added { kind: String, id: PAIdent },
+    /// An object exists in the first snapshot, but not the second
+    Removed { kind: String, id: PAIdent },
+    /// A field of an object (or the server) is different; `path` can be passed to `get`
+    Changed {
+        kind: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        id: Option<PAIdent>,
+        path: String,
+        old: Value,
+        new: Value,
+    },
+}
+
+/// A snapshot is the output of `pulser-cli list` (with either no kinds, or more than one kind).
+type Snapshot = Map<String, Value>;
+
+fn read_snapshot(path: &Path) -> Result<Snapshot, Box<dyn Error>> {
+    match serde_json::from_str(&fs::read_to_string(path)?)? {
+        Value::Object(map) => Ok(map),
+        _ => Err(format!(
+            "Expected a snapshot created with `pulser-cli list`: {}",
+            path.display()
+        )
+        .into()),
+    }
+}
+
+fn live_snapshot(pa: &PulseAudio) -> Result<Snapshot, Box<dyn Error>> {
+    match to_value(list(
+        pa,
+        &Kind::expand(&[Kind::All]),
+        SortBy::Index,
+        false,
+        None,
+    )?)? {
+        Value::Object(map) => Ok(map),
+        _ => unreachable!(),
+    }
+}
+
+/// Compares a snapshot with another snapshot, or with the current state of the server if `b` is
+/// `None`.
+pub fn diff(pa: &PulseAudio, a: &Path, b: Option<&Path>) -> Result<Vec<Change>, Box<dyn Error>> {
+    let a = read_snapshot(a)?;
+    let b = match b {
+        Some(b) => read_snapshot(b)?,
+        None => live_snapshot(pa)?,
+    };
+
+    let mut changes = vec![];
+    let kinds = a.keys().chain(b.keys()).collect::<BTreeSet<_>>();
+    for kind in kinds {
+        match (a.get(kind), b.get(kind)) {
+            (Some(Value::Array(a)), Some(Value::Array(b))) => {
+                diff_objects(kind, a, b, &mut changes)
+            }
+            // the server, which is a single object rather than a list
+            (Some(a), Some(b)) => {
+                for (path, old, new) in diff_fields(a, b) {
+                    changes.push(Change::Changed {
+                        kind: kind.clone(),
+                        id: None,
+                        path,
+                        old,
+                        new,
+                    });
+                }
+            }
+            // this kind wasn't listed in one of the snapshots, so there's nothing to compare
+            _ => {}
+        }
+    }
+
+    Ok(changes)
+}
+
+fn object_id(kind: &str, object: &Value) -> Option<PAIdent> {
+    if MATCH_BY_NAME.contains(&kind) {
+        if let Some(name) = object.get("name").and_then(|n| n.as_str()) {
+            return Some(PAIdent::Name(name.to_string()));
+        }
+    }
+
+    object
+        .get("index")
+        .and_then(|i| i.as_u64())
+        .map(|i| PAIdent::Index(i as u32))
+}
+
+fn diff_objects(kind: &str, a: &[Value], b: &[Value], changes: &mut Vec<Change>) {
+    // key by the serialized id, since `PAIdent` can't be used as a key
+    let by_id = |objects: &[Value]| {
+        objects
+            .iter()
+            .filter_map(|o| object_id(kind, o).map(|id| (format!("{:?}", id), (id, o.clone()))))
+            .collect::<BTreeMap<_, _>>()
+    };
+    let mut a = by_id(a);
+    let mut b = by_id(b);
+
+    let keys = a.keys().chain(b.keys()).cloned().collect::<BTreeSet<_>>();
+    for key in keys {
+        match (a.remove(&key), b.remove(&key)) {
+            (Some((id, _)), None) => changes.push(Change::Removed {
+                kind: kind.to_string(),
+                id,
+            }),
+            (None, Some((id, _))) => changes.push(Change::Added {
+                kind: kind.to_string(),
+                id,
+            }),
+            (Some((id, a)), Some((_, b))) => {
+                for (path, old, new) in diff_fields(&a, &b) {
+                    changes.push(Change::Changed {
+                        kind: kind.to_string(),
+                        id: Some(id.clone()),
+                        path,
+                        old,
+                        new,
+                    });
+                }
+            }
+            (None, None) => unreachable!(),
+        }
+    }
+}
+
+/// Returns the path, old value and new value of each field which differs between two objects.
+fn diff_fields(a: &Value, b: &Value) -> Vec<(String, Value, Value)> {
+    let mut fields = vec![];
+    if let (Value::Object(a), Value::Object(b)) = (a, b) {
+        let keys = a.keys().chain(b.keys()).collect::<BTreeSet<_>>();
+        for key in keys {
+            if IGNORED_FIELDS.contains(&key.as_str()) {
+                continue;
+            }
+
+            let mut path = vec![key.clone()];
+            diff_values(
+                &mut path,
+                a.get(key).unwrap_or(&Value::Null),
+                b.get(key).unwrap_or(&Value::Null),
+                &mut fields,
+            );
+        }
+    } else {
+        diff_values(&mut vec![], a, b, &mut fields);
+    }
+
+    fields
+}
+
+fn diff_values(
+    path: &mut Vec<String>,
+    a: &Value,
+    b: &Value,
+    fields: &mut Vec<(String, Value, Value)>,
+) {
+    match (a, b) {
+        (Value::Object(a), Value::Object(b)) => {
+            let keys = a.keys().chain(b.keys()).collect::<BTreeSet<_>>();
+            for key in keys {
+                path.push(key.clone());
+                diff_values(
+                    path,
+                    a.get(key).unwrap_or(&Value::Null),
+                    b.get(key).unwrap_or(&Value::Null),
+                    fields,
+                );
+                path.pop();
+            }
+        }
+        (Value::Array(a), Value::Array(b)) if a.len() == b.len() => {
+            for (i, (a, b)) in a.iter().zip(b).enumerate() {
+                path.push(i.to_string());
+                diff_values(path, a, b, fields);
+                path.pop();
+            }
+        }
+        (a, b) => {
+            if a != b {
+                fields.push((path.join("."), a.clone(), b.clone()));
+            }
+        }
+    }
+}
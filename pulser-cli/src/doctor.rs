@@ -0,0 +1,190 @@
+use std::error::Error;
+
+use pulser::api::{PADevice, PAServerInfo, ServerCapabilities};
+use pulser::simple::PulseAudio;
+use serde::Serialize;
+
+/// Latency offsets (in microseconds) at least this large are likely a misconfiguration
+const DUBIOUS_LATENCY_OFFSET: i64 = 500_000;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Pass,
+    Warn,
+    Fail,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: Status,
+    pub message: String,
+}
+
+impl Check {
+    fn new(name: &'static str, status: Status, message: impl Into<String>) -> Check {
+        Check {
+            name,
+            status,
+            message: message.into(),
+        }
+    }
+}
+
+/// Runs a series of checks for common causes of "no sound", such as a missing or muted default
+/// sink. If the server can't be reached, only that check is returned.
+pub fn doctor(pa: &PulseAudio) -> Vec<Check> {
+    let info = match pa.get_server_info() {
+        Ok(info) => info,
+        Err(e) => {
+            return vec![Check::new(
+                "server",
+                Status::Fail,
+                format!("Couldn't reach the server: {}", e),
+            )]
+        }
+    };
+
+    let mut checks = vec![server(&info)];
+    checks.extend(
+        [devices(pa, &info), latency_offsets(pa)]
+            .into_iter()
+            .flat_map(|result| match result {
+                Ok(checks) => checks,
+                Err(e) => vec![Check::new(
+                    "introspection",
+                    Status::Fail,
+                    format!("Couldn't query the server: {}", e),
+                )],
+            }),
+    );
+
+    checks
+}
+
+fn server(info: &PAServerInfo) -> Check {
+    let capabilities = ServerCapabilities::from(info);
+    let version = info
+        .server_version
+        .as_deref()
+        .unwrap_or("(unknown version)");
+    let message = match capabilities.pipewire_version {
+        Some(pipewire) => format!(
+            "Connected to pipewire-pulse {} (PipeWire {})",
+            version, pipewire
+        ),
+        None => format!(
+            "Connected to {} {}",
+            info.server_name.as_deref().unwrap_or("an unknown server"),
+            version
+        ),
+    };
+
+    Check::new("server", Status::Pass, message)
+}
+
+fn devices(pa: &PulseAudio, info: &PAServerInfo) -> Result<Vec<Check>, Box<dyn Error>> {
+    let sinks = pa.get_sink_info_list()?;
+    let sources = pa.get_source_info_list()?;
+
+    let suspended = sinks
+        .iter()
+        .map(|sink| sink as &dyn PADevice)
+        .chain(sources.iter().map(|source| source as &dyn PADevice))
+        .filter(|device| device.is_suspended())
+        .map(display_name)
+        .collect::<Vec<_>>();
+
+    Ok(vec![
+        default_device("default_sink", "sink", &info.default_sink_name, &sinks),
+        default_device(
+            "default_source",
+            "source",
+            &info.default_source_name,
+            &sources,
+        ),
+        match suspended.is_empty() {
+            true => Check::new(
+                "suspended_devices",
+                Status::Pass,
+                "No devices are suspended",
+            ),
+            false => Check::new(
+                "suspended_devices",
+                Status::Warn,
+                format!(
+                    "Suspended (this is normal if they're idle): {}",
+                    suspended.join(", ")
+                ),
+            ),
+        },
+    ])
+}
+
+fn default_device<T: PADevice>(
+    name: &'static str,
+    kind: &str,
+    default: &Option<String>,
+    devices: &[T],
+) -> Check {
+    let default = match default {
+        Some(default) => default,
+        None => return Check::new(name, Status::Fail, format!("No default {} is set", kind)),
+    };
+
+    match devices.iter().find(|d| d.name() == Some(default.as_str())) {
+        None => Check::new(
+            name,
+            Status::Fail,
+            format!("The default {} ({}) doesn't exist", kind, default),
+        ),
+        Some(device) if device.mute() => Check::new(
+            name,
+            Status::Warn,
+            format!("The default {} ({}) is muted", kind, display_name(device)),
+        ),
+        Some(device) => Check::new(
+            name,
+            Status::Pass,
+            format!("The default {} is {}", kind, display_name(device)),
+        ),
+    }
+}
+
+fn latency_offsets(pa: &PulseAudio) -> Result<Vec<Check>, Box<dyn Error>> {
+    let dubious = pa
+        .get_card_info_list()?
+        .iter()
+        .flat_map(|card| {
+            card.ports
+                .iter()
+                .filter(|port| port.latency_offset.abs() >= DUBIOUS_LATENCY_OFFSET)
+                .map(move |port| {
+                    format!(
+                        "{} {} ({}ms)",
+                        card.name.as_deref().unwrap_or("(unnamed card)"),
+                        port.name.as_deref().unwrap_or("(unnamed port)"),
+                        port.latency_offset / 1000
+                    )
+                })
+        })
+        .collect::<Vec<_>>();
+
+    Ok(vec![match dubious.is_empty() {
+        true => Check::new("latency_offsets", Status::Pass, "No large latency offsets"),
+        false => Check::new(
+            "latency_offsets",
+            Status::Warn,
+            format!("Large latency offsets: {}", dubious.join(", ")),
+        ),
+    }])
+}
+
+fn display_name(device: &(impl PADevice + ?Sized)) -> String {
+    device
+        .description()
+        .or(device.name())
+        .map(|name| name.to_string())
+        .unwrap_or_else(|| format!("#{}", device.index()))
+}
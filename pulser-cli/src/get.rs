@@ -0,0 +1,86 @@
+use std::error::Error;
+
+use pulser::api::PAIdent;
+use pulser::simple::PulseAudio;
+use serde_json::{to_value, Value};
+
+use crate::cli::ObjectKind;
+
+/// Fetches an object and serializes it, so it can be queried by path.
+pub fn resolve(pa: &PulseAudio, kind: ObjectKind, id: PAIdent) -> Result<Value, Box<dyn Error>> {
+    Ok(match kind {
+        ObjectKind::Card => to_value(pa.get_card_info(id)?)?,
+        ObjectKind::Client => to_value(pa.get_client_info(id)?)?,
+        ObjectKind::Module => to_value(pa.get_module_info(id)?)?,
+        ObjectKind::Sink => to_value(pa.get_sink_info(id)?)?,
+        ObjectKind::SinkInput => to_value(pa.get_sink_input_info(id)?)?,
+        ObjectKind::Source => to_value(pa.get_source_info(id)?)?,
+        ObjectKind::SourceOutput => to_value(pa.get_source_output_info(id)?)?,
+    })
+}
+
+/// Selects values with a dot-separated path, such as `active_port.name` or `ports.0.name`.
+/// Numeric segments index into arrays, any other segment is applied to each item of an array, so
+/// `volume.volumes.percentage` selects the percentage of each channel. A field which an object
+/// doesn't have is looked up in its list instead, if it has exactly one, so `volume.percentage`
+/// works too.
+pub fn select<'a>(value: &'a Value, path: &[&str]) -> Result<Vec<&'a Value>, String> {
+    let (segment, rest) = match path.split_first() {
+        Some(split) => split,
+        None => return Ok(vec![value]),
+    };
+
+    match value {
+        Value::Object(map) => match map.get(*segment) {
+            Some(value) => select(value, rest),
+            None => {
+                let mut lists = map.values().filter(|value| value.is_array());
+                match (lists.next(), lists.next()) {
+                    (Some(list), None) => select(list, path)
+                        .map_err(|_| format!("No field found with name: {}", segment)),
+                    _ => Err(format!("No field found with name: {}", segment)),
+                }
+            }
+        },
+        Value::Array(items) => match segment.parse::<usize>() {
+            Ok(i) => match items.get(i) {
+                Some(value) => select(value, rest),
+                None => Err(format!("No item found at index: {}", i)),
+            },
+            Err(_) => {
+                let mut values = vec![];
+                for item in items {
+                    values.extend(select(item, path)?);
+                }
+                Ok(values)
+            }
+        },
+        _ => Err(format!("Cannot select {} from value: {}", segment, value)),
+    }
+}
+
+/// Formats a value without any JSON quoting, unless it's an object or array.
+pub fn bare(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        value => value.to_string(),
+    }
+}
+
+pub fn get(
+    pa: &PulseAudio,
+    kind: ObjectKind,
+    id: PAIdent,
+    path: &str,
+) -> Result<(), Box<dyn Error>> {
+    let value = resolve(pa, kind, id)?;
+    let path = path
+        .split('.')
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>();
+    for value in select(&value, &path)? {
+        println!("{}", bare(value));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,30 @@
+use std::error::Error;
+
+use pulser::api::{PAEvent, PAMask};
+use pulser::simple::PulseAudio;
+
+/// Prints the server's info, and then prints it again each time it changes (such as when the
+/// default sink or the default sample spec changes). Runs until it's interrupted.
+pub fn watch(pa: &PulseAudio) -> Result<(), Box<dyn Error>> {
+    let events = pa.events(PAMask::SERVER)?;
+
+    let mut last = serde_json::to_string(&pa.get_server_info()?)?;
+    println!("{}", last);
+
+    for ev in events {
+        let info = match ev {
+            PAEvent::ServerChanged(info) => info,
+            _ => continue,
+        };
+
+        // the server sends change events for things which aren't part of its info, so only print
+        // the info when something in it actually changed
+        let json = serde_json::to_string(&info)?;
+        if json != last {
+            println!("{}", json);
+            last = json;
+        }
+    }
+
+    Ok(())
+}
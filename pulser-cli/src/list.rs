@@ -0,0 +1,106 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::error::Error;
+
+use pulser::api::StreamFilter;
+use pulser::simple::PulseAudio;
+use serde_json::{to_value, Value};
+
+use crate::cli::{Kind, SortBy};
+
+/// Lists the objects of each kind, collected into a `BTreeMap` to have it sorted by key. The
+/// objects of each kind are sorted too, since the server's enumeration order isn't stable.
+///
+/// If `only_active` is set, corked (paused) streams are left out. If `name` is set, only objects
+/// whose name contains it are listed.
+pub fn list(
+    pa: &PulseAudio,
+    kinds: &[Kind],
+    sort: SortBy,
+    only_active: bool,
+    name: Option<&str>,
+) -> Result<BTreeMap<Kind, Value>, Box<dyn Error>> {
+    kinds
+        .iter()
+        .map(|k| -> Result<(Kind, Value), Box<dyn Error>> {
+            let mut value = match k {
+                Kind::Cards => to_value(pa.get_card_info_list()?)?,
+                Kind::Clients => to_value(pa.get_client_info_list()?)?,
+                Kind::Modules => to_value(pa.get_module_info_list()?)?,
+                Kind::Samples => to_value(pa.get_sample_info_list()?)?,
+                Kind::Server => to_value(pa.get_server_info()?)?,
+                Kind::Sinks => to_value(pa.get_sink_info_list()?)?,
+                Kind::SinkInputs => {
+                    to_value(pa.get_sink_input_info_list_filtered(StreamFilter {
+                        corked: only_active.then_some(false),
+                        app: None,
+                    })?)?
+                }
+                Kind::Sources => to_value(pa.get_source_info_list()?)?,
+                Kind::SourceOutputs => {
+                    let mut outputs = pa.get_source_output_info_list()?;
+                    outputs.retain(|output| !(only_active && output.corked));
+                    to_value(outputs)?
+                }
+                Kind::All => unreachable!("kinds should be expanded before listing"),
+            };
+
+            if let Value::Array(ref mut objects) = value {
+                if let Some(filter) = name {
+                    let filter = filter.to_lowercase();
+                    objects.retain(|object| {
+                        self::name(object).map_or(false, |n| n.to_lowercase().contains(&filter))
+                    });
+                }
+
+                // a stable sort, with ties broken by index so the output is deterministic
+                objects.sort_by(|a, b| compare(a, b, sort).then_with(|| index(a).cmp(&index(b))));
+            }
+
+            Ok((*k, value))
+        })
+        .collect()
+}
+
+fn compare(a: &Value, b: &Value, sort: SortBy) -> Ordering {
+    match sort {
+        SortBy::Index => index(a).cmp(&index(b)),
+        // objects without a name are sorted last
+        SortBy::Name => match (name(a), name(b)) {
+            (Some(a), Some(b)) => a.cmp(b),
+            (a, b) => a.is_none().cmp(&b.is_none()),
+        },
+        // the most useful objects come first
+        SortBy::Priority => priority(b).cmp(&priority(a)),
+        SortBy::Volume => volume(a).cmp(&volume(b)),
+    }
+}
+
+fn index(object: &Value) -> Option<u64> {
+    object.get("index").and_then(Value::as_u64)
+}
+
+fn name(object: &Value) -> Option<&str> {
+    object.get("name").and_then(Value::as_str)
+}
+
+/// The priority of the active port (sinks and sources) or active profile (cards)
+fn priority(object: &Value) -> Option<u64> {
+    ["active_port", "active_profile"]
+        .iter()
+        .find_map(|key| object.get(key)?.get("priority")?.as_u64())
+}
+
+/// The average raw volume of each channel
+fn volume(object: &Value) -> Option<u64> {
+    let volumes = object.get("volume")?.get("volumes")?.as_array()?;
+    let raw = volumes
+        .iter()
+        .filter_map(|v| v.get("raw")?.as_u64())
+        .collect::<Vec<_>>();
+
+    match raw.len() {
+        0 => None,
+        n => Some(raw.iter().sum::<u64>() / n as u64),
+    }
+}
@@ -0,0 +1,50 @@
+use std::io::{self, Write};
+use std::time::Instant;
+
+use log::{LevelFilter, Log, Metadata, Record};
+
+/// Logs to stderr, prefixing each line with the time since the logger was started
+struct Logger {
+    started: Instant,
+}
+
+impl Log for Logger {
+    fn enabled(&self, _: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        let elapsed = self.started.elapsed().as_secs_f64() * 1000.0;
+        let _ = writeln!(
+            io::stderr().lock(),
+            "[{:>10.3}ms {:<5} {}] {}",
+            elapsed,
+            record.level(),
+            record.target(),
+            record.args()
+        );
+    }
+
+    fn flush(&self) {
+        let _ = io::stderr().flush();
+    }
+}
+
+/// Sets up logging for the number of times `--verbose` was passed: warnings only by default, then
+/// info, then each command and how long its response took, and then each response in full
+pub fn init(verbose: u8) {
+    let level = match verbose {
+        0 => LevelFilter::Warn,
+        1 => LevelFilter::Info,
+        2 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    };
+
+    let logger = Logger {
+        started: Instant::now(),
+    };
+    // the logger lives for the rest of the program, so it's fine to leak it
+    if log::set_logger(Box::leak(Box::new(logger))).is_ok() {
+        log::set_max_level(level);
+    }
+}
@@ -1,16 +1,40 @@
+mod attempt;
+mod card_profiles;
 mod cli;
+mod describe;
+mod diff;
+mod doctor;
+mod get;
+mod info;
+mod list;
+mod logger;
+mod meter;
+mod mic_indicator;
+mod output;
+mod pick;
+mod prefer;
+mod rotate;
+mod set;
+mod sleep_timer;
+mod style;
 mod subscribe;
+mod systemd;
+mod top;
 
-use std::collections::BTreeMap;
 use std::error::Error;
+use std::io::{self, Write};
+use std::{fs, process};
 
-use clap::{Parser, ValueEnum};
-use pulser::api::PAMask;
+use clap::{CommandFactory, FromArgMatches};
+use pulser::api::{HasFormats, PAIdent, PAMask};
 use pulser::simple::{OperationResult, PulseAudio};
-use serde_json::{to_value, Value};
 
+use crate::attempt::Attempt;
 use crate::cli::Command::*;
-use crate::cli::{Cli, Kind};
+use crate::cli::{AppVolumeCommand, Cli, Kind, PickKind, RenameKind};
+use crate::output::Output;
+use crate::rotate::RotatingFile;
+use crate::style::Style;
 
 #[macro_export]
 macro_rules! json_print {
@@ -19,51 +43,123 @@ macro_rules! json_print {
     };
 }
 
-fn run() -> Result<(), Box<dyn Error>> {
-    let args = Cli::parse();
+/// Prints the result of an operation, along with what was attempted
+macro_rules! op_print {
+    ($output:expr, $x:expr) => {
+        $output.result($x)?
+    };
+}
 
-    let pa = PulseAudio::connect(Some("PulserCli"));
+fn run(args: Cli, output: &Output) -> Result<(), Box<dyn Error>> {
+    let mut builder = PulseAudio::builder()
+        .name("PulserCli")
+        .confirm(args.confirm);
+    if let Some(server) = args.server {
+        builder = builder.server(server);
+    }
+    if let Some(cookie) = args.cookie {
+        builder = builder.cookie_path(cookie);
+    }
+    let pa = builder.connect();
+    let style = Style::new(args.color);
     match args.command {
-        Info => {
-            json_print!(pa.get_server_info()?);
+        Info(args) => {
+            if args.protocol {
+                json_print!(pa.get_protocol_info()?);
+            } else if args.capabilities {
+                json_print!(pa.capabilities()?);
+            } else if args.watch {
+                info::watch(&pa)?;
+            } else {
+                json_print!(pa.get_server_info()?);
+            }
         }
+        Get(args) => get::get(&pa, args.kind, args.base_args.ident()?, &args.path)?,
+        Diff(args) => json_print!(diff::diff(&pa, &args.a, args.b.as_deref())?),
+        Doctor => json_print!(doctor::doctor(&pa)),
+        Top => top::top(&pa, style)?,
+        Meter(args) => meter::meter(&pa, args.per_app)?,
+        MicIndicator(args) => mic_indicator::mic_indicator(
+            &pa,
+            args.exec_on_start.as_deref(),
+            args.exec_on_stop.as_deref(),
+        )?,
+        Describe(args) => describe::describe(&pa, style, args.kind, args.base_args.ident()?)?,
+        Rename(args) => {
+            let id = args.base_args.ident()?;
+            op_print!(
+                output,
+                match args.kind {
+                    RenameKind::Sink => pa.rename_sink(id, args.description)?,
+                    RenameKind::Source => pa.rename_source(id, args.description)?,
+                }
+            )
+        }
+        Latency => json_print!(pa.get_latency_report()?),
+        AppVolume(args) => match args.command {
+            AppVolumeCommand::List => json_print!(pa.get_app_volumes()?),
+            AppVolumeCommand::Save => {
+                op_print!(output, pa.save_app_volumes(pa.current_app_volumes()?)?)
+            }
+            AppVolumeCommand::Load { path } => {
+                let volumes = serde_json::from_str(&fs::read_to_string(path)?)?;
+                op_print!(output, pa.save_app_volumes(volumes)?)
+            }
+            AppVolumeCommand::Set {
+                application,
+                volume,
+            } => {
+                let volume = pulser::api::AppVolume::new(application, volume.percentage());
+                op_print!(output, pa.save_app_volumes(vec![volume])?)
+            }
+            AppVolumeCommand::Forget { applications } => {
+                op_print!(output, pa.delete_app_volumes(applications)?)
+            }
+        },
+        Set(args) => op_print!(
+            output,
+            set::set(
+                &pa,
+                args.kind,
+                args.base_args.ident()?,
+                args.property,
+                &args.values
+            )?
+        ),
+
         GetDefaultSink => json_print!(pa.get_default_sink()?),
         GetDefaultSource => json_print!(pa.get_default_source()?),
-        SetDefaultSink(args) => json_print!(pa.set_default_sink((&args).into())?),
-        SetDefaultSource(args) => json_print!(pa.set_default_source((&args).into())?),
+        SetDefaultSink(args) => op_print!(output, pa.set_default_sink(args.ident()?)?),
+        Pick(args) => match pick::pick(&pa, style, args.kind)? {
+            Some(name) if args.print => println!("{}", name),
+            Some(name) => op_print!(
+                output,
+                match args.kind {
+                    PickKind::Sink => pa.set_default_sink(PAIdent::Name(name))?,
+                    PickKind::Source => pa.set_default_source(PAIdent::Name(name))?,
+                }
+            ),
+            None => return Err("Nothing was picked".into()),
+        },
+        ToggleDefaultSink(args) => {
+            let (a, b) = args.idents()?;
+            let sink = pa.toggle_default_sink(a, b, args.move_streams)?;
+            let target = sink.name.unwrap_or_else(|| format!("#{}", sink.index));
+            output.result_for(target, OperationResult::Success)?;
+        }
+        SetDefaultSource(args) => op_print!(output, pa.set_default_source(args.ident()?)?),
 
         List(args) => {
             // unfortunately can't dedup with clap, so we do that here and silently ignore duplicates
-            let mut kinds = args.kinds;
-            kinds.sort();
-            kinds.dedup();
+            let kinds = Kind::expand(&args.kinds);
 
-            let kinds = if kinds.len() == 0 {
-                Kind::value_variants().to_vec()
-            } else {
-                kinds
-            };
-
-            // collect into a `BTreeMap` to have it sorted by key
-            let map = kinds
-                .into_iter()
-                .map(|k| -> Result<(Kind, Value), Box<dyn Error>> {
-                    Ok((
-                        k,
-                        match k {
-                            Kind::Cards => to_value(pa.get_card_info_list()?)?,
-                            Kind::Clients => to_value(pa.get_client_info_list()?)?,
-                            Kind::Modules => to_value(pa.get_module_info_list()?)?,
-                            Kind::Samples => to_value(pa.get_sample_info_list()?)?,
-                            Kind::Sinks => to_value(pa.get_sink_info_list()?)?,
-                            Kind::SinkInputs => to_value(pa.get_sink_input_info_list()?)?,
-                            Kind::Sources => to_value(pa.get_source_info_list()?)?,
-                            Kind::SourceOutputs => to_value(pa.get_source_output_info_list()?)?,
-                        },
-                    ))
-                })
-                .collect::<Result<BTreeMap<Kind, _>, _>>()
-                .unwrap();
+            let map = list::list(
+                &pa,
+                &kinds,
+                args.sort,
+                args.only_active,
+                args.name.as_deref(),
+            )?;
 
             if map.len() == 1 {
                 json_print!(map.values().next().unwrap());
@@ -72,93 +168,247 @@ fn run() -> Result<(), Box<dyn Error>> {
             }
         }
 
-        GetCardInfo(args) => json_print!(pa.get_card_info((&args).into())?),
-        SetCardProfile(args) => {
-            json_print!(pa.set_card_profile((&args.base_args).into(), args.profile)?)
-        }
-        SetPortLatencyOffset(args) => {
-            json_print!(pa.set_port_latency_offset(args.card_id(), args.port_id(), args.offset)?)
+        GetCardInfo(args) => json_print!(pa.get_card_info(args.ident()?)?),
+        SetCardProfile(args) => op_print!(
+            output,
+            match args.profile {
+                Some(profile) => pa.set_card_profile(args.base_args.ident()?, profile)?,
+                None => pa.toggle_card_profile(args.base_args.ident()?, &args.toggle)?,
+            }
+        ),
+        GetPortLatencyOffset(args) => {
+            json_print!(pa.get_port_latency_offset(args.card_id()?, args.port_id()?)?)
         }
+        SetPortLatencyOffset(args) => op_print!(
+            output,
+            pa.set_port_latency_offset(
+                args.port_args.card_id()?,
+                args.port_args.port_id()?,
+                args.offset
+            )?
+        ),
+        BtProfile(args) => op_print!(
+            output,
+            pa.set_bluetooth_profile(
+                args.base_args.ident()?,
+                args.profile.into(),
+                args.codec.as_deref()
+            )?
+        ),
 
-        GetClientInfo(args) => json_print!(pa.get_client_info((&args).into())?),
-        KillClient(args) => json_print!(pa.kill_client((&args).into())?),
+        GetClientInfo(args) => json_print!(pa.get_client_info(args.ident()?)?),
+        KillClient(args) => op_print!(output, pa.kill_client(args.ident()?)?),
 
-        GetModuleInfo(args) => json_print!(pa.get_module_info((&args).into())?),
-        LoadModule(args) => json_print!(pa.load_module(args.name, args.args)?),
-        UnloadModule(args) => json_print!(pa.unload_module((&args).into())?),
+        GetModuleInfo(args) => match args.by_argument {
+            Some(ref argument) => json_print!(pa.find_modules_by_argument(argument)?),
+            None => json_print!(pa.get_module_info(args.ident()?)?),
+        },
+        LoadModule(args) => json_print!(pa.load_module(args.name.clone(), args.argument())?),
+        UnloadModule(args) => match args.all_matching {
+            Some(ref name) => {
+                let modules = match args.argument_contains {
+                    Some(ref argument) => pa.find_modules_by_argument(argument)?,
+                    None => pa.get_module_info_list()?,
+                };
+                for module in modules.iter().filter(|m| m.name.as_ref() == Some(name)) {
+                    let result = pa.unload_module(PAIdent::Index(module.index))?;
+                    output.result_for(format!("#{}", module.index), result)?;
+                }
+            }
+            None => op_print!(output, pa.unload_module(args.ident()?)?),
+        },
 
-        GetSinkInfo(args) => json_print!(pa.get_sink_info((&args).into())?),
-        GetSinkMute(args) => json_print!(pa.get_sink_mute((&args).into())?),
-        GetSinkVolume(args) => json_print!(pa.get_sink_volume((&args).into())?),
+        GetSinkInfo(args) => json_print!(pa.get_sink_info(args.ident()?)?),
+        GetSinkMute(args) => json_print!(pa.get_sink_mute(args.ident()?)?),
+        GetSinkVolume(args) => json_print!(pa.get_sink_volume(args.ident()?)?),
         SetSinkMute(args) => {
-            json_print!(pa.set_sink_mute((&args.base_args).into(), args.mute.into())?)
+            op_print!(
+                output,
+                pa.set_sink_mute(args.base_args.ident()?, args.mute.into())?
+            )
         }
-        SetSinkVolume(args) => json_print!(pa.set_sink_volume((&args).into(), (&args).into())?),
-        SetSinkPort(args) => json_print!(pa.set_sink_port((&args.base_args).into(), args.port)?),
+        SetSinkVolume(args) => {
+            op_print!(
+                output,
+                pa.set_sink_volume(args.base_args.ident()?, args.volume_spec()?)?
+            )
+        }
+        SetSinkPort(args) => op_print!(
+            output,
+            pa.set_sink_port(args.base_args.ident()?, args.port)?
+        ),
         SuspendSink(args) => {
-            json_print!(pa.suspend_sink((&args.base_args).into(), args.suspend.into())?)
+            op_print!(
+                output,
+                pa.suspend_sink(args.base_args.ident()?, args.suspend.into())?
+            )
+        }
+        Supports(args) => match args.encoding {
+            Some(encoding) => {
+                json_print!(pa.sink_supports_format(args.base_args.ident()?, encoding)?)
+            }
+            None => json_print!(pa.get_sink_info(args.base_args.ident()?)?.encodings()),
+        },
+        FadeSinkVolume(args) => {
+            let fade_id = pa.fade_sink_volume(
+                args.volume_args.base_args.ident()?,
+                args.volume_args.volume_spec()?,
+                args.duration,
+                args.curve.into(),
+            )?;
+            op_print!(output, pa.wait_fade(fade_id)?)
         }
+        VolumeUp(args) => op_print!(
+            output,
+            pa.step_sink_volume(args.base_args.ident()?, args.steps.into())?
+        ),
+        VolumeDown(args) => op_print!(
+            output,
+            pa.step_sink_volume(args.base_args.ident()?, -i32::from(args.steps))?
+        ),
 
-        GetSourceInfo(args) => json_print!(pa.get_source_info((&args).into())?),
-        GetSourceMute(args) => json_print!(pa.get_source_mute((&args).into())?),
-        GetSourceVolume(args) => json_print!(pa.get_source_volume((&args).into())?),
+        GetSourceInfo(args) => json_print!(pa.get_source_info(args.ident()?)?),
+        GetSourceMute(args) => json_print!(pa.get_source_mute(args.ident()?)?),
+        GetSourceVolume(args) => json_print!(pa.get_source_volume(args.ident()?)?),
         SetSourceMute(args) => {
-            json_print!(pa.set_source_mute((&args.base_args).into(), args.mute.into())?)
+            op_print!(
+                output,
+                pa.set_source_mute(args.base_args.ident()?, args.mute.into())?
+            )
+        }
+        SetSourceVolume(args) => {
+            op_print!(
+                output,
+                pa.set_source_volume(args.base_args.ident()?, args.volume_spec()?)?
+            )
         }
-        SetSourceVolume(args) => json_print!(pa.set_source_volume((&args).into(), (&args).into())?),
         SetSourcePort(args) => {
-            json_print!(pa.set_source_port((&args.base_args).into(), args.port)?)
+            op_print!(
+                output,
+                pa.set_source_port(args.base_args.ident()?, args.port)?
+            )
         }
         SuspendSource(args) => {
-            json_print!(pa.suspend_source((&args.base_args).into(), args.suspend.into())?)
+            op_print!(
+                output,
+                pa.suspend_source(args.base_args.ident()?, args.suspend.into())?
+            )
         }
 
-        GetSinkInputInfo(args) => json_print!(pa.get_sink_input_info((&args).into())?),
-        GetSinkInputMute(args) => json_print!(pa.get_sink_input_mute((&args).into())?),
-        GetSinkInputVolume(args) => json_print!(pa.get_sink_input_volume((&args).into())?),
+        GetSinkInputInfo(args) => json_print!(pa.get_sink_input_info(args.ident()?)?),
+        GetSinkInputMute(args) => json_print!(pa.get_sink_input_mute(args.ident()?)?),
+        GetSinkInputVolume(args) => json_print!(pa.get_sink_input_volume(args.ident()?)?),
         SetSinkInputMute(args) => {
-            json_print!(pa.set_sink_input_mute((&args.base_args).into(), args.mute.into())?)
+            op_print!(
+                output,
+                pa.set_sink_input_mute(args.base_args.ident()?, args.mute.into())?
+            )
         }
         SetSinkInputVolume(args) => {
-            json_print!(pa.set_sink_input_volume((&args).into(), (&args).into())?)
+            op_print!(
+                output,
+                pa.set_sink_input_volume(args.base_args.ident()?, args.volume_spec()?)?
+            )
         }
-        MoveSinkInput(args) => json_print!(pa.move_sink_input(args.from_id(), args.to_id())?),
-        KillSinkInput(args) => json_print!(pa.kill_sink_input((&args).into())?),
+        MoveSinkInput(args) => match args.app {
+            true => op_print!(
+                output,
+                pa.move_sink_inputs_by_app(&args.from_id, args.to_id()?)?
+            ),
+            false => op_print!(output, pa.move_sink_input(args.from_id()?, args.to_id()?)?),
+        },
+        KillSinkInput(args) => op_print!(output, pa.kill_sink_input(args.ident()?)?),
 
-        GetSourceOutputInfo(args) => json_print!(pa.get_source_output_info((&args).into())?),
-        GetSourceOutputMute(args) => json_print!(pa.get_source_output_mute((&args).into())?),
-        GetSourceOutputVolume(args) => json_print!(pa.get_source_output_volume((&args).into())?),
+        GetSourceOutputInfo(args) => json_print!(pa.get_source_output_info(args.ident()?)?),
+        GetSourceOutputMute(args) => json_print!(pa.get_source_output_mute(args.ident()?)?),
+        GetSourceOutputVolume(args) => json_print!(pa.get_source_output_volume(args.ident()?)?),
         SetSourceOutputMute(args) => {
-            json_print!(pa.set_source_output_mute((&args.base_args).into(), args.mute.into())?)
+            op_print!(
+                output,
+                pa.set_source_output_mute(args.base_args.ident()?, args.mute.into())?
+            )
         }
         SetSourceOutputVolume(args) => {
-            json_print!(pa.set_source_output_volume((&args).into(), (&args).into())?)
+            op_print!(
+                output,
+                pa.set_source_output_volume(args.base_args.ident()?, args.volume_spec()?)?
+            )
+        }
+        MoveSourceOutput(args) => match args.app {
+            true => op_print!(
+                output,
+                pa.move_source_outputs_by_app(&args.from_id, args.to_id()?)?
+            ),
+            false => op_print!(
+                output,
+                pa.move_source_output(args.from_id()?, args.to_id()?)?
+            ),
+        },
+        KillSourceOutput(args) => op_print!(output, pa.kill_source_output(args.ident()?)?),
+
+        MuteAll(args) => op_print!(output, pa.mute_all(args.mask(), true, args.except()?)?),
+        UnmuteAll(args) => op_print!(output, pa.mute_all(args.mask(), false, args.except()?)?),
+
+        Play(args) => {
+            let result = match &args.file {
+                Some(path) => pa.play_file(path, args.options())?,
+                None => pa.play_raw(args.options(), io::stdin().lock())?,
+            };
+            op_print!(output, result);
+        }
+        PlayFile(args) => op_print!(
+            output,
+            pa.play_file_once(&args.path, args.sink()?, args.volume)?
+        ),
+        Record(args) => {
+            let stream = match args.monitor_of()? {
+                Some(sink) => pa.record_sink_monitor(sink, args.raw_args.spec())?,
+                None => pa.open_recording(args.raw_args.options(args.device))?,
+            };
+            let mut stdout = io::stdout().lock();
+            while let Some(chunk) = stream.read() {
+                stdout.write_all(&chunk)?;
+            }
         }
-        MoveSourceOutput(args) => json_print!(pa.move_source_output(args.from_id(), args.to_id())?),
-        KillSourceOutput(args) => json_print!(pa.kill_source_output((&args).into())?),
 
         Subscribe(args) => {
-            let mask = if args.kinds.is_empty() {
+            let filter = args.filter();
+            let bounds = args.bounds();
+            let file = match args.output {
+                Some(ref path) => Some(RotatingFile::open(path, args.rotation())?),
+                None => None,
+            };
+
+            let mut mask = if args.kinds.is_empty() {
                 PAMask::ALL
             } else {
-                let mut mask = PAMask::empty();
-                for kind in args.kinds {
-                    mask.insert(match kind {
-                        Kind::Cards => PAMask::CARD,
-                        Kind::Clients => PAMask::CLIENT,
-                        Kind::Modules => PAMask::MODULE,
-                        Kind::Samples => PAMask::SAMPLE_CACHE,
-                        Kind::Sinks => PAMask::SINK,
-                        Kind::SinkInputs => PAMask::SINK_INPUT,
-                        Kind::Sources => PAMask::SOURCE,
-                        Kind::SourceOutputs => PAMask::SOURCE_OUTPUT,
-                    });
-                }
-
-                mask
+                args.kinds
+                    .into_iter()
+                    .fold(PAMask::empty(), |mask, kind| mask | kind.into())
             };
 
-            subscribe::subscribe(pa, mask)?;
+            // idle detection is derived from sink events
+            if args.idle_timeout.is_some() {
+                mask.insert(PAMask::SINK);
+            }
+
+            subscribe::subscribe(
+                pa,
+                mask,
+                filter,
+                args.idle_timeout,
+                args.heartbeat,
+                bounds,
+                file,
+            )?;
+        }
+        Prefer(args) => prefer::prefer(&pa, args.into())?,
+        CardProfiles(args) => card_profiles::card_profiles(&pa, &args.rules)?,
+        SleepTimer(args) => {
+            op_print!(
+                output,
+                sleep_timer::sleep_timer(&pa, args.duration, args.sink_id()?)?
+            )
         }
     };
 
@@ -166,13 +416,19 @@ fn run() -> Result<(), Box<dyn Error>> {
 }
 
 fn main() {
-    if let Err(e) = run() {
-        println!(
-            "{}",
-            serde_json::to_string(&OperationResult::Failure {
-                error: e.to_string(),
-            })
-            .unwrap_or_else(|e| format!("Failed to serialize error: {}", e))
-        );
+    let matches = Cli::command().get_matches();
+    let attempt = Attempt::new(&matches);
+    let args = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let output = Output::new(attempt, &args);
+    logger::init(args.verbose);
+    log::info!("running {:?}", args.command);
+
+    if let Err(e) = run(args, &output) {
+        if let Err(e) = output.error(e) {
+            eprintln!("Failed to serialize error: {}", e);
+        }
     }
+
+    log::info!("exiting with code {}", output.exit_code());
+    process::exit(output.exit_code());
 }
@@ -0,0 +1,22 @@
+use std::error::Error;
+use std::io::{self, Write};
+
+use pulser::simple::PulseAudio;
+use serde::Serialize;
+
+/// Prints the peak level of each stream which is playing as a line of JSON, until it's interrupted
+/// or the streams have finished. With `per_app`, the streams of each application are grouped.
+pub fn meter(pa: &PulseAudio, per_app: bool) -> Result<(), Box<dyn Error>> {
+    match per_app {
+        true => pa.monitor_app_levels(|levels| print_line(levels)),
+        false => pa.monitor_stream_levels(|levels| print_line(levels)),
+    }
+}
+
+/// Returns whether the line was printed, so metering stops once stdout is closed
+fn print_line<T: Serialize>(levels: &[T]) -> bool {
+    match serde_json::to_string(levels) {
+        Ok(json) => writeln!(io::stdout(), "{}", json).is_ok(),
+        Err(_) => false,
+    }
+}
@@ -0,0 +1,96 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::process::Command;
+
+use pulser::api::{PAEvent, PAIdent, PAMask};
+use pulser::simple::PulseAudio;
+use serde::Serialize;
+
+use crate::json_print;
+
+#[derive(Debug, Serialize)]
+struct State<'a> {
+    recording: bool,
+    /// The applications which are recording
+    apps: Vec<&'a str>,
+}
+
+/// Runs until it's interrupted, printing whether anything is recording whenever that changes, and
+/// running `on_start` when the first stream starts recording and `on_stop` when the last one stops.
+pub fn mic_indicator(
+    pa: &PulseAudio,
+    on_start: Option<&str>,
+    on_stop: Option<&str>,
+) -> Result<(), Box<dyn Error>> {
+    pa.with_subscription(PAMask::SOURCE_OUTPUT, |rx| {
+        // subscribed first, so no streams are missed between listing them and the first event
+        let mut streams = pa
+            .get_recording_streams()?
+            .into_iter()
+            .map(|so| (so.index, so.proplist.get_str("application.name")))
+            .collect::<BTreeMap<_, _>>();
+
+        print_state(&streams)?;
+        if !streams.is_empty() {
+            run_hook(on_start);
+        }
+
+        for ev in rx.iter() {
+            let was_recording = !streams.is_empty();
+            match ev {
+                PAEvent::RecordingStarted {
+                    id: PAIdent::Index(idx),
+                    app,
+                    ..
+                } => {
+                    streams.insert(idx, app);
+                }
+                PAEvent::RecordingStopped {
+                    id: PAIdent::Index(idx),
+                    ..
+                } => {
+                    streams.remove(&idx);
+                }
+                _ => continue,
+            }
+
+            if was_recording != !streams.is_empty() {
+                print_state(&streams)?;
+                run_hook(if was_recording { on_stop } else { on_start });
+            }
+        }
+
+        Err("The subscription ended".into())
+    })?
+}
+
+fn print_state(streams: &BTreeMap<u32, Option<String>>) -> Result<(), Box<dyn Error>> {
+    let mut apps = streams
+        .values()
+        .flatten()
+        .map(|s| s.as_str())
+        .collect::<Vec<_>>();
+    apps.sort_unstable();
+    apps.dedup();
+    json_print!(State {
+        recording: !streams.is_empty(),
+        apps,
+    });
+
+    Ok(())
+}
+
+/// Runs the hook with `sh`, waiting for it to finish. Failures are logged rather than stopping us.
+fn run_hook(cmd: Option<&str>) {
+    let cmd = match cmd {
+        Some(cmd) => cmd,
+        None => return,
+    };
+
+    log::info!("Running: {}", cmd);
+    match Command::new("sh").arg("-c").arg(cmd).status() {
+        Ok(status) if status.success() => {}
+        Ok(status) => log::warn!("Hook exited with {}: {}", status, cmd),
+        Err(e) => log::warn!("Failed to run hook: {}: {}", e, cmd),
+    }
+}
@@ -0,0 +1,124 @@
+use std::cell::Cell;
+use std::error::Error;
+
+use pulser::api::{ErrorKind, PAError};
+use pulser::simple::OperationResult;
+
+use crate::attempt::Attempt;
+use crate::cli::Cli;
+
+/// Exit codes, so that automation can tell which failures are worth retrying
+pub mod exit_code {
+    /// Any other failure (or a conflict)
+    pub const FAILURE: i32 = 1;
+    /// The object doesn't exist
+    pub const NOT_FOUND: i32 = 3;
+    /// The server denied access
+    pub const ACCESS_DENIED: i32 = 4;
+    /// Couldn't connect to the server, or the connection was lost
+    pub const DISCONNECTED: i32 = 5;
+    /// The server didn't respond in time
+    pub const TIMEOUT: i32 = 6;
+}
+
+/// Prints the results of operations, and keeps track of the exit code
+pub struct Output {
+    attempt: Attempt,
+    quiet: bool,
+    errors_to_stderr: bool,
+    exit_code: Cell<i32>,
+}
+
+impl Output {
+    pub fn new(attempt: Attempt, args: &Cli) -> Output {
+        Output {
+            attempt,
+            quiet: args.quiet,
+            errors_to_stderr: args.errors_to_stderr,
+            exit_code: Cell::new(0),
+        }
+    }
+
+    /// Prints the result of an operation, along with what was attempted
+    pub fn result(&self, result: OperationResult) -> Result<(), serde_json::Error> {
+        self.result_with(&self.attempt, result)
+    }
+
+    /// Prints the result of an operation on one of many objects, such as each module unloaded by
+    /// `unload-module --all-matching`
+    pub fn result_for(
+        &self,
+        target: impl Into<String>,
+        result: OperationResult,
+    ) -> Result<(), serde_json::Error> {
+        self.result_with(&self.attempt.with_target(target), result)
+    }
+
+    fn result_with(
+        &self,
+        attempt: &Attempt,
+        result: OperationResult,
+    ) -> Result<(), serde_json::Error> {
+        let exit_code = match (&result, result.error_kind()) {
+            (result, _) if result.is_success() => 0,
+            (_, Some(ErrorKind::NotFound)) => exit_code::NOT_FOUND,
+            (_, Some(ErrorKind::AccessDenied)) => exit_code::ACCESS_DENIED,
+            (_, Some(ErrorKind::Disconnected)) => exit_code::DISCONNECTED,
+            (_, Some(ErrorKind::Timeout)) => exit_code::TIMEOUT,
+            (_, _) => exit_code::FAILURE,
+        };
+
+        self.print(attempt, result, exit_code)
+    }
+
+    /// Prints an error which stopped the command from running
+    pub fn error(&self, e: Box<dyn Error>) -> Result<(), serde_json::Error> {
+        let e = match e.downcast::<OperationResult>() {
+            Ok(result) => return self.result(*result),
+            Err(e) => e,
+        };
+
+        let exit_code = match e.is::<PAError>() {
+            true => exit_code::DISCONNECTED,
+            false => exit_code::FAILURE,
+        };
+
+        self.print(
+            &self.attempt,
+            OperationResult::Failure {
+                error: e.to_string(),
+                code: None,
+                channel_count: None,
+            },
+            exit_code,
+        )
+    }
+
+    fn print(
+        &self,
+        attempt: &Attempt,
+        result: OperationResult,
+        exit_code: i32,
+    ) -> Result<(), serde_json::Error> {
+        let failed = exit_code != 0;
+        if failed && self.exit_code.get() == 0 {
+            self.exit_code.set(exit_code);
+        } else if !failed && self.quiet {
+            return Ok(());
+        }
+
+        let json = serde_json::to_string(&attempt.result(result))?;
+        if failed && self.errors_to_stderr {
+            eprintln!("{}", json);
+        } else {
+            println!("{}", json);
+        }
+
+        Ok(())
+    }
+
+    /// The exit code of the first operation which failed, or `0` if none did
+    pub fn exit_code(&self) -> i32 {
+        self.exit_code.get()
+    }
+}
@@ -0,0 +1,232 @@
+use std::error::Error;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::os::fd::AsRawFd;
+
+use pulser::simple::PulseAudio;
+
+use crate::cli::PickKind;
+use crate::style::{Color, Style};
+
+/// How many matches to show at once
+const MAX_ROWS: usize = 10;
+
+/// Clears from the cursor to the end of the screen
+const CLEAR_BELOW: &str = "\r\x1b[J";
+
+/// A device which can be picked
+struct Candidate {
+    name: String,
+    description: String,
+}
+
+/// Shows an interactive list of sinks or sources which can be narrowed down by typing (matching
+/// both descriptions and names), and returns the name of the one which was picked. Returns `None`
+/// if the picker was cancelled.
+///
+/// The list is drawn on the terminal rather than stdout, so the output can still be piped.
+pub fn pick(
+    pa: &PulseAudio,
+    style: Style,
+    kind: PickKind,
+) -> Result<Option<String>, Box<dyn Error>> {
+    let candidates = match kind {
+        PickKind::Sink => pa
+            .get_sink_info_list()?
+            .into_iter()
+            .map(|sink| (sink.name, sink.description))
+            .collect::<Vec<_>>(),
+        PickKind::Source => pa
+            .get_source_info_list()?
+            .into_iter()
+            .map(|source| (source.name, source.description))
+            .collect::<Vec<_>>(),
+    };
+    let candidates = candidates
+        .into_iter()
+        .filter_map(|(name, description)| {
+            let name = name?;
+            Some(Candidate {
+                description: description.unwrap_or_else(|| name.clone()),
+                name,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let mut tty = Terminal::open()?;
+    let mut query = String::new();
+    let mut selected = 0;
+    loop {
+        let matches = matches(&candidates, &query);
+        selected = selected.min(matches.len().saturating_sub(1));
+        tty.draw(style, &query, &matches, selected)?;
+
+        match tty.read_key()? {
+            Key::Char(c) => {
+                query.push(c);
+                selected = 0;
+            }
+            Key::Backspace => {
+                query.pop();
+                selected = 0;
+            }
+            Key::Up => selected = selected.saturating_sub(1),
+            Key::Down => selected += 1,
+            Key::Enter => return Ok(matches.get(selected).map(|c| c.name.clone())),
+            Key::Cancel => return Ok(None),
+            Key::Other => {}
+        }
+    }
+}
+
+/// The candidates which match the query, best match first
+fn matches<'a>(candidates: &'a [Candidate], query: &str) -> Vec<&'a Candidate> {
+    let mut scored = candidates
+        .iter()
+        .filter_map(|c| {
+            let description = fuzzy_score(query, &c.description);
+            let name = fuzzy_score(query, &c.name);
+            description.max(name).map(|score| (score, c))
+        })
+        .collect::<Vec<_>>();
+
+    // stable, so equally good matches stay in the server's order
+    scored.sort_by(|(a, _), (b, _)| b.cmp(a));
+    scored.into_iter().map(|(_, c)| c).collect()
+}
+
+/// Scores how well the query matches the text, if all of its characters appear in order
+/// (case-insensitively). Consecutive characters and characters at the start of words score higher,
+/// so "hdmi" prefers "HDMI Output" over "Headphones (Dual Mic)".
+fn fuzzy_score(query: &str, text: &str) -> Option<i64> {
+    let text = text.chars().collect::<Vec<_>>();
+    let mut score = 0;
+    let mut pos = 0;
+    let mut last_match: Option<usize> = None;
+    for q in query.chars().filter(|c| !c.is_whitespace()) {
+        let found = text[pos..]
+            .iter()
+            .position(|c| c.to_lowercase().eq(q.to_lowercase()))?;
+        let idx = pos + found;
+
+        score += 1;
+        if last_match.map_or(false, |last| last + 1 == idx) {
+            score += 5;
+        }
+        if idx == 0 || !text[idx - 1].is_alphanumeric() {
+            score += 3;
+        }
+        // prefer matches which start earlier
+        score -= found as i64 / 4;
+
+        last_match = Some(idx);
+        pos = idx + 1;
+    }
+
+    Some(score)
+}
+
+enum Key {
+    Char(char),
+    Backspace,
+    Up,
+    Down,
+    Enter,
+    Cancel,
+    Other,
+}
+
+/// The controlling terminal, in raw mode until it's dropped
+struct Terminal {
+    tty: File,
+    original: libc::termios,
+    /// How many lines were drawn below the prompt last time
+    drawn: usize,
+}
+
+impl Terminal {
+    fn open() -> io::Result<Terminal> {
+        let tty = OpenOptions::new().read(true).write(true).open("/dev/tty")?;
+
+        let fd = tty.as_raw_fd();
+        // SAFETY: termios is a plain C struct which is filled in by tcgetattr
+        let mut original = unsafe { std::mem::zeroed::<libc::termios>() };
+        if unsafe { libc::tcgetattr(fd, &mut original) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut raw = original;
+        unsafe { libc::cfmakeraw(&mut raw) };
+        // keep translating "\n" into "\r\n" when writing
+        raw.c_oflag |= libc::OPOST;
+        if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &raw) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(Terminal {
+            tty,
+            original,
+            drawn: 0,
+        })
+    }
+
+    fn draw(
+        &mut self,
+        style: Style,
+        query: &str,
+        matches: &[&Candidate],
+        selected: usize,
+    ) -> io::Result<()> {
+        let mut frame = String::from(CLEAR_BELOW);
+        frame.push_str(&format!("> {}", query));
+
+        let start = selected.saturating_sub(MAX_ROWS - 1);
+        let rows = matches.iter().enumerate().skip(start).take(MAX_ROWS);
+        self.drawn = 0;
+        for (i, candidate) in rows {
+            let marker = if i == selected { ">" } else { " " };
+            let name = style.paint(Color::Dim, &candidate.name);
+            frame.push_str(&format!("\n{} {}  {}", marker, candidate.description, name));
+            self.drawn += 1;
+        }
+
+        // move back up to the end of the prompt line, where the cursor belongs
+        if self.drawn > 0 {
+            frame.push_str(&format!("\x1b[{}A", self.drawn));
+        }
+        frame.push_str(&format!("\r\x1b[{}C", query.chars().count() + 2));
+
+        self.tty.write_all(frame.as_bytes())?;
+        self.tty.flush()
+    }
+
+    fn read_key(&mut self) -> io::Result<Key> {
+        // escape sequences (such as the arrow keys) arrive in a single read
+        let mut buf = [0; 8];
+        let n = self.tty.read(&mut buf)?;
+        Ok(match &buf[..n] {
+            [] => Key::Cancel,
+            b"\x1b[A" | [0x10] => Key::Up,
+            b"\x1b[B" | [0x0e] => Key::Down,
+            // Escape, Ctrl-C, Ctrl-D and Ctrl-G
+            [0x1b] | [0x03] | [0x04] | [0x07] => Key::Cancel,
+            [b'\r'] | [b'\n'] => Key::Enter,
+            [0x7f] | [0x08] => Key::Backspace,
+            bytes => match std::str::from_utf8(bytes) {
+                Ok(s) => s
+                    .chars()
+                    .next()
+                    .filter(|c| !c.is_control())
+                    .map_or(Key::Other, Key::Char),
+                Err(_) => Key::Other,
+            },
+        })
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let _ = self.tty.write_all(CLEAR_BELOW.as_bytes());
+        unsafe { libc::tcsetattr(self.tty.as_raw_fd(), libc::TCSANOW, &self.original) };
+    }
+}
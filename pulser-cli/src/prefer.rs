@@ -0,0 +1,45 @@
+use std::error::Error;
+
+use pulser::api::{PAEvent, PAIdent, PAMask, SinkPreference};
+use pulser::simple::PulseAudio;
+use serde::Serialize;
+
+use crate::json_print;
+
+/// Printed each time the default sink is switched
+#[derive(Debug, Serialize)]
+struct Switched {
+    /// The default sink which disappeared
+    from: PAIdent,
+    /// The preferred sink which is now the default
+    to: Option<String>,
+}
+
+/// Waits for the default sink to disappear, and then switches to the most preferred available sink.
+/// Runs until it's interrupted.
+pub fn prefer(pa: &PulseAudio, pref: SinkPreference) -> Result<(), Box<dyn Error>> {
+    for ev in pa.events(PAMask::SERVER)? {
+        let old = match ev {
+            PAEvent::DefaultSinkChanged { old: Some(old), .. } => old,
+            _ => continue,
+        };
+
+        // only step in if the server picked a new default because the old one went away
+        let exists = pa.get_sink_info_list()?.iter().any(|sink| match old {
+            PAIdent::Index(idx) => sink.index == idx,
+            PAIdent::Name(ref name) => sink.name.as_ref() == Some(name),
+        });
+        if exists {
+            continue;
+        }
+
+        if let Some(sink) = pa.apply_sink_preference(&pref)? {
+            json_print!(Switched {
+                from: old,
+                to: sink.name,
+            });
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,91 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// When to rotate a `RotatingFile`
+#[derive(Debug, Clone, Copy)]
+pub struct Rotation {
+    /// Rotate once the file is at least this many bytes
+    pub max_size: Option<u64>,
+    /// Rotate once the file has been open for this long
+    pub max_age: Option<Duration>,
+    /// How many rotated files to keep, named `<path>.1` (the newest) to `<path>.<keep>`
+    pub keep: usize,
+}
+
+/// A file which lines are appended to, which rotates itself rather than relying on an external tool
+/// (such as logrotate, which can't safely rotate a file that's being written to by a pipe).
+pub struct RotatingFile {
+    path: PathBuf,
+    rotation: Rotation,
+    file: File,
+    size: u64,
+    opened: Instant,
+}
+
+impl RotatingFile {
+    pub fn open(path: impl AsRef<Path>, rotation: Rotation) -> io::Result<RotatingFile> {
+        let path = path.as_ref().to_path_buf();
+        let (file, size) = Self::open_file(&path)?;
+        Ok(RotatingFile {
+            path,
+            rotation,
+            file,
+            size,
+            opened: Instant::now(),
+        })
+    }
+
+    fn open_file(path: &Path) -> io::Result<(File, u64)> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let size = file.metadata()?.len();
+        Ok((file, size))
+    }
+
+    /// Appends a line to the file (rotating it first, if required), and flushes it to disk
+    pub fn write_line(&mut self, line: &str) -> io::Result<()> {
+        if self.should_rotate() {
+            self.rotate()?;
+        }
+
+        // write the line in a single call, so readers never see partial lines
+        let line = format!("{}\n", line);
+        self.file.write_all(line.as_bytes())?;
+        self.file.sync_data()?;
+        self.size += line.len() as u64;
+
+        Ok(())
+    }
+
+    fn should_rotate(&self) -> bool {
+        let too_big = matches!(self.rotation.max_size, Some(max) if self.size >= max);
+        let too_old = matches!(self.rotation.max_age, Some(max) if self.opened.elapsed() >= max);
+        self.size > 0 && (too_big || too_old)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        let rotated = |n: usize| {
+            let mut name = self.path.clone().into_os_string();
+            name.push(format!(".{}", n));
+            PathBuf::from(name)
+        };
+
+        if self.rotation.keep == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            // shift `<path>.N` to `<path>.N+1`, dropping the oldest
+            for n in (1..self.rotation.keep).rev() {
+                match fs::rename(rotated(n), rotated(n + 1)) {
+                    Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                    _ => {}
+                }
+            }
+            fs::rename(&self.path, rotated(1))?;
+        }
+
+        (self.file, self.size) = Self::open_file(&self.path)?;
+        self.opened = Instant::now();
+        Ok(())
+    }
+}
@@ -0,0 +1,80 @@
+use std::error::Error;
+
+use clap::ValueEnum;
+use pulser::api::{PAIdent, VolumeSpec};
+use pulser::simple::{OperationResult, PulseAudio};
+
+use crate::cli::{vol_from_str, Bool, ObjectKind, Property};
+
+fn parse_bool(values: &[String]) -> Result<bool, Box<dyn Error>> {
+    match values {
+        [value] => Ok(Bool::from_str(value, false)?.into()),
+        _ => Err("Expected a single value: yes, no, on, off, true or false".into()),
+    }
+}
+
+fn parse_string(values: &[String]) -> Result<String, Box<dyn Error>> {
+    match values {
+        [value] => Ok(value.clone()),
+        _ => Err("Expected a single value".into()),
+    }
+}
+
+fn parse_volume(values: &[String]) -> Result<VolumeSpec, Box<dyn Error>> {
+    let volumes = values
+        .iter()
+        .map(|v| vol_from_str(v))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    match volumes.len() {
+        0 => Err("Expected at least one volume".into()),
+        1 => Ok(VolumeSpec::All(volumes[0])),
+        _ => Ok(VolumeSpec::Channels(volumes)),
+    }
+}
+
+/// Sets a property of an object, parsing the values the same way the typed subcommands do.
+pub fn set(
+    pa: &PulseAudio,
+    kind: ObjectKind,
+    id: PAIdent,
+    property: Property,
+    values: &[String],
+) -> Result<OperationResult, Box<dyn Error>> {
+    use ObjectKind::*;
+
+    Ok(match (property, kind) {
+        (Property::Volume, Sink) => pa.set_sink_volume(id, parse_volume(values)?)?,
+        (Property::Volume, Source) => pa.set_source_volume(id, parse_volume(values)?)?,
+        (Property::Volume, SinkInput) => pa.set_sink_input_volume(id, parse_volume(values)?)?,
+        (Property::Volume, SourceOutput) => {
+            pa.set_source_output_volume(id, parse_volume(values)?)?
+        }
+
+        (Property::Mute, Sink) => pa.set_sink_mute(id, parse_bool(values)?)?,
+        (Property::Mute, Source) => pa.set_source_mute(id, parse_bool(values)?)?,
+        (Property::Mute, SinkInput) => pa.set_sink_input_mute(id, parse_bool(values)?)?,
+        (Property::Mute, SourceOutput) => pa.set_source_output_mute(id, parse_bool(values)?)?,
+
+        (Property::Port, Sink) => pa.set_sink_port(id, parse_string(values)?)?,
+        (Property::Port, Source) => pa.set_source_port(id, parse_string(values)?)?,
+
+        (Property::Profile, Card) => pa.set_card_profile(id, parse_string(values)?)?,
+
+        // the default can only be changed by choosing another object, so there's no value to parse
+        (Property::Default, Sink) if values.is_empty() => pa.set_default_sink(id)?,
+        (Property::Default, Source) if values.is_empty() => pa.set_default_source(id)?,
+        (Property::Default, Sink | Source) => {
+            return Err("The default property does not take a value".into())
+        }
+
+        (property, kind) => {
+            return Err(format!(
+                "Cannot set {} of {}",
+                property.to_possible_value().unwrap().get_name(),
+                kind.to_possible_value().unwrap().get_name()
+            )
+            .into())
+        }
+    })
+}
@@ -0,0 +1,45 @@
+use std::error::Error;
+use std::thread;
+use std::time::Duration;
+
+use pulser::api::{FadeCurve, PAIdent, PAVol, VolumeSpec};
+use pulser::simple::{OperationResult, PulseAudio};
+
+/// How long before the timer ends the fade out starts
+const FADE_DURATION: Duration = Duration::from_secs(60);
+
+/// Waits for `duration`, fading out the sink over the last minute, and then suspends it.
+/// The sink's volume is restored after it's suspended, so it's not silent the next time it's used.
+pub fn sleep_timer(
+    pa: &PulseAudio,
+    duration: Duration,
+    sink: Option<PAIdent>,
+) -> Result<OperationResult, Box<dyn Error>> {
+    let sink = match sink {
+        Some(sink) => sink,
+        None => pa.get_default_sink()?.ok_or("No default sink is set")?,
+    };
+
+    let fade_duration = duration.min(FADE_DURATION);
+    thread::sleep(duration - fade_duration);
+
+    let volume = pa.get_sink_volume(sink.clone())?;
+    let fade_id = pa.fade_sink_volume(
+        sink.clone(),
+        VolumeSpec::All(PAVol::Value(0)),
+        fade_duration,
+        FadeCurve::Exponential,
+    )?;
+
+    match pa.wait_fade(fade_id)? {
+        OperationResult::Success => {}
+        failure => return Ok(failure),
+    }
+
+    match pa.suspend_sink(sink.clone(), true)? {
+        OperationResult::Success => {}
+        failure => return Ok(failure),
+    }
+
+    pa.set_sink_volume(sink, (&volume).into())
+}
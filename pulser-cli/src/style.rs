@@ -0,0 +1,94 @@
+use std::env;
+use std::io::{self, IsTerminal};
+
+use pulser::api::PAVolume;
+
+use crate::cli::ColorChoice;
+
+const RESET: &str = "\x1b[0m";
+
+#[derive(Debug, Copy, Clone)]
+pub enum Color {
+    Red,
+    Green,
+    Yellow,
+    Dim,
+}
+
+impl Color {
+    fn code(&self) -> &'static str {
+        match self {
+            Color::Red => "\x1b[31m",
+            Color::Green => "\x1b[32m",
+            Color::Yellow => "\x1b[33m",
+            Color::Dim => "\x1b[2m",
+        }
+    }
+}
+
+/// Colors the human-readable output (such as `describe` and `top`), never JSON
+#[derive(Debug, Copy, Clone)]
+pub struct Style {
+    color: bool,
+}
+
+impl Style {
+    /// With `auto`, colors are only used when stdout is a terminal and `NO_COLOR` isn't set
+    /// (see https://no-color.org)
+    pub fn new(choice: ColorChoice) -> Style {
+        let color = match choice {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                io::stdout().is_terminal() && env::var_os("NO_COLOR").map_or(true, |v| v.is_empty())
+            }
+        };
+
+        Style { color }
+    }
+
+    pub fn paint(&self, color: Color, s: impl AsRef<str>) -> String {
+        match self.color {
+            true => format!("{}{}{}", color.code(), s.as_ref(), RESET),
+            false => s.as_ref().to_string(),
+        }
+    }
+
+    /// Muted is red, so it stands out
+    pub fn mute(&self, s: impl AsRef<str>, muted: bool) -> String {
+        match muted {
+            true => self.paint(Color::Red, s),
+            false => s.as_ref().to_string(),
+        }
+    }
+
+    /// Red when amplified (over 100%), dim when silent or muted
+    pub fn volume(&self, s: impl AsRef<str>, percentage: f64, muted: bool) -> String {
+        if muted || percentage <= 0.0 {
+            self.paint(Color::Dim, s)
+        } else if percentage > 100.0 {
+            self.paint(Color::Red, s)
+        } else {
+            s.as_ref().to_string()
+        }
+    }
+
+    /// Green when available, red when not, and unchanged when unknown
+    pub fn available(&self, s: impl AsRef<str>, available: Option<bool>) -> String {
+        match available {
+            Some(true) => self.paint(Color::Green, s),
+            Some(false) => self.paint(Color::Red, s),
+            None => s.as_ref().to_string(),
+        }
+    }
+}
+
+/// Formats a volume as a percentage and in decibels, such as "65% / -11.2 dB"
+pub fn volume(volume: &PAVolume) -> String {
+    format!("{} / {:.1} dB", volume, volume.decibels())
+}
+
+/// Formats a latency (in microseconds) in milliseconds, such as "23.4 ms"
+pub fn latency(usec: u64) -> String {
+    format!("{:.1} ms", usec as f64 / 1000.0)
+}
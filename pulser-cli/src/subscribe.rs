@@ -1,40 +1,108 @@
+use std::collections::VecDeque;
 use std::error::Error;
 use std::fmt::Debug;
 use std::io::ErrorKind;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::SendError;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
 
 use mio::{Events, Interest, Poll, Token, Waker};
-use mio_misc::queue::NotificationQueue;
-use mio_misc::NotificationId;
-use pulser::api::{PAEvent, PAMask};
+use pulser::api::{EventFilter, PAEvent, PAMask};
 use pulser::sender::EventSender;
 use pulser::simple::PulseAudio;
 use signal_hook::consts::signal::*;
 use signal_hook_mio::v0_8::Signals;
 
+use crate::cli::Overflow;
 use crate::json_print;
+use crate::rotate::RotatingFile;
+use crate::systemd::Notifier;
 
-// wrap up `mio_misc`'s sender so we can `impl EventSender` for it
-struct Sender(mio_misc::channel::Sender<PAEvent>);
+/// The events which haven't been written yet. The mainloop's thread pushes them, and wakes us up
+/// to take them.
+// TODO: if events are ever served to clients (such as a daemon's `/events`), number them and keep
+// the last N written in a ring buffer, so a client which reconnects can ask for everything since
+// the last number it saw rather than missing changes (such as of the default sink).
+struct Queue {
+    events: Mutex<VecDeque<PAEvent>>,
+    /// Notified when events are taken, for a sender blocked on a full queue
+    taken: Condvar,
+    waker: Waker,
+    /// The most events which may wait, and what to do when there are more
+    bounds: Option<(usize, Overflow)>,
+    /// Set once we stop taking events
+    closed: AtomicBool,
+    /// Set when the queue filled up with `Overflow::Exit`
+    overflowed: AtomicBool,
+}
+
+impl Queue {
+    fn take(&self) -> VecDeque<PAEvent> {
+        let events = std::mem::take(&mut *self.events.lock().unwrap());
+        self.taken.notify_all();
+        events
+    }
+
+    fn wake(&self) {
+        // this should only occur if there's an IO error in `mio`'s `Waker`
+        if let Err(e) = self.waker.wake() {
+            panic!("An underlying error occurred: {}", e);
+        }
+    }
+
+    fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+        self.taken.notify_all();
+    }
+}
+
+struct Sender(Arc<Queue>);
 
 impl Debug for Sender {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str("Sender(mio_misc::channel::Sender<PAEvent>)")
+        f.write_str("Sender(Queue)")
     }
 }
 
 impl EventSender for Sender {
-    fn send(&self, ev: PAEvent) -> Result<(), std::sync::mpsc::SendError<PAEvent>> {
-        self.0.send(ev).map_err(|e| match e {
-            // map to type expected from `EventSender` trait
-            mio_misc::channel::SendError::Disconnected(ev) => std::sync::mpsc::SendError(ev),
-            // we use `NotificationQueue` which is an unbounded queue
-            mio_misc::channel::SendError::NotificationQueueFull => unreachable!(),
-            // this should only occur if there's an IO error in `mio`'s `Waker`
-            mio_misc::channel::SendError::Io(e) => {
-                panic!("An underlying error occurred: {}", e)
+    fn send(&self, ev: PAEvent) -> Result<(), SendError<PAEvent>> {
+        let queue = &self.0;
+        let mut events = queue.events.lock().unwrap();
+        if let Some((capacity, overflow)) = queue.bounds {
+            while events.len() >= capacity && !queue.closed.load(Ordering::SeqCst) {
+                match overflow {
+                    Overflow::DropOldest => {
+                        events.pop_front();
+                    }
+                    Overflow::Block => events = queue.taken.wait(events).unwrap(),
+                    Overflow::Exit => {
+                        queue.overflowed.store(true, Ordering::SeqCst);
+                        break;
+                    }
+                }
             }
-        })
+        }
+
+        // returning an error ends the subscription
+        if queue.closed.load(Ordering::SeqCst) || queue.overflowed.load(Ordering::SeqCst) {
+            queue.wake();
+            return Err(SendError(ev));
+        }
+
+        events.push_back(ev);
+        drop(events);
+        queue.wake();
+        Ok(())
+    }
+}
+
+/// Closes the queue when we stop taking events, so a blocked sender doesn't wait forever
+struct Closing(Arc<Queue>);
+
+impl Drop for Closing {
+    fn drop(&mut self) {
+        self.0.close();
     }
 }
 
@@ -47,29 +115,65 @@ macro_rules! token {
     };
 }
 
-pub fn subscribe(pa: PulseAudio, mask: PAMask) -> Result<(), Box<dyn Error>> {
+pub fn subscribe(
+    pa: PulseAudio,
+    mask: PAMask,
+    filter: Option<EventFilter>,
+    idle_timeout: Option<Duration>,
+    heartbeat: Option<Duration>,
+    bounds: Option<(usize, Overflow)>,
+    mut output: Option<RotatingFile>,
+) -> Result<(), Box<dyn Error>> {
     let mut poll = Poll::new()?;
+    let mut systemd = Notifier::from_env();
+    // the watchdog is only petted while the server responds to the heartbeat, so a wedged server
+    // gets us restarted
+    let heartbeat = heartbeat.or(systemd.watchdog_interval());
+    let mut responsive = true;
 
-    // setup a channel that will land notifications in a wake-able queue each time a message is sent
-    // then use this channel for subscribing to PulseAudio events
-    let (pa_event_queue, rx) = {
-        let waker = Arc::new(Waker::new(poll.registry(), token!(PA_EVENT)).unwrap());
-        let queue = Arc::new(NotificationQueue::new(waker));
-        let (tx, rx) = mio_misc::channel::channel(queue.clone(), NotificationId::gen_next());
-        pa.subscribe(mask, Box::new(Sender(tx)))?;
+    // setup a queue which wakes us up each time an event is sent, then use it for subscribing to
+    // PulseAudio events
+    let queue = {
+        let queue = Arc::new(Queue {
+            events: Mutex::default(),
+            taken: Condvar::new(),
+            waker: Waker::new(poll.registry(), token!(PA_EVENT))?,
+            bounds,
+            closed: AtomicBool::new(false),
+            overflowed: AtomicBool::new(false),
+        });
+        let tx = Box::new(Sender(queue.clone()));
+        // filtering happens before the event is sent, so filtered events don't wake us up
+        match filter {
+            Some(filter) => pa.subscribe_filtered(mask, filter, tx)?,
+            None => pa.subscribe(mask, tx)?,
+        };
+        if idle_timeout.is_some() {
+            pa.set_idle_timeout(idle_timeout)?;
+        }
+        if heartbeat.is_some() {
+            pa.set_heartbeat(heartbeat)?;
+        }
 
-        (queue, rx)
+        Closing(queue)
     };
+    systemd.ready();
 
-    // register to receive wakeups for received signals
-    let mut signals = Signals::new(&[SIGINT, SIGTERM])?;
+    // register to receive wakeups for received signals; SIGHUP is caught so that a reload (such as
+    // `systemctl reload`) doesn't end the subscription
+    let mut signals = Signals::new(&[SIGINT, SIGTERM, SIGHUP])?;
     poll.registry()
         .register(&mut signals, token!(SIGNALS), Interest::READABLE)?;
 
     // setup and start our event loop
     let mut events = Events::with_capacity(128);
     'outer: loop {
-        match poll.poll(&mut events, None) {
+        // while the server is unresponsive, only its events need to wake us up
+        let timeout = match responsive {
+            true => systemd.watchdog_due_in(),
+            false => None,
+        };
+        match poll.poll(&mut events, timeout) {
             Err(e) if e.kind() == ErrorKind::Interrupted => {
                 // We get interrupt when a signal happens inside poll. That's non-fatal, just retry.
                 events.clear();
@@ -81,20 +185,38 @@ pub fn subscribe(pa: PulseAudio, mask: PAMask) -> Result<(), Box<dyn Error>> {
         for event in events.iter() {
             match event.token() {
                 token!(PA_EVENT) => {
-                    // mio_extra's channel integration uses a queue to notify us of events - each notification
-                    // corresponds to a sent event, so we must take care to drain those notifications
-                    while let Some(_) = pa_event_queue.pop() {
-                        let ev = rx
-                            .try_recv()
-                            .expect("Channel notification count != channel item count");
-
-                        json_print!(ev);
+                    // wakeups may be merged, so take every event which is waiting
+                    for ev in queue.0.take() {
+                        match ev {
+                            PAEvent::ServerUnresponsive { .. } => {
+                                responsive = false;
+                                systemd.status("The server isn't responding");
+                            }
+                            PAEvent::ServerResponsive => {
+                                responsive = true;
+                                systemd.status("");
+                            }
+                            _ => {}
+                        }
+
+                        match output {
+                            Some(ref mut file) => file.write_line(&serde_json::to_string(&ev)?)?,
+                            None => json_print!(ev),
+                        }
+                    }
+
+                    if queue.0.overflowed.load(Ordering::SeqCst) {
+                        return Err("Too many events were waiting to be written".into());
                     }
                 }
                 token!(SIGNALS) => {
                     for signal in signals.pending() {
                         match signal {
                             SIGINT | SIGTERM => break 'outer,
+                            // TODO: there's no config file yet, only arguments. Once there is (with
+                            // rules, aliases or scenes), re-read it here and swap it in whole, keeping
+                            // the connection and the subscription.
+                            SIGHUP => log::info!("Received SIGHUP, but there's nothing to reload"),
                             n => unreachable!("Received unexpected signal event in loop: {}", n),
                         }
                     }
@@ -102,7 +224,12 @@ pub fn subscribe(pa: PulseAudio, mask: PAMask) -> Result<(), Box<dyn Error>> {
                 token => unreachable!("Unknown token with id: {}", token.0),
             }
         }
+
+        if responsive {
+            systemd.pet_watchdog();
+        }
     }
 
+    systemd.stopping();
     Ok(())
 }
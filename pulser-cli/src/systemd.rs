@@ -0,0 +1,109 @@
+#[cfg(target_os = "linux")]
+use std::os::linux::net::SocketAddrExt;
+use std::os::unix::net::{SocketAddr, UnixDatagram};
+use std::time::{Duration, Instant};
+use std::{env, io};
+
+/// Notifies systemd of our state when running as a `Type=notify` service, see `sd_notify(3)`.
+/// Does nothing when we weren't started by systemd (`NOTIFY_SOCKET` isn't set).
+///
+/// There aren't any listening sockets, so socket activation (`LISTEN_FDS`) isn't handled.
+pub struct Notifier {
+    socket: Option<(UnixDatagram, SocketAddr)>,
+    /// How often to pet the watchdog, which is half of `WatchdogSec=` so a late pet isn't fatal
+    watchdog: Option<Duration>,
+    /// When the watchdog was last petted
+    petted_at: Instant,
+}
+
+impl Notifier {
+    pub fn from_env() -> Notifier {
+        let socket = match env::var_os("NOTIFY_SOCKET") {
+            Some(path) => match connect(path.to_string_lossy().as_ref()) {
+                Ok(socket) => Some(socket),
+                Err(e) => {
+                    log::warn!("Failed to open the systemd notify socket: {}", e);
+                    None
+                }
+            },
+            None => None,
+        };
+
+        // the watchdog may be meant for another process, such as the shell which started us
+        let for_us = env::var("WATCHDOG_PID").map_or(true, |pid| {
+            pid.parse::<u32>().ok() == Some(std::process::id())
+        });
+        let watchdog = env::var("WATCHDOG_USEC")
+            .ok()
+            .and_then(|usec| usec.parse::<u64>().ok())
+            .filter(|usec| *usec > 0 && for_us && socket.is_some())
+            .map(|usec| Duration::from_micros(usec / 2));
+
+        Notifier {
+            socket,
+            watchdog,
+            petted_at: Instant::now(),
+        }
+    }
+
+    /// How often the watchdog must be petted, if systemd enabled it
+    pub fn watchdog_interval(&self) -> Option<Duration> {
+        self.watchdog
+    }
+
+    /// How long until the watchdog should be petted, if systemd enabled it
+    pub fn watchdog_due_in(&self) -> Option<Duration> {
+        self.watchdog
+            .map(|interval| interval.saturating_sub(self.petted_at.elapsed()))
+    }
+
+    /// Tells systemd that startup has finished
+    pub fn ready(&self) {
+        self.notify("READY=1");
+    }
+
+    /// Tells systemd that we're still alive, if the watchdog is due
+    pub fn pet_watchdog(&mut self) {
+        if self.watchdog_due_in() == Some(Duration::ZERO) {
+            self.notify("WATCHDOG=1");
+            self.petted_at = Instant::now();
+        }
+    }
+
+    /// Sets the status shown by `systemctl status`
+    pub fn status(&self, status: &str) {
+        self.notify(&format!("STATUS={}", status));
+    }
+
+    /// Tells systemd that we're shutting down
+    pub fn stopping(&self) {
+        self.notify("STOPPING=1");
+    }
+
+    fn notify(&self, state: &str) {
+        if let Some((socket, addr)) = &self.socket {
+            if let Err(e) = socket.send_to_addr(state.as_bytes(), addr) {
+                log::warn!("Failed to notify systemd ({}): {}", state, e);
+            }
+        }
+    }
+}
+
+/// Opens a socket for sending to `NOTIFY_SOCKET`, which is either a path or (when it starts with
+/// an `@`) the name of an abstract socket. Abstract sockets only exist on Linux.
+fn connect(path: &str) -> io::Result<(UnixDatagram, SocketAddr)> {
+    let addr = match path.strip_prefix('@') {
+        #[cfg(target_os = "linux")]
+        Some(name) => SocketAddr::from_abstract_name(name)?,
+        #[cfg(not(target_os = "linux"))]
+        Some(_) => {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "abstract sockets are only supported on Linux",
+            ))
+        }
+        None => SocketAddr::from_pathname(path)?,
+    };
+
+    Ok((UnixDatagram::unbound()?, addr))
+}
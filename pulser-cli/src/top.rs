@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::{self, Write};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::Duration;
+
+use pulser::api::{HasMute, HasVolume, PAMask, PAProplist, PAVolume};
+use pulser::simple::{OperationResult, PulseAudio};
+
+use crate::style::{self, Color, Style};
+
+/// How often to redraw without any events, since latencies change constantly
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Clears the terminal and moves the cursor to the top left
+const CLEAR: &str = "\x1b[2J\x1b[H";
+
+struct Row {
+    kind: &'static str,
+    index: u32,
+    app: String,
+    device: String,
+    /// The volume of the loudest channel
+    volume: Option<PAVolume>,
+    muted: bool,
+    /// Total latency in microseconds (the stream's buffer plus the device's)
+    latency: u64,
+    corked: bool,
+}
+
+/// Continuously shows the streams which are playing or recording, redrawing whenever something
+/// changes. Runs until it's interrupted.
+pub fn top(pa: &PulseAudio, style: Style) -> Result<(), Box<dyn Error>> {
+    let (tx, rx) = mpsc::channel();
+    let mask = PAMask::SINK | PAMask::SOURCE | PAMask::SINK_INPUT | PAMask::SOURCE_OUTPUT;
+    match pa.subscribe(mask, Box::new(tx))? {
+        OperationResult::Success => {}
+        result => return Err(result.into()),
+    }
+
+    loop {
+        draw(style, &rows(pa)?)?;
+
+        match rx.recv_timeout(REFRESH_INTERVAL) {
+            // redraw once for a burst of events
+            Ok(_) => while rx.try_recv().is_ok() {},
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+    }
+}
+
+fn rows(pa: &PulseAudio) -> Result<Vec<Row>, Box<dyn Error>> {
+    let sinks = pa
+        .get_sink_info_list()?
+        .into_iter()
+        .map(|sink| (sink.index, sink.name.unwrap_or_default()))
+        .collect::<HashMap<_, _>>();
+    let sources = pa
+        .get_source_info_list()?
+        .into_iter()
+        .map(|source| (source.index, source.name.unwrap_or_default()))
+        .collect::<HashMap<_, _>>();
+
+    let mut rows = vec![];
+    for input in pa.get_sink_input_info_list()? {
+        rows.push(Row {
+            kind: "playback",
+            index: input.index,
+            app: app_name(&input.proplist, &input.name),
+            device: sinks.get(&input.sink).cloned().unwrap_or_default(),
+            volume: loudest_volume(&input),
+            muted: input.mute(),
+            latency: input.buffer_usec + input.sink_usec,
+            corked: input.corked,
+        });
+    }
+    for output in pa.get_source_output_info_list()? {
+        rows.push(Row {
+            kind: "record",
+            index: output.index,
+            app: app_name(&output.proplist, &output.name),
+            device: sources.get(&output.source).cloned().unwrap_or_default(),
+            volume: loudest_volume(&output),
+            muted: output.mute(),
+            latency: output.buffer_usec + output.source_usec,
+            corked: output.corked,
+        });
+    }
+
+    Ok(rows)
+}
+
+fn app_name(proplist: &PAProplist, name: &Option<String>) -> String {
+    proplist
+        .get_str("application.name")
+        .or_else(|| name.clone())
+        .unwrap_or_default()
+}
+
+fn loudest_volume(info: &impl HasVolume) -> Option<PAVolume> {
+    info.volume_readings()
+        .iter()
+        .map(|r| r.volume())
+        .max_by(|a, b| a.value().cmp(&b.value()))
+}
+
+fn draw(style: Style, rows: &[Row]) -> io::Result<()> {
+    let mut out = io::stdout().lock();
+    write!(out, "{}", CLEAR)?;
+    writeln!(
+        out,
+        "{:<8} {:>5}  {:<24} {:<40} {:>15} {:>9}  STATE",
+        "KIND", "INDEX", "APP", "DEVICE", "VOLUME", "LATENCY"
+    )?;
+    for row in rows {
+        // pad before coloring, since the escape codes would count towards the width
+        let volume = format!(
+            "{:>15}",
+            row.volume.as_ref().map(style::volume).unwrap_or_default()
+        );
+        let percentage = row.volume.map_or(0.0, |v| v.percentage());
+        let state = match (row.corked, row.muted) {
+            (true, _) => style.paint(Color::Dim, "corked"),
+            (false, true) => style.mute("muted", true),
+            (false, false) => "running".to_string(),
+        };
+        writeln!(
+            out,
+            "{:<8} {:>5}  {:<24} {:<40} {} {:>9}  {}",
+            row.kind,
+            row.index,
+            truncate(&row.app, 24),
+            truncate(&row.device, 40),
+            style.volume(volume, percentage, row.muted),
+            style::latency(row.latency),
+            state
+        )?;
+    }
+
+    out.flush()
+}
+
+fn truncate(s: &str, width: usize) -> String {
+    match s.chars().count() > width {
+        true => format!("{}…", s.chars().take(width - 1).collect::<String>()),
+        false => s.to_string(),
+    }
+}
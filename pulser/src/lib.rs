@@ -1,6 +1,14 @@
+#![forbid(unsafe_code)]
+
+#[cfg(feature = "backend")]
 mod ignore;
+#[cfg(feature = "backend")]
+pub mod multi;
 mod pulseaudio;
 pub mod sender;
+#[cfg(feature = "backend")]
 pub mod simple;
 
+// the channels used by the API, re-exported so `select!` can be used with them
+pub use crossbeam_channel;
 pub use pulseaudio::*;
@@ -0,0 +1,138 @@
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::sync::mpsc::SendError;
+
+use crossbeam_channel::{self as channel, Receiver, Sender};
+use serde::Serialize;
+
+use crate::api::{PAEvent, PAMask};
+use crate::sender::EventSender;
+use crate::simple::{OperationResult, PulseAudio, Result};
+
+/// An event from one of the servers of a `MultiServer`
+#[derive(Debug, Serialize)]
+pub struct ServerEvent {
+    /// The name the server was added with
+    pub server: String,
+    #[serde(flatten)]
+    pub event: PAEvent,
+}
+
+/// Holds connections to several servers by name (such as "local" and "kitchen"), so commands can
+/// be routed to a server by its name, and their events merged into a single stream.
+#[derive(Default)]
+pub struct MultiServer {
+    servers: BTreeMap<String, PulseAudio>,
+}
+
+impl MultiServer {
+    pub fn new() -> MultiServer {
+        MultiServer::default()
+    }
+
+    /// Adds a connection, returning the one it replaced if the name was already taken
+    pub fn add(&mut self, name: impl Into<String>, pa: PulseAudio) -> Option<PulseAudio> {
+        self.servers.insert(name.into(), pa)
+    }
+
+    /// Removes a connection, which is disconnected once it's dropped
+    pub fn remove(&mut self, name: &str) -> Option<PulseAudio> {
+        self.servers.remove(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.servers.keys().map(|name| name.as_str())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &PulseAudio)> {
+        self.servers.iter().map(|(name, pa)| (name.as_str(), pa))
+    }
+
+    /// The connection to the server with the given name
+    pub fn get(&self, name: &str) -> Result<&PulseAudio> {
+        self.servers
+            .get(name)
+            .ok_or_else(|| format!("No server named: {}", name).into())
+    }
+
+    /// Runs a command on the server with the given name, such as
+    /// `multi.on("kitchen", |pa| pa.set_sink_mute(id, true))`
+    pub fn on<T>(&self, name: &str, f: impl FnOnce(&PulseAudio) -> Result<T>) -> Result<T> {
+        f(self.get(name)?)
+    }
+
+    /// Runs a command on every server, returning each one's result by name
+    pub fn on_all<T>(
+        &self,
+        mut f: impl FnMut(&PulseAudio) -> Result<T>,
+    ) -> BTreeMap<&str, Result<T>> {
+        self.iter().map(|(name, pa)| (name, f(pa))).collect()
+    }
+
+    /// Subscribes to the events of every server, merged into one channel with each event tagged
+    /// with its server's name. This replaces any existing subscription on each server.
+    pub fn subscribe(&self, mask: PAMask) -> Result<Receiver<ServerEvent>> {
+        let (tx, rx) = channel::unbounded();
+        for (name, pa) in self.iter() {
+            let sender = Tagged {
+                server: name.to_string(),
+                tx: tx.clone(),
+            };
+            match pa.subscribe(mask, Box::new(sender))? {
+                result if result.is_success() => {}
+                result => return Err(format!("{}: {}", name, result).into()),
+            }
+        }
+
+        Ok(rx)
+    }
+
+    /// Ends the subscription of every server
+    pub fn unsubscribe(&self) -> Result<OperationResult> {
+        for (name, pa) in self.iter() {
+            match pa.unsubscribe()? {
+                result if result.is_success() => {}
+                result => return Err(format!("{}: {}", name, result).into()),
+            }
+        }
+
+        Ok(OperationResult::Success)
+    }
+
+    /// Disconnects from every server, returning the first error (every server is disconnected
+    /// regardless)
+    pub fn disconnect(self) -> Result<()> {
+        let mut first = Ok(());
+        for (name, pa) in self.servers {
+            if let (Err(e), Ok(())) = (pa.disconnect(), &first) {
+                first = Err(format!("{}: {}", name, e).into());
+            }
+        }
+
+        first
+    }
+}
+
+/// Tags each event with the name of the server it came from
+struct Tagged {
+    server: String,
+    tx: Sender<ServerEvent>,
+}
+
+impl Debug for Tagged {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Tagged")
+            .field("server", &self.server)
+            .finish()
+    }
+}
+
+impl EventSender for Tagged {
+    fn send(&self, ev: PAEvent) -> std::result::Result<(), SendError<PAEvent>> {
+        let ev = ServerEvent {
+            server: self.server.clone(),
+            event: ev,
+        };
+        self.tx.send(ev).map_err(|e| SendError(e.0.event))
+    }
+}
@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+
+/// A volume which module-stream-restore applies to each stream of an application when it appears,
+/// see `PACommand::SaveAppVolumes`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppVolume {
+    /// The `application.name` property of the streams
+    pub application: String,
+    /// The volume as a percentage, where `100.0` is 100%, or `None` if only the mute is restored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub volume: Option<f64>,
+    #[serde(default)]
+    pub mute: bool,
+}
+
+impl AppVolume {
+    /// The prefix of module-stream-restore's entries which match streams by their application
+    pub const PREFIX: &str = "sink-input-by-application-name:";
+
+    pub fn new(application: impl Into<String>, volume: f64) -> AppVolume {
+        AppVolume {
+            application: application.into(),
+            volume: Some(volume),
+            mute: false,
+        }
+    }
+
+    /// The name of the entry in module-stream-restore's database
+    pub fn entry_name(application: &str) -> String {
+        format!("{}{}", Self::PREFIX, application)
+    }
+}
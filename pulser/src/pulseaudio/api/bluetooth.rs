@@ -0,0 +1,30 @@
+use serde::Serialize;
+
+/// The Bluetooth profiles which are commonly switched between
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BluetoothProfile {
+    /// High quality playback, without a microphone
+    A2dp,
+    /// Lower quality playback, with a microphone
+    Hfp,
+}
+
+impl BluetoothProfile {
+    /// The name of the card profile
+    pub fn profile_name(&self) -> &'static str {
+        match self {
+            BluetoothProfile::A2dp => "a2dp-sink",
+            BluetoothProfile::Hfp => "headset-head-unit",
+        }
+    }
+}
+
+/// A codec supported by a Bluetooth card
+#[derive(Debug, Clone, Serialize)]
+pub struct BluetoothCodec {
+    /// The name of the codec, such as "ldac"
+    pub name: String,
+    /// A description of the codec, such as "LDAC"
+    pub description: String,
+}
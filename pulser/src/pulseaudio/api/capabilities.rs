@@ -0,0 +1,111 @@
+use serde::Serialize;
+
+use super::PAServerInfo;
+
+/// The first protocol version which supports the message API (PulseAudio 15)
+pub const MESSAGE_API_PROTOCOL: u32 = 35;
+/// The first protocol version which supports memfd-backed shared memory (PulseAudio 9)
+pub const MEMFD_PROTOCOL: u32 = 31;
+
+/// The prefix of the server name reported by pipewire-pulse, such as "PulseAudio (on PipeWire 0.3.65)"
+const PIPEWIRE_PREFIX: &str = "PulseAudio (on PipeWire ";
+
+/// What the server is, and what it supports. Useful for tools which need to behave differently when
+/// running against pipewire-pulse rather than PulseAudio itself.
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerCapabilities {
+    /// Whether the server is pipewire-pulse
+    pub pipewire: bool,
+    /// The version of PipeWire, if the server is pipewire-pulse
+    pub pipewire_version: Option<String>,
+    /// The (PulseAudio) version reported by the server
+    pub server_version: Option<String>,
+    /// The protocol version of the server, if it's known
+    pub protocol_version: Option<u32>,
+    /// Whether the server implements the message API (protocol 35 and later, or PulseAudio 15 and
+    /// later if the protocol version isn't known). Sending messages also requires the `pa_v15`
+    /// feature.
+    pub message_api: bool,
+}
+
+impl ServerCapabilities {
+    /// Determines the capabilities from the server's info, and its protocol version if available
+    /// (which is more reliable than the version string).
+    pub fn new(info: &PAServerInfo, protocol: Option<&ProtocolInfo>) -> ServerCapabilities {
+        let pipewire_version = info.server_name.as_deref().and_then(|name| {
+            name.strip_prefix(PIPEWIRE_PREFIX)
+                .map(|rest| rest.trim_end_matches(')').to_string())
+        });
+
+        let mut capabilities = ServerCapabilities {
+            pipewire: pipewire_version.is_some(),
+            pipewire_version,
+            server_version: info.server_version.clone(),
+            protocol_version: protocol.and_then(|p| p.server_version),
+            message_api: false,
+        };
+        capabilities.message_api = match capabilities.protocol_version {
+            Some(version) => version >= MESSAGE_API_PROTOCOL,
+            None => capabilities.major_version().map_or(false, |v| v >= 15),
+        };
+
+        capabilities
+    }
+
+    /// The major version reported by the server, if it could be parsed
+    pub fn major_version(&self) -> Option<u32> {
+        self.server_version
+            .as_deref()
+            .and_then(|version| version.split('.').next())
+            .and_then(|major| major.parse().ok())
+    }
+}
+
+impl From<&PAServerInfo> for ServerCapabilities {
+    fn from(info: &PAServerInfo) -> Self {
+        ServerCapabilities::new(info, None)
+    }
+}
+
+/// Details about the connection to the server, read from the context
+#[derive(Debug, Clone, Serialize)]
+pub struct ProtocolInfo {
+    /// The protocol version of the library
+    pub client_version: u32,
+    /// The protocol version of the server, if connected
+    pub server_version: Option<u32>,
+    /// The server this is connected to
+    pub server: Option<String>,
+    /// Whether the server is running on the local machine
+    pub local: Option<bool>,
+    /// The index of this client on the server
+    pub client_index: Option<u32>,
+    /// The optimal size (in bytes) of a memory block, used when writing to streams
+    pub tile_size: Option<usize>,
+}
+
+impl ProtocolInfo {
+    /// Whether the server's protocol supports the message API
+    pub fn supports_message_api(&self) -> bool {
+        self.server_version
+            .map_or(false, |version| version >= MESSAGE_API_PROTOCOL)
+    }
+}
+
+/// What the server supports, probed once when connecting (see `PACommand::GetCapabilities`). Use
+/// this to degrade gracefully, rather than sending requests which will fail on older servers.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Capabilities {
+    /// The message API (protocol 35 and later), which also requires the `pa_v15` feature
+    pub message_api: bool,
+    /// The stream-restore extension (`module-stream-restore`)
+    pub stream_restore: bool,
+    /// The device-restore extension (`module-device-restore`)
+    pub device_restore: bool,
+    /// The device-manager extension (`module-device-manager`)
+    pub device_manager: bool,
+    /// Shared memory can be used to transfer audio, since the server is local
+    pub shm: bool,
+    /// Shared memory can be backed by memfd (protocol 31 and later, on a local server)
+    pub memfd: bool,
+}
@@ -0,0 +1,31 @@
+use serde::Deserialize;
+
+use super::pattern::glob_matches;
+use super::PACardInfo;
+
+/// Sets a card's profile when it appears, such as forcing `a2dp-sink` for a Bluetooth headset or
+/// `pro-audio` for an audio interface
+#[derive(Debug, Clone, Deserialize)]
+pub struct CardProfileRule {
+    /// The name of the card; a `*` matches any number of characters, so `bluez_card.*` matches any
+    /// Bluetooth card
+    pub card: String,
+    /// The name of the profile to set
+    pub profile: String,
+}
+
+impl CardProfileRule {
+    pub fn matches(&self, card: &PACardInfo) -> bool {
+        card.name
+            .as_deref()
+            .map_or(false, |name| glob_matches(&self.card, name))
+    }
+
+    /// The profile from the first rule which matches the card, if any
+    pub fn profile_for<'a>(rules: &'a [CardProfileRule], card: &PACardInfo) -> Option<&'a str> {
+        rules
+            .iter()
+            .find(|rule| rule.matches(card))
+            .map(|rule| rule.profile.as_str())
+    }
+}
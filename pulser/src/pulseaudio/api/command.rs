@@ -1,4 +1,9 @@
-use serde::Serialize;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crossbeam_channel::{Receiver, Sender};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use super::*;
 use crate::sender::EventSender;
@@ -6,6 +11,10 @@ use crate::sender::EventSender;
 #[derive(Debug)]
 pub enum PACommand {
     GetServerInfo,
+    /// Reads the protocol versions and other details about the connection
+    GetProtocolInfo,
+    /// Returns what the server supports, which is probed once when connecting
+    GetCapabilities,
 
     GetDefaultSink,
     GetDefaultSource,
@@ -29,31 +38,56 @@ pub enum PACommand {
     GetSinkVolume(PAIdent),
     SetSinkMute(PAIdent, bool),
     SetSinkVolume(PAIdent, VolumeSpec),
+    /// Sets the mute of a sink, but only if it's still the expected value (the first `bool`)
+    SetSinkMuteIf(PAIdent, bool, bool),
+    /// Sets the volume of a sink, but only if it still matches the expected readings
+    SetSinkVolumeIf(PAIdent, VolumeReadings, VolumeSpec),
     SetSinkPort(PAIdent, String),
     SuspendSink(PAIdent, bool),
+    /// Sets the description of a sink (its user-facing name), which requires module-device-manager
+    RenameSink(PAIdent, String),
+    /// Gradually changes the volume of a sink over the given duration
+    FadeSinkVolume(PAIdent, VolumeSpec, Duration, FadeCurve),
+    /// Mutes a sink, and unmutes it after the given duration
+    MuteSinkFor(PAIdent, Duration),
+    /// Lowers the volume of a sink to the given percentage of its current volume, and restores it
+    /// after the given duration
+    DuckSink(PAIdent, f64, Duration),
 
     GetSourceInfo(PAIdent),
     GetSourceMute(PAIdent),
     GetSourceVolume(PAIdent),
     SetSourceMute(PAIdent, bool),
     SetSourceVolume(PAIdent, VolumeSpec),
+    /// Sets the mute of a source, but only if it's still the expected value (the first `bool`)
+    SetSourceMuteIf(PAIdent, bool, bool),
+    /// Sets the volume of a source, but only if it still matches the expected readings
+    SetSourceVolumeIf(PAIdent, VolumeReadings, VolumeSpec),
     SetSourcePort(PAIdent, String),
     SuspendSource(PAIdent, bool),
+    /// Sets the description of a source (its user-facing name), which requires
+    /// module-device-manager
+    RenameSource(PAIdent, String),
 
     GetSinkInputInfo(u32),
     GetSinkInputMute(u32),
     GetSinkInputVolume(u32),
     SetSinkInputMute(u32, bool),
     SetSinkInputVolume(u32, VolumeSpec),
-    MoveSinkInput(u32, PAIdent),
+    MoveSinkInput(PAStreamIdent, PAIdent),
     KillSinkInput(u32),
+    /// Mutes a sink input, and unmutes it after the given duration
+    MuteSinkInputFor(u32, Duration),
+    /// Lowers the volume of a sink input to the given percentage of its current volume, and
+    /// restores it after the given duration
+    DuckSinkInput(u32, f64, Duration),
 
     GetSourceOutputInfo(u32),
     GetSourceOutputMute(u32),
     GetSourceOutputVolume(u32),
     SetSourceOutputMute(u32, bool),
     SetSourceOutputVolume(u32, VolumeSpec),
-    MoveSourceOutput(u32, PAIdent),
+    MoveSourceOutput(PAStreamIdent, PAIdent),
     KillSourceOutput(u32),
 
     GetCardInfoList,
@@ -62,31 +96,446 @@ pub enum PACommand {
     GetSampleInfoList,
     GetSinkInfoList,
     GetSinkInputInfoList,
+    /// The same as `GetSinkInputInfoList`, but only with the sink inputs which match the filter
+    GetSinkInputInfoListFiltered(StreamFilter),
     GetSourceInfoList,
     GetSourceOutputInfoList,
 
+    /// Mutes (or unmutes) every sink, source, sink input and source output in the mask at once,
+    /// except for the given object (matched by name or index for devices, and by index for streams)
+    MuteAll(PAMask, bool, Option<PAIdent>),
+
+    /// Plays raw audio on the given sink (or the default sink) as it's received from the channel,
+    /// until the sender is dropped. Responds with the id of the stream once it's ready.
+    PlayRaw(RawStreamOptions, Receiver<Vec<u8>>),
+    /// Records raw audio from the given source (or the default source) into the channel, until the
+    /// receiver is dropped. Responds with the id of the stream once it's ready.
+    RecordRaw(RawStreamOptions, Sender<Vec<u8>>),
+    /// Pauses (`true`) or resumes (`false`) a stream started with `PlayRaw` or `RecordRaw`
+    CorkStream(u32, bool),
+    /// Drops the data in a stream's buffer, which hasn't been played (or read) yet
+    FlushStream(u32),
+    /// Responds once everything sent to a playback stream so far has been played, or once the
+    /// stream has finished (with an error, if it failed)
+    DrainStream(u32),
+    /// Reads the current latency of a stream started with `PlayRaw` or `RecordRaw`
+    GetStreamLatency(u32),
+
+    /// Updates the properties of an object, such as to tag it for rules to match on later
+    UpdateDeviceProplist(ProplistTarget, BTreeMap<String, String>, ProplistMode),
+
+    /// Lists the per-application volumes saved by module-stream-restore
+    GetAppVolumes,
+    /// Saves per-application volumes with module-stream-restore, replacing any already saved for
+    /// the same applications. They're applied to the application's streams straight away, and
+    /// restored whenever new ones appear.
+    SaveAppVolumes(Vec<AppVolume>),
+    /// Forgets the saved volumes of the given applications
+    DeleteAppVolumes(Vec<String>),
+
+    /// Stops a running fade, leaving the volume wherever it currently is
+    CancelFade(u32),
+    /// Responds once a fade has finished
+    WaitFade(u32),
+    /// Stops every multi-step operation which was started before the connection's `CancelToken`
+    /// was last cancelled
+    CancelPending,
+
     Subscribe(PAMask, Box<dyn EventSender>),
+    /// Like `PACommand::Subscribe`, but only events which pass the filter are sent
+    SubscribeFiltered(PAMask, EventFilter, Box<dyn EventSender>),
+    /// Ends the current subscription, if there is one
+    Unsubscribe,
+    /// Emit `PAEvent::AllSinksIdle` once all sinks have been idle for this long (`None` disables it)
+    SetIdleTimeout(Option<Duration>),
+    /// Ping the server this often, emitting `PAEvent::ServerUnresponsive` if a ping isn't answered
+    /// before the next one is due (`None` disables it)
+    SetHeartbeat(Option<Duration>),
+    /// Keep retrying lookups of objects which don't exist for this long (`None` disables retries),
+    /// since objects sometimes aren't visible immediately after their `SubscriptionNew` event
+    SetLookupRetry(Option<Duration>),
 
+    /// Check whether the connection to PulseAudio is still ready
+    IsConnected,
     Disconnect,
-    // TODO: send message
+
+    /// Sends a message to an object which has registered a message handler (recipient, message,
+    /// parameters), see PulseAudio's `messaging_api.txt` for more
+    #[cfg(feature = "pa_v15")]
+    SendMessage(String, String, String),
 }
-/// Subscription events
-#[derive(Debug, Serialize)]
-#[serde(untagged)]
+/// Subscription events.
+///
+/// These serialize as an object with an `"event"` tag, such as
+/// `{"event":"changed","facility":"sink","id":{"index":42}}`.
+#[derive(Debug)]
 pub enum PAEvent {
     SubscriptionNew(PAFacility, PAIdent),
     SubscriptionRemoved(PAFacility, PAIdent),
     SubscriptionChanged(PAFacility, PAIdent),
+
+    /// The server's info, sent after each `SubscriptionChanged` event for the server facility.
+    /// Only emitted when subscribed to server events.
+    ServerChanged(Box<PAServerInfo>),
+    /// The default sink changed, sent after the `ServerChanged` event which changed it.
+    /// Only emitted when subscribed to server events.
+    DefaultSinkChanged {
+        old: Option<PAIdent>,
+        new: Option<PAIdent>,
+    },
+    /// The default source changed, sent after the `ServerChanged` event which changed it.
+    /// Only emitted when subscribed to server events.
+    DefaultSourceChanged {
+        old: Option<PAIdent>,
+        new: Option<PAIdent>,
+    },
+
+    /// A sink started running (at least one uncorked stream is playing to it).
+    /// Only emitted when subscribed to sink events.
+    SinkBecameActive(PAIdent),
+    /// A sink stopped running (it's now idle, suspended or was removed).
+    /// Only emitted when subscribed to sink events.
+    SinkBecameIdle(PAIdent),
+    /// No sinks have been running for at least the duration set with `PACommand::SetIdleTimeout`.
+    /// Emitted once per idle period; only emitted when subscribed to sink events.
+    AllSinksIdle {
+        r#for: Duration,
+    },
+
+    /// A sink input was corked (paused), such as when a media player is paused.
+    /// Only emitted when subscribed to sink input events.
+    StreamCorked(PAIdent),
+    /// A sink input was uncorked (resumed).
+    /// Only emitted when subscribed to sink input events.
+    StreamUncorked(PAIdent),
+
+    /// A source output started recording from a microphone (or another source which isn't a
+    /// monitor), or was uncorked. Peak meters (such as pavucontrol's) aren't included.
+    /// Only emitted when subscribed to source output events.
+    RecordingStarted {
+        /// The source output
+        id: PAIdent,
+        /// The `application.name` property of the source output
+        app: Option<String>,
+        source: PAIdent,
+    },
+    /// A source output which emitted `RecordingStarted` was removed or corked.
+    /// Only emitted when subscribed to source output events.
+    RecordingStopped {
+        id: PAIdent,
+        app: Option<String>,
+    },
+
+    /// A playback stream started with `PACommand::PlayRaw` ran out of data (contains the stream's
+    /// id). Only emitted when subscribed to sink input events.
+    StreamUnderflow(u32),
+    /// A recording stream started with `PACommand::RecordRaw` wasn't read quickly enough, and data
+    /// was lost. Only emitted when subscribed to source output events.
+    StreamOverflow(u32),
+    /// The device of a raw stream was suspended (or resumed).
+    /// Only emitted when subscribed to sink input (or source output) events.
+    StreamSuspended {
+        stream: u32,
+        suspended: bool,
+    },
+    /// A raw stream was moved to another device, such as when the user picked a different output.
+    /// Only emitted when subscribed to sink input (or source output) events.
+    StreamMoved {
+        stream: u32,
+        device: Option<String>,
+    },
+
+    /// The server hasn't answered a ping for at least the interval set with
+    /// `PACommand::SetHeartbeat`. Emitted once until the server responds again.
+    ServerUnresponsive {
+        r#for: Duration,
+    },
+    /// The server answered a ping, after a `ServerUnresponsive` event.
+    ServerResponsive,
+}
+
+impl Serialize for PAEvent {
+    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = s.serialize_map(None)?;
+        match self {
+            PAEvent::SubscriptionNew(facility, id)
+            | PAEvent::SubscriptionRemoved(facility, id)
+            | PAEvent::SubscriptionChanged(facility, id) => {
+                map.serialize_entry("event", self.name())?;
+                map.serialize_entry("facility", facility)?;
+                map.serialize_entry("id", id)?;
+            }
+            PAEvent::ServerChanged(info) => {
+                map.serialize_entry("event", self.name())?;
+                map.serialize_entry("info", info)?;
+            }
+            PAEvent::DefaultSinkChanged { old, new }
+            | PAEvent::DefaultSourceChanged { old, new } => {
+                map.serialize_entry("event", self.name())?;
+                map.serialize_entry("old", old)?;
+                map.serialize_entry("new", new)?;
+            }
+            PAEvent::SinkBecameActive(id)
+            | PAEvent::SinkBecameIdle(id)
+            | PAEvent::StreamCorked(id)
+            | PAEvent::StreamUncorked(id) => {
+                map.serialize_entry("event", self.name())?;
+                map.serialize_entry("id", id)?;
+            }
+            PAEvent::RecordingStarted { id, app, source } => {
+                map.serialize_entry("event", self.name())?;
+                map.serialize_entry("id", id)?;
+                map.serialize_entry("app", app)?;
+                map.serialize_entry("source", source)?;
+            }
+            PAEvent::RecordingStopped { id, app } => {
+                map.serialize_entry("event", self.name())?;
+                map.serialize_entry("id", id)?;
+                map.serialize_entry("app", app)?;
+            }
+            PAEvent::StreamUnderflow(stream) | PAEvent::StreamOverflow(stream) => {
+                map.serialize_entry("event", self.name())?;
+                map.serialize_entry("stream", stream)?;
+            }
+            PAEvent::StreamSuspended { stream, suspended } => {
+                map.serialize_entry("event", self.name())?;
+                map.serialize_entry("stream", stream)?;
+                map.serialize_entry("suspended", suspended)?;
+            }
+            PAEvent::StreamMoved { stream, device } => {
+                map.serialize_entry("event", self.name())?;
+                map.serialize_entry("stream", stream)?;
+                map.serialize_entry("device", device)?;
+            }
+            PAEvent::AllSinksIdle { r#for } | PAEvent::ServerUnresponsive { r#for } => {
+                map.serialize_entry("event", self.name())?;
+                map.serialize_entry("for", r#for)?;
+            }
+            PAEvent::ServerResponsive => {
+                map.serialize_entry("event", self.name())?;
+            }
+        }
+        map.end()
+    }
 }
 
+/// Reads the events back from how they're serialized, such as from the output of `pulser subscribe`
+impl<'de> Deserialize<'de> for PAEvent {
+    fn deserialize<D>(d: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(tag = "event", rename_all = "snake_case")]
+        enum Tagged {
+            New {
+                facility: PAFacility,
+                id: PAIdent,
+            },
+            Removed {
+                facility: PAFacility,
+                id: PAIdent,
+            },
+            Changed {
+                facility: PAFacility,
+                id: PAIdent,
+            },
+            ServerChanged {
+                info: Box<PAServerInfo>,
+            },
+            DefaultSinkChanged {
+                old: Option<PAIdent>,
+                new: Option<PAIdent>,
+            },
+            DefaultSourceChanged {
+                old: Option<PAIdent>,
+                new: Option<PAIdent>,
+            },
+            SinkBecameActive {
+                id: PAIdent,
+            },
+            SinkBecameIdle {
+                id: PAIdent,
+            },
+            AllSinksIdle {
+                r#for: Duration,
+            },
+            StreamCorked {
+                id: PAIdent,
+            },
+            StreamUncorked {
+                id: PAIdent,
+            },
+            RecordingStarted {
+                id: PAIdent,
+                app: Option<String>,
+                source: PAIdent,
+            },
+            RecordingStopped {
+                id: PAIdent,
+                app: Option<String>,
+            },
+            StreamUnderflow {
+                stream: u32,
+            },
+            StreamOverflow {
+                stream: u32,
+            },
+            StreamSuspended {
+                stream: u32,
+                suspended: bool,
+            },
+            StreamMoved {
+                stream: u32,
+                device: Option<String>,
+            },
+            ServerUnresponsive {
+                r#for: Duration,
+            },
+            ServerResponsive,
+        }
+
+        Ok(match Tagged::deserialize(d)? {
+            Tagged::New { facility, id } => PAEvent::SubscriptionNew(facility, id),
+            Tagged::Removed { facility, id } => PAEvent::SubscriptionRemoved(facility, id),
+            Tagged::Changed { facility, id } => PAEvent::SubscriptionChanged(facility, id),
+            Tagged::ServerChanged { info } => PAEvent::ServerChanged(info),
+            Tagged::DefaultSinkChanged { old, new } => PAEvent::DefaultSinkChanged { old, new },
+            Tagged::DefaultSourceChanged { old, new } => PAEvent::DefaultSourceChanged { old, new },
+            Tagged::SinkBecameActive { id } => PAEvent::SinkBecameActive(id),
+            Tagged::SinkBecameIdle { id } => PAEvent::SinkBecameIdle(id),
+            Tagged::AllSinksIdle { r#for } => PAEvent::AllSinksIdle { r#for },
+            Tagged::StreamCorked { id } => PAEvent::StreamCorked(id),
+            Tagged::StreamUncorked { id } => PAEvent::StreamUncorked(id),
+            Tagged::RecordingStarted { id, app, source } => {
+                PAEvent::RecordingStarted { id, app, source }
+            }
+            Tagged::RecordingStopped { id, app } => PAEvent::RecordingStopped { id, app },
+            Tagged::StreamUnderflow { stream } => PAEvent::StreamUnderflow(stream),
+            Tagged::StreamOverflow { stream } => PAEvent::StreamOverflow(stream),
+            Tagged::StreamSuspended { stream, suspended } => {
+                PAEvent::StreamSuspended { stream, suspended }
+            }
+            Tagged::StreamMoved { stream, device } => PAEvent::StreamMoved { stream, device },
+            Tagged::ServerUnresponsive { r#for } => PAEvent::ServerUnresponsive { r#for },
+            Tagged::ServerResponsive => PAEvent::ServerResponsive,
+        })
+    }
+}
+
+impl PAEvent {
+    /// The facility the event is about, if any. Derived events belong to the facility of the
+    /// subscription which emits them (such as `PAFacility::Sink` for `PAEvent::AllSinksIdle`).
+    pub fn facility(&self) -> Option<PAFacility> {
+        match self {
+            PAEvent::SubscriptionNew(facility, _)
+            | PAEvent::SubscriptionRemoved(facility, _)
+            | PAEvent::SubscriptionChanged(facility, _) => Some(*facility),
+            PAEvent::ServerChanged(_)
+            | PAEvent::DefaultSinkChanged { .. }
+            | PAEvent::DefaultSourceChanged { .. }
+            | PAEvent::ServerUnresponsive { .. }
+            | PAEvent::ServerResponsive => Some(PAFacility::Server),
+            PAEvent::SinkBecameActive(_)
+            | PAEvent::SinkBecameIdle(_)
+            | PAEvent::AllSinksIdle { .. } => Some(PAFacility::Sink),
+            PAEvent::StreamCorked(_) | PAEvent::StreamUncorked(_) => Some(PAFacility::SinkInput),
+            PAEvent::RecordingStarted { .. } | PAEvent::RecordingStopped { .. } => {
+                Some(PAFacility::SourceOutput)
+            }
+            // raw streams are identified by their own ids, rather than as server objects
+            PAEvent::StreamUnderflow(_)
+            | PAEvent::StreamOverflow(_)
+            | PAEvent::StreamSuspended { .. }
+            | PAEvent::StreamMoved { .. } => None,
+        }
+    }
+
+    /// The object the event is about, if any
+    pub fn ident(&self) -> Option<&PAIdent> {
+        match self {
+            PAEvent::SubscriptionNew(_, id)
+            | PAEvent::SubscriptionRemoved(_, id)
+            | PAEvent::SubscriptionChanged(_, id)
+            | PAEvent::SinkBecameActive(id)
+            | PAEvent::SinkBecameIdle(id)
+            | PAEvent::StreamCorked(id)
+            | PAEvent::StreamUncorked(id)
+            | PAEvent::RecordingStarted { id, .. }
+            | PAEvent::RecordingStopped { id, .. } => Some(id),
+            _ => None,
+        }
+    }
+
+    /// The names of every event, see `PAEvent::name`
+    pub const NAMES: [&'static str; 19] = [
+        "new",
+        "removed",
+        "changed",
+        "server_changed",
+        "default_sink_changed",
+        "default_source_changed",
+        "sink_became_active",
+        "sink_became_idle",
+        "all_sinks_idle",
+        "stream_corked",
+        "stream_uncorked",
+        "recording_started",
+        "recording_stopped",
+        "stream_underflow",
+        "stream_overflow",
+        "stream_suspended",
+        "stream_moved",
+        "server_unresponsive",
+        "server_responsive",
+    ];
+
+    /// The name of the event, used as its tag when serialized
+    pub fn name(&self) -> &'static str {
+        match self {
+            PAEvent::SubscriptionNew(..) => "new",
+            PAEvent::SubscriptionRemoved(..) => "removed",
+            PAEvent::SubscriptionChanged(..) => "changed",
+            PAEvent::ServerChanged(_) => "server_changed",
+            PAEvent::DefaultSinkChanged { .. } => "default_sink_changed",
+            PAEvent::DefaultSourceChanged { .. } => "default_source_changed",
+            PAEvent::SinkBecameActive(_) => "sink_became_active",
+            PAEvent::SinkBecameIdle(_) => "sink_became_idle",
+            PAEvent::AllSinksIdle { .. } => "all_sinks_idle",
+            PAEvent::StreamCorked(_) => "stream_corked",
+            PAEvent::StreamUncorked(_) => "stream_uncorked",
+            PAEvent::RecordingStarted { .. } => "recording_started",
+            PAEvent::RecordingStopped { .. } => "recording_stopped",
+            PAEvent::StreamUnderflow(_) => "stream_underflow",
+            PAEvent::StreamOverflow(_) => "stream_overflow",
+            PAEvent::StreamSuspended { .. } => "stream_suspended",
+            PAEvent::StreamMoved { .. } => "stream_moved",
+            PAEvent::ServerUnresponsive { .. } => "server_unresponsive",
+            PAEvent::ServerResponsive => "server_responsive",
+        }
+    }
+}
+
+/// Responses to `PACommand`s.
+///
+/// These serialize with a `"type"` tag and their value (if any) in `"data"`, such as
+/// `{"type":"mute","data":[{"index":42},true]}`.
 #[derive(Debug, Serialize)]
-#[serde(untagged)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
 pub enum PAResponse {
     /// Returned when an operation succeeded (such as setting mute/volume, or starting a subscription)
     OpComplete,
     /// Returned when an operation failed (such as setting mute/volume, or starting a subscription)
-    OpError(String),
+    OpError(OpError),
+    /// Returned when a conditional operation wasn't performed, since the current value didn't match
+    /// the expected value
+    OpConflict(String),
 
+    /// `PACommand::GetAppVolumes` response
+    AppVolumes(Vec<AppVolume>),
     /// `PACommand::CardInfoList` response
     CardInfoList(Vec<PACardInfo>),
     /// `PACommand::CardInfo` response
@@ -105,6 +554,12 @@ pub enum PAResponse {
     ModuleInfo(PAModuleInfo),
     /// `PACommand::LoadModule` response
     ModuleLoaded(u32),
+    /// `PACommand::Fade*Volume` response, contains the id of the fade
+    FadeStarted(u32),
+    /// `PACommand::PlayRaw` and `PACommand::RecordRaw` response, contains the id of the stream
+    StreamStarted(u32),
+    /// `PACommand::GetStreamLatency` response
+    StreamLatency(RawStreamLatency),
     /// `PACommand::Get*Mute` response
     Mute(PAIdent, bool),
     /// `PACommand::SampleInfoList` response
@@ -112,12 +567,16 @@ pub enum PAResponse {
     /// `PACommand::SampleInfo` response
     SampleInfo(PASampleInfo),
     /// `PACommand::GetServerInfo` response
-    ServerInfo(PAServerInfo),
+    ServerInfo(Box<PAServerInfo>),
+    /// `PACommand::GetProtocolInfo` response
+    ProtocolInfo(ProtocolInfo),
+    /// `PACommand::GetCapabilities` response
+    Capabilities(Capabilities),
     /// `PACommand::GetSinkInfoList` response
     SinkInfoList(Vec<PASinkInfo>),
     /// `PACommand::GetSinkInfo` response
     SinkInfo(PASinkInfo),
-    /// `PACommand::GetSinkInputList` response
+    /// `PACommand::GetSinkInputList` and `PACommand::GetSinkInputInfoListFiltered` response
     SinkInputInfoList(Vec<PASinkInputInfo>),
     /// `PACommand::GetSinkInput` response
     SinkInputInfo(PASinkInputInfo),
@@ -131,6 +590,15 @@ pub enum PAResponse {
     SourceOutputInfo(PASourceOutputInfo),
     /// `PACommand::Get*Volume` response
     Volume(PAIdent, VolumeReadings),
+    /// `PACommand::SendMessage` response
+    #[cfg(feature = "pa_v15")]
+    Message(Option<String>),
+
+    /// Sent when a fatal error occurs, after which no more `PACommand`s will be processed
+    Fatal(PAError),
+
+    /// `PACommand::IsConnected` response
+    Connected(bool),
 
     /// `PACommand::Disconnect` response.
     /// Once this is received, no other `PACommand`s should be sent, since the
@@ -0,0 +1,170 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+#[cfg(feature = "backend")]
+use libpulse_binding::format::Encoding;
+use serde::{Deserialize, Serialize};
+
+use super::{PAInfo, PASinkInfo, PASourceInfo};
+
+/// The encoding of a stream format, such as PCM or one of the compressed formats that can be passed
+/// through to a receiver over S/PDIF or HDMI.
+///
+/// Like `PAFacility`, this is owned so that its names are stable and it can be deserialized.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PAEncoding {
+    /// Any encoding, PCM or compressed
+    Any,
+    Pcm,
+    Ac3,
+    Eac3,
+    Mpeg,
+    Dts,
+    Mpeg2Aac,
+    /// Only reported by servers with PulseAudio 13 or later (requires the `pa_v15` feature)
+    Truehd,
+    /// Only reported by servers with PulseAudio 13 or later (requires the `pa_v15` feature)
+    Dtshd,
+    Invalid,
+}
+
+impl PAEncoding {
+    pub const ALL: [PAEncoding; 10] = [
+        PAEncoding::Any,
+        PAEncoding::Pcm,
+        PAEncoding::Ac3,
+        PAEncoding::Eac3,
+        PAEncoding::Mpeg,
+        PAEncoding::Dts,
+        PAEncoding::Mpeg2Aac,
+        PAEncoding::Truehd,
+        PAEncoding::Dtshd,
+        PAEncoding::Invalid,
+    ];
+
+    /// The name of the encoding, as it's serialized
+    pub fn name(&self) -> &'static str {
+        match self {
+            PAEncoding::Any => "any",
+            PAEncoding::Pcm => "pcm",
+            PAEncoding::Ac3 => "ac3",
+            PAEncoding::Eac3 => "eac3",
+            PAEncoding::Mpeg => "mpeg",
+            PAEncoding::Dts => "dts",
+            PAEncoding::Mpeg2Aac => "mpeg2_aac",
+            PAEncoding::Truehd => "truehd",
+            PAEncoding::Dtshd => "dtshd",
+            PAEncoding::Invalid => "invalid",
+        }
+    }
+
+    /// The value of the encoding in libpulse's `pa_encoding_t`
+    pub fn value(&self) -> i8 {
+        match self {
+            PAEncoding::Any => 0,
+            PAEncoding::Pcm => 1,
+            PAEncoding::Ac3 => 2,
+            PAEncoding::Eac3 => 3,
+            PAEncoding::Mpeg => 4,
+            PAEncoding::Dts => 5,
+            PAEncoding::Mpeg2Aac => 6,
+            PAEncoding::Truehd => 7,
+            PAEncoding::Dtshd => 8,
+            PAEncoding::Invalid => -1,
+        }
+    }
+
+    /// The encoding with the given value in libpulse's `pa_encoding_t`, which is `Invalid` for any
+    /// unknown values
+    pub fn from_value(value: i8) -> PAEncoding {
+        PAEncoding::ALL
+            .into_iter()
+            .find(|e| e.value() == value)
+            .unwrap_or(PAEncoding::Invalid)
+    }
+}
+
+#[cfg(feature = "backend")]
+impl From<Encoding> for PAEncoding {
+    fn from(value: Encoding) -> Self {
+        match value {
+            Encoding::Any => PAEncoding::Any,
+            Encoding::PCM => PAEncoding::Pcm,
+            Encoding::AC3_IEC61937 => PAEncoding::Ac3,
+            Encoding::EAC3_IEC61937 => PAEncoding::Eac3,
+            Encoding::MPEG_IEC61937 => PAEncoding::Mpeg,
+            Encoding::DTS_IEC61937 => PAEncoding::Dts,
+            Encoding::MPEG2_AAC_IEC61937 => PAEncoding::Mpeg2Aac,
+            #[cfg(feature = "pa_v15")]
+            Encoding::TRUEHD_IEC61937 => PAEncoding::Truehd,
+            #[cfg(feature = "pa_v15")]
+            Encoding::DTSHD_IEC61937 => PAEncoding::Dtshd,
+            // the enum is non-exhaustive, so treat any new encodings as invalid
+            _ => PAEncoding::Invalid,
+        }
+    }
+}
+
+impl Display for PAEncoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for PAEncoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        PAEncoding::ALL
+            .iter()
+            .find(|e| e.name().eq_ignore_ascii_case(s))
+            .copied()
+            .ok_or_else(|| {
+                let names = PAEncoding::ALL.map(|e| e.name()).join(", ");
+                format!("Unknown encoding \"{}\", expected one of: {}", s, names)
+            })
+    }
+}
+
+impl PAInfo {
+    pub fn encoding(&self) -> PAEncoding {
+        self.encoding
+    }
+}
+
+/// Implemented by sinks and sources, which advertise the formats they accept
+pub trait HasFormats {
+    fn formats(&self) -> &[PAInfo];
+
+    /// The encodings supported by the device, without duplicates
+    fn encodings(&self) -> Vec<PAEncoding> {
+        let mut encodings = vec![];
+        for encoding in self.formats().iter().map(PAInfo::encoding) {
+            if !encodings.contains(&encoding) {
+                encodings.push(encoding);
+            }
+        }
+
+        encodings
+    }
+
+    /// Whether the device accepts the encoding (a device which accepts `Any` accepts everything)
+    fn supports_encoding(&self, encoding: PAEncoding) -> bool {
+        self.encodings()
+            .iter()
+            .any(|e| *e == encoding || *e == PAEncoding::Any)
+    }
+}
+
+impl HasFormats for PASinkInfo {
+    fn formats(&self) -> &[PAInfo] {
+        &self.formats
+    }
+}
+
+impl HasFormats for PASourceInfo {
+    fn formats(&self) -> &[PAInfo] {
+        &self.formats
+    }
+}
@@ -0,0 +1,190 @@
+use std::error::Error;
+use std::fmt::Display;
+
+#[cfg(feature = "backend")]
+use libpulse_binding::error::PAErr;
+use serde::Serialize;
+
+/// A fatal error, after which the connection to PulseAudio is closed and no more commands will be
+/// processed
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", content = "error", rename_all = "snake_case")]
+pub enum PAError {
+    /// Failed to connect to PulseAudio
+    ConnectionFailed(String),
+    /// The connection to PulseAudio was lost, or an unrecoverable error occurred while using it
+    ConnectionLost(String),
+}
+
+impl Display for PAError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PAError::ConnectionFailed(e) => write!(f, "Failed to connect to PulseAudio: {}", e),
+            PAError::ConnectionLost(e) => write!(f, "Lost connection to PulseAudio: {}", e),
+        }
+    }
+}
+
+impl Error for PAError {}
+
+/// Returned when a volume is provided for each channel, but the number of volumes doesn't match
+/// the number of channels of the object
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InvalidChannelCount {
+    /// How many channels the object has
+    pub expected: u8,
+    /// How many volumes were provided
+    pub got: usize,
+    /// The object whose volume was being set, such as "sink #42"
+    pub device: Option<String>,
+    /// The object's channel map, such as "front-left,front-right"
+    pub channel_map: Option<String>,
+}
+
+impl InvalidChannelCount {
+    pub fn new(expected: u8, got: usize) -> InvalidChannelCount {
+        InvalidChannelCount {
+            expected,
+            got,
+            device: None,
+            channel_map: None,
+        }
+    }
+
+    /// Records which object (and its channel map) caused this error
+    pub fn with_device(mut self, device: impl Display, channel_map: impl Into<String>) -> Self {
+        self.device = Some(device.to_string());
+        self.channel_map = Some(channel_map.into());
+        self
+    }
+}
+
+impl Display for InvalidChannelCount {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Invalid channel count: expected {} volumes, got {}",
+            self.expected, self.got
+        )?;
+        if let Some(ref device) = self.device {
+            write!(f, " for {}", device)?;
+        }
+        if let Some(ref channel_map) = self.channel_map {
+            write!(f, " (channel map: {})", channel_map)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Error for InvalidChannelCount {}
+
+/// Why an operation failed, derived from the libpulse error code (or from the error itself, if the
+/// operation was rejected before it was sent to the server)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    /// The object doesn't exist
+    NotFound,
+    /// The server denied access
+    AccessDenied,
+    /// The connection to the server was refused or lost
+    Disconnected,
+    /// The server didn't respond in time
+    Timeout,
+    /// The number of volumes provided didn't match the number of channels of the object
+    InvalidChannelCount(InvalidChannelCount),
+    /// Any other error, or one without an error code
+    Other,
+}
+
+impl ErrorKind {
+    /// Categorises an error code, as returned by `pa_context_errno`
+    pub fn from_code(code: Option<i32>) -> ErrorKind {
+        // the values of libpulse's `pa_error_code_t`, so this can be used without libpulse
+        const ACCESS: i32 = 1;
+        const NO_ENTITY: i32 = 5;
+        const CONNECTION_REFUSED: i32 = 6;
+        const TIMEOUT: i32 = 8;
+        const CONNECTION_TERMINATED: i32 = 11;
+        const KILLED: i32 = 12;
+
+        match code {
+            Some(NO_ENTITY) => ErrorKind::NotFound,
+            Some(ACCESS) => ErrorKind::AccessDenied,
+            Some(CONNECTION_REFUSED | CONNECTION_TERMINATED | KILLED) => ErrorKind::Disconnected,
+            Some(TIMEOUT) => ErrorKind::Timeout,
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
+/// The error from a single operation, after which the connection is still usable
+#[derive(Debug, Clone, Serialize)]
+pub struct OpError {
+    pub message: String,
+    /// The libpulse error code (as returned by `pa_context_errno`), if the server rejected the
+    /// operation
+    pub code: Option<i32>,
+    /// Set if the volumes provided didn't match the number of channels of the object
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel_count: Option<InvalidChannelCount>,
+}
+
+impl OpError {
+    /// Creates an error from the last error of a context
+    #[cfg(feature = "backend")]
+    pub fn from_errno(err: PAErr) -> OpError {
+        let message = err
+            .to_string()
+            .unwrap_or("An unknown error occurred".into());
+
+        OpError {
+            message: format!("Operation failed: {}", message),
+            // `pa_context_errno` returns a positive code, but other calls return negative codes
+            code: Some(err.0.abs()),
+            channel_count: None,
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        match self.channel_count {
+            Some(ref e) => ErrorKind::InvalidChannelCount(e.clone()),
+            None => ErrorKind::from_code(self.code),
+        }
+    }
+}
+
+impl From<InvalidChannelCount> for OpError {
+    fn from(e: InvalidChannelCount) -> Self {
+        OpError {
+            message: e.to_string(),
+            code: None,
+            channel_count: Some(e),
+        }
+    }
+}
+
+impl From<String> for OpError {
+    fn from(message: String) -> Self {
+        OpError {
+            message,
+            code: None,
+            channel_count: None,
+        }
+    }
+}
+
+impl From<&str> for OpError {
+    fn from(message: &str) -> Self {
+        message.to_string().into()
+    }
+}
+
+impl Display for OpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl Error for OpError {}
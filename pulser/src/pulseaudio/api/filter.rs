@@ -0,0 +1,61 @@
+use std::fmt::Debug;
+
+use super::{PAEvent, PAFacility, PAIdent};
+
+/// Decides which events are sent to a subscription, see `PACommand::SubscribeFiltered`. Events
+/// which don't pass are dropped in the mainloop's thread, so they never wake up the subscriber.
+pub enum EventFilter {
+    /// Only events which match
+    Match(EventMatch),
+    /// Only events for which the function returns `true`
+    Custom(Box<dyn Fn(&PAEvent) -> bool + Send>),
+}
+
+impl EventFilter {
+    pub fn matches(&self, ev: &PAEvent) -> bool {
+        match self {
+            EventFilter::Match(m) => m.matches(ev),
+            EventFilter::Custom(f) => f(ev),
+        }
+    }
+}
+
+impl Debug for EventFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EventFilter::Match(m) => f.debug_tuple("Match").field(m).finish(),
+            EventFilter::Custom(_) => f.write_str("Custom(..)"),
+        }
+    }
+}
+
+impl From<EventMatch> for EventFilter {
+    fn from(value: EventMatch) -> Self {
+        EventFilter::Match(value)
+    }
+}
+
+/// A declarative event filter. Each part which isn't empty must match, and events which don't
+/// have a facility or an object (such as `PAEvent::StreamUnderflow`) always match that part.
+#[derive(Debug, Clone, Default)]
+pub struct EventMatch {
+    /// The facilities events must be about, see `PAEvent::facility`
+    pub facilities: Vec<PAFacility>,
+    /// The indices of the objects events must be about, see `PAEvent::ident`
+    pub indices: Vec<u32>,
+}
+
+impl EventMatch {
+    pub fn matches(&self, ev: &PAEvent) -> bool {
+        let facility = match ev.facility() {
+            Some(facility) if !self.facilities.is_empty() => self.facilities.contains(&facility),
+            _ => true,
+        };
+        let index = match ev.ident() {
+            Some(PAIdent::Index(idx)) if !self.indices.is_empty() => self.indices.contains(idx),
+            _ => true,
+        };
+
+        facility && index
+    }
+}
@@ -0,0 +1,96 @@
+use serde::Serialize;
+
+use super::{PASinkInfo, PASinkInputInfo, PASourceInfo, PASourceOutputInfo};
+
+/// The latency of every device and stream, gathered into a single report. All values are in
+/// microseconds.
+#[derive(Debug, Clone, Serialize)]
+pub struct LatencyReport {
+    pub sinks: Vec<DeviceLatency>,
+    pub sources: Vec<DeviceLatency>,
+    pub sink_inputs: Vec<StreamLatency>,
+    pub source_outputs: Vec<StreamLatency>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceLatency {
+    pub index: u32,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    /// The current latency of the device
+    pub latency: u64,
+    /// The latency the device has been configured to
+    pub configured_latency: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamLatency {
+    pub index: u32,
+    pub name: Option<String>,
+    /// The `application.name` property of the stream
+    pub application: Option<String>,
+    /// The index of the sink or source the stream is connected to
+    pub device: u32,
+    /// Latency due to buffering in the stream
+    pub buffer: u64,
+    /// Latency of the device the stream is connected to
+    pub device_latency: u64,
+    /// The sum of `buffer` and `device_latency`
+    pub total: u64,
+}
+
+impl LatencyReport {
+    pub fn new(
+        sinks: &[PASinkInfo],
+        sources: &[PASourceInfo],
+        sink_inputs: &[PASinkInputInfo],
+        source_outputs: &[PASourceOutputInfo],
+    ) -> LatencyReport {
+        LatencyReport {
+            sinks: sinks
+                .iter()
+                .map(|sink| DeviceLatency {
+                    index: sink.index,
+                    name: sink.name.clone(),
+                    description: sink.description.clone(),
+                    latency: sink.latency,
+                    configured_latency: sink.configured_latency,
+                })
+                .collect(),
+            sources: sources
+                .iter()
+                .map(|source| DeviceLatency {
+                    index: source.index,
+                    name: source.name.clone(),
+                    description: source.description.clone(),
+                    latency: source.latency,
+                    configured_latency: source.configured_latency,
+                })
+                .collect(),
+            sink_inputs: sink_inputs
+                .iter()
+                .map(|input| StreamLatency {
+                    index: input.index,
+                    name: input.name.clone(),
+                    application: input.proplist.get_str("application.name"),
+                    device: input.sink,
+                    buffer: input.buffer_usec,
+                    device_latency: input.sink_usec,
+                    total: input.buffer_usec + input.sink_usec,
+                })
+                .collect(),
+            source_outputs: source_outputs
+                .iter()
+                .map(|output| StreamLatency {
+                    index: output.index,
+                    name: output.name.clone(),
+                    application: output.proplist.get_str("application.name"),
+                    device: output.source,
+                    buffer: output.buffer_usec,
+                    device_latency: output.source_usec,
+                    total: output.buffer_usec + output.source_usec,
+                })
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use serde::Serialize;
+
+use super::PASinkInputInfo;
+
+/// The peak level of a sink input, see `PulseAudio::monitor_stream_levels`
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamLevel {
+    pub index: u32,
+    pub name: Option<String>,
+    /// The `application.name` property of the stream
+    pub application: Option<String>,
+    /// The loudest sample since the last reading, where `1.0` is full scale
+    pub peak: f32,
+}
+
+impl StreamLevel {
+    pub fn new(input: &PASinkInputInfo) -> StreamLevel {
+        StreamLevel {
+            index: input.index,
+            name: input.name.clone(),
+            application: input.proplist.get_str("application.name"),
+            peak: 0.0,
+        }
+    }
+}
+
+/// The peak level of all the sink inputs of an application, see `PulseAudio::monitor_app_levels`
+#[derive(Debug, Clone, Serialize)]
+pub struct AppLevel {
+    /// The `application.name` property of the streams, or the stream's name if it has none
+    pub application: String,
+    /// The indices of the sink inputs
+    pub streams: Vec<u32>,
+    /// The loudest peak of any of the streams
+    pub peak: f32,
+}
+
+impl AppLevel {
+    /// Groups the levels of streams by their application, keeping the order they first appear in
+    pub fn group(levels: &[StreamLevel]) -> Vec<AppLevel> {
+        let mut apps: Vec<AppLevel> = vec![];
+        for level in levels {
+            let application = level
+                .application
+                .clone()
+                .or_else(|| level.name.clone())
+                .unwrap_or_else(|| format!("#{}", level.index));
+
+            match apps.iter_mut().find(|app| app.application == application) {
+                Some(app) => {
+                    app.streams.push(level.index);
+                    app.peak = app.peak.max(level.peak);
+                }
+                None => apps.push(AppLevel {
+                    application,
+                    streams: vec![level.index],
+                    peak: level.peak,
+                }),
+            }
+        }
+
+        apps
+    }
+}
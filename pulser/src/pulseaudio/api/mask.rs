@@ -0,0 +1,52 @@
+//! `PAMask` doesn't implement serde itself, since it's just a set of bits. Instead, use this module
+//! with `#[serde(with = "pulser::api::mask")]`, which (de)serializes masks as a list of names, such
+//! as `["sink", "sink_input"]` or `["all"]`. The names are the same as those of `PAFacility`.
+
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::{PAFacility, PAMask};
+
+/// The names of each facility in the mask, or just `all` if it contains all of them
+pub fn names(mask: PAMask) -> Vec<&'static str> {
+    if mask.contains(PAMask::ALL) {
+        return vec!["all"];
+    }
+
+    PAFacility::ALL
+        .iter()
+        .filter(|f| mask.contains(f.mask()))
+        .map(PAFacility::name)
+        .collect()
+}
+
+/// Parses a single name (as returned by `names`) into a mask
+pub fn from_name(name: &str) -> Result<PAMask, String> {
+    if name == "all" {
+        return Ok(PAMask::ALL);
+    }
+
+    PAFacility::ALL
+        .iter()
+        .find(|f| f.name() == name)
+        .map(PAFacility::mask)
+        .ok_or_else(|| format!("Unknown mask \"{}\"", name))
+}
+
+pub fn serialize<S>(mask: &PAMask, s: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    names(*mask).serialize(s)
+}
+
+pub fn deserialize<'de, D>(deserializer: D) -> Result<PAMask, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .try_fold(PAMask::empty(), |mask, name| {
+            from_name(name).map(|m| mask | m).map_err(D::Error::custom)
+        })
+}
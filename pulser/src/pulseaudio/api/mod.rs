@@ -1,15 +1,47 @@
+pub mod app_volume;
+pub mod bluetooth;
+pub mod capabilities;
+pub mod card_rules;
 pub mod command;
+pub mod encoding;
+pub mod error;
+pub mod filter;
+pub mod latency;
+pub mod level;
+pub mod mask;
+mod pattern;
+pub mod prefer;
+pub mod proplist;
+pub mod raw;
+pub mod request;
 pub mod structs;
+pub mod traits;
 pub mod volume;
 
 use std::fmt::Display;
+use std::str::FromStr;
 
+pub use app_volume::*;
+pub use bluetooth::*;
+pub use capabilities::*;
+pub use card_rules::*;
 pub use command::*;
-use serde::Serialize;
+pub use encoding::*;
+pub use error::*;
+pub use filter::*;
+pub use latency::*;
+pub use level::*;
+pub use prefer::*;
+pub use proplist::*;
+pub use raw::*;
+pub use request::PARequest;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize};
 pub use structs::*;
+pub use traits::*;
 pub use volume::*;
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 #[serde(rename_all = "lowercase")]
 pub enum PAIdent {
     Index(u32),
@@ -24,3 +56,126 @@ impl Display for PAIdent {
         }
     }
 }
+
+impl PAIdent {
+    /// Parses an index, either as "42" or "#42"
+    pub fn parse_index(s: &str) -> Result<PAIdent, String> {
+        s.strip_prefix('#')
+            .unwrap_or(s)
+            .parse::<u32>()
+            .map(PAIdent::Index)
+            .map_err(|e| format!("Invalid index \"{}\": {}", s, e))
+    }
+}
+
+/// Identifies the stream (or streams) to act on, for commands which resolve streams on the server
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PAStreamIdent {
+    Index(u32),
+    /// Every stream whose `application.name` matches this (case-insensitively)
+    App(String),
+}
+
+impl PAStreamIdent {
+    /// Whether a stream with the given property list matches this, by application name
+    pub fn matches_app(&self, proplist: &PAProplist) -> bool {
+        match self {
+            PAStreamIdent::Index(_) => false,
+            PAStreamIdent::App(app) => proplist
+                .get_str("application.name")
+                .map_or(false, |name| name.eq_ignore_ascii_case(app)),
+        }
+    }
+}
+
+impl Display for PAStreamIdent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PAStreamIdent::Index(idx) => write!(f, "#{}", idx),
+            PAStreamIdent::App(app) => write!(f, "app \"{}\"", app),
+        }
+    }
+}
+
+impl From<u32> for PAStreamIdent {
+    fn from(value: u32) -> Self {
+        PAStreamIdent::Index(value)
+    }
+}
+
+/// Narrows down a list of streams. This is applied on the mainloop as the list is received, so
+/// streams which don't match are never copied or sent back.
+#[derive(Debug, Clone, Default)]
+pub struct StreamFilter {
+    /// Only streams which are (or aren't) corked
+    pub corked: Option<bool>,
+    /// Only streams whose `application.name` contains this (case-insensitively)
+    pub app: Option<String>,
+}
+
+impl StreamFilter {
+    pub fn matches(&self, corked: bool, app: Option<&str>) -> bool {
+        if self.corked.map_or(false, |c| c != corked) {
+            return false;
+        }
+
+        match (&self.app, app) {
+            (None, _) => true,
+            (Some(_), None) => false,
+            (Some(filter), Some(app)) => app.to_lowercase().contains(&filter.to_lowercase()),
+        }
+    }
+}
+
+/// Parses "42" and "#42" as indices, and anything else as a name
+impl FromStr for PAIdent {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err("An identifier can't be empty".into());
+        }
+
+        if s.starts_with('#') {
+            return PAIdent::parse_index(s);
+        }
+
+        Ok(match s.parse::<u32>() {
+            Ok(idx) => PAIdent::Index(idx),
+            Err(_) => PAIdent::Name(s.to_string()),
+        })
+    }
+}
+
+/// Accepts a number (an index), a string (parsed with `FromStr`) or the serialized form, such as
+/// `{"index": 42}` or `{"name": "foo"}`
+impl<'de> Deserialize<'de> for PAIdent {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "lowercase")]
+        enum Tagged {
+            Index(u32),
+            Name(String),
+        }
+
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Index(u32),
+            String(String),
+            Tagged(Tagged),
+        }
+
+        match Repr::deserialize(deserializer) {
+            Ok(Repr::Index(idx)) | Ok(Repr::Tagged(Tagged::Index(idx))) => Ok(PAIdent::Index(idx)),
+            Ok(Repr::Tagged(Tagged::Name(name))) => Ok(PAIdent::Name(name)),
+            Ok(Repr::String(s)) => s.parse().map_err(D::Error::custom),
+            Err(_) => Err(D::Error::custom(
+                "expected an index, a name, or an object with an \"index\" or \"name\"",
+            )),
+        }
+    }
+}
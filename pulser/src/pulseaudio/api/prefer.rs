@@ -0,0 +1,36 @@
+use serde::Deserialize;
+
+use super::pattern::glob_matches;
+use super::PASinkInfo;
+
+/// An ordered list of preferred sinks, used to pick a new default sink when the current one
+/// disappears (such as when a headset is turned off), rather than whichever sink the server picks.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SinkPreference {
+    /// Sink names, most preferred first. A `*` matches any number of characters, so
+    /// `bluez_output.*` matches any Bluetooth sink.
+    pub patterns: Vec<String>,
+    /// Whether to move all streams to the new default sink
+    #[serde(default)]
+    pub move_streams: bool,
+}
+
+impl SinkPreference {
+    pub fn new(patterns: Vec<String>) -> SinkPreference {
+        SinkPreference {
+            patterns,
+            move_streams: false,
+        }
+    }
+
+    /// The most preferred of the given sinks, or `None` if none of them match
+    pub fn choose<'a>(&self, sinks: &'a [PASinkInfo]) -> Option<&'a PASinkInfo> {
+        self.patterns.iter().find_map(|pattern| {
+            sinks.iter().find(|sink| {
+                sink.name
+                    .as_deref()
+                    .map_or(false, |name| glob_matches(pattern, name))
+            })
+        })
+    }
+}
@@ -0,0 +1,40 @@
+#[cfg(feature = "backend")]
+use libpulse_binding::proplist::UpdateMode;
+
+use super::PAIdent;
+
+/// The object whose properties `PACommand::UpdateDeviceProplist` updates
+#[derive(Debug, Clone)]
+pub enum ProplistTarget {
+    /// Not supported by the native protocol, which has no way to change a sink's properties
+    Sink(PAIdent),
+    /// Not supported by the native protocol, which has no way to change a source's properties
+    Source(PAIdent),
+    /// Our own client, whose properties every stream it opens inherits
+    Client,
+    /// A stream started with `PACommand::PlayRaw` or `PACommand::RecordRaw`
+    Stream(u32),
+}
+
+/// How `PACommand::UpdateDeviceProplist` combines the given properties with the current ones
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum ProplistMode {
+    /// Replace all of the properties with the given ones
+    Set,
+    /// Only add the properties which aren't set yet
+    Merge,
+    /// Add the given properties, replacing any which are already set
+    #[default]
+    Replace,
+}
+
+#[cfg(feature = "backend")]
+impl From<ProplistMode> for UpdateMode {
+    fn from(value: ProplistMode) -> Self {
+        match value {
+            ProplistMode::Set => UpdateMode::Set,
+            ProplistMode::Merge => UpdateMode::Merge,
+            ProplistMode::Replace => UpdateMode::Replace,
+        }
+    }
+}
@@ -0,0 +1,335 @@
+use std::fmt::Display;
+use std::str::FromStr;
+use std::time::Duration;
+
+#[cfg(feature = "backend")]
+use libpulse_binding::def::BufferAttr;
+#[cfg(feature = "backend")]
+use libpulse_binding::sample::{Format, Spec};
+#[cfg(feature = "backend")]
+use libpulse_binding::stream::FlagSet;
+#[cfg(feature = "backend")]
+use libpulse_binding::time::MicroSeconds;
+use serde::Serialize;
+
+use super::{PASampleSpec, PAVol};
+
+/// The format of each sample in raw PCM audio, named like `pacat --format`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RawFormat {
+    U8,
+    S16le,
+    S16be,
+    S24le,
+    S24be,
+    S32le,
+    S32be,
+    F32le,
+    F32be,
+}
+
+impl RawFormat {
+    pub const ALL: [RawFormat; 9] = [
+        RawFormat::U8,
+        RawFormat::S16le,
+        RawFormat::S16be,
+        RawFormat::S24le,
+        RawFormat::S24be,
+        RawFormat::S32le,
+        RawFormat::S32be,
+        RawFormat::F32le,
+        RawFormat::F32be,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            RawFormat::U8 => "u8",
+            RawFormat::S16le => "s16le",
+            RawFormat::S16be => "s16be",
+            RawFormat::S24le => "s24le",
+            RawFormat::S24be => "s24be",
+            RawFormat::S32le => "s32le",
+            RawFormat::S32be => "s32be",
+            RawFormat::F32le => "f32le",
+            RawFormat::F32be => "f32be",
+        }
+    }
+
+    /// The name libpulse gives this format, as in `PASampleSpec::format`
+    pub fn sample_format_name(&self) -> &'static str {
+        match self {
+            RawFormat::F32le => "float32le",
+            RawFormat::F32be => "float32be",
+            other => other.name(),
+        }
+    }
+}
+
+#[cfg(feature = "backend")]
+impl From<RawFormat> for Format {
+    fn from(value: RawFormat) -> Self {
+        match value {
+            RawFormat::U8 => Format::U8,
+            RawFormat::S16le => Format::S16le,
+            RawFormat::S16be => Format::S16be,
+            RawFormat::S24le => Format::S24le,
+            RawFormat::S24be => Format::S24be,
+            RawFormat::S32le => Format::S32le,
+            RawFormat::S32be => Format::S32be,
+            RawFormat::F32le => Format::F32le,
+            RawFormat::F32be => Format::F32be,
+        }
+    }
+}
+
+impl Display for RawFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for RawFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        RawFormat::ALL
+            .into_iter()
+            .find(|format| format.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                let names = RawFormat::ALL.map(|f| f.name()).join(", ");
+                format!(
+                    "Unknown sample format \"{}\", expected one of: {}",
+                    s, names
+                )
+            })
+    }
+}
+
+/// The layout of raw PCM audio, such as what's read from stdin by `pacat --raw`
+#[derive(Debug, Copy, Clone, Serialize)]
+pub struct RawSpec {
+    pub format: RawFormat,
+    /// Samples per second, per channel
+    pub rate: u32,
+    pub channels: u8,
+}
+
+#[cfg(feature = "backend")]
+impl RawSpec {
+    /// The equivalent sample spec, or an error if the rate or channel count is out of range
+    pub fn sample_spec(&self) -> Result<Spec, String> {
+        let spec = Spec {
+            format: self.format.into(),
+            rate: self.rate,
+            channels: self.channels,
+        };
+
+        match spec.is_valid() {
+            true => Ok(spec),
+            false => Err(format!(
+                "Invalid sample spec: {} {}Hz with {} channels",
+                self.format, self.rate, self.channels
+            )),
+        }
+    }
+}
+
+#[cfg(feature = "backend")]
+impl TryFrom<&Spec> for RawSpec {
+    type Error = String;
+
+    fn try_from(value: &Spec) -> Result<Self, Self::Error> {
+        let format = RawFormat::ALL
+            .into_iter()
+            .find(|format| Format::from(*format) == value.format)
+            .ok_or_else(|| format!("Unsupported sample format: {:?}", value.format))?;
+
+        Ok(RawSpec {
+            format,
+            rate: value.rate,
+            channels: value.channels,
+        })
+    }
+}
+
+impl TryFrom<&PASampleSpec> for RawSpec {
+    type Error = String;
+
+    fn try_from(value: &PASampleSpec) -> Result<Self, Self::Error> {
+        let format = value.raw_format().ok_or_else(|| {
+            let name = value.format.as_deref().unwrap_or("invalid");
+            format!("Unsupported sample format: {}", name)
+        })?;
+
+        Ok(RawSpec {
+            format,
+            rate: value.rate,
+            channels: value.channels,
+        })
+    }
+}
+
+impl Default for RawSpec {
+    fn default() -> Self {
+        RawSpec {
+            format: RawFormat::S16le,
+            rate: 44100,
+            channels: 2,
+        }
+    }
+}
+
+/// How to open a raw stream
+#[derive(Debug, Clone, Default)]
+pub struct RawStreamOptions {
+    /// The name of the sink (or source) to connect to, otherwise the default is used
+    pub device: Option<String>,
+    pub spec: RawSpec,
+    /// Playback only: the layout of the data being played, if it differs from `spec`. It's
+    /// converted to `spec` as it's played.
+    #[cfg(feature = "resample")]
+    pub input: Option<RawSpec>,
+    pub buffer: StreamBuffer,
+    /// The initial volume of every channel, otherwise the server picks one (playback only)
+    pub volume: Option<PAVol>,
+    /// Don't let the stream be moved to another device (such as by the user, or when the device is
+    /// removed); the stream fails instead
+    pub dont_move: bool,
+    /// Recording only: record just this sink input, rather than everything playing on the sink.
+    /// `device` must be the monitor source of the sink input's sink.
+    pub monitor_stream: Option<u32>,
+    /// Recording only: each sample is the peak of a chunk of audio, rather than the audio itself.
+    /// Use with a low rate (such as 25Hz) for level meters.
+    pub peak_detect: bool,
+}
+
+impl RawStreamOptions {
+    pub fn new(device: Option<String>, spec: RawSpec) -> RawStreamOptions {
+        RawStreamOptions {
+            device,
+            spec,
+            ..Default::default()
+        }
+    }
+
+    /// The flags to connect the stream with
+    #[cfg(feature = "backend")]
+    pub fn flags(&self) -> FlagSet {
+        // the timing info is needed by `PACommand::GetStreamLatency`
+        let mut flags = FlagSet::AUTO_TIMING_UPDATE;
+        if self.buffer.adjust_latency() {
+            flags |= FlagSet::ADJUST_LATENCY;
+        }
+        if self.dont_move {
+            flags |= FlagSet::DONT_MOVE;
+        }
+        if self.peak_detect {
+            flags |= FlagSet::PEAK_DETECT;
+        }
+        flags
+    }
+}
+
+/// How much audio a stream buffers, which trades latency against the risk of underruns (or
+/// overruns, when recording). Each value is in bytes, and `None` leaves it up to the server.
+#[derive(Debug, Copy, Clone, Default, Serialize)]
+pub struct PABufferAttr {
+    /// The maximum length of the buffer
+    pub maxlength: Option<u32>,
+    /// Playback only: the length the server tries to keep the buffer filled to
+    pub tlength: Option<u32>,
+    /// Playback only: how much must be buffered before playback starts
+    pub prebuf: Option<u32>,
+    /// Playback only: the minimum amount the server asks for at once
+    pub minreq: Option<u32>,
+    /// Recording only: how much is read at once
+    pub fragsize: Option<u32>,
+}
+
+#[cfg(feature = "backend")]
+impl From<&PABufferAttr> for BufferAttr {
+    fn from(value: &PABufferAttr) -> Self {
+        let or_default = |v: Option<u32>| v.unwrap_or(u32::MAX);
+        BufferAttr {
+            maxlength: or_default(value.maxlength),
+            tlength: or_default(value.tlength),
+            prebuf: or_default(value.prebuf),
+            minreq: or_default(value.minreq),
+            fragsize: or_default(value.fragsize),
+        }
+    }
+}
+
+#[cfg(feature = "backend")]
+impl From<&BufferAttr> for PABufferAttr {
+    fn from(value: &BufferAttr) -> Self {
+        let known = |v: u32| (v != u32::MAX).then_some(v);
+        PABufferAttr {
+            maxlength: known(value.maxlength),
+            tlength: known(value.tlength),
+            prebuf: known(value.prebuf),
+            minreq: known(value.minreq),
+            fragsize: known(value.fragsize),
+        }
+    }
+}
+
+/// How the buffer of a raw stream is sized
+#[derive(Debug, Copy, Clone, Default)]
+pub enum StreamBuffer {
+    /// The server's defaults, which usually means around two seconds of latency
+    #[default]
+    Default,
+    /// Exact buffer attributes
+    Attr(PABufferAttr),
+    /// Sizes the buffer for this latency, like `pacat --latency-msec`
+    Latency(Duration),
+}
+
+impl StreamBuffer {
+    /// The buffer attributes to connect a stream with, if any
+    #[cfg(feature = "backend")]
+    pub fn buffer_attr(&self, spec: &Spec, playback: bool) -> Option<BufferAttr> {
+        match self {
+            StreamBuffer::Default => None,
+            StreamBuffer::Attr(attr) => Some(attr.into()),
+            StreamBuffer::Latency(latency) => {
+                let bytes = spec.usec_to_bytes(MicroSeconds(latency.as_micros() as u64));
+                let bytes = Some(bytes.min(u32::MAX as usize - 1) as u32);
+                let attr = match playback {
+                    true => PABufferAttr {
+                        tlength: bytes,
+                        ..Default::default()
+                    },
+                    false => PABufferAttr {
+                        fragsize: bytes,
+                        ..Default::default()
+                    },
+                };
+                Some((&attr).into())
+            }
+        }
+    }
+
+    /// Whether the server should adjust the device's latency to meet the stream's
+    pub fn adjust_latency(&self) -> bool {
+        matches!(self, StreamBuffer::Latency(_))
+    }
+}
+
+/// The current latency of a stream opened with `PACommand::PlayRaw` or `PACommand::RecordRaw`. All
+/// durations are in microseconds.
+#[derive(Debug, Clone, Serialize)]
+pub struct RawStreamLatency {
+    /// The total latency, which is negative if a recording stream has read past the device
+    pub latency: i64,
+    /// The latency of the sink or source
+    pub device_latency: u64,
+    /// How long it takes to transfer data between the client and the server
+    pub transport: u64,
+    /// Whether the stream is playing (or recording), rather than corked or waiting for data
+    pub playing: bool,
+    /// The buffer attributes the server chose
+    pub buffer: PABufferAttr,
+}
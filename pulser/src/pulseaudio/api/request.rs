@@ -0,0 +1,168 @@
+//! Commands paired with the response they're answered with, so a getter can't be wired to the
+//! wrong `PAResponse` variant. See `PulseAudio::request`.
+
+use std::time::Duration;
+
+use crossbeam_channel::{Receiver, Sender};
+
+use super::*;
+
+/// A `PACommand` which is answered with a single `PAResponse` carrying a value
+pub trait PARequest {
+    type Response;
+
+    fn into_command(self) -> PACommand;
+
+    /// Takes the value out of the response this request is answered with, or returns the response
+    /// as it is if it's any other variant (such as `PAResponse::OpError`)
+    fn from_response(res: PAResponse) -> Result<Self::Response, PAResponse>;
+}
+
+macro_rules! request {
+    ($(#[$meta:meta])* $name:ident -> $ret:ty, $pattern:pat => $mapping:expr) => {
+        $(#[$meta])*
+        #[derive(Debug)]
+        pub struct $name;
+
+        $(#[$meta])*
+        impl PARequest for $name {
+            type Response = $ret;
+
+            fn into_command(self) -> PACommand {
+                PACommand::$name
+            }
+
+            fn from_response(res: PAResponse) -> Result<$ret, PAResponse> {
+                match res {
+                    $pattern => Ok($mapping),
+                    res => Err(res),
+                }
+            }
+        }
+    };
+    ($(#[$meta:meta])* $name:ident($($arg:ident: $ty:ty),+) -> $ret:ty, $pattern:pat => $mapping:expr) => {
+        $(#[$meta])*
+        #[derive(Debug)]
+        pub struct $name($(pub $ty),+);
+
+        $(#[$meta])*
+        impl PARequest for $name {
+            type Response = $ret;
+
+            fn into_command(self) -> PACommand {
+                let $name($($arg),+) = self;
+                PACommand::$name($($arg),+)
+            }
+
+            fn from_response(res: PAResponse) -> Result<$ret, PAResponse> {
+                match res {
+                    $pattern => Ok($mapping),
+                    res => Err(res),
+                }
+            }
+        }
+    };
+}
+
+/*
+ * Server
+ */
+
+request!(GetServerInfo -> PAServerInfo, PAResponse::ServerInfo(x) => *x);
+request!(GetProtocolInfo -> ProtocolInfo, PAResponse::ProtocolInfo(x) => x);
+request!(GetCapabilities -> Capabilities, PAResponse::Capabilities(x) => x);
+request!(GetDefaultSink -> Option<PAIdent>, PAResponse::DefaultSink(x) => x);
+request!(GetDefaultSource -> Option<PAIdent>, PAResponse::DefaultSource(x) => x);
+request!(
+    #[cfg(feature = "pa_v15")]
+    SendMessage(recipient: String, message: String, params: String) -> Option<String>,
+    PAResponse::Message(x) => x
+);
+
+/*
+ * Cards, clients and modules
+ */
+
+request!(GetCardInfo(id: PAIdent) -> PACardInfo, PAResponse::CardInfo(x) => x);
+request!(GetClientInfo(idx: u32) -> PAClientInfo, PAResponse::ClientInfo(x) => x);
+request!(GetModuleInfo(idx: u32) -> PAModuleInfo, PAResponse::ModuleInfo(x) => x);
+request!(LoadModule(name: String, args: String) -> u32, PAResponse::ModuleLoaded(x) => x);
+
+/*
+ * Sinks
+ */
+
+request!(GetSinkInfo(id: PAIdent) -> PASinkInfo, PAResponse::SinkInfo(x) => x);
+request!(GetSinkMute(id: PAIdent) -> bool, PAResponse::Mute(_, x) => x);
+request!(GetSinkVolume(id: PAIdent) -> VolumeReadings, PAResponse::Volume(_, x) => x);
+request!(
+    FadeSinkVolume(id: PAIdent, vol: VolumeSpec, duration: Duration, curve: FadeCurve) -> u32,
+    PAResponse::FadeStarted(x) => x
+);
+
+/*
+ * Sources
+ */
+
+request!(GetSourceInfo(id: PAIdent) -> PASourceInfo, PAResponse::SourceInfo(x) => x);
+request!(GetSourceMute(id: PAIdent) -> bool, PAResponse::Mute(_, x) => x);
+request!(GetSourceVolume(id: PAIdent) -> VolumeReadings, PAResponse::Volume(_, x) => x);
+
+/*
+ * Sink inputs
+ */
+
+request!(GetSinkInputInfo(idx: u32) -> PASinkInputInfo, PAResponse::SinkInputInfo(x) => x);
+request!(GetSinkInputMute(idx: u32) -> bool, PAResponse::Mute(_, x) => x);
+request!(GetSinkInputVolume(idx: u32) -> VolumeReadings, PAResponse::Volume(_, x) => x);
+
+/*
+ * Source outputs
+ */
+
+request!(
+    GetSourceOutputInfo(idx: u32) -> PASourceOutputInfo,
+    PAResponse::SourceOutputInfo(x) => x
+);
+request!(GetSourceOutputMute(idx: u32) -> bool, PAResponse::Mute(_, x) => x);
+request!(GetSourceOutputVolume(idx: u32) -> VolumeReadings, PAResponse::Volume(_, x) => x);
+
+/*
+ * Lists
+ */
+
+request!(GetCardInfoList -> Vec<PACardInfo>, PAResponse::CardInfoList(x) => x);
+request!(GetClientInfoList -> Vec<PAClientInfo>, PAResponse::ClientInfoList(x) => x);
+request!(GetModuleInfoList -> Vec<PAModuleInfo>, PAResponse::ModuleInfoList(x) => x);
+request!(GetSampleInfoList -> Vec<PASampleInfo>, PAResponse::SampleInfoList(x) => x);
+request!(GetSinkInfoList -> Vec<PASinkInfo>, PAResponse::SinkInfoList(x) => x);
+request!(GetSinkInputInfoList -> Vec<PASinkInputInfo>, PAResponse::SinkInputInfoList(x) => x);
+request!(
+    GetSinkInputInfoListFiltered(filter: StreamFilter) -> Vec<PASinkInputInfo>,
+    PAResponse::SinkInputInfoList(x) => x
+);
+request!(GetSourceInfoList -> Vec<PASourceInfo>, PAResponse::SourceInfoList(x) => x);
+request!(
+    GetSourceOutputInfoList -> Vec<PASourceOutputInfo>,
+    PAResponse::SourceOutputInfoList(x) => x
+);
+
+/*
+ * Streams
+ */
+
+request!(
+    PlayRaw(options: RawStreamOptions, data: Receiver<Vec<u8>>) -> u32,
+    PAResponse::StreamStarted(x) => x
+);
+request!(
+    RecordRaw(options: RawStreamOptions, data: Sender<Vec<u8>>) -> u32,
+    PAResponse::StreamStarted(x) => x
+);
+request!(GetStreamLatency(stream_id: u32) -> RawStreamLatency, PAResponse::StreamLatency(x) => x);
+
+/*
+ * Stream restore
+ */
+
+request!(GetAppVolumes -> Vec<AppVolume>, PAResponse::AppVolumes(x) => x);
@@ -9,10 +9,17 @@
 //! TODO: see if there's a way to automate this (proc macro? hacky script?)
 //! TODO: these structs are currently missing any fields that are gated behind feature flags
 
-use libpulse_binding::channelmap::Position;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Display;
+use std::ops::{BitOr, BitOrAssign};
+
+#[cfg(feature = "backend")]
+use libpulse_binding::channelmap::{Map, Position};
+#[cfg(feature = "backend")]
 use libpulse_binding::context::introspect::{
     CardInfo,
     CardPortInfo,
+    CardProfileInfo2,
     ClientInfo,
     ModuleInfo,
     SampleInfo,
@@ -24,99 +31,36 @@ use libpulse_binding::context::introspect::{
     SourceOutputInfo,
     SourcePortInfo,
 };
-use libpulse_binding::context::subscribe::Facility;
-pub use libpulse_binding::context::subscribe::InterestMaskSet as PAMask;
+#[cfg(feature = "backend")]
+use libpulse_binding::context::subscribe::{Facility, InterestMaskSet};
+#[cfg(feature = "backend")]
+use libpulse_binding::def::{PortAvailable, SinkState, SourceState};
+#[cfg(feature = "backend")]
 use libpulse_binding::proplist::Proplist;
-use libpulse_binding::time::MicroSeconds;
-use libpulse_binding::volume::{ChannelVolumes, Volume, VolumeDB, VolumeLinear};
-use libpulse_binding::{channelmap, def, direction, format, sample};
+#[cfg(feature = "backend")]
+use libpulse_binding::volume::{ChannelVolumes, Volume};
+#[cfg(feature = "backend")]
+use libpulse_binding::{format, sample};
+use serde::de::Error as _;
 use serde::ser::SerializeMap;
-use serde::{Serialize, Serializer};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::{HasVolume, PAEncoding, RawFormat};
+#[cfg(feature = "backend")]
+use crate::volume::new_channel_volumes;
+use crate::volume::{volume_to_decibels, volume_to_linear, volume_to_percentage};
 
+/// The most channels a sample spec or channel map can have (`PA_CHANNELS_MAX`)
+pub const CHANNELS_MAX: usize = 32;
+
+#[cfg(feature = "backend")]
 macro_rules! cow {
     ($cow:expr) => {
         $cow.as_ref().map(|p| (&**p).into())
     };
 }
 
-fn ser_sample_spec<S>(sample_sec: &sample::Spec, s: S) -> Result<S::Ok, S::Error>
-where
-    S: Serializer,
-{
-    let mut map = s.serialize_map(None)?;
-    map.serialize_entry("channels", &sample_sec.channels)?;
-    map.serialize_entry("rate", &sample_sec.rate)?;
-    map.serialize_entry("format", &sample_sec.format.to_string())?;
-    map.end()
-}
-
-fn ser_port_available<S>(available: &def::PortAvailable, s: S) -> Result<S::Ok, S::Error>
-where
-    S: Serializer,
-{
-    s.serialize_i8(*available as i8)
-}
-
-fn ser_channel_volumes<S>(volume: &ChannelVolumes, s: S) -> Result<S::Ok, S::Error>
-where
-    S: Serializer,
-{
-    let mut map = s.serialize_map(None)?;
-    map.serialize_entry("channels", &volume.len())?;
-    map.serialize_entry(
-        "volumes",
-        &volume
-            .get()
-            .iter()
-            .map(|v| PAVolume::from(*v))
-            .collect::<Vec<_>>(),
-    )?;
-    map.end()
-}
-
-fn ser_microseconds<S>(latency: &MicroSeconds, s: S) -> Result<S::Ok, S::Error>
-where
-    S: Serializer,
-{
-    s.serialize_u64(latency.0)
-}
-
-fn ser_sink_flag_set<S>(flags: &def::SinkFlagSet, s: S) -> Result<S::Ok, S::Error>
-where
-    S: Serializer,
-{
-    s.serialize_u32(flags.bits())
-}
-
-fn ser_source_flag_set<S>(flags: &def::SourceFlagSet, s: S) -> Result<S::Ok, S::Error>
-where
-    S: Serializer,
-{
-    s.serialize_u32(flags.bits())
-}
-
-fn ser_sink_state<S>(state: &def::SinkState, s: S) -> Result<S::Ok, S::Error>
-where
-    S: Serializer,
-{
-    s.serialize_i8(*state as i8)
-}
-
-fn ser_source_state<S>(state: &def::SourceState, s: S) -> Result<S::Ok, S::Error>
-where
-    S: Serializer,
-{
-    s.serialize_i8(*state as i8)
-}
-
-fn ser_flag_set<S>(formats: &direction::FlagSet, s: S) -> Result<S::Ok, S::Error>
-where
-    S: Serializer,
-{
-    s.serialize_i32(formats.bits())
-}
-
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PAServerInfo {
     /// User name of the daemon process.
     pub user_name: Option<String>,
@@ -127,8 +71,7 @@ pub struct PAServerInfo {
     /// Server package name (usually “pulseaudio”).
     pub server_name: Option<String>,
     /// Default sample specification.
-    #[serde(serialize_with = "ser_sample_spec")]
-    pub sample_spec: sample::Spec,
+    pub sample_spec: PASampleSpec,
     /// Name of default sink.
     pub default_sink_name: Option<String>,
     /// Name of default source.
@@ -139,6 +82,7 @@ pub struct PAServerInfo {
     pub channel_map: PAChannelMap,
 }
 
+#[cfg(feature = "backend")]
 impl<'a> From<&'a ServerInfo<'a>> for PAServerInfo {
     fn from(value: &ServerInfo) -> Self {
         PAServerInfo {
@@ -146,7 +90,7 @@ impl<'a> From<&'a ServerInfo<'a>> for PAServerInfo {
             host_name: cow!(value.host_name),
             server_version: cow!(value.server_version),
             server_name: cow!(value.server_name),
-            sample_spec: value.sample_spec,
+            sample_spec: value.sample_spec.into(),
             default_sink_name: cow!(value.default_sink_name),
             default_source_name: value
                 .default_source_name
@@ -158,7 +102,7 @@ impl<'a> From<&'a ServerInfo<'a>> for PAServerInfo {
     }
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PASinkPortInfo {
     /// Name of this port.
     pub name: Option<String>,
@@ -167,22 +111,22 @@ pub struct PASinkPortInfo {
     /// The higher this value is, the more useful this port is as a default.
     pub priority: u32,
     /// A flag indicating availability status of this port.
-    #[serde(serialize_with = "ser_port_available")]
-    pub available: def::PortAvailable,
+    pub available: PAPortAvailable,
 }
 
+#[cfg(feature = "backend")]
 impl<'a> From<&'a SinkPortInfo<'a>> for PASinkPortInfo {
     fn from(value: &'a SinkPortInfo<'a>) -> Self {
         PASinkPortInfo {
             name: cow!(value.name),
             description: cow!(value.description),
             priority: value.priority,
-            available: value.available,
+            available: value.available.into(),
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PASinkInfo {
     /// Name of the sink.
     pub name: Option<String>,
@@ -191,40 +135,34 @@ pub struct PASinkInfo {
     /// Description of this sink.
     pub description: Option<String>,
     /// Sample spec of this sink.
-    #[serde(serialize_with = "ser_sample_spec")]
-    pub sample_spec: sample::Spec,
+    pub sample_spec: PASampleSpec,
     /// Channel map.
     pub channel_map: PAChannelMap,
     /// Index of the owning module of this sink, or `None` if is invalid.
     pub owner_module: Option<u32>,
     /// Volume of the sink.
-    #[serde(serialize_with = "ser_channel_volumes")]
-    pub volume: ChannelVolumes,
+    pub volume: PAChannelVolumes,
     /// Mute switch of the sink.
     pub mute: bool,
     /// Index of the monitor source connected to this sink.
     pub monitor_source: u32,
     /// The name of the monitor source.
     pub monitor_source_name: Option<String>,
-    /// Length of queued audio in the output buffer.
-    #[serde(serialize_with = "ser_microseconds")]
-    pub latency: MicroSeconds,
+    /// Length of queued audio in the output buffer, in microseconds.
+    pub latency: u64,
     /// Driver name.
     pub driver: Option<String>,
-    /// Flags.
-    #[serde(serialize_with = "ser_sink_flag_set")]
-    pub flags: def::SinkFlagSet,
+    /// Flags (`pa_sink_flags_t` or `pa_source_flags_t`).
+    pub flags: u32,
     /// Property list.
     pub proplist: PAProplist,
-    /// The latency this device has been configured to.
-    #[serde(serialize_with = "ser_microseconds")]
-    pub configured_latency: MicroSeconds,
+    /// The latency this device has been configured to, in microseconds.
+    pub configured_latency: u64,
     /// Some kind of “base” volume that refers to unamplified/unattenuated volume in the context of
     /// the output device.
     pub base_volume: PAVolume,
     /// State.
-    #[serde(serialize_with = "ser_sink_state")]
-    pub state: def::SinkState,
+    pub state: PADeviceState,
     /// Number of volume steps for sinks which do not support arbitrary volumes.
     pub n_volume_steps: u32,
     /// Card index, or `None` if invalid.
@@ -237,39 +175,54 @@ pub struct PASinkInfo {
     pub formats: Vec<PAInfo>,
 }
 
+/// A one line summary, such as `#1 Built-in Audio: FL: 65%, FR: 65% (muted)`
+impl Display for PASinkInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = self.description.as_ref().or(self.name.as_ref());
+        write!(f, "#{} {}: ", self.index, name.map_or("-", |n| n.as_str()))?;
+        write!(f, "{}", self.volume_readings())?;
+        if self.mute {
+            f.write_str(" (muted)")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "backend")]
 impl<'a> From<&'a SinkInfo<'a>> for PASinkInfo {
     fn from(value: &'a SinkInfo<'a>) -> Self {
         PASinkInfo {
             name: cow!(value.name),
             index: value.index,
             description: cow!(value.description),
-            sample_spec: value.sample_spec,
+            sample_spec: value.sample_spec.into(),
             channel_map: value.channel_map.into(),
             owner_module: value.owner_module,
-            volume: value.volume,
+            volume: value.volume.into(),
             mute: value.mute,
             monitor_source: value.monitor_source,
             monitor_source_name: value
                 .monitor_source_name
                 .as_ref()
                 .map(|cow| cow.to_string()),
-            latency: value.latency,
+            latency: value.latency.0,
             driver: cow!(value.driver),
-            flags: value.flags,
-            proplist: value.proplist.clone().into(),
-            configured_latency: value.configured_latency,
+            flags: value.flags.bits(),
+            proplist: (&value.proplist).into(),
+            configured_latency: value.configured_latency.0,
             base_volume: value.base_volume.into(),
-            state: value.state,
+            state: value.state.into(),
             n_volume_steps: value.n_volume_steps,
             card: value.card,
             ports: value.ports.iter().map(|p| p.into()).collect(),
             active_port: value.active_port.as_ref().map(|p| (&**p).into()),
-            formats: value.formats.iter().map(|i| PAInfo(i.clone())).collect(),
+            formats: value.formats.iter().map(PAInfo::from).collect(),
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PASourcePortInfo {
     /// Name of this port.
     pub name: Option<String>,
@@ -278,22 +231,22 @@ pub struct PASourcePortInfo {
     /// The higher this value is, the more useful this port is as a default.
     pub priority: u32,
     /// A flag indicating availability status of this port.
-    #[serde(serialize_with = "ser_port_available")]
-    pub available: def::PortAvailable,
+    pub available: PAPortAvailable,
 }
 
+#[cfg(feature = "backend")]
 impl<'a> From<&'a SourcePortInfo<'a>> for PASourcePortInfo {
     fn from(value: &'a SourcePortInfo<'a>) -> Self {
         PASourcePortInfo {
             name: cow!(value.name),
             description: cow!(value.description),
             priority: value.priority,
-            available: value.available,
+            available: value.available.into(),
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PASourceInfo {
     /// Name of the source.
     pub name: Option<String>,
@@ -302,40 +255,34 @@ pub struct PASourceInfo {
     /// Description of this source.
     pub description: Option<String>,
     /// Sample spec of this source.
-    #[serde(serialize_with = "ser_sample_spec")]
-    pub sample_spec: sample::Spec,
+    pub sample_spec: PASampleSpec,
     /// Channel map.
     pub channel_map: PAChannelMap,
     /// Owning module index, or `None`.
     pub owner_module: Option<u32>,
     /// Volume of the source.
-    #[serde(serialize_with = "ser_channel_volumes")]
-    pub volume: ChannelVolumes,
+    pub volume: PAChannelVolumes,
     /// Mute switch of the sink.
     pub mute: bool,
     /// If this is a monitor source, the index of the owning sink, otherwise `None`.
     pub monitor_of_sink: Option<u32>,
     /// Name of the owning sink, or `None`.
     pub monitor_of_sink_name: Option<String>,
-    /// Length of filled record buffer of this source.
-    #[serde(serialize_with = "ser_microseconds")]
-    pub latency: MicroSeconds,
+    /// Length of filled record buffer of this source, in microseconds.
+    pub latency: u64,
     /// Driver name.
     pub driver: Option<String>,
-    /// Flags.
-    #[serde(serialize_with = "ser_source_flag_set")]
-    pub flags: def::SourceFlagSet,
+    /// Flags (`pa_sink_flags_t` or `pa_source_flags_t`).
+    pub flags: u32,
     /// Property list.
     pub proplist: PAProplist,
-    /// The latency this device has been configured to.
-    #[serde(serialize_with = "ser_microseconds")]
-    pub configured_latency: MicroSeconds,
+    /// The latency this device has been configured to, in microseconds.
+    pub configured_latency: u64,
     /// Some kind of “base” volume that refers to unamplified/unattenuated volume in the context of
     /// the input device.
     pub base_volume: PAVolume,
     /// State.
-    #[serde(serialize_with = "ser_source_state")]
-    pub state: def::SourceState,
+    pub state: PADeviceState,
     /// Number of volume steps for sources which do not support arbitrary volumes.
     pub n_volume_steps: u32,
     /// Card index, or `None`.
@@ -348,39 +295,40 @@ pub struct PASourceInfo {
     pub formats: Vec<PAInfo>,
 }
 
+#[cfg(feature = "backend")]
 impl<'a> From<&'a SourceInfo<'a>> for PASourceInfo {
     fn from(value: &'a SourceInfo<'a>) -> Self {
         PASourceInfo {
             name: cow!(value.name),
             index: value.index,
             description: cow!(value.description),
-            sample_spec: value.sample_spec,
+            sample_spec: value.sample_spec.into(),
             channel_map: value.channel_map.into(),
             owner_module: value.owner_module,
-            volume: value.volume,
+            volume: value.volume.into(),
             mute: value.mute,
             monitor_of_sink: value.monitor_of_sink,
             monitor_of_sink_name: value
                 .monitor_of_sink_name
                 .as_ref()
                 .map(|cow| cow.to_string()),
-            latency: value.latency,
+            latency: value.latency.0,
             driver: cow!(value.driver),
-            flags: value.flags,
-            proplist: value.proplist.clone().into(),
-            configured_latency: value.configured_latency,
+            flags: value.flags.bits(),
+            proplist: (&value.proplist).into(),
+            configured_latency: value.configured_latency.0,
             base_volume: value.base_volume.into(),
-            state: value.state,
+            state: value.state.into(),
             n_volume_steps: value.n_volume_steps,
             card: value.card,
             ports: value.ports.iter().map(|p| p.into()).collect(),
             active_port: value.active_port.as_ref().map(|p| (&**p).into()),
-            formats: value.formats.iter().map(|i| PAInfo(i.clone())).collect(),
+            formats: value.formats.iter().map(PAInfo::from).collect(),
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PASinkInputInfo {
     /// Index of the sink input.
     pub index: u32,
@@ -395,20 +343,15 @@ pub struct PASinkInputInfo {
     /// Index of the connected sink.
     pub sink: u32,
     /// The sample specification of the sink input.
-    #[serde(serialize_with = "ser_sample_spec")]
-    pub sample_spec: sample::Spec,
+    pub sample_spec: PASampleSpec,
     /// Channel map.
     pub channel_map: PAChannelMap,
     /// The volume of this sink input.
-    #[serde(serialize_with = "ser_channel_volumes")]
-    pub volume: ChannelVolumes,
-    /// Latency due to buffering in sink input, see [`TimingInfo`](crate::def::TimingInfo) for
-    /// details.
-    #[serde(serialize_with = "ser_microseconds")]
-    pub buffer_usec: MicroSeconds,
-    /// Latency of the sink device, see [`TimingInfo`](crate::def::TimingInfo) for details.
-    #[serde(serialize_with = "ser_microseconds")]
-    pub sink_usec: MicroSeconds,
+    pub volume: PAChannelVolumes,
+    /// Latency due to buffering in sink input, in microseconds.
+    pub buffer_usec: u64,
+    /// Latency of the sink device, in microseconds.
+    pub sink_usec: u64,
     /// The resampling method used by this sink input.
     pub resample_method: Option<String>,
     /// Driver name.
@@ -429,6 +372,7 @@ pub struct PASinkInputInfo {
     pub format: PAInfo,
 }
 
+#[cfg(feature = "backend")]
 impl<'a> From<&'a SinkInputInfo<'a>> for PASinkInputInfo {
     fn from(value: &'a SinkInputInfo<'a>) -> Self {
         PASinkInputInfo {
@@ -437,24 +381,24 @@ impl<'a> From<&'a SinkInputInfo<'a>> for PASinkInputInfo {
             owner_module: value.owner_module,
             client: value.client,
             sink: value.sink,
-            sample_spec: value.sample_spec,
+            sample_spec: value.sample_spec.into(),
             channel_map: value.channel_map.into(),
-            volume: value.volume,
-            buffer_usec: value.buffer_usec,
-            sink_usec: value.sink_usec,
+            volume: value.volume.into(),
+            buffer_usec: value.buffer_usec.0,
+            sink_usec: value.sink_usec.0,
             resample_method: cow!(value.resample_method),
             driver: cow!(value.driver),
             mute: value.mute,
-            proplist: value.proplist.clone().into(),
+            proplist: (&value.proplist).into(),
             corked: value.corked,
             has_volume: value.has_volume,
             volume_writable: value.volume_writable,
-            format: value.format.clone().into(),
+            format: (&value.format).into(),
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PASourceOutputInfo {
     /// Index of the source output.
     pub index: u32,
@@ -469,17 +413,13 @@ pub struct PASourceOutputInfo {
     /// Index of the connected source.
     pub source: u32,
     /// The sample specification of the source output.
-    #[serde(serialize_with = "ser_sample_spec")]
-    pub sample_spec: sample::Spec,
+    pub sample_spec: PASampleSpec,
     /// Channel map.
     pub channel_map: PAChannelMap,
-    /// Latency due to buffering in the source output, see [`TimingInfo`](crate::def::TimingInfo)
-    /// for details.
-    #[serde(serialize_with = "ser_microseconds")]
-    pub buffer_usec: MicroSeconds,
-    /// Latency of the source device, see [`TimingInfo`](crate::def::TimingInfo) for details.
-    #[serde(serialize_with = "ser_microseconds")]
-    pub source_usec: MicroSeconds,
+    /// Latency due to buffering in the source output, in microseconds.
+    pub buffer_usec: u64,
+    /// Latency of the source device, in microseconds.
+    pub source_usec: u64,
     /// The resampling method used by this source output.
     pub resample_method: Option<String>,
     /// Driver name.
@@ -489,8 +429,7 @@ pub struct PASourceOutputInfo {
     /// Stream corked.
     pub corked: bool,
     /// The volume of this source output.
-    #[serde(serialize_with = "ser_channel_volumes")]
-    pub volume: ChannelVolumes,
+    pub volume: PAChannelVolumes,
     /// Stream muted.
     pub mute: bool,
     /// Stream has volume. If not set, then the meaning of this struct’s volume member is
@@ -503,6 +442,7 @@ pub struct PASourceOutputInfo {
     pub format: PAInfo,
 }
 
+#[cfg(feature = "backend")]
 impl<'a> From<&'a SourceOutputInfo<'a>> for PASourceOutputInfo {
     fn from(value: &'a SourceOutputInfo<'a>) -> Self {
         PASourceOutputInfo {
@@ -511,24 +451,24 @@ impl<'a> From<&'a SourceOutputInfo<'a>> for PASourceOutputInfo {
             owner_module: value.owner_module,
             client: value.client,
             source: value.source,
-            sample_spec: value.sample_spec,
+            sample_spec: value.sample_spec.into(),
             channel_map: value.channel_map.into(),
-            buffer_usec: value.buffer_usec,
-            source_usec: value.source_usec,
+            buffer_usec: value.buffer_usec.0,
+            source_usec: value.source_usec.0,
             resample_method: value.resample_method.as_ref().map(|p| (&**p).into()),
             driver: value.driver.as_ref().map(|p| (&**p).into()),
-            proplist: value.proplist.clone().into(),
+            proplist: (&value.proplist).into(),
             corked: value.corked,
-            volume: value.volume,
+            volume: value.volume.into(),
             mute: value.mute,
             has_volume: value.has_volume,
             volume_writable: value.volume_writable,
-            format: value.format.clone().into(),
+            format: (&value.format).into(),
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PAClientInfo {
     /// Index of this client.
     pub index: u32,
@@ -542,6 +482,7 @@ pub struct PAClientInfo {
     pub proplist: PAProplist,
 }
 
+#[cfg(feature = "backend")]
 impl<'a> From<&'a ClientInfo<'a>> for PAClientInfo {
     fn from(value: &'a ClientInfo<'a>) -> Self {
         PAClientInfo {
@@ -549,28 +490,25 @@ impl<'a> From<&'a ClientInfo<'a>> for PAClientInfo {
             name: value.name.as_ref().map(|p| (&**p).into()),
             owner_module: value.owner_module,
             driver: value.driver.as_ref().map(|p| (&**p).into()),
-            proplist: value.proplist.clone().into(),
+            proplist: (&value.proplist).into(),
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PASampleInfo {
     /// Index of this entry.
     pub index: u32,
     /// Name of this entry.
     pub name: Option<String>,
     /// Default volume of this entry.
-    #[serde(serialize_with = "ser_channel_volumes")]
-    pub volume: ChannelVolumes,
+    pub volume: PAChannelVolumes,
     /// Sample specification of the sample.
-    #[serde(serialize_with = "ser_sample_spec")]
-    pub sample_spec: sample::Spec,
+    pub sample_spec: PASampleSpec,
     /// The channel map.
     pub channel_map: PAChannelMap,
-    /// Duration of this entry.
-    #[serde(serialize_with = "ser_microseconds")]
-    pub duration: MicroSeconds,
+    /// Duration of this entry, in microseconds.
+    pub duration: u64,
     /// Length of this sample in bytes.
     pub bytes: u32,
     /// Non-zero when this is a lazy cache entry.
@@ -581,24 +519,25 @@ pub struct PASampleInfo {
     pub proplist: PAProplist,
 }
 
+#[cfg(feature = "backend")]
 impl<'a> From<&'a SampleInfo<'a>> for PASampleInfo {
     fn from(value: &'a SampleInfo<'a>) -> Self {
         PASampleInfo {
             index: value.index,
             name: cow!(value.name),
-            volume: value.volume,
-            sample_spec: value.sample_spec,
+            volume: value.volume.into(),
+            sample_spec: value.sample_spec.into(),
             channel_map: value.channel_map.into(),
-            duration: value.duration,
+            duration: value.duration.0,
             bytes: value.bytes,
             lazy: value.lazy,
             filename: cow!(value.filename),
-            proplist: value.proplist.clone().into(),
+            proplist: (&value.proplist).into(),
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PACardPortInfo {
     /// Name of this port.
     pub name: Option<String>,
@@ -607,11 +546,9 @@ pub struct PACardPortInfo {
     /// The higher this value is, the more useful this port is as a default.
     pub priority: u32,
     /// Availability status of this port.
-    #[serde(serialize_with = "ser_port_available")]
-    pub available: def::PortAvailable,
-    /// The direction of this port.
-    #[serde(serialize_with = "ser_flag_set")]
-    pub direction: direction::FlagSet,
+    pub available: PAPortAvailable,
+    /// The direction of this port (`pa_direction_t`).
+    pub direction: i32,
     /// Property list.
     pub proplist: PAProplist,
     /// Latency offset of the port that gets added to the sink/source latency when the port is
@@ -619,21 +556,53 @@ pub struct PACardPortInfo {
     pub latency_offset: i64,
 }
 
+#[cfg(feature = "backend")]
 impl<'a> From<&'a CardPortInfo<'a>> for PACardPortInfo {
     fn from(value: &'a CardPortInfo<'a>) -> Self {
         PACardPortInfo {
             name: cow!(value.name),
             description: cow!(value.description),
             priority: value.priority,
-            available: value.available,
-            direction: value.direction,
-            proplist: value.proplist.clone().into(),
+            available: value.available.into(),
+            direction: value.direction.bits(),
+            proplist: (&value.proplist).into(),
             latency_offset: value.latency_offset,
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PACardProfileInfo {
+    /// Name of this profile.
+    pub name: Option<String>,
+    /// Description of this profile.
+    pub description: Option<String>,
+    /// Number of sinks this profile would create.
+    pub n_sinks: u32,
+    /// Number of sources this profile would create.
+    pub n_sources: u32,
+    /// The higher this value is, the more useful this profile is as a default.
+    pub priority: u32,
+    /// Is this profile available? If this is `false`, then it makes no sense to try to activate
+    /// this profile.
+    pub available: bool,
+}
+
+#[cfg(feature = "backend")]
+impl<'a> From<&'a CardProfileInfo2<'a>> for PACardProfileInfo {
+    fn from(value: &'a CardProfileInfo2<'a>) -> Self {
+        PACardProfileInfo {
+            name: cow!(value.name),
+            description: cow!(value.description),
+            n_sinks: value.n_sinks,
+            n_sources: value.n_sources,
+            priority: value.priority,
+            available: value.available,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PACardInfo {
     /// Index of this card.
     pub index: u32,
@@ -647,8 +616,13 @@ pub struct PACardInfo {
     pub proplist: PAProplist,
     /// Set of ports.
     pub ports: Vec<PACardPortInfo>,
+    /// Set of available profiles.
+    pub profiles: Vec<PACardProfileInfo>,
+    /// The active profile, if any.
+    pub active_profile: Option<PACardProfileInfo>,
 }
 
+#[cfg(feature = "backend")]
 impl<'a> From<&'a CardInfo<'a>> for PACardInfo {
     fn from(value: &'a CardInfo<'a>) -> Self {
         PACardInfo {
@@ -656,13 +630,15 @@ impl<'a> From<&'a CardInfo<'a>> for PACardInfo {
             name: cow!(value.name),
             owner_module: value.owner_module,
             driver: cow!(value.driver),
-            proplist: value.proplist.clone().into(),
+            proplist: (&value.proplist).into(),
             ports: value.ports.iter().map(|p| p.into()).collect(),
+            profiles: value.profiles.iter().map(|p| p.into()).collect(),
+            active_profile: value.active_profile.as_deref().map(|p| p.into()),
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PAModuleInfo {
     /// Index of the module.
     pub index: u32,
@@ -676,6 +652,7 @@ pub struct PAModuleInfo {
     pub proplist: PAProplist,
 }
 
+#[cfg(feature = "backend")]
 impl<'a> From<&'a ModuleInfo<'a>> for PAModuleInfo {
     fn from(value: &'a ModuleInfo<'a>) -> Self {
         PAModuleInfo {
@@ -683,33 +660,168 @@ impl<'a> From<&'a ModuleInfo<'a>> for PAModuleInfo {
             name: cow!(value.name),
             argument: cow!(value.argument),
             n_used: value.n_used,
-            proplist: value.proplist.clone().into(),
+            proplist: (&value.proplist).into(),
         }
     }
 }
 
+/// Whether a port is available, such as whether headphones are plugged into a jack. This is
+/// (de)serialized as libpulse's value (`pa_port_available_t`).
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
-pub struct PAVolume(pub Volume);
+pub enum PAPortAvailable {
+    /// The port doesn't support jack detection
+    Unknown,
+    No,
+    Yes,
+}
+
+impl PAPortAvailable {
+    /// The value of this in libpulse's `pa_port_available_t`
+    pub fn value(&self) -> i8 {
+        match self {
+            PAPortAvailable::Unknown => 0,
+            PAPortAvailable::No => 1,
+            PAPortAvailable::Yes => 2,
+        }
+    }
+
+    /// The availability with the given value, which is `Unknown` for any unknown values
+    pub fn from_value(value: i8) -> PAPortAvailable {
+        match value {
+            1 => PAPortAvailable::No,
+            2 => PAPortAvailable::Yes,
+            _ => PAPortAvailable::Unknown,
+        }
+    }
+}
+
+impl Serialize for PAPortAvailable {
+    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        s.serialize_i8(self.value())
+    }
+}
+
+impl<'de> Deserialize<'de> for PAPortAvailable {
+    fn deserialize<D>(d: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        i8::deserialize(d).map(PAPortAvailable::from_value)
+    }
+}
+
+#[cfg(feature = "backend")]
+impl From<PortAvailable> for PAPortAvailable {
+    fn from(value: PortAvailable) -> Self {
+        match value {
+            PortAvailable::Unknown => PAPortAvailable::Unknown,
+            PortAvailable::No => PAPortAvailable::No,
+            PortAvailable::Yes => PAPortAvailable::Yes,
+        }
+    }
+}
+
+/// The state of a sink or a source. This is (de)serialized as libpulse's value (`pa_sink_state_t`
+/// or `pa_source_state_t`, which are the same).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PADeviceState {
+    /// The server doesn't support state introspection
+    Invalid,
+    /// Used by at least one stream which isn't corked
+    Running,
+    /// Open, but not used by any stream which isn't corked
+    Idle,
+    /// Closed, such as after it's been idle for a while
+    Suspended,
+}
+
+impl PADeviceState {
+    /// The value of this in libpulse's `pa_sink_state_t` (or `pa_source_state_t`)
+    pub fn value(&self) -> i8 {
+        match self {
+            PADeviceState::Invalid => -1,
+            PADeviceState::Running => 0,
+            PADeviceState::Idle => 1,
+            PADeviceState::Suspended => 2,
+        }
+    }
+
+    /// The state with the given value, which is `Invalid` for any unknown values
+    pub fn from_value(value: i8) -> PADeviceState {
+        match value {
+            0 => PADeviceState::Running,
+            1 => PADeviceState::Idle,
+            2 => PADeviceState::Suspended,
+            _ => PADeviceState::Invalid,
+        }
+    }
+}
+
+impl Serialize for PADeviceState {
+    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        s.serialize_i8(self.value())
+    }
+}
+
+impl<'de> Deserialize<'de> for PADeviceState {
+    fn deserialize<D>(d: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        i8::deserialize(d).map(PADeviceState::from_value)
+    }
+}
+
+#[cfg(feature = "backend")]
+impl From<SinkState> for PADeviceState {
+    fn from(value: SinkState) -> Self {
+        PADeviceState::from_value(value as i8)
+    }
+}
+
+#[cfg(feature = "backend")]
+impl From<SourceState> for PADeviceState {
+    fn from(value: SourceState) -> Self {
+        PADeviceState::from_value(value as i8)
+    }
+}
+
+/// A volume, as PulseAudio stores it (`pa_volume_t`)
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PAVolume(pub u32);
 
 impl PAVolume {
+    /// No volume at all
+    pub const MUTED: PAVolume = PAVolume(0);
+    /// 100%, the volume at which audio isn't amplified or attenuated
+    pub const NORMAL: PAVolume = PAVolume(0x10000);
+    /// The loudest volume PulseAudio accepts
+    pub const MAX: PAVolume = PAVolume(u32::MAX / 2);
+
     /// Volume as a percentage; `0.0` is 0%, and `100.0` is 100%
     pub fn percentage(&self) -> f64 {
-        (self.0 .0 as f64 / (Volume::NORMAL.0 as f64)) * 100.0
+        volume_to_percentage(*self)
     }
 
     /// Volume as a linear factor
     pub fn linear(&self) -> f64 {
-        VolumeLinear::from(self.0).0
+        volume_to_linear(*self)
     }
 
     /// Volume in decibels
     pub fn decibels(&self) -> f64 {
-        VolumeDB::from(self.0).0
+        volume_to_decibels(*self)
     }
 
     /// Volume actual value (`pa_volume_t`)
     pub fn value(&self) -> u32 {
-        self.0 .0
+        self.0
     }
 }
 
@@ -727,76 +839,537 @@ impl Serialize for PAVolume {
     }
 }
 
+/// Only the raw value is read, since the others are derived from it
+impl<'de> Deserialize<'de> for PAVolume {
+    fn deserialize<D>(d: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct Raw {
+            raw: u32,
+        }
+
+        Ok(PAVolume(Raw::deserialize(d)?.raw))
+    }
+}
+
+/// The volume as a percentage, such as `65%`. The precision sets the number of decimal places,
+/// which defaults to none.
+impl Display for PAVolume {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:.*}%", f.precision().unwrap_or(0), self.percentage())
+    }
+}
+
+#[cfg(feature = "backend")]
 impl From<Volume> for PAVolume {
     fn from(value: Volume) -> Self {
-        PAVolume(value)
+        PAVolume(value.0)
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
-pub struct PAPosition(pub Position);
+#[cfg(feature = "backend")]
+impl From<PAVolume> for Volume {
+    fn from(value: PAVolume) -> Self {
+        Volume(value.0)
+    }
+}
 
-impl Serialize for PAPosition {
-    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
+macro_rules! positions {
+    ($($position:ident => $short:literal),+ $(,)?) => {
+        /// The position of a channel, such as front left. These are the same as libpulse's, and
+        /// are serialized with their names (such as `"FrontLeft"`).
+        #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+        pub enum PAPosition {
+            $($position),+
+        }
+
+        impl PAPosition {
+            /// Every position, in the order libpulse defines them
+            pub const ALL: &'static [PAPosition] = &[$(PAPosition::$position),+];
+
+            /// The abbreviated name of the channel, such as `FL` for the front left channel
+            pub fn short_name(&self) -> &'static str {
+                match self {
+                    $(PAPosition::$position => $short),+
+                }
+            }
+        }
+
+        #[cfg(feature = "backend")]
+        impl From<Position> for PAPosition {
+            fn from(value: Position) -> Self {
+                match value {
+                    $(Position::$position => PAPosition::$position),+
+                }
+            }
+        }
+
+        #[cfg(feature = "backend")]
+        impl From<PAPosition> for Position {
+            fn from(value: PAPosition) -> Self {
+                match value {
+                    $(PAPosition::$position => Position::$position),+
+                }
+            }
+        }
+    };
+}
+
+positions! {
+    Invalid => "?",
+    Mono => "M",
+    FrontLeft => "FL",
+    FrontRight => "FR",
+    FrontCenter => "FC",
+    RearCenter => "RC",
+    RearLeft => "RL",
+    RearRight => "RR",
+    Lfe => "LFE",
+    FrontLeftOfCenter => "FLC",
+    FrontRightOfCenter => "FRC",
+    SideLeft => "SL",
+    SideRight => "SR",
+    Aux0 => "AUX0",
+    Aux1 => "AUX1",
+    Aux2 => "AUX2",
+    Aux3 => "AUX3",
+    Aux4 => "AUX4",
+    Aux5 => "AUX5",
+    Aux6 => "AUX6",
+    Aux7 => "AUX7",
+    Aux8 => "AUX8",
+    Aux9 => "AUX9",
+    Aux10 => "AUX10",
+    Aux11 => "AUX11",
+    Aux12 => "AUX12",
+    Aux13 => "AUX13",
+    Aux14 => "AUX14",
+    Aux15 => "AUX15",
+    Aux16 => "AUX16",
+    Aux17 => "AUX17",
+    Aux18 => "AUX18",
+    Aux19 => "AUX19",
+    Aux20 => "AUX20",
+    Aux21 => "AUX21",
+    Aux22 => "AUX22",
+    Aux23 => "AUX23",
+    Aux24 => "AUX24",
+    Aux25 => "AUX25",
+    Aux26 => "AUX26",
+    Aux27 => "AUX27",
+    Aux28 => "AUX28",
+    Aux29 => "AUX29",
+    Aux30 => "AUX30",
+    Aux31 => "AUX31",
+    TopCenter => "TC",
+    TopFrontLeft => "TFL",
+    TopFrontRight => "TFR",
+    TopFrontCenter => "TFC",
+    TopRearLeft => "TRL",
+    TopRearRight => "TRR",
+    TopRearCenter => "TRC",
+}
+
+impl PAPosition {
+    /// The name libpulse uses for the channel, such as `front-left`
+    pub fn name(&self) -> String {
+        let mut name = String::new();
+        for (i, c) in format!("{:?}", self).chars().enumerate() {
+            if c.is_ascii_uppercase() && i > 0 {
+                name.push('-');
+            }
+            name.push(c.to_ascii_lowercase());
+        }
+
+        name
+    }
+
+    /// Parses a channel by its name, either as libpulse names it (such as `front-left`, or one of
+    /// the aliases `left`, `right`, `center` and `subwoofer`) or as it's serialized (`FrontLeft`)
+    pub fn from_name(name: &str) -> Option<PAPosition> {
+        match name {
+            "left" => return Some(PAPosition::FrontLeft),
+            "right" => return Some(PAPosition::FrontRight),
+            "center" => return Some(PAPosition::FrontCenter),
+            "subwoofer" => return Some(PAPosition::Lfe),
+            _ => {}
+        }
+
+        PAPosition::ALL
+            .iter()
+            .filter(|p| **p != PAPosition::Invalid)
+            .find(|p| p.name() == name || format!("{:?}", p) == name)
+            .copied()
+    }
+}
+
+/// The abbreviated name of the channel, see `PAPosition::short_name`
+impl Display for PAPosition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.short_name())
+    }
+}
+
+impl<'de> Deserialize<'de> for PAPosition {
+    fn deserialize<D>(d: D) -> Result<Self, D::Error>
     where
-        S: Serializer,
+        D: Deserializer<'de>,
     {
-        // HACK: use the debug representation to get the names of the channels
-        // Not sure if this is better than just casting to an integer - will revisit if it's ever needed
-        s.serialize_str(&format!("{:?}", self.0))
+        // the variant's name is serialized (such as "FrontLeft"), while libpulse's names are
+        // kebab-case (such as "front-left"), so accept either
+        let name = String::deserialize(d)?;
+        PAPosition::from_name(&name)
+            .ok_or_else(|| D::Error::custom(format!("Unknown channel: {}", name)))
     }
 }
 
-impl From<Position> for PAPosition {
-    fn from(value: Position) -> Self {
-        PAPosition(value)
+/// The kind of object a subscription event is about.
+///
+/// This is owned rather than wrapping libpulse's `Facility`, so its (snake_case) names are stable and
+/// it can be deserialized; the names match those used for masks (see `api::mask`).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PAFacility {
+    Sink,
+    Source,
+    SinkInput,
+    SourceOutput,
+    Module,
+    Client,
+    SampleCache,
+    Server,
+    Card,
+}
+
+impl PAFacility {
+    /// Every facility, in the order libpulse defines them
+    pub const ALL: [PAFacility; 9] = [
+        PAFacility::Sink,
+        PAFacility::Source,
+        PAFacility::SinkInput,
+        PAFacility::SourceOutput,
+        PAFacility::Module,
+        PAFacility::Client,
+        PAFacility::SampleCache,
+        PAFacility::Server,
+        PAFacility::Card,
+    ];
+
+    /// The name of the facility, as it's serialized
+    pub fn name(&self) -> &'static str {
+        match self {
+            PAFacility::Sink => "sink",
+            PAFacility::Source => "source",
+            PAFacility::SinkInput => "sink_input",
+            PAFacility::SourceOutput => "source_output",
+            PAFacility::Module => "module",
+            PAFacility::Client => "client",
+            PAFacility::SampleCache => "sample_cache",
+            PAFacility::Server => "server",
+            PAFacility::Card => "card",
+        }
+    }
+
+    /// The subscription mask which only contains this facility
+    pub fn mask(&self) -> PAMask {
+        match self {
+            PAFacility::Sink => PAMask::SINK,
+            PAFacility::Source => PAMask::SOURCE,
+            PAFacility::SinkInput => PAMask::SINK_INPUT,
+            PAFacility::SourceOutput => PAMask::SOURCE_OUTPUT,
+            PAFacility::Module => PAMask::MODULE,
+            PAFacility::Client => PAMask::CLIENT,
+            PAFacility::SampleCache => PAMask::SAMPLE_CACHE,
+            PAFacility::Server => PAMask::SERVER,
+            PAFacility::Card => PAMask::CARD,
+        }
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
-pub struct PAFacility(pub Facility);
+#[cfg(feature = "backend")]
+impl From<Facility> for PAFacility {
+    fn from(value: Facility) -> Self {
+        match value {
+            Facility::Sink => PAFacility::Sink,
+            Facility::Source => PAFacility::Source,
+            Facility::SinkInput => PAFacility::SinkInput,
+            Facility::SourceOutput => PAFacility::SourceOutput,
+            Facility::Module => PAFacility::Module,
+            Facility::Client => PAFacility::Client,
+            Facility::SampleCache => PAFacility::SampleCache,
+            Facility::Server => PAFacility::Server,
+            Facility::Card => PAFacility::Card,
+        }
+    }
+}
+
+impl From<PAFacility> for PAMask {
+    fn from(value: PAFacility) -> Self {
+        value.mask()
+    }
+}
+
+/// Which kinds of objects a subscription receives events for. The values are the same as libpulse's
+/// (`pa_subscription_mask_t`). See `api::mask` to (de)serialize it.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct PAMask(u32);
+
+impl PAMask {
+    pub const NULL: PAMask = PAMask(0x0000);
+    pub const SINK: PAMask = PAMask(0x0001);
+    pub const SOURCE: PAMask = PAMask(0x0002);
+    pub const SINK_INPUT: PAMask = PAMask(0x0004);
+    pub const SOURCE_OUTPUT: PAMask = PAMask(0x0008);
+    pub const MODULE: PAMask = PAMask(0x0010);
+    pub const CLIENT: PAMask = PAMask(0x0020);
+    pub const SAMPLE_CACHE: PAMask = PAMask(0x0040);
+    pub const SERVER: PAMask = PAMask(0x0080);
+    pub const CARD: PAMask = PAMask(0x0200);
+    pub const ALL: PAMask = PAMask(0x02ff);
+
+    /// A mask without any facilities
+    pub const fn empty() -> PAMask {
+        PAMask::NULL
+    }
+
+    /// The value of this mask in libpulse's `pa_subscription_mask_t`
+    pub const fn bits(&self) -> u32 {
+        self.0
+    }
 
-impl Serialize for PAFacility {
+    /// Whether this mask contains every facility in `other`
+    pub const fn contains(&self, other: PAMask) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Adds the facilities in `other` to this mask
+    pub fn insert(&mut self, other: PAMask) {
+        self.0 |= other.0;
+    }
+}
+
+impl BitOr for PAMask {
+    type Output = PAMask;
+
+    fn bitor(self, rhs: PAMask) -> PAMask {
+        PAMask(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for PAMask {
+    fn bitor_assign(&mut self, rhs: PAMask) {
+        self.insert(rhs);
+    }
+}
+
+#[cfg(feature = "backend")]
+impl From<PAMask> for InterestMaskSet {
+    fn from(value: PAMask) -> Self {
+        InterestMaskSet::from_bits_truncate(value.bits())
+    }
+}
+
+/// The position of each channel of an object, in order
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PAChannelMap(pub Vec<PAPosition>);
+
+impl Serialize for PAChannelMap {
     fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        // HACK: use the debug representation to get the names of the facility
-        s.serialize_str(&format!("{:?}", self.0).to_lowercase())
+        let mut map = s.serialize_map(None)?;
+        map.serialize_entry("channels", &self.0.len())?;
+        map.serialize_entry("map", &self.0)?;
+        map.end()
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct PAChannelMap(pub channelmap::Map);
+impl<'de> Deserialize<'de> for PAChannelMap {
+    fn deserialize<D>(d: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct Map {
+            map: Vec<PAPosition>,
+        }
 
-impl Serialize for PAChannelMap {
+        let positions = Map::deserialize(d)?.map;
+        if positions.len() > CHANNELS_MAX {
+            return Err(D::Error::custom(format!(
+                "Too many channels: {}, the most is {}",
+                positions.len(),
+                CHANNELS_MAX
+            )));
+        }
+
+        Ok(PAChannelMap(positions))
+    }
+}
+
+impl PAChannelMap {
+    /// The standard stereo map, front left then front right
+    pub fn stereo() -> PAChannelMap {
+        PAChannelMap(vec![PAPosition::FrontLeft, PAPosition::FrontRight])
+    }
+
+    /// The position of each channel, in order
+    pub fn positions(&self) -> Vec<PAPosition> {
+        self.0.clone()
+    }
+
+    /// Whether this is the standard stereo map, front left then front right
+    pub fn is_stereo(&self) -> bool {
+        self.0 == [PAPosition::FrontLeft, PAPosition::FrontRight]
+    }
+
+    /// The name of a standard map (such as "stereo" or "surround-51"), if this is one. Like
+    /// libpulse, only which channels are in the map is compared, not their order.
+    pub fn to_name(&self) -> Option<String> {
+        use PAPosition::*;
+
+        let standard: [(&str, &[PAPosition]); 6] = [
+            ("mono", &[Mono]),
+            ("stereo", &[FrontLeft, FrontRight]),
+            ("surround-40", &[FrontLeft, FrontRight, RearLeft, RearRight]),
+            (
+                "surround-50",
+                &[FrontLeft, FrontRight, RearLeft, RearRight, FrontCenter],
+            ),
+            (
+                "surround-51",
+                &[FrontLeft, FrontRight, RearLeft, RearRight, FrontCenter, Lfe],
+            ),
+            (
+                "surround-71",
+                &[
+                    FrontLeft,
+                    FrontRight,
+                    RearLeft,
+                    RearRight,
+                    FrontCenter,
+                    Lfe,
+                    SideLeft,
+                    SideRight,
+                ],
+            ),
+        ];
+
+        let positions = self.0.iter().collect::<BTreeSet<_>>();
+        standard
+            .iter()
+            .find(|(_, map)| map.iter().collect::<BTreeSet<_>>() == positions)
+            .map(|(name, _)| name.to_string())
+    }
+
+    /// The same map as libpulse's type, such as for remapping volumes
+    #[cfg(feature = "backend")]
+    pub fn to_map(&self) -> Map {
+        crate::volume::new_channel_map(self.0.iter().map(|p| Position::from(*p)).collect())
+    }
+}
+
+#[cfg(feature = "backend")]
+impl From<Map> for PAChannelMap {
+    fn from(value: Map) -> Self {
+        PAChannelMap(value.get().iter().map(|p| PAPosition::from(*p)).collect())
+    }
+}
+
+/// The volume of each channel, in the order of the object's channel map
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PAChannelVolumes(pub Vec<PAVolume>);
+
+impl Serialize for PAChannelVolumes {
     fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
         let mut map = s.serialize_map(None)?;
         map.serialize_entry("channels", &self.0.len())?;
-        map.serialize_entry(
-            "map",
-            &self
-                .0
-                .get()
-                .iter()
-                .map(|p| PAPosition::from(*p))
-                .collect::<Vec<_>>(),
-        )?;
+        map.serialize_entry("volumes", &self.0)?;
         map.end()
     }
 }
 
-impl From<channelmap::Map> for PAChannelMap {
-    fn from(value: channelmap::Map) -> Self {
-        PAChannelMap(value)
+impl<'de> Deserialize<'de> for PAChannelVolumes {
+    fn deserialize<D>(d: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct Volumes {
+            volumes: Vec<PAVolume>,
+        }
+
+        Ok(PAChannelVolumes(Volumes::deserialize(d)?.volumes))
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct PAProplist(pub Proplist);
+impl PAChannelVolumes {
+    /// The average volume of the channels, which is muted if there aren't any
+    pub fn avg(&self) -> PAVolume {
+        match self.0.len() {
+            0 => PAVolume::MUTED,
+            len => {
+                let sum = self.0.iter().map(|v| v.value() as u64).sum::<u64>();
+                PAVolume((sum / len as u64) as u32)
+            }
+        }
+    }
+
+    /// The same volumes as libpulse's type, such as for setting them
+    #[cfg(feature = "backend")]
+    pub fn to_channel_volumes(&self) -> ChannelVolumes {
+        new_channel_volumes(self.0.iter().map(|v| Volume::from(*v)).collect())
+    }
+}
+
+#[cfg(feature = "backend")]
+impl From<ChannelVolumes> for PAChannelVolumes {
+    fn from(value: ChannelVolumes) -> Self {
+        PAChannelVolumes(value.get().iter().map(|v| PAVolume::from(*v)).collect())
+    }
+}
+
+/// The format, rate and channel count of audio, such as the server's default
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PASampleSpec {
+    pub channels: u8,
+    /// Samples per second, per channel
+    pub rate: u32,
+    /// The sample format, named as libpulse names it (such as "s16le" or "float32le"), or `None`
+    /// if it's invalid
+    pub format: Option<String>,
+}
+
+impl PASampleSpec {
+    /// The sample format, if it's one which can be streamed (see `RawSpec`)
+    pub fn raw_format(&self) -> Option<RawFormat> {
+        let format = self.format.as_deref()?;
+        RawFormat::ALL
+            .into_iter()
+            .find(|raw| raw.sample_format_name() == format)
+    }
+}
+
+#[cfg(feature = "backend")]
+impl From<sample::Spec> for PASampleSpec {
+    fn from(value: sample::Spec) -> Self {
+        PASampleSpec {
+            channels: value.channels,
+            rate: value.rate,
+            format: value.format.to_string().map(|name| name.into_owned()),
+        }
+    }
+}
+
+/// A property list. Each value is kept as libpulse stores it, so strings end with a NUL byte.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PAProplist(pub BTreeMap<String, Vec<u8>>);
 
 impl Serialize for PAProplist {
     fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
@@ -804,28 +1377,75 @@ impl Serialize for PAProplist {
         S: Serializer,
     {
         let mut map = s.serialize_map(None)?;
-        for key in self.0.iter() {
-            // SAFETY: only returns `None` if the key doesn't exist, but we're iterating keys
-            // so it must exist
-            let value = self.0.get(&key).unwrap();
+        for (key, value) in &self.0 {
             // remove trailing null byte
-            let value = &value[..value.len() - 1];
+            let value = value.strip_suffix(&[0]).unwrap_or(value);
             // TODO: handle non-utf8 as escape codes
             let value = String::from_utf8_lossy(value);
-            map.serialize_entry(&key, &value)?;
+            map.serialize_entry(key, &value)?;
         }
         map.end()
     }
 }
 
-impl From<Proplist> for PAProplist {
-    fn from(value: Proplist) -> Self {
-        PAProplist(value.clone())
+/// Values are serialized as strings, so they're stored as strings again (ending with a NUL byte)
+impl<'de> Deserialize<'de> for PAProplist {
+    fn deserialize<D>(d: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let props = BTreeMap::<String, String>::deserialize(d)?
+            .into_iter()
+            .map(|(key, value)| {
+                let mut value = value.into_bytes();
+                value.push(0);
+                (key, value)
+            })
+            .collect();
+
+        Ok(PAProplist(props))
+    }
+}
+
+impl PAProplist {
+    /// Returns the value of the given property as a string, if it exists and is one
+    pub fn get_str(&self, key: &str) -> Option<String> {
+        let value = self.0.get(key)?.strip_suffix(&[0])?;
+        match value.contains(&0) {
+            true => None,
+            false => String::from_utf8(value.to_vec()).ok(),
+        }
+    }
+
+    /// The name of the PipeWire node, which pipewire-pulse passes through as `node.name`.
+    /// Not set when running on PulseAudio.
+    pub fn node_name(&self) -> Option<String> {
+        self.get_str("node.name")
+    }
+}
+
+#[cfg(feature = "backend")]
+impl From<&Proplist> for PAProplist {
+    fn from(value: &Proplist) -> Self {
+        let props = value
+            .iter()
+            .filter_map(|key| {
+                let value = value.get(&key)?.to_vec();
+                Some((key, value))
+            })
+            .collect();
+
+        PAProplist(props)
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct PAInfo(pub format::Info);
+/// A stream format, such as one which a sink accepts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PAInfo {
+    pub encoding: PAEncoding,
+    /// Properties of the format, such as its sample format, rate and channels
+    pub properties: PAProplist,
+}
 
 impl Serialize for PAInfo {
     fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
@@ -833,17 +1453,37 @@ impl Serialize for PAInfo {
         S: Serializer,
     {
         let mut map = s.serialize_map(None)?;
-        map.serialize_entry("encoding", &(self.0.get_encoding() as i8))?;
-        map.serialize_entry(
-            "properties",
-            &PAProplist::from(self.0.get_properties().clone()),
-        )?;
+        map.serialize_entry("encoding", &self.encoding.value())?;
+        map.serialize_entry("properties", &self.properties)?;
         map.end()
     }
 }
 
-impl From<format::Info> for PAInfo {
-    fn from(value: format::Info) -> Self {
-        PAInfo(value.clone())
+impl<'de> Deserialize<'de> for PAInfo {
+    fn deserialize<D>(d: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct Info {
+            encoding: i8,
+            properties: PAProplist,
+        }
+
+        let info = Info::deserialize(d)?;
+        Ok(PAInfo {
+            encoding: PAEncoding::from_value(info.encoding),
+            properties: info.properties,
+        })
+    }
+}
+
+#[cfg(feature = "backend")]
+impl From<&format::Info> for PAInfo {
+    fn from(value: &format::Info) -> Self {
+        PAInfo {
+            encoding: value.get_encoding().into(),
+            properties: value.get_properties().into(),
+        }
     }
 }
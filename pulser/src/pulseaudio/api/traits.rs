@@ -0,0 +1,142 @@
+//! Traits implemented by the `PA*Info` structs, so that code can be written once for objects which
+//! share the same fields (such as a volume control for sinks, sources, sink inputs and source
+//! outputs).
+
+use super::*;
+
+/// An object on the server, identified by its index and (usually) a name
+pub trait PAObjectInfo {
+    fn index(&self) -> u32;
+    fn name(&self) -> Option<&str>;
+
+    /// The index of this object as a `PAIdent`
+    fn ident(&self) -> PAIdent {
+        PAIdent::Index(self.index())
+    }
+}
+
+/// An object which has a volume for each of its channels
+pub trait HasVolume {
+    fn volume(&self) -> &PAChannelVolumes;
+    fn channel_map(&self) -> &PAChannelMap;
+
+    /// The volume of each channel, paired with its position
+    fn volume_readings(&self) -> VolumeReadings {
+        self.channel_map()
+            .0
+            .iter()
+            .zip(&self.volume().0)
+            .map(|(chan, vol)| VolumeReading::new(*chan, *vol))
+            .collect()
+    }
+}
+
+/// An object which can be muted
+pub trait HasMute {
+    fn mute(&self) -> bool;
+}
+
+/// An object which has a property list
+pub trait HasProplist {
+    fn proplist(&self) -> &PAProplist;
+}
+
+/// A sink or a source
+pub trait PADevice: PAObjectInfo + HasVolume + HasMute {
+    fn description(&self) -> Option<&str>;
+    fn is_suspended(&self) -> bool;
+}
+
+macro_rules! impl_object_info {
+    ($($ty:ident),+) => {
+        $(
+            impl PAObjectInfo for $ty {
+                fn index(&self) -> u32 {
+                    self.index
+                }
+
+                fn name(&self) -> Option<&str> {
+                    self.name.as_deref()
+                }
+            }
+
+            impl HasProplist for $ty {
+                fn proplist(&self) -> &PAProplist {
+                    &self.proplist
+                }
+            }
+        )+
+    };
+}
+
+macro_rules! impl_has_volume {
+    ($($ty:ident),+) => {
+        $(
+            impl HasVolume for $ty {
+                fn volume(&self) -> &PAChannelVolumes {
+                    &self.volume
+                }
+
+                fn channel_map(&self) -> &PAChannelMap {
+                    &self.channel_map
+                }
+            }
+        )+
+    };
+}
+
+macro_rules! impl_has_mute {
+    ($($ty:ident),+) => {
+        $(
+            impl HasMute for $ty {
+                fn mute(&self) -> bool {
+                    self.mute
+                }
+            }
+        )+
+    };
+}
+
+impl_object_info!(
+    PACardInfo,
+    PAClientInfo,
+    PAModuleInfo,
+    PASampleInfo,
+    PASinkInfo,
+    PASinkInputInfo,
+    PASourceInfo,
+    PASourceOutputInfo
+);
+impl_has_volume!(
+    PASampleInfo,
+    PASinkInfo,
+    PASinkInputInfo,
+    PASourceInfo,
+    PASourceOutputInfo
+);
+impl_has_mute!(
+    PASinkInfo,
+    PASinkInputInfo,
+    PASourceInfo,
+    PASourceOutputInfo
+);
+
+impl PADevice for PASinkInfo {
+    fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    fn is_suspended(&self) -> bool {
+        self.state == PADeviceState::Suspended
+    }
+}
+
+impl PADevice for PASourceInfo {
+    fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    fn is_suspended(&self) -> bool {
+        self.state == PADeviceState::Suspended
+    }
+}
@@ -1,14 +1,16 @@
 use std::error::Error;
+use std::fmt::Display;
 use std::str::FromStr;
 
-use libpulse_binding::channelmap::Position;
-use libpulse_binding::volume::{Volume, VolumeDB, VolumeLinear};
-use serde::Serialize;
+#[cfg(feature = "backend")]
+use libpulse_binding::volume::{ChannelVolumes, Volume};
+use serde::{Deserialize, Serialize};
 
-use super::{PAPosition, PAVolume};
+use super::{PAChannelMap, PAPosition, PAVolume};
+use crate::volume::{decibels_to_volume, linear_to_volume, percentage_to_volume};
 
 /// Used when requesting the volume from an object
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct VolumeReading {
     /// Which channel this volume belongs to
     pub channel: PAPosition,
@@ -16,19 +18,72 @@ pub struct VolumeReading {
 }
 
 impl VolumeReading {
-    pub fn new(channel: &Position, volume: &Volume) -> VolumeReading {
-        VolumeReading {
-            channel: PAPosition(*channel),
-            volume: PAVolume(*volume),
-        }
+    pub fn new(channel: PAPosition, volume: PAVolume) -> VolumeReading {
+        VolumeReading { channel, volume }
+    }
+
+    /// The volume of this channel
+    pub fn volume(&self) -> PAVolume {
+        self.volume
     }
 }
 
-#[derive(Debug, Clone, Serialize)]
+/// The channel and its volume, such as `FL: 65%`. The precision is passed on to the volume.
+impl Display for VolumeReading {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: ", self.channel)?;
+        self.volume.fmt(f)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VolumeReadings {
     pub(crate) inner: Vec<VolumeReading>,
 }
 
+impl VolumeReadings {
+    pub fn iter(&self) -> impl Iterator<Item = &VolumeReading> {
+        self.inner.iter()
+    }
+
+    /// Whether these readings are exactly the same as the given volumes
+    #[cfg(feature = "backend")]
+    pub(crate) fn matches(&self, cv: &ChannelVolumes) -> bool {
+        self.inner.len() == cv.len() as usize
+            && self
+                .inner
+                .iter()
+                .zip(cv.get())
+                .all(|(r, v)| r.volume.0 == v.0)
+    }
+}
+
+/// Each channel's reading separated by commas, such as `FL: 65%, FR: 65%`
+impl Display for VolumeReadings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, reading) in self.inner.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            reading.fmt(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl From<&VolumeReadings> for VolumeSpec {
+    /// A `VolumeSpec` which sets each channel back to these readings
+    fn from(value: &VolumeReadings) -> Self {
+        VolumeSpec::Channels(
+            value
+                .iter()
+                .map(|r| PAVol::Value(r.volume.value()))
+                .collect(),
+        )
+    }
+}
+
 impl From<Vec<VolumeReading>> for VolumeReadings {
     fn from(value: Vec<VolumeReading>) -> Self {
         VolumeReadings { inner: value }
@@ -53,24 +108,45 @@ pub enum PAVol {
 }
 
 impl PAVol {
+    /// No volume at all
+    pub const MUTED: PAVol = PAVol::Value(PAVolume::MUTED.0);
+    /// 100%, the volume at which audio isn't amplified or attenuated
+    pub const NORMAL: PAVol = PAVol::Value(PAVolume::NORMAL.0);
+
+    /// A volume as a percentage, rounded to the nearest raw value so it survives a round trip
+    /// through `percentage`
+    pub fn from_percentage(pct: f64) -> PAVol {
+        PAVol::Value((PAVolume::NORMAL.0 as f64 * (pct / 100.0)).round() as u32)
+    }
+
     pub fn value(&self) -> u32 {
-        let v: Volume = (*self).into();
-        v.0
+        PAVolume::from(*self).0
+    }
+
+    /// The volume as a percentage; `0.0` is 0%, and `100.0` is 100%
+    pub fn percentage(&self) -> f64 {
+        PAVolume::from(*self).percentage()
     }
 }
 
-impl From<PAVol> for Volume {
+impl From<PAVol> for PAVolume {
     fn from(value: PAVol) -> Self {
         match value {
-            PAVol::Value(value) => Volume(value),
-            PAVol::Decibels(db) => VolumeDB(db).into(),
-            PAVol::Linear(lin) => VolumeLinear(lin).into(),
-            // libpulse doesn't seem to offer a way to calculate percentages...
-            PAVol::Percentage(pct) => Volume((Volume::NORMAL.0 as f64 * (pct / 100.0)) as u32),
+            PAVol::Value(value) => PAVolume(value),
+            PAVol::Decibels(db) => decibels_to_volume(db),
+            PAVol::Linear(lin) => linear_to_volume(lin),
+            PAVol::Percentage(pct) => percentage_to_volume(pct),
         }
     }
 }
 
+#[cfg(feature = "backend")]
+impl From<PAVol> for Volume {
+    fn from(value: PAVol) -> Self {
+        PAVolume::from(value).into()
+    }
+}
+
 impl FromStr for PAVol {
     type Err = Box<dyn Error>;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
@@ -109,6 +185,32 @@ pub enum VolumeSpec {
     /// Single volume; this will set each channel to this volume
     All(PAVol),
     /// List of volumes; each is a tuple of `Position` (channel) and `PAVol` (volume for that channel)
-    /// Length of this `Vec` cannot exceed `libpulse_binding::sample::Spec::CHANNELS_MAX`
+    /// Length of this `Vec` cannot exceed `CHANNELS_MAX`
     Channels(Vec<PAVol>),
+    /// List of volumes for the given channel map (such as stereo), which are remapped onto the
+    /// channels of the object. Channels without an equivalent in the given map (such as the LFE
+    /// channel when setting stereo volumes on a 5.1 device) keep their current volume.
+    Mapped(PAChannelMap, Vec<PAVol>),
+    /// For streams (sink inputs and source outputs), sets the volume so that the stream's output is
+    /// this percentage of its device's volume, which is how pavucontrol displays it (`100.0` is the
+    /// same as the device). For devices, this is the same as `All(PAVol::Percentage(..))`.
+    RelativeToDevice(f64),
+}
+
+impl VolumeSpec {
+    /// Left and right volumes, which are remapped onto the channels of the object. On a 5.1 or 7.1
+    /// device, the front and rear pairs follow `left` and `right`, the center channel takes the
+    /// average of both and the LFE channel is left untouched.
+    pub fn stereo(left: PAVol, right: PAVol) -> VolumeSpec {
+        VolumeSpec::Mapped(PAChannelMap::stereo(), vec![left, right])
+    }
+}
+
+/// How the volume changes over the course of a fade
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FadeCurve {
+    /// Volume changes by the same amount at each step
+    Linear,
+    /// Volume changes by the same number of decibels at each step, which sounds more natural
+    Exponential,
 }
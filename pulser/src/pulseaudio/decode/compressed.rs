@@ -0,0 +1,141 @@
+use std::io::{self, Read};
+
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{Decoder, DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::{FormatOptions, FormatReader};
+use symphonia::core::io::{MediaSourceStream, ReadOnlySource};
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+use super::{invalid, Decoded};
+use crate::api::{RawFormat, RawSpec};
+
+/// Reads a FLAC or Ogg Vorbis file with symphonia. Only the headers are read straight away, and
+/// each packet is decoded as `Decoded::data` is read. Samples are converted to 32 bit floats, which
+/// hold FLAC's samples (up to 24 bits) without any loss.
+pub fn read_compressed(r: impl Read + Send + Sync + 'static) -> io::Result<Decoded> {
+    let source = MediaSourceStream::new(Box::new(ReadOnlySource::new(r)), Default::default());
+    let probed = symphonia::default::get_probe()
+        .format(
+            &Hint::new(),
+            source,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .map_err(to_io)?;
+
+    let format = probed.format;
+    let track = format
+        .default_track()
+        .filter(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or_else(|| invalid("The file has no audio track"))?;
+    let params = &track.codec_params;
+    let rate = params
+        .sample_rate
+        .ok_or_else(|| invalid("The file doesn't give its sample rate"))?;
+    let channels = params
+        .channels
+        .ok_or_else(|| invalid("The file doesn't give its channels"))?
+        .count();
+    let spec = RawSpec {
+        format: RawFormat::F32le,
+        rate,
+        channels: u8::try_from(channels).map_err(|_| invalid("Too many channels"))?,
+    };
+
+    let decoder = symphonia::default::get_codecs()
+        .make(params, &DecoderOptions::default())
+        .map_err(to_io)?;
+    let packets = Packets {
+        track_id: track.id,
+        format,
+        decoder,
+        spec,
+        buf: vec![],
+        pos: 0,
+    };
+
+    Ok(Decoded {
+        spec,
+        data: Box::new(packets),
+    })
+}
+
+/// Decodes a track's packets as they're read
+struct Packets {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn Decoder>,
+    track_id: u32,
+    /// The layout every packet must be decoded to, from the file's headers
+    spec: RawSpec,
+    /// The last packet's samples, of which those from `pos` haven't been read yet
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+impl Packets {
+    /// Decodes the next packet of the track into `buf`, returning `false` at the end of the file
+    fn decode_next(&mut self) -> io::Result<bool> {
+        loop {
+            let packet = match self.format.next_packet() {
+                Ok(packet) => packet,
+                Err(SymphoniaError::IoError(e)) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                    return Ok(false)
+                }
+                Err(e) => return Err(to_io(e)),
+            };
+            if packet.track_id() != self.track_id {
+                continue;
+            }
+
+            let decoded = match self.decoder.decode(&packet) {
+                Ok(decoded) => decoded,
+                // a corrupt packet is skipped, since the ones after it may be fine
+                Err(SymphoniaError::DecodeError(_)) => continue,
+                Err(e) => return Err(to_io(e)),
+            };
+
+            // the stream was opened with the headers' layout, which it can't change part way
+            let spec = *decoded.spec();
+            if spec.rate != self.spec.rate || spec.channels.count() != self.spec.channels as usize {
+                return Err(invalid(
+                    "The file's sample rate or channels change part way through",
+                ));
+            }
+
+            let mut samples = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+            samples.copy_interleaved_ref(decoded);
+            self.buf.clear();
+            self.pos = 0;
+            for sample in samples.samples() {
+                self.buf.extend_from_slice(&sample.to_le_bytes());
+            }
+
+            return Ok(true);
+        }
+    }
+}
+
+impl Read for Packets {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.buf.len() {
+            if !self.decode_next()? {
+                return Ok(0);
+            }
+        }
+
+        let n = out.len().min(self.buf.len() - self.pos);
+        out[..n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+fn to_io(e: SymphoniaError) -> io::Error {
+    match e {
+        SymphoniaError::IoError(e) => e,
+        SymphoniaError::Unsupported(what) => invalid(format!("Unsupported audio file: {}", what)),
+        e => invalid(e.to_string()),
+    }
+}
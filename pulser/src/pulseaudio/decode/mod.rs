@@ -0,0 +1,42 @@
+use std::io::{self, Cursor, Read};
+
+use super::api::RawSpec;
+
+#[cfg(feature = "decode")]
+mod compressed;
+mod wav;
+
+#[cfg(feature = "decode")]
+pub use compressed::read_compressed;
+pub use wav::read_wav;
+
+/// A decoded audio file
+pub struct Decoded {
+    pub spec: RawSpec,
+    /// The PCM samples, in the layout described by `spec`. They're decoded as they're read, so the
+    /// file is never held in memory as a whole.
+    pub data: Box<dyn Read>,
+}
+
+/// Decodes an audio file, working out its format from its contents. WAV files are always
+/// supported, and FLAC and Ogg Vorbis files are too with the `decode` feature.
+pub fn decode(mut r: impl Read + Send + Sync + 'static) -> io::Result<Decoded> {
+    let mut magic = [0; 4];
+    r.read_exact(&mut magic)?;
+    let r = Cursor::new(magic).chain(r);
+
+    match &magic {
+        b"RIFF" => read_wav(r),
+        #[cfg(feature = "decode")]
+        b"fLaC" | b"OggS" => read_compressed(r),
+        #[cfg(not(feature = "decode"))]
+        b"fLaC" | b"OggS" => Err(invalid("FLAC and Ogg files require the \"decode\" feature")),
+        _ => Err(invalid(
+            "Unknown audio format, expected a WAV, FLAC or Ogg file",
+        )),
+    }
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
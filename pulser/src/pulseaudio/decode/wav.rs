@@ -0,0 +1,104 @@
+use std::io::{self, Read};
+
+use super::{invalid, Decoded};
+use crate::api::{RawFormat, RawSpec};
+
+/// `WAVE_FORMAT_PCM`
+const FORMAT_PCM: u16 = 1;
+/// `WAVE_FORMAT_IEEE_FLOAT`
+const FORMAT_FLOAT: u16 = 3;
+/// `WAVE_FORMAT_EXTENSIBLE`, where the actual format is the start of the sub-format GUID
+const FORMAT_EXTENSIBLE: u16 = 0xfffe;
+/// The largest `fmt ` chunk which is read, `WAVE_FORMATEXTENSIBLE` is 40 bytes
+const MAX_FMT_LEN: u32 = 1024;
+
+/// Reads a WAV file containing PCM (8, 16, 24 or 32 bit) or 32 bit float samples, which covers
+/// practically every notification sound. The header is read straight away, and the samples as
+/// `Decoded::data` is read.
+pub fn read_wav(mut r: impl Read + 'static) -> io::Result<Decoded> {
+    let mut header = [0; 12];
+    r.read_exact(&mut header)?;
+    if &header[0..4] != b"RIFF" || &header[8..12] != b"WAVE" {
+        return Err(invalid("Not a WAV file"));
+    }
+
+    let mut spec = None;
+    loop {
+        let mut chunk = [0; 8];
+        r.read_exact(&mut chunk)?;
+        let len = u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]);
+
+        match &chunk[0..4] {
+            b"fmt " => {
+                if len > MAX_FMT_LEN {
+                    return Err(invalid("WAV format chunk is too long"));
+                }
+                let fmt = read_chunk(&mut r, len)?;
+                spec = Some(parse_fmt(&fmt)?);
+            }
+            b"data" => {
+                let spec = spec.ok_or_else(|| invalid("WAV file has no format before its data"))?;
+                // streamed files may not know their length, in which case the data runs to the end
+                let data: Box<dyn Read> = match len {
+                    u32::MAX | 0 => Box::new(r),
+                    len => Box::new(r.take(len as u64)),
+                };
+                return Ok(Decoded { spec, data });
+            }
+            // skip any other chunks, such as metadata
+            _ => skip_chunk(&mut r, len)?,
+        }
+    }
+}
+
+/// Reads a chunk's payload, along with its padding byte if its length is odd
+fn read_chunk(r: &mut impl Read, len: u32) -> io::Result<Vec<u8>> {
+    let mut buf = vec![0; len as usize + (len as usize & 1)];
+    r.read_exact(&mut buf)?;
+    buf.truncate(len as usize);
+    Ok(buf)
+}
+
+/// Skips a chunk's payload and padding byte without buffering it, since its length can't be trusted
+fn skip_chunk(r: &mut impl Read, len: u32) -> io::Result<()> {
+    let len = len as u64 + (len as u64 & 1);
+    match io::copy(&mut r.by_ref().take(len), &mut io::sink())? {
+        skipped if skipped == len => Ok(()),
+        _ => Err(io::ErrorKind::UnexpectedEof.into()),
+    }
+}
+
+fn parse_fmt(fmt: &[u8]) -> io::Result<RawSpec> {
+    if fmt.len() < 16 {
+        return Err(invalid("WAV format chunk is too short"));
+    }
+
+    let u16_at = |i: usize| u16::from_le_bytes([fmt[i], fmt[i + 1]]);
+    let mut format = u16_at(0);
+    if format == FORMAT_EXTENSIBLE && fmt.len() >= 26 {
+        format = u16_at(24);
+    }
+    let channels = u16_at(2);
+    let rate = u32::from_le_bytes([fmt[4], fmt[5], fmt[6], fmt[7]]);
+    let bits = u16_at(14);
+
+    let format = match (format, bits) {
+        (FORMAT_PCM, 8) => RawFormat::U8,
+        (FORMAT_PCM, 16) => RawFormat::S16le,
+        (FORMAT_PCM, 24) => RawFormat::S24le,
+        (FORMAT_PCM, 32) => RawFormat::S32le,
+        (FORMAT_FLOAT, 32) => RawFormat::F32le,
+        (format, bits) => {
+            return Err(invalid(format!(
+                "Unsupported WAV format {} with {} bits per sample",
+                format, bits
+            )))
+        }
+    };
+
+    Ok(RawSpec {
+        format,
+        rate,
+        channels: u8::try_from(channels).map_err(|_| invalid("Too many channels"))?,
+    })
+}
@@ -0,0 +1,138 @@
+use std::cell::RefCell;
+use std::error::Error;
+use std::rc::Rc;
+use std::thread;
+
+use crossbeam_channel::{self as channel, Receiver, Sender};
+use libpulse_binding::context::{Context, FlagSet, State};
+use libpulse_binding::mainloop::standard::{IterateResult, Mainloop};
+
+use super::api::{PACommand, PAError, PAResponse};
+use super::mainloop::{ConnectOptions, PulseAudioLoop, StopReason};
+use super::timer::LoopHandle;
+use crate::ignore::Ignore;
+
+type Connection = (Rc<RefCell<Mainloop>>, Rc<RefCell<Context>>);
+
+/// Drives a connection to PulseAudio from the caller's own event loop, rather than from a
+/// background thread (see `PulseAudioLoop::start`).
+///
+/// Commands are sent and responses are received over the returned channels, exactly like the
+/// threaded loop, but nothing happens until `Driver::iterate` is called. Commands are handled at the
+/// start of each iteration, so a blocking iteration won't wake up when a command is sent; if you need
+/// that, iterate without blocking whenever your own loop wakes up instead.
+pub struct Driver {
+    // NOTE: `pa` must be dropped before `mainloop`, since it holds the context
+    pa: PulseAudioLoop,
+    mainloop: Rc<RefCell<Mainloop>>,
+    tx: Sender<PAResponse>,
+}
+
+impl Driver {
+    /// Connects to PulseAudio, iterating the mainloop until the connection is ready.
+    pub fn connect(
+        options: ConnectOptions,
+    ) -> Result<(Driver, Sender<PACommand>, Receiver<PAResponse>), PAError> {
+        let (response_tx, response_rx) = channel::unbounded();
+        let (cmd_tx, cmd_rx) = channel::unbounded();
+
+        let mut attempts = 0;
+        let (mainloop, ctx) = loop {
+            match Self::connect_context(&options) {
+                Ok(connection) => break connection,
+                Err(_) if attempts < options.retry.attempts => {
+                    attempts += 1;
+                    thread::sleep(options.retry.delay);
+                }
+                Err(e) => return Err(PAError::ConnectionFailed(e.to_string())),
+            }
+        };
+
+        let handle = LoopHandle::standard(&mainloop.borrow());
+        let pa = PulseAudioLoop::new(&options, response_tx.clone(), cmd_rx, ctx, None, handle);
+        pa.probe_capabilities();
+        let driver = Driver {
+            pa,
+            mainloop,
+            tx: response_tx,
+        };
+
+        Ok((driver, cmd_tx, response_rx))
+    }
+
+    fn connect_context(options: &ConnectOptions) -> Result<Connection, Box<dyn Error>> {
+        let mainloop = Rc::new(RefCell::new(
+            Mainloop::new().ok_or("Failed to create PulseAudio Mainloop")?,
+        ));
+        let ctx = Rc::new(RefCell::new(PulseAudioLoop::new_context(
+            &*mainloop.borrow(),
+            options,
+        )?));
+
+        ctx.borrow_mut()
+            .connect(options.server.as_deref(), FlagSet::NOFLAGS, None)?;
+
+        // iterate, waiting for context to be ready
+        loop {
+            match ctx.borrow().get_state() {
+                State::Ready => break,
+                State::Failed | State::Terminated => return Err("Failed to connect".into()),
+                _ => {}
+            }
+
+            match mainloop.borrow_mut().iterate(true) {
+                IterateResult::Success(_) => {}
+                IterateResult::Quit(_) => return Err("Mainloop quit while connecting".into()),
+                IterateResult::Err(e) => return Err(e.into()),
+            }
+        }
+
+        Ok((mainloop, ctx))
+    }
+
+    /// Handles any pending commands, and then runs a single iteration of the mainloop. If `block`
+    /// is true, this waits until at least one event (such as a response from PulseAudio) has been
+    /// dispatched.
+    ///
+    /// Returns a `StopReason` once the loop should no longer be iterated, in which case
+    /// `PAResponse::Disconnected` will have been sent. If an error is returned, then
+    /// `PAResponse::Fatal` will have been sent. In either case, the driver should be dropped.
+    pub fn iterate(&mut self, block: bool) -> Result<Option<StopReason>, PAError> {
+        let result = self.pa.handle_pending().and_then(|stop| match stop {
+            Some(reason) => Ok(Some(reason)),
+            None => match self.mainloop.borrow_mut().iterate(block) {
+                IterateResult::Success(_) => Ok(None),
+                IterateResult::Quit(_) => Err("Mainloop quit unexpectedly".into()),
+                IterateResult::Err(e) => Err(e.into()),
+            },
+        });
+
+        match result {
+            Ok(Some(reason)) => {
+                self.restore_all();
+                self.tx.send(PAResponse::Disconnected).ignore();
+                Ok(Some(reason))
+            }
+            Ok(None) => Ok(None),
+            Err(e) => {
+                let err = PAError::ConnectionLost(e.to_string());
+                self.tx.send(PAResponse::Fatal(err.clone())).ignore();
+                Err(err)
+            }
+        }
+    }
+
+    /// Applies every pending restore before the loop stops (see `PulseAudioLoop::restore_all`),
+    /// iterating until the server has acknowledged them. Callbacks are still called if the
+    /// connection fails meanwhile, so this doesn't wait forever.
+    fn restore_all(&mut self) {
+        let (done_rx, count) = self.pa.apply_restores();
+        let mut done = 0;
+        while done < count {
+            match self.mainloop.borrow_mut().iterate(true) {
+                IterateResult::Success(_) => done += done_rx.try_iter().count(),
+                IterateResult::Quit(_) | IterateResult::Err(_) => break,
+            }
+        }
+    }
+}
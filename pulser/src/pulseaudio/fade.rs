@@ -0,0 +1,229 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::Sender;
+use libpulse_binding::volume::{ChannelVolumes, Volume, VolumeDB};
+
+use super::api::{FadeCurve, PAResponse};
+use super::mainloop::CancelToken;
+use super::timer::Timer;
+use super::volume::new_channel_volumes;
+use crate::ignore::Ignore;
+
+/// How often the volume is updated during a fade
+pub const FADE_STEP: Duration = Duration::from_millis(50);
+
+/// Exponential fades can't start or end at -inf dB, so treat anything quieter than this as silent
+const FADE_FLOOR_DB: f64 = -60.0;
+
+#[derive(Debug, Clone)]
+enum FadeState {
+    Running,
+    Done,
+    Cancelled,
+    Failed(String),
+}
+
+pub struct Fade {
+    /// Index of the sink being faded
+    pub sink: u32,
+    from: ChannelVolumes,
+    to: ChannelVolumes,
+    duration: Duration,
+    curve: FadeCurve,
+    started: Instant,
+    state: FadeState,
+    /// Whether a `PACommand::WaitFade` is waiting on a response
+    waiting: bool,
+    /// The `CancelToken`'s generation when the fade was started
+    generation: u32,
+    /// The timer driving the fade; kept alive here until the fade is purged
+    pub timer: Option<Timer>,
+}
+
+impl Fade {
+    pub fn new(
+        sink: u32,
+        from: ChannelVolumes,
+        to: ChannelVolumes,
+        duration: Duration,
+        curve: FadeCurve,
+    ) -> Fade {
+        Fade {
+            sink,
+            from,
+            to,
+            duration,
+            curve,
+            started: Instant::now(),
+            state: FadeState::Running,
+            waiting: false,
+            generation: 0,
+            timer: None,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        matches!(self.state, FadeState::Running)
+    }
+
+    /// Returns the volumes for the current point in time, and whether the fade has reached its end.
+    pub fn step(&self) -> (ChannelVolumes, bool) {
+        let t = match self.duration.is_zero() {
+            true => 1.0,
+            false => self.started.elapsed().as_secs_f64() / self.duration.as_secs_f64(),
+        };
+
+        if t >= 1.0 {
+            return (self.to, true);
+        }
+
+        let volumes = self
+            .from
+            .get()
+            .iter()
+            .zip(self.to.get())
+            .map(|(from, to)| interpolate(self.curve, *from, *to, t))
+            .collect();
+
+        (new_channel_volumes(volumes), false)
+    }
+}
+
+fn interpolate(curve: FadeCurve, from: Volume, to: Volume, t: f64) -> Volume {
+    match curve {
+        FadeCurve::Linear => {
+            let (from, to) = (from.0 as f64, to.0 as f64);
+            Volume((from + (to - from) * t).round() as u32)
+        }
+        FadeCurve::Exponential => {
+            let db = |v: Volume| VolumeDB::from(v).0.max(FADE_FLOOR_DB);
+            let (from, to) = (db(from), db(to));
+            VolumeDB(from + (to - from) * t).into()
+        }
+    }
+}
+
+/// All fades started on this connection, by id.
+pub struct Fades {
+    next_id: u32,
+    inner: HashMap<u32, Fade>,
+    cancel: CancelToken,
+}
+
+impl Fades {
+    pub fn new(cancel: CancelToken) -> Fades {
+        Fades {
+            next_id: 0,
+            inner: HashMap::new(),
+            cancel,
+        }
+    }
+
+    /// Adds a new fade, cancelling any running fade on the same sink and responding to any
+    /// `PACommand::WaitFade` for it.
+    /// Must not be called from within a fade's timer callback, since finished fades are dropped here.
+    pub fn insert(&mut self, mut fade: Fade, tx: &Sender<PAResponse>) -> u32 {
+        fade.generation = self.cancel.generation();
+        self.inner.retain(|_, f| f.is_running() || f.waiting);
+        for other in self.inner.values_mut() {
+            if other.sink == fade.sink && other.is_running() {
+                other.state = FadeState::Cancelled;
+                if other.waiting {
+                    other.waiting = false;
+                    tx.send(Self::response(&other.state)).ignore();
+                }
+            }
+        }
+
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+        self.inner.insert(id, fade);
+        id
+    }
+
+    pub fn get_mut(&mut self, id: u32) -> Option<&mut Fade> {
+        self.inner.get_mut(&id)
+    }
+
+    /// Returns the fade if it's still running, after cancelling it if the `CancelToken` was
+    /// cancelled since it started.
+    pub fn running(&mut self, id: u32, tx: &Sender<PAResponse>) -> Option<&mut Fade> {
+        self.cancel_stale(tx);
+        self.inner.get_mut(&id).filter(|fade| fade.is_running())
+    }
+
+    /// Cancels every running fade which was started before the `CancelToken` was last cancelled,
+    /// and responds to any `PACommand::WaitFade` for them. Their timers are left alone, since this
+    /// may be called from within one; they stop once they see the fade isn't running.
+    pub fn cancel_stale(&mut self, tx: &Sender<PAResponse>) {
+        let generation = self.cancel.generation();
+        for fade in self.inner.values_mut() {
+            if fade.is_running() && fade.generation != generation {
+                fade.state = FadeState::Cancelled;
+                if fade.waiting {
+                    fade.waiting = false;
+                    tx.send(Self::response(&fade.state)).ignore();
+                }
+            }
+        }
+    }
+
+    /// Marks a fade as finished, and responds to any `PACommand::WaitFade` for it.
+    pub fn finish(&mut self, id: u32, result: Result<(), String>, tx: &Sender<PAResponse>) {
+        if let Some(fade) = self.inner.get_mut(&id) {
+            if !fade.is_running() {
+                return;
+            }
+
+            fade.state = match result {
+                Ok(()) => FadeState::Done,
+                Err(e) => FadeState::Failed(e),
+            };
+
+            if fade.waiting {
+                fade.waiting = false;
+                tx.send(Self::response(&fade.state)).ignore();
+            }
+        }
+    }
+
+    /// Cancels a running fade, and responds to any `PACommand::WaitFade` for it.
+    pub fn cancel(&mut self, id: u32, tx: &Sender<PAResponse>) -> Result<(), String> {
+        match self.inner.get_mut(&id) {
+            Some(fade) if fade.is_running() => {
+                fade.state = FadeState::Cancelled;
+                fade.timer = None;
+                if fade.waiting {
+                    fade.waiting = false;
+                    tx.send(Self::response(&fade.state)).ignore();
+                }
+                Ok(())
+            }
+            Some(_) => Err(format!("Fade with id {} has already finished", id)),
+            None => Err(format!("No fade found with id: {}", id)),
+        }
+    }
+
+    /// Responds immediately if the fade has finished, otherwise once it does.
+    pub fn wait(&mut self, id: u32, tx: &Sender<PAResponse>) {
+        match self.inner.get_mut(&id) {
+            Some(fade) if fade.is_running() => fade.waiting = true,
+            Some(fade) => tx.send(Self::response(&fade.state)).ignore(),
+            None => tx
+                .send(PAResponse::OpError(
+                    format!("No fade found with id: {}", id).into(),
+                ))
+                .ignore(),
+        }
+    }
+
+    fn response(state: &FadeState) -> PAResponse {
+        match state {
+            FadeState::Running => unreachable!("fade has not finished"),
+            FadeState::Done => PAResponse::OpComplete,
+            FadeState::Cancelled => PAResponse::OpError("Fade was cancelled".into()),
+            FadeState::Failed(e) => PAResponse::OpError(e.clone().into()),
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use std::time::{Duration, Instant};
+
+use super::api::PAEvent;
+use super::timer::Timer;
+
+/// What to do each time the heartbeat timer fires
+pub enum Tick {
+    /// Send a ping to the server
+    Ping,
+    /// The last ping hasn't been answered within an interval; emit the event
+    Unresponsive(PAEvent),
+    /// The last ping still hasn't been answered, and the event has already been emitted
+    Wait,
+}
+
+/// Periodically pings the server (with a cheap introspection call), so that a wedged server can be
+/// detected, rather than every call just hanging.
+#[derive(Default)]
+pub struct Heartbeat {
+    /// How often to ping the server, `None` disables it. A ping which isn't answered before the
+    /// next one is due means the server is unresponsive.
+    pub interval: Option<Duration>,
+    /// When the unanswered ping was sent, if any
+    pending: Option<Instant>,
+    /// Whether `ServerUnresponsive` has been emitted for the unanswered ping
+    unresponsive: bool,
+    pub timer: Option<Timer>,
+}
+
+impl Heartbeat {
+    pub fn reset(&mut self, interval: Option<Duration>) {
+        self.interval = interval;
+        self.pending = None;
+        self.unresponsive = false;
+        self.timer = None;
+    }
+
+    pub fn tick(&mut self) -> Tick {
+        match self.pending {
+            None => {
+                self.pending = Some(Instant::now());
+                Tick::Ping
+            }
+            Some(_) if self.unresponsive => Tick::Wait,
+            Some(sent) => {
+                self.unresponsive = true;
+                Tick::Unresponsive(PAEvent::ServerUnresponsive {
+                    r#for: sent.elapsed(),
+                })
+            }
+        }
+    }
+
+    /// Records that the server answered a ping. Returns `ServerResponsive` if it was unresponsive.
+    pub fn pong(&mut self) -> Option<PAEvent> {
+        self.pending = None;
+        match std::mem::replace(&mut self.unresponsive, false) {
+            true => Some(PAEvent::ServerResponsive),
+            false => None,
+        }
+    }
+}
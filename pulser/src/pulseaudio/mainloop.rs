@@ -1,15 +1,29 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell, RefMut};
+use std::collections::hash_map::RandomState;
+use std::collections::BTreeMap;
 use std::error::Error;
+use std::fmt::Debug;
+use std::fs::{self, DirBuilder, OpenOptions};
+use std::hash::{BuildHasher, Hasher};
+use std::io::Write;
 use std::ops::Deref;
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
-use std::sync::mpsc::{self, Receiver, SendError, Sender};
-use std::thread;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::mpsc::SendError;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use std::{env, process, thread};
 
+use crossbeam_channel::{self as channel, Receiver, Sender, TryRecvError};
 use libpulse_binding::callbacks::ListResult;
-use libpulse_binding::channelmap::Position;
+use libpulse_binding::channelmap::{Map, Position};
+use libpulse_binding::context::ext_stream_restore::Info as StreamRestoreInfo;
 use libpulse_binding::context::introspect::{
     CardInfo,
     ClientInfo,
+    Introspector,
     ModuleInfo,
     SampleInfo,
     ServerInfo,
@@ -18,54 +32,87 @@ use libpulse_binding::context::introspect::{
     SourceInfo,
     SourceOutputInfo,
 };
-use libpulse_binding::context::subscribe::Operation;
+use libpulse_binding::context::subscribe::{Facility, Operation};
 use libpulse_binding::context::{Context, FlagSet, State};
+use libpulse_binding::def::{SinkFlagSet, SourceFlagSet};
+use libpulse_binding::error::Code;
+use libpulse_binding::mainloop::api::Mainloop as MainloopTrait;
 use libpulse_binding::mainloop::threaded::Mainloop;
-use libpulse_binding::proplist::{properties, Proplist};
-use libpulse_binding::volume::Volume;
+use libpulse_binding::proplist::{properties, Proplist, UpdateMode};
+use libpulse_binding::sample::Spec;
+use libpulse_binding::stream::{Latency, PeekResult, SeekMode, State as StreamState, Stream};
+use libpulse_binding::time::MonotonicTs;
+use libpulse_binding::volume::{ChannelVolumes, Volume};
 use libpulse_sys::PA_INVALID_INDEX;
 
 use super::api::*;
-use super::util::updated_channel_volumes;
+use super::fade::{Fade, Fades, FADE_STEP};
+use super::heartbeat::{Heartbeat, Tick};
+use super::raw::{Feed, RawStream, RawStreams, RAW_POLL};
+#[cfg(feature = "resample")]
+use super::resample::Resampler;
+use super::restore::{scale_volumes, Restores, Saved, Target};
+use super::retry::Retries;
+use super::subscription::Subscription;
+use super::timer::{LoopHandle, TimerRef};
+use super::util::{limit_channel_volumes, relative_channel_volumes, updated_channel_volumes};
+use super::volume::new_channel_volumes;
 use crate::ignore::Ignore;
 use crate::pulseaudio::api::VolumeReading;
 use crate::sender::EventSender;
 
 type Ctx = Rc<RefCell<Context>>;
 type Res = Result<(), Box<dyn Error>>;
+type Connection = (Rc<RefCell<Mainloop>>, Ctx);
 
 macro_rules! cb {
-    ($f:expr, $ident:expr, $ctx:expr, $tx:expr) => {
+    ($lookup:expr, $retry:expr) => {{
+        let lookup = $lookup.clone();
         move |result| {
             match result {
                 // The result we wanted, act on it
                 ListResult::Item(inner) => {
-                    if let Err(e) = (&mut $f)($ident.clone(), $ctx.clone(), inner) {
-                        $tx.send(PAResponse::OpError(e.to_string())).ignore();
+                    let ident = lookup.ident.clone();
+                    if let Err(e) = (lookup.f.borrow_mut())(ident, lookup.ctx.clone(), inner) {
+                        let err = match e.downcast::<InvalidChannelCount>() {
+                            Ok(e) => OpError::from(*e),
+                            Err(e) => e.to_string().into(),
+                        };
+                        lookup.tx.send(PAResponse::OpError(err)).ignore();
+                    }
+                }
+                // An error occurred, retry if the object may just not exist yet, otherwise check it
+                // and send an error event
+                ListResult::Error => {
+                    if !lookup.retry($retry) {
+                        Self::handle_error(&lookup.ctx, &lookup.tx)
                     }
                 }
-                // An error occurred, check it and send an error event
-                ListResult::Error => Self::handle_error(&$ctx, &$tx),
                 // We reached the end of the list
                 ListResult::End => {}
             }
         }
-    };
+    }};
 }
 
 macro_rules! impl_call_ident_both {
     ($ty:ident) => {
         paste::paste! {
-            fn [<with_ $ty:snake>]<F>(&self, ident: PAIdent, mut f: F)
+            fn [<with_ $ty:snake>]<F>(&self, ident: PAIdent, f: F)
             where
                 F: FnMut(PAIdent, Ctx, &$ty) -> Res + 'static,
             {
-                let tx = self.tx.clone();
-                let ctx = self.ctx.clone();
-                let introspector = ctx.borrow_mut().introspect();
-                match ident.clone() {
-                    PAIdent::Index(idx) => introspector.[<get_ $ty:snake _by_index>](idx, cb!(f, ident, ctx, tx)),
-                    PAIdent::Name(ref name) => introspector.[<get_ $ty:snake _by_name>](name, cb!(f, ident, ctx, tx)),
+                Self::[<lookup_ $ty:snake>](self.lookup(ident, f));
+            }
+
+            fn [<lookup_ $ty:snake>]<F>(lookup: Lookup<F>)
+            where
+                F: FnMut(PAIdent, Ctx, &$ty) -> Res + 'static,
+            {
+                let introspector = lookup.introspector.borrow();
+                match lookup.ident {
+                    PAIdent::Index(idx) => introspector.[<get_ $ty:snake _by_index>](idx, cb!(lookup, Self::[<lookup_ $ty:snake>])),
+                    PAIdent::Name(ref name) => introspector.[<get_ $ty:snake _by_name>](name, cb!(lookup, Self::[<lookup_ $ty:snake>])),
                 };
             }
         }
@@ -75,25 +122,129 @@ macro_rules! impl_call_ident_both {
 macro_rules! impl_call_ident_index {
     ($ty:ident) => {
         paste::paste! {
-            fn [<with_ $ty:snake>]<F>(&self, idx: u32, mut f: F)
+            fn [<with_ $ty:snake>]<F>(&self, idx: u32, f: F)
             where
                 F: FnMut(PAIdent, Ctx, &$ty) -> Res + 'static,
             {
-                let tx = self.tx.clone();
-                let ctx = self.ctx.clone();
-                let introspector = ctx.borrow_mut().introspect();
-                let ident = PAIdent::Index(idx);
-                introspector.[<get_ $ty:snake>](idx, cb!(f, ident, ctx, tx));
+                Self::[<lookup_ $ty:snake>](self.lookup(PAIdent::Index(idx), f));
+            }
+
+            fn [<lookup_ $ty:snake>]<F>(lookup: Lookup<F>)
+            where
+                F: FnMut(PAIdent, Ctx, &$ty) -> Res + 'static,
+            {
+                let introspector = lookup.introspector.borrow();
+                let idx = match lookup.ident {
+                    PAIdent::Index(idx) => idx,
+                    PAIdent::Name(_) => unreachable!("only looked up by index"),
+                };
+                introspector.[<get_ $ty:snake>](idx, cb!(lookup, Self::[<lookup_ $ty:snake>]));
             }
         }
     };
 }
 
+/// A lookup of a single object, which can be retried if the object doesn't exist yet
+struct Lookup<F> {
+    ident: PAIdent,
+    f: Rc<RefCell<F>>,
+    ctx: Ctx,
+    introspector: Rc<RefCell<Introspector>>,
+    tx: Sender<PAResponse>,
+    handle: LoopHandle,
+    retries: Rc<RefCell<Retries>>,
+    deadline: Option<Instant>,
+}
+
+impl<F> Clone for Lookup<F> {
+    fn clone(&self) -> Self {
+        Lookup {
+            ident: self.ident.clone(),
+            f: self.f.clone(),
+            ctx: self.ctx.clone(),
+            introspector: self.introspector.clone(),
+            tx: self.tx.clone(),
+            handle: self.handle.clone(),
+            retries: self.retries.clone(),
+            deadline: self.deadline,
+        }
+    }
+}
+
+impl<F: 'static> Lookup<F> {
+    /// If the lookup failed because the object doesn't exist and the deadline hasn't passed yet,
+    /// schedules `f` to be called again with this lookup and returns `true`.
+    fn retry(&self, f: fn(Lookup<F>)) -> bool {
+        match self.deadline {
+            Some(deadline) if Instant::now() < deadline => {}
+            _ => return false,
+        }
+
+        // `pa_context_errno` returns a positive code, but `PAErr` expects negative codes
+        if self.ctx.borrow().errno().0.abs() != Code::NoEntity as i32 {
+            return false;
+        }
+
+        let lookup = self.clone();
+        self.retries
+            .borrow_mut()
+            .schedule(&self.ctx.borrow(), &self.handle, move || f(lookup.clone()));
+        true
+    }
+}
+
+/// Responds to operations. Shared between their callbacks, so making a callback only clones an
+/// `Rc`, rather than the context and the response channel.
+struct Reply {
+    ctx: Ctx,
+    tx: Sender<PAResponse>,
+}
+
+impl Reply {
+    fn done(&self, success: bool) {
+        if success {
+            self.tx.send(PAResponse::OpComplete).ignore();
+        } else {
+            self.error();
+        }
+    }
+
+    /// The error is only read and formatted here, so successful operations never pay for it
+    fn error(&self) {
+        PulseAudioLoop::handle_error(&self.ctx, &self.tx);
+    }
+}
+
+/// Responds once each of a number of operations has succeeded, or as soon as one fails
+struct Countdown {
+    reply: Rc<Reply>,
+    remaining: Cell<usize>,
+}
+
+impl Countdown {
+    fn done(&self, success: bool) {
+        match (self.remaining.get(), success) {
+            // already responded, since another operation failed
+            (0, _) => {}
+            (_, false) => {
+                self.remaining.set(0);
+                self.reply.error();
+            }
+            (n, true) => {
+                self.remaining.set(n - 1);
+                if n == 1 {
+                    self.reply.done(true);
+                }
+            }
+        }
+    }
+}
+
 macro_rules! impl_list_call {
     ($ty:ident) => {
         paste::paste! {
             fn [<get_ $ty:snake _list>](&self) {
-                let introspector = self.ctx.borrow_mut().introspect();
+                let introspector = self.introspect();
                 let tx = self.tx.clone();
                 let ctx = self.ctx.clone();
                 let mut v: Vec<[<PA $ty>]> = vec![];
@@ -102,7 +253,7 @@ macro_rules! impl_list_call {
                         // Called for each item in the list
                         ListResult::Item(info) => v.push([<PA $ty>]::from(info)),
                         // Called at the end of the iteration, send the event back
-                        ListResult::End => tx.send(PAResponse::[<$ty List>](v.clone())).ignore(),
+                        ListResult::End => tx.send(PAResponse::[<$ty List>](std::mem::take(&mut v))).ignore(),
                         // An error occurred, check it and send an error event
                         ListResult::Error => Self::handle_error(&ctx, &tx),
                     };
@@ -118,11 +269,106 @@ pub enum StopReason {
     ExplicitDisconnect,
 }
 
+/// How to retry connecting to PulseAudio if an attempt fails
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RetryPolicy {
+    /// How many times to retry, the default of `0` means only one attempt is made
+    pub attempts: u32,
+    /// How long to wait between attempts
+    pub delay: Duration,
+}
+
+/// Cancels the multi-step operations (such as fades) which were started before `cancel` was
+/// called. Clones share the same state, so it can be cancelled from any thread.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicU32>);
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Changes each time `cancel` is called, operations record it when they start and stop once it
+    /// no longer matches
+    pub fn generation(&self) -> u32 {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// The length of PulseAudio's authentication cookie, in bytes
+pub const COOKIE_LENGTH: usize = 256;
+
+/// How long to wait for the server to acknowledge the pending restores when disconnecting
+const RESTORE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The authentication cookie to connect with, rather than the one libpulse finds itself (such as
+/// `~/.config/pulse/cookie`). Usually only needed for remote servers.
+#[derive(Clone)]
+pub enum Cookie {
+    /// A file containing the cookie, such as the server's `~/.config/pulse/cookie`
+    Path(PathBuf),
+    /// The cookie itself, which must be `COOKIE_LENGTH` bytes
+    Bytes(Vec<u8>),
+}
+
+impl Debug for Cookie {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Cookie::Path(path) => f.debug_tuple("Path").field(path).finish(),
+            // keep the secret out of logs
+            Cookie::Bytes(_) => f.write_str("Bytes(..)"),
+        }
+    }
+}
+
+/// Options used when connecting to PulseAudio
+#[derive(Debug, Clone)]
+pub struct ConnectOptions {
+    /// The name of the application, which appears in the client's properties
+    pub name: String,
+    /// The server to connect to (such as "tcp:host:port"), or the default server if `None`
+    pub server: Option<String>,
+    /// The authentication cookie, otherwise libpulse looks for one in the usual places
+    pub cookie: Option<Cookie>,
+    pub retry: RetryPolicy,
+    /// The maximum volume which can be set, where `1.0` is 100%
+    pub volume_limit: Option<f64>,
+    /// Checked between the steps of long running operations
+    pub cancel: CancelToken,
+}
+
+impl ConnectOptions {
+    pub fn new(name: impl Into<String>) -> ConnectOptions {
+        ConnectOptions {
+            name: name.into(),
+            server: None,
+            cookie: None,
+            retry: RetryPolicy::default(),
+            volume_limit: None,
+            cancel: CancelToken::default(),
+        }
+    }
+}
+
 pub struct PulseAudioLoop {
     rx: Receiver<PACommand>,
     tx: Sender<PAResponse>,
     ctx: Rc<RefCell<Context>>,
-    mainloop: Rc<RefCell<Mainloop>>,
+    /// Created once and reused, rather than for every command
+    introspector: Rc<RefCell<Introspector>>,
+    reply: Rc<Reply>,
+    /// The threaded mainloop, or `None` when the loop is driven by an `embedded::Driver`
+    mainloop: Option<Rc<RefCell<Mainloop>>>,
+    handle: LoopHandle,
+    /// The maximum volume which can be set, if any
+    volume_limit: Option<Volume>,
+    subscription: Rc<RefCell<Subscription>>,
+    fades: Rc<RefCell<Fades>>,
+    restores: Rc<RefCell<Restores>>,
+    retries: Rc<RefCell<Retries>>,
+    heartbeat: Rc<RefCell<Heartbeat>>,
+    capabilities: Rc<RefCell<Capabilities>>,
+    raw_streams: Rc<RefCell<RawStreams>>,
 }
 
 impl PulseAudioLoop {
@@ -154,21 +400,34 @@ impl PulseAudioLoop {
     pub fn start(
         app_name: impl AsRef<str> + Send + 'static,
     ) -> (Sender<PACommand>, Receiver<PAResponse>) {
-        let (response_tx, response_rx) = mpsc::channel();
-        let (cmd_tx, cmd_rx) = mpsc::channel();
+        Self::start_with(ConnectOptions::new(app_name.as_ref()))
+    }
+
+    /// The same as `start`, but with more control over how the connection is made.
+    pub fn start_with(options: ConnectOptions) -> (Sender<PACommand>, Receiver<PAResponse>) {
+        let (response_tx, response_rx) = channel::unbounded();
+        let (cmd_tx, cmd_rx) = channel::unbounded();
 
         // Run pulseaudio loop in background thread
         thread::spawn(move || {
-            let pa = match PulseAudioLoop::init(app_name.as_ref(), response_tx.clone(), cmd_rx) {
+            let pa = match PulseAudioLoop::init(&options, response_tx.clone(), cmd_rx) {
                 Ok(pa) => pa,
-                Err(e) => panic!("An error occurred while connecting to pulseaudio: {}", e),
+                Err(e) => {
+                    let err = PAError::ConnectionFailed(e.to_string());
+                    response_tx.send(PAResponse::Fatal(err)).ignore();
+                    return;
+                }
             };
 
             match pa.start_loop() {
                 Ok(reason) => match reason {
                     StopReason::CommandSenderDropped | StopReason::ExplicitDisconnect => {}
                 },
-                Err(e) => panic!("An error occurred while interfacing with pulseaudio: {}", e),
+                Err(e) => {
+                    let err = PAError::ConnectionLost(e.to_string());
+                    response_tx.send(PAResponse::Fatal(err)).ignore();
+                    return;
+                }
             }
 
             // Signal that we're done
@@ -178,64 +437,185 @@ impl PulseAudioLoop {
         (cmd_tx, response_rx)
     }
 
-    // https://freedesktop.org/software/pulseaudio/doxygen/threaded_mainloop.html
-    // https://gavv.net/articles/pulseaudio-under-the-hood/#asynchronous-api
-    // https://docs.rs/libpulse-binding/2.26.0/libpulse_binding/mainloop/threaded/index.html#example
     fn init(
-        with_app_name: impl AsRef<str>,
+        options: &ConnectOptions,
         tx: Sender<PAResponse>,
         rx: Receiver<PACommand>,
     ) -> Result<PulseAudioLoop, Box<dyn Error>> {
-        let app_name = with_app_name.as_ref();
+        let mut attempts = 0;
+        let (mainloop, ctx) = loop {
+            match Self::connect(options) {
+                Ok(connection) => break connection,
+                Err(_) if attempts < options.retry.attempts => {
+                    attempts += 1;
+                    thread::sleep(options.retry.delay);
+                }
+                Err(e) => return Err(e),
+            }
+        };
+
+        let handle = LoopHandle::threaded(&mainloop.borrow());
+        Ok(Self::new(options, tx, rx, ctx, Some(mainloop), handle))
+    }
+
+    pub(crate) fn new(
+        options: &ConnectOptions,
+        tx: Sender<PAResponse>,
+        rx: Receiver<PACommand>,
+        ctx: Ctx,
+        mainloop: Option<Rc<RefCell<Mainloop>>>,
+        handle: LoopHandle,
+    ) -> PulseAudioLoop {
+        let introspector = ctx.borrow_mut().introspect();
+        PulseAudioLoop {
+            introspector: Rc::new(RefCell::new(introspector)),
+            reply: Rc::new(Reply {
+                ctx: ctx.clone(),
+                tx: tx.clone(),
+            }),
+            tx,
+            rx,
+            ctx,
+            mainloop,
+            handle,
+            volume_limit: options
+                .volume_limit
+                .map(|limit| Volume((Volume::NORMAL.0 as f64 * limit.max(0.0)).round() as u32)),
+            subscription: Rc::new(RefCell::new(Subscription::default())),
+            fades: Rc::new(RefCell::new(Fades::new(options.cancel.clone()))),
+            restores: Rc::new(RefCell::new(Restores::default())),
+            retries: Rc::new(RefCell::new(Retries::default())),
+            heartbeat: Rc::new(RefCell::new(Heartbeat::default())),
+            capabilities: Rc::new(RefCell::new(Capabilities::default())),
+            raw_streams: Rc::new(RefCell::new(RawStreams::default())),
+        }
+    }
+
+    /// Creates a new context for the given mainloop, which isn't connected yet.
+    pub(crate) fn new_context(
+        mainloop: &impl MainloopTrait,
+        options: &ConnectOptions,
+    ) -> Result<Context, Box<dyn Error>> {
+        let app_name = options.name.as_str();
 
         let mut proplist = Proplist::new().ok_or("Failed to create PulseAudio Proplist")?;
         proplist
             .set_str(properties::APPLICATION_NAME, app_name)
             .map_err(|_| "Failed to update property list")?;
 
+        let mut ctx =
+            Context::new_with_proplist(mainloop, &format!("{}Context", app_name), &proplist)
+                .ok_or("Failed to create PulseAudio Context")?;
+        if let Some(ref cookie) = options.cookie {
+            Self::load_cookie(&mut ctx, cookie)?;
+        }
+
+        Ok(ctx)
+    }
+
+    /// Sets the cookie the context authenticates with. libpulse only loads cookies from files, so
+    /// raw bytes are written to a new file with a random name in a private directory (the user's
+    /// runtime directory, or a new one in the temporary directory), which is removed once it's
+    /// been read, whether or not that succeeded.
+    fn load_cookie(ctx: &mut Context, cookie: &Cookie) -> Result<(), Box<dyn Error>> {
+        match cookie {
+            Cookie::Path(path) => {
+                let path = path.to_str().ok_or("The cookie's path isn't valid UTF-8")?;
+                ctx.load_cookie_from_file(path)?;
+            }
+            Cookie::Bytes(bytes) => {
+                if bytes.len() != COOKIE_LENGTH {
+                    return Err(format!(
+                        "Expected a cookie of {} bytes, but it's {} bytes",
+                        COOKIE_LENGTH,
+                        bytes.len()
+                    )
+                    .into());
+                }
+
+                let (dir, created_dir) = match env::var_os("XDG_RUNTIME_DIR") {
+                    Some(dir) => (PathBuf::from(dir), false),
+                    None => {
+                        // creating the directory fails if anything (even a symlink) is already there
+                        let dir = env::temp_dir().join(format!("pulser-{:x}", random_id()));
+                        DirBuilder::new().mode(0o700).create(&dir)?;
+                        (dir, true)
+                    }
+                };
+
+                let path = dir.join(format!("pulser-cookie-{:x}", random_id()));
+                let loaded = Self::load_cookie_bytes(ctx, &path, bytes);
+                if created_dir {
+                    fs::remove_dir(&dir).ignore();
+                }
+                loaded?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes the cookie to a file which must not exist yet, and loads it from there. The file is
+    /// removed afterwards, unless it couldn't be created.
+    fn load_cookie_bytes(ctx: &mut Context, path: &Path, bytes: &[u8]) -> Res {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(path)?;
+        let loaded = file
+            .write_all(bytes)
+            .map_err(Box::<dyn Error>::from)
+            .and_then(|_| {
+                let path = path.to_str().ok_or("The cookie's path isn't valid UTF-8")?;
+                Ok(ctx.load_cookie_from_file(path)?)
+            });
+        fs::remove_file(path).ignore();
+        loaded
+    }
+
+    // https://freedesktop.org/software/pulseaudio/doxygen/threaded_mainloop.html
+    // https://gavv.net/articles/pulseaudio-under-the-hood/#asynchronous-api
+    // https://docs.rs/libpulse-binding/2.26.0/libpulse_binding/mainloop/threaded/index.html#example
+    fn connect(options: &ConnectOptions) -> Result<Connection, Box<dyn Error>> {
         let mainloop: Rc<RefCell<Mainloop>> = Rc::new(RefCell::new(
             Mainloop::new().ok_or("Failed to create PulseAudio Mainloop")?,
         ));
-        let ctx = Rc::new(RefCell::new(
-            Context::new_with_proplist(
-                mainloop.borrow_mut().deref(),
-                &format!("{}Context", app_name),
-                &proplist,
-            )
-            .ok_or("Failed to create PulseAudio Context")?,
-        ));
+        let ctx = Rc::new(RefCell::new(Self::new_context(
+            mainloop.borrow_mut().deref(),
+            options,
+        )?));
 
-        // setup context
-        {
-            let mainloop_ref = mainloop.clone();
-            let context_ref = ctx.clone();
-            ctx.borrow_mut().set_state_callback(Some(Box::new(move || {
-                // TODO: investigate removing unsafe??
-                // let state = context_ref.borrow_mut().get_state();
-                let state = unsafe { (*context_ref.as_ptr()).get_state() };
-                if matches!(state, State::Ready | State::Failed | State::Terminated) {
-                    unsafe { (*mainloop_ref.as_ptr()).signal(false) };
-                }
-            })));
-        }
+        // the state callback runs in the mainloop's thread, so rather than touching the context or
+        // the mainloop from there, it only says that the state changed
+        let (state_tx, state_rx) = channel::unbounded();
+        ctx.borrow_mut()
+            .set_state_callback(Some(Box::new(move || state_tx.send(()).ignore())));
 
         // connect to pulse
-        ctx.borrow_mut().connect(None, FlagSet::NOFLAGS, None)?;
+        ctx.borrow_mut()
+            .connect(options.server.as_deref(), FlagSet::NOFLAGS, None)?;
 
         // start mainloop
-        mainloop.borrow_mut().lock();
         mainloop.borrow_mut().start()?;
 
         // loop, waiting for context to be ready
         loop {
-            match ctx.borrow_mut().get_state() {
+            mainloop.borrow_mut().lock();
+            let state = ctx.borrow().get_state();
+            match state {
                 State::Ready => break,
                 State::Failed | State::Terminated => {
                     mainloop.borrow_mut().unlock();
                     mainloop.borrow_mut().stop();
                     return Err("Failed to connect".into());
                 }
-                _ => mainloop.borrow_mut().wait(),
+                _ => mainloop.borrow_mut().unlock(),
+            }
+
+            if state_rx.recv().is_err() {
+                mainloop.borrow_mut().stop();
+                return Err("Failed to connect".into());
             }
         }
 
@@ -245,114 +625,251 @@ impl PulseAudioLoop {
         // release lock to allow loop to continue
         mainloop.borrow_mut().unlock();
 
-        Ok(PulseAudioLoop {
-            tx,
-            rx,
-            ctx,
-            mainloop,
-        })
+        Ok((mainloop, ctx))
     }
 
     pub fn start_loop(&self) -> Result<StopReason, Box<dyn Error>> {
+        let mainloop = match self.mainloop {
+            Some(ref mainloop) => mainloop.clone(),
+            None => return Err("No threaded mainloop to start, use `embedded::Driver`".into()),
+        };
+
+        mainloop.borrow_mut().lock();
+        self.probe_capabilities();
+        mainloop.borrow_mut().unlock();
+
         loop {
             // wait for our next command
             let cmd = match self.rx.recv() {
                 Ok(cmd) => cmd,
                 Err(_) => {
-                    self.mainloop.borrow_mut().stop();
+                    mainloop.borrow_mut().lock();
+                    self.restore_all(&mainloop);
+                    mainloop.borrow_mut().unlock();
+                    mainloop.borrow_mut().stop();
                     return Ok(StopReason::CommandSenderDropped);
                 }
             };
 
             // lock and pause mainloop
-            self.mainloop.borrow_mut().lock();
+            mainloop.borrow_mut().lock();
 
             // verify connection state
-            match self.ctx.borrow_mut().get_state() {
-                State::Ready => {}
-                _ => {
-                    self.mainloop.borrow_mut().unlock();
-                    return Err("Disconnected while working, shutting down".into());
-                }
-            }
-
-            match cmd {
-                PACommand::GetServerInfo => self.get_server_info(),
-                PACommand::GetDefaultSink => self.get_default_sink(),
-                PACommand::GetDefaultSource => self.get_default_source(),
-                PACommand::SetDefaultSink(id) => self.set_default_sink(id),
-                PACommand::SetDefaultSource(id) => self.set_default_source(id),
-
-                PACommand::GetCardInfo(id) => self.get_card_info(id),
-                PACommand::SetCardProfile(id, profile) => self.set_card_profile(id, &profile),
-                PACommand::SetPortLatencyOffset(card, port, offset) => {
-                    self.set_port_latency_offset(&card, &port, offset)
-                }
-
-                PACommand::GetClientInfo(idx) => self.get_client_info(idx),
-                PACommand::KillClient(idx) => self.kill_client(idx),
-
-                PACommand::GetModuleInfo(idx) => self.get_module_info(idx),
-                PACommand::LoadModule(name, args) => self.load_module(&name, &args),
-                PACommand::UnloadModule(idx) => self.unload_module(idx),
-
-                PACommand::GetSinkInfo(id) => self.get_sink_info(id),
-                PACommand::GetSinkMute(id) => self.get_sink_mute(id),
-                PACommand::GetSinkVolume(id) => self.get_sink_volume(id),
-                PACommand::SetSinkMute(id, mute) => self.set_sink_mute(id, mute),
-                PACommand::SetSinkVolume(id, vol) => self.set_sink_volume(id, vol),
-                PACommand::SetSinkPort(id, ref name) => self.set_sink_port(id, name),
-                PACommand::SuspendSink(id, suspend) => self.suspend_sink(id, suspend),
-
-                PACommand::GetSourceInfo(id) => self.get_source_info(id),
-                PACommand::GetSourceMute(id) => self.get_source_mute(id),
-                PACommand::GetSourceVolume(id) => self.get_source_volume(id),
-                PACommand::SetSourceMute(id, mute) => self.set_source_mute(id, mute),
-                PACommand::SetSourceVolume(id, vol) => self.set_source_volume(id, vol),
-                PACommand::SetSourcePort(id, ref name) => self.set_source_port(id, name),
-                PACommand::SuspendSource(id, suspend) => self.suspend_source(id, suspend),
-
-                PACommand::GetSinkInputInfo(idx) => self.get_sink_input_info(idx),
-                PACommand::GetSinkInputMute(idx) => self.get_sink_input_mute(idx),
-                PACommand::GetSinkInputVolume(idx) => self.get_sink_input_volume(idx),
-                PACommand::SetSinkInputMute(idx, mute) => self.set_sink_input_mute(idx, mute),
-                PACommand::SetSinkInputVolume(idx, vol) => self.set_sink_input_volume(idx, vol),
-                PACommand::MoveSinkInput(idx, sink_id) => self.move_sink_input(idx, sink_id),
-                PACommand::KillSinkInput(idx) => self.kill_sink_input(idx),
-
-                PACommand::GetSourceOutputInfo(idx) => self.get_source_output_info(idx),
-                PACommand::GetSourceOutputMute(idx) => self.get_source_output_mute(idx),
-                PACommand::GetSourceOutputVolume(idx) => self.get_source_output_volume(idx),
-                PACommand::SetSourceOutputMute(idx, mute) => self.set_source_output_mute(idx, mute),
-                PACommand::SetSourceOutputVolume(idx, vol) => {
-                    self.set_source_output_volume(idx, vol)
-                }
-                PACommand::MoveSourceOutput(idx, source_id) => {
-                    self.move_source_output(idx, source_id)
-                }
-                PACommand::KillSourceOutput(idx) => self.kill_source_output(idx),
-
-                PACommand::GetCardInfoList => self.get_card_info_list(),
-                PACommand::GetClientInfoList => self.get_client_info_list(),
-                PACommand::GetModuleInfoList => self.get_module_info_list(),
-                PACommand::GetSampleInfoList => self.get_sample_info_list(),
-                PACommand::GetSinkInfoList => self.get_sink_info_list(),
-                PACommand::GetSinkInputInfoList => self.get_sink_input_info_list(),
-                PACommand::GetSourceInfoList => self.get_source_info_list(),
-                PACommand::GetSourceOutputInfoList => self.get_source_output_info_list(),
-
-                PACommand::Subscribe(mask, tx) => self.setup_subscribe(mask, tx),
-
-                PACommand::Disconnect => {
-                    self.mainloop.borrow_mut().unlock();
-                    self.mainloop.borrow_mut().stop();
-                    return Ok(StopReason::ExplicitDisconnect);
-                }
+            if let Err(e) = self.verify_state() {
+                mainloop.borrow_mut().unlock();
+                mainloop.borrow_mut().stop();
+                return Err(e);
+            }
+
+            let stop = self.handle_command(cmd);
+            if stop.is_some() {
+                self.restore_all(&mainloop);
             }
 
             // resume mainloop
-            self.mainloop.borrow_mut().unlock();
+            mainloop.borrow_mut().unlock();
+
+            if let Some(reason) = stop {
+                mainloop.borrow_mut().stop();
+                return Ok(reason);
+            }
+        }
+    }
+
+    /// Handles each command which has been sent, without blocking. Used by `embedded::Driver`.
+    pub(crate) fn handle_pending(&self) -> Result<Option<StopReason>, Box<dyn Error>> {
+        loop {
+            let cmd = match self.rx.try_recv() {
+                Ok(cmd) => cmd,
+                Err(TryRecvError::Empty) => return Ok(None),
+                Err(TryRecvError::Disconnected) => {
+                    return Ok(Some(StopReason::CommandSenderDropped))
+                }
+            };
+
+            self.verify_state()?;
+            if let Some(reason) = self.handle_command(cmd) {
+                return Ok(Some(reason));
+            }
+        }
+    }
+
+    /// Applies every pending restore before the loop stops, so temporary changes don't outlive the
+    /// connection, and waits (up to `RESTORE_TIMEOUT`) for the server to acknowledge them. The
+    /// mainloop must be locked, it's unlocked while waiting.
+    fn restore_all(&self, mainloop: &RefCell<Mainloop>) {
+        let (done_rx, count) = self.apply_restores();
+        if count == 0 {
+            return;
+        }
+
+        mainloop.borrow_mut().unlock();
+        let deadline = Instant::now() + RESTORE_TIMEOUT;
+        for _ in 0..count {
+            if done_rx.recv_deadline(deadline).is_err() {
+                break;
+            }
+        }
+        mainloop.borrow_mut().lock();
+    }
+
+    /// Applies every saved state which hasn't been restored yet. Returns a receiver which is sent
+    /// to as each one is acknowledged, and how many there are (none if the connection was lost).
+    pub(crate) fn apply_restores(&self) -> (Receiver<()>, usize) {
+        let (done_tx, done_rx) = channel::unbounded();
+        if self.verify_state().is_err() {
+            return (done_rx, 0);
         }
+
+        let pending = self.restores.borrow_mut().drain();
+        for &(target, saved) in &pending {
+            let done_tx = done_tx.clone();
+            let cb = Box::new(move |_| done_tx.send(()).ignore());
+            Self::apply_saved(&self.ctx, target, saved, Some(cb));
+        }
+
+        (done_rx, pending.len())
+    }
+
+    fn verify_state(&self) -> Result<(), Box<dyn Error>> {
+        match self.ctx.borrow_mut().get_state() {
+            State::Ready => Ok(()),
+            _ => Err("Disconnected while working, shutting down".into()),
+        }
+    }
+
+    /// Handles a single command, returning a `StopReason` if the loop should stop.
+    fn handle_command(&self, cmd: PACommand) -> Option<StopReason> {
+        match cmd {
+            PACommand::GetServerInfo => self.get_server_info(),
+            PACommand::GetProtocolInfo => self.get_protocol_info(),
+            PACommand::GetCapabilities => self.get_capabilities(),
+            PACommand::GetDefaultSink => self.get_default_sink(),
+            PACommand::GetDefaultSource => self.get_default_source(),
+            PACommand::SetDefaultSink(id) => self.set_default_sink(id),
+            PACommand::SetDefaultSource(id) => self.set_default_source(id),
+
+            PACommand::GetCardInfo(id) => self.get_card_info(id),
+            PACommand::SetCardProfile(id, profile) => self.set_card_profile(id, &profile),
+            PACommand::SetPortLatencyOffset(card, port, offset) => {
+                self.set_port_latency_offset(&card, &port, offset)
+            }
+
+            PACommand::GetClientInfo(idx) => self.get_client_info(idx),
+            PACommand::KillClient(idx) => self.kill_client(idx),
+
+            PACommand::GetModuleInfo(idx) => self.get_module_info(idx),
+            PACommand::LoadModule(name, args) => self.load_module(&name, &args),
+            PACommand::UnloadModule(idx) => self.unload_module(idx),
+
+            PACommand::GetSinkInfo(id) => self.get_sink_info(id),
+            PACommand::GetSinkMute(id) => self.get_sink_mute(id),
+            PACommand::GetSinkVolume(id) => self.get_sink_volume(id),
+            PACommand::SetSinkMute(id, mute) => self.set_sink_mute(id, mute),
+            PACommand::SetSinkVolume(id, vol) => self.set_sink_volume(id, vol, None),
+            PACommand::SetSinkMuteIf(id, expected, mute) => {
+                self.set_sink_mute_if(id, expected, mute)
+            }
+            PACommand::SetSinkVolumeIf(id, expected, vol) => {
+                self.set_sink_volume(id, vol, Some(expected))
+            }
+            PACommand::SetSinkPort(id, ref name) => self.set_sink_port(id, name),
+            PACommand::SuspendSink(id, suspend) => self.suspend_sink(id, suspend),
+            PACommand::RenameSink(id, ref description) => self.rename_sink(id, description),
+            PACommand::FadeSinkVolume(id, vol, duration, curve) => {
+                self.fade_sink_volume(id, vol, duration, curve)
+            }
+            PACommand::MuteSinkFor(id, duration) => self.mute_sink_for(id, duration),
+            PACommand::DuckSink(id, pct, duration) => self.duck_sink(id, pct, duration),
+
+            PACommand::GetSourceInfo(id) => self.get_source_info(id),
+            PACommand::GetSourceMute(id) => self.get_source_mute(id),
+            PACommand::GetSourceVolume(id) => self.get_source_volume(id),
+            PACommand::SetSourceMute(id, mute) => self.set_source_mute(id, mute),
+            PACommand::SetSourceVolume(id, vol) => self.set_source_volume(id, vol, None),
+            PACommand::SetSourceMuteIf(id, expected, mute) => {
+                self.set_source_mute_if(id, expected, mute)
+            }
+            PACommand::SetSourceVolumeIf(id, expected, vol) => {
+                self.set_source_volume(id, vol, Some(expected))
+            }
+            PACommand::SetSourcePort(id, ref name) => self.set_source_port(id, name),
+            PACommand::SuspendSource(id, suspend) => self.suspend_source(id, suspend),
+            PACommand::RenameSource(id, ref description) => self.rename_source(id, description),
+
+            PACommand::GetSinkInputInfo(idx) => self.get_sink_input_info(idx),
+            PACommand::GetSinkInputMute(idx) => self.get_sink_input_mute(idx),
+            PACommand::GetSinkInputVolume(idx) => self.get_sink_input_volume(idx),
+            PACommand::SetSinkInputMute(idx, mute) => self.set_sink_input_mute(idx, mute),
+            PACommand::SetSinkInputVolume(idx, vol) => self.set_sink_input_volume(idx, vol),
+            PACommand::MoveSinkInput(stream, sink_id) => self.move_sink_input(stream, sink_id),
+            PACommand::KillSinkInput(idx) => self.kill_sink_input(idx),
+            PACommand::MuteSinkInputFor(idx, duration) => self.mute_sink_input_for(idx, duration),
+            PACommand::DuckSinkInput(idx, pct, duration) => {
+                self.duck_sink_input(idx, pct, duration)
+            }
+
+            PACommand::GetSourceOutputInfo(idx) => self.get_source_output_info(idx),
+            PACommand::GetSourceOutputMute(idx) => self.get_source_output_mute(idx),
+            PACommand::GetSourceOutputVolume(idx) => self.get_source_output_volume(idx),
+            PACommand::SetSourceOutputMute(idx, mute) => self.set_source_output_mute(idx, mute),
+            PACommand::SetSourceOutputVolume(idx, vol) => self.set_source_output_volume(idx, vol),
+            PACommand::MoveSourceOutput(stream, source_id) => {
+                self.move_source_output(stream, source_id)
+            }
+            PACommand::KillSourceOutput(idx) => self.kill_source_output(idx),
+
+            PACommand::GetCardInfoList => self.get_card_info_list(),
+            PACommand::GetClientInfoList => self.get_client_info_list(),
+            PACommand::GetModuleInfoList => self.get_module_info_list(),
+            PACommand::GetSampleInfoList => self.get_sample_info_list(),
+            PACommand::GetSinkInfoList => self.get_sink_info_list(),
+            PACommand::GetSinkInputInfoList => self.get_sink_input_info_list(),
+            PACommand::GetSinkInputInfoListFiltered(filter) => {
+                self.get_sink_input_info_list_filtered(filter)
+            }
+            PACommand::GetSourceInfoList => self.get_source_info_list(),
+            PACommand::GetSourceOutputInfoList => self.get_source_output_info_list(),
+
+            PACommand::MuteAll(mask, mute, except) => self.mute_all(mask, mute, except),
+
+            PACommand::PlayRaw(options, data) => self.play_raw(options, data),
+            PACommand::RecordRaw(options, data) => self.record_raw(options, data),
+            PACommand::CorkStream(id, cork) => self.cork_stream(id, cork),
+            PACommand::FlushStream(id) => self.flush_stream(id),
+            PACommand::DrainStream(id) => self.drain_stream(id),
+            PACommand::GetStreamLatency(id) => self.get_stream_latency(id),
+            PACommand::UpdateDeviceProplist(target, ref props, mode) => {
+                self.update_proplist(target, props, mode)
+            }
+
+            PACommand::GetAppVolumes => self.get_app_volumes(),
+            PACommand::SaveAppVolumes(ref volumes) => self.save_app_volumes(volumes),
+            PACommand::DeleteAppVolumes(ref apps) => self.delete_app_volumes(apps),
+
+            PACommand::CancelFade(id) => self.cancel_fade(id),
+            PACommand::CancelPending => self.cancel_pending(),
+            PACommand::WaitFade(id) => self.fades.borrow_mut().wait(id, &self.tx),
+
+            PACommand::Subscribe(mask, tx) => self.setup_subscribe(mask, None, tx),
+            PACommand::SubscribeFiltered(mask, filter, tx) => {
+                self.setup_subscribe(mask, Some(filter), tx)
+            }
+            PACommand::Unsubscribe => self.unsubscribe(),
+            PACommand::SetIdleTimeout(timeout) => self.set_idle_timeout(timeout),
+            PACommand::SetHeartbeat(interval) => self.set_heartbeat(interval),
+            PACommand::SetLookupRetry(timeout) => self.set_lookup_retry(timeout),
+
+            // if we got here, the connection state has already been verified as ready
+            PACommand::IsConnected => self.tx.send(PAResponse::Connected(true)).ignore(),
+            PACommand::Disconnect => return Some(StopReason::ExplicitDisconnect),
+            #[cfg(feature = "pa_v15")]
+            PACommand::SendMessage(recipient, message, params) => {
+                self.send_message(&recipient, &message, &params)
+            }
+        }
+
+        None
     }
 
     /*
@@ -363,14 +880,73 @@ impl PulseAudioLoop {
     where
         F: FnMut(&ServerInfo) + 'static,
     {
-        let introspector = self.ctx.borrow_mut().introspect();
+        let introspector = self.introspect();
         introspector.get_server_info(move |info| f(info));
     }
 
     fn get_server_info(&self) {
         let tx = self.tx.clone();
         self.with_server_info(move |info| {
-            tx.send(PAResponse::ServerInfo(info.into())).ignore();
+            tx.send(PAResponse::ServerInfo(Box::new(info.into())))
+                .ignore();
+        });
+    }
+
+    fn protocol_info(&self) -> ProtocolInfo {
+        let ctx = self.ctx.borrow();
+        ProtocolInfo {
+            client_version: ctx.get_protocol_version(),
+            server_version: ctx.get_server_protocol_version(),
+            server: ctx.get_server(),
+            local: ctx.is_local(),
+            client_index: ctx.get_index(),
+            tile_size: ctx.get_tile_size(None),
+        }
+    }
+
+    fn get_protocol_info(&self) {
+        self.tx
+            .send(PAResponse::ProtocolInfo(self.protocol_info()))
+            .ignore();
+    }
+
+    /// Works out what the server supports, filling in `self.capabilities` as the server responds
+    /// to each probe. Must be called once the context is ready (with the mainloop locked).
+    pub(crate) fn probe_capabilities(&self) {
+        let protocol = self.protocol_info();
+        {
+            let mut capabilities = self.capabilities.borrow_mut();
+            capabilities.message_api = protocol.supports_message_api();
+            capabilities.shm = protocol.local == Some(true);
+            capabilities.memfd = capabilities.shm
+                && protocol
+                    .server_version
+                    .map_or(false, |version| version >= MEMFD_PROTOCOL);
+        }
+
+        // each extension responds with its version, or `PA_INVALID_INDEX` if it isn't loaded
+        let ctx = self.ctx.borrow();
+        let capabilities = self.capabilities.clone();
+        ctx.stream_restore().test(move |version| {
+            capabilities.borrow_mut().stream_restore = version != PA_INVALID_INDEX;
+        });
+        let capabilities = self.capabilities.clone();
+        ctx.device_restore().test(move |version| {
+            capabilities.borrow_mut().device_restore = version != PA_INVALID_INDEX;
+        });
+        let capabilities = self.capabilities.clone();
+        ctx.device_manager().test(move |version| {
+            capabilities.borrow_mut().device_manager = version != PA_INVALID_INDEX;
+        });
+    }
+
+    fn get_capabilities(&self) {
+        // the server answers requests in order, so once this is answered every probe has been too
+        let tx = self.tx.clone();
+        let capabilities = self.capabilities.clone();
+        self.with_server_info(move |_| {
+            let capabilities = capabilities.borrow().clone();
+            tx.send(PAResponse::Capabilities(capabilities)).ignore();
         });
     }
 
@@ -392,17 +968,17 @@ impl PulseAudioLoop {
             PAIdent::Name(name) => {
                 self.ctx
                     .borrow_mut()
-                    .set_default_sink(&name, Self::success_cb(self.ctx.clone(), self.tx.clone()));
+                    .set_default_sink(&name, Self::success_cb(&self.reply));
             }
             // but if it's an index, first find the index, and then set the default
             PAIdent::Index(idx) => {
-                let tx = self.tx.clone();
+                let reply = self.reply.clone();
                 self.with_sink_info(ident, move |_, ctx, info| {
                     info.name
                         .as_ref()
                         .map(|name| {
                             ctx.borrow_mut()
-                                .set_default_sink(&name, Self::success_cb(ctx.clone(), tx.clone()));
+                                .set_default_sink(&name, Self::success_cb(&reply));
                         })
                         .ok_or_else(|| format!("Failed to find sink with id: {}", idx).into())
                 })
@@ -428,19 +1004,17 @@ impl PulseAudioLoop {
             PAIdent::Name(name) => {
                 self.ctx
                     .borrow_mut()
-                    .set_default_source(&name, Self::success_cb(self.ctx.clone(), self.tx.clone()));
+                    .set_default_source(&name, Self::success_cb(&self.reply));
             }
             // but if it's an index, first find the index, and then set the default
             PAIdent::Index(idx) => {
-                let tx = self.tx.clone();
+                let reply = self.reply.clone();
                 self.with_source_info(ident, move |_, ctx, info| {
                     info.name
                         .as_ref()
                         .map(|name| {
-                            ctx.borrow_mut().set_default_source(
-                                &name,
-                                Self::success_cb(ctx.clone(), tx.clone()),
-                            );
+                            ctx.borrow_mut()
+                                .set_default_source(&name, Self::success_cb(&reply));
                         })
                         .ok_or_else(|| format!("Failed to find source with id: {}", idx).into())
                 })
@@ -461,32 +1035,33 @@ impl PulseAudioLoop {
     }
 
     fn set_card_profile(&self, ident: PAIdent, profile: &String) {
-        let mut introspector = self.ctx.borrow_mut().introspect();
-        let tx = self.tx.clone();
-        let ctx = self.ctx.clone();
+        let mut introspector = self.introspect();
         match ident {
             PAIdent::Index(idx) => {
                 introspector.set_card_profile_by_index(
                     idx,
                     profile,
-                    Some(Self::success_cb(ctx, tx)),
+                    Some(Self::success_cb(&self.reply)),
                 );
             }
             PAIdent::Name(ref name) => {
                 introspector.set_card_profile_by_name(
                     name,
                     profile,
-                    Some(Self::success_cb(ctx, tx)),
+                    Some(Self::success_cb(&self.reply)),
                 );
             }
         }
     }
 
     fn set_port_latency_offset(&self, card: &String, port: &String, offset: i64) {
-        let mut introspector = self.ctx.borrow_mut().introspect();
-        let tx = self.tx.clone();
-        let ctx = self.ctx.clone();
-        introspector.set_port_latency_offset(card, port, offset, Some(Self::success_cb(ctx, tx)));
+        let mut introspector = self.introspect();
+        introspector.set_port_latency_offset(
+            card,
+            port,
+            offset,
+            Some(Self::success_cb(&self.reply)),
+        );
     }
 
     /*
@@ -502,8 +1077,8 @@ impl PulseAudioLoop {
     }
 
     fn kill_client(&self, idx: u32) {
-        let mut introspector = self.ctx.borrow_mut().introspect();
-        introspector.kill_client(idx, Self::success_cb(self.ctx.clone(), self.tx.clone()));
+        let mut introspector = self.introspect();
+        introspector.kill_client(idx, Self::success_cb(&self.reply));
     }
 
     /*
@@ -521,7 +1096,7 @@ impl PulseAudioLoop {
     fn load_module(&self, name: &String, argument: &String) {
         let tx = self.tx.clone();
         let ctx = self.ctx.clone();
-        let mut introspector = self.ctx.borrow_mut().introspect();
+        let mut introspector = self.introspect();
         introspector.load_module(name, argument, move |index: u32| {
             if index == PA_INVALID_INDEX {
                 Self::handle_error(&ctx, &tx);
@@ -532,53 +1107,377 @@ impl PulseAudioLoop {
     }
 
     fn unload_module(&self, idx: u32) {
-        let mut introspector = self.ctx.borrow_mut().introspect();
-        introspector.unload_module(idx, Self::success_cb(self.ctx.clone(), self.tx.clone()));
+        let mut introspector = self.introspect();
+        introspector.unload_module(idx, Self::success_cb(&self.reply));
+    }
+
+    #[cfg(feature = "pa_v15")]
+    fn send_message(&self, recipient: &str, message: &str, params: &str) {
+        let protocol = self.protocol_info();
+        if !protocol.supports_message_api() {
+            let err = OpError {
+                message: format!(
+                    "The server doesn't support messages (protocol {}, but {} is required)",
+                    protocol
+                        .server_version
+                        .map_or_else(|| "unknown".into(), |v| v.to_string()),
+                    MESSAGE_API_PROTOCOL
+                ),
+                code: Some(Code::NotSupported as i32),
+                channel_count: None,
+            };
+            self.tx.send(PAResponse::OpError(err)).ignore();
+            return;
+        }
+
+        let tx = self.tx.clone();
+        let ctx = self.ctx.clone();
+        let mut introspector = self.introspect();
+        introspector.send_message_to_object(
+            recipient,
+            message,
+            params,
+            move |success, response| {
+                if success {
+                    tx.send(PAResponse::Message(response)).ignore();
+                } else {
+                    Self::handle_error(&ctx, &tx);
+                }
+            },
+        );
     }
 
     /*
      * Subscriptions
      */
 
-    fn setup_subscribe(&self, mask: PAMask, tx: Box<dyn EventSender>) {
+    fn setup_subscribe(&self, mask: PAMask, filter: Option<EventFilter>, tx: Box<dyn EventSender>) {
         self.ctx
             .borrow_mut()
-            .subscribe(mask, Self::success_cb(self.ctx.clone(), self.tx.clone()));
+            .subscribe(mask.into(), Self::success_cb(&self.reply));
+
+        self.subscription.borrow_mut().start(mask, filter, tx);
+
+        // seed sink activity with the current state, so derived events reflect actual changes
+        if mask.contains(PAMask::SINK) {
+            let ctx = self.ctx.clone();
+            let handle = self.handle.clone();
+            let subscription = self.subscription.clone();
+            let introspector = self.introspect();
+            introspector.get_sink_info_list(move |result| match result {
+                ListResult::Item(info) => {
+                    let running = info.state.is_running();
+                    Self::update_sink_activity(&ctx, &handle, &subscription, info.index, running);
+                }
+                ListResult::End => Self::arm_idle_timer(&ctx, &handle, &subscription),
+                ListResult::Error => {}
+            });
+        }
+
+        // seed the cork state of streams, so the first change has something to compare against
+        if mask.contains(PAMask::SINK_INPUT) {
+            let subscription = self.subscription.clone();
+            let introspector = self.introspect();
+            introspector.get_sink_input_info_list(move |result| {
+                if let ListResult::Item(info) = result {
+                    let mut subscription = subscription.borrow_mut();
+                    subscription.corks.update(info.index, Some(info.corked));
+                }
+            });
+        }
+
+        // seed the streams which are recording, so only the ones which start later emit events
+        if mask.contains(PAMask::SOURCE_OUTPUT) {
+            let ctx = self.ctx.clone();
+            let subscription = self.subscription.clone();
+            let introspector = self.introspect();
+            introspector.get_source_output_info_list(move |result| {
+                if let ListResult::Item(info) = result {
+                    Self::track_recording(&ctx, &subscription, info, true);
+                }
+            });
+        }
+
+        // seed the default sink and source, so the first change has something to compare against
+        if mask.contains(PAMask::SERVER) {
+            let subscription = self.subscription.clone();
+            self.with_server_info(move |info| {
+                subscription.borrow_mut().defaults.update(&info.into());
+            });
+        }
 
         let ctx = self.ctx.clone();
+        let handle = self.handle.clone();
+        let subscription = self.subscription.clone();
         self.ctx.borrow_mut().set_subscribe_callback(Some(Box::new(
             move |facility, operation, index| {
                 // SAFETY: as per libpulse_binding's documentation, this should be safe
                 let operation = operation.unwrap();
-                let kind = facility.unwrap();
+                let facility = facility.unwrap();
 
                 // send off a subscription event
-                let kind = PAFacility(kind);
+                let kind = PAFacility::from(facility);
                 let id = PAIdent::Index(index);
-                let res = match operation {
-                    Operation::New => tx.send(PAEvent::SubscriptionNew(kind, id)),
-                    Operation::Removed => tx.send(PAEvent::SubscriptionRemoved(kind, id)),
-                    Operation::Changed => tx.send(PAEvent::SubscriptionChanged(kind, id)),
-                };
+                let res = subscription.borrow().send(match operation {
+                    Operation::New => PAEvent::SubscriptionNew(kind, id),
+                    Operation::Removed => PAEvent::SubscriptionRemoved(kind, id),
+                    Operation::Changed => PAEvent::SubscriptionChanged(kind, id),
+                });
 
                 // No one is listening to these events anymore, so remove the subscribe callback
                 if let Err(SendError(_)) = res {
                     // TODO: verify with pa docs if this is enough, or if we need to set the mask to 0
+                    subscription.borrow_mut().stop();
                     ctx.borrow_mut().set_subscribe_callback(None);
+                    return;
+                }
+
+                // server events carry no useful index, so also send along the server's info
+                if facility == Facility::Server {
+                    let subscription = subscription.clone();
+                    let introspector = ctx.borrow_mut().introspect();
+                    introspector.get_server_info(move |info| {
+                        let info = Box::new(PAServerInfo::from(info));
+                        let mut subscription = subscription.borrow_mut();
+                        let events = subscription.defaults.update(&info);
+                        subscription.send(PAEvent::ServerChanged(info)).ignore();
+                        for ev in events {
+                            subscription.send(ev).ignore();
+                        }
+                    });
+                }
+
+                // derive sink activity events
+                if facility == Facility::Sink {
+                    match operation {
+                        Operation::Removed => {
+                            Self::update_sink_activity(&ctx, &handle, &subscription, index, None)
+                        }
+                        Operation::New | Operation::Changed => {
+                            let ctx = ctx.clone();
+                            let handle = handle.clone();
+                            let subscription = subscription.clone();
+                            let introspector = ctx.borrow_mut().introspect();
+                            introspector.get_sink_info_by_index(index, move |result| {
+                                if let ListResult::Item(info) = result {
+                                    let running = info.state.is_running();
+                                    Self::update_sink_activity(
+                                        &ctx,
+                                        &handle,
+                                        &subscription,
+                                        index,
+                                        running,
+                                    );
+                                }
+                            });
+                        }
+                    }
+                }
+
+                // derive stream cork events
+                if facility == Facility::SinkInput {
+                    match operation {
+                        Operation::Removed => {
+                            subscription.borrow_mut().corks.update(index, None);
+                        }
+                        Operation::New | Operation::Changed => {
+                            let subscription = subscription.clone();
+                            let introspector = ctx.borrow_mut().introspect();
+                            introspector.get_sink_input_info(index, move |result| {
+                                if let ListResult::Item(info) = result {
+                                    let mut subscription = subscription.borrow_mut();
+                                    if let Some(ev) =
+                                        subscription.corks.update(index, Some(info.corked))
+                                    {
+                                        subscription.send(ev).ignore();
+                                    }
+                                }
+                            });
+                        }
+                    }
+                }
+
+                // derive recording events
+                if facility == Facility::SourceOutput {
+                    match operation {
+                        Operation::Removed => {
+                            Self::update_recording(&subscription, index, None, false);
+                        }
+                        Operation::New | Operation::Changed => {
+                            let ctx = ctx.clone();
+                            let subscription = subscription.clone();
+                            let introspector = ctx.borrow_mut().introspect();
+                            introspector.get_source_output_info(index, move |result| {
+                                if let ListResult::Item(info) = result {
+                                    Self::track_recording(&ctx, &subscription, info, false);
+                                }
+                            });
+                        }
+                    }
                 }
             },
         )));
     }
 
-    /*
-     * Sinks
-     */
+    /// Works out whether a source output is recording from a microphone (or another source which
+    /// isn't a monitor), and updates the subscription's recordings. When `seeding`, no event is
+    /// sent.
+    fn track_recording(
+        ctx: &Ctx,
+        subscription: &Rc<RefCell<Subscription>>,
+        info: &SourceOutputInfo,
+        seeding: bool,
+    ) {
+        let index = info.index;
+        // peak meters (such as pavucontrol's) open streams which only read levels
+        if info.corked || info.resample_method.as_deref() == Some("peaks") {
+            Self::update_recording(subscription, index, None, seeding);
+            return;
+        }
 
-    fn get_sink_info(&self, ident: PAIdent) {
-        let tx = self.tx.clone();
-        self.with_sink_info(ident, move |_, _, info| {
-            tx.send(PAResponse::SinkInfo(info.into())).ignore();
-            Ok(())
+        let app = info.proplist.get_str(properties::APPLICATION_NAME);
+        let source = info.source;
+        let subscription = subscription.clone();
+        let introspector = ctx.borrow_mut().introspect();
+        introspector.get_source_info_by_index(source, move |result| {
+            if let ListResult::Item(info) = result {
+                let recording = match info.monitor_of_sink {
+                    Some(_) => None,
+                    None => Some((app.clone(), source)),
+                };
+                Self::update_recording(&subscription, index, recording, seeding);
+            }
+        });
+    }
+
+    fn update_recording(
+        subscription: &Rc<RefCell<Subscription>>,
+        index: u32,
+        recording: Option<(Option<String>, u32)>,
+        seeding: bool,
+    ) {
+        let mut subscription = subscription.borrow_mut();
+        if let Some(ev) = subscription.recordings.update(index, recording) {
+            if !seeding {
+                subscription.send(ev).ignore();
+            }
+        }
+    }
+
+    fn unsubscribe(&self) {
+        self.subscription.borrow_mut().stop();
+        let mut ctx = self.ctx.borrow_mut();
+        ctx.set_subscribe_callback(None);
+        ctx.subscribe(PAMask::empty().into(), Self::success_cb(&self.reply));
+    }
+
+    fn set_idle_timeout(&self, timeout: Option<Duration>) {
+        {
+            let mut subscription = self.subscription.borrow_mut();
+            subscription.sinks.idle_timeout = timeout;
+            subscription.sinks.timer = None;
+        }
+
+        Self::arm_idle_timer(&self.ctx, &self.handle, &self.subscription);
+        self.tx.send(PAResponse::OpComplete).ignore();
+    }
+
+    fn set_heartbeat(&self, interval: Option<Duration>) {
+        self.heartbeat.borrow_mut().reset(interval);
+        if let Some(interval) = interval {
+            let ctx = self.ctx.clone();
+            // hold weak references, since the timer itself is stored in the heartbeat
+            let heartbeat = Rc::downgrade(&self.heartbeat);
+            let subscription = Rc::downgrade(&self.subscription);
+            let timer = self
+                .handle
+                .timer(&self.ctx.borrow(), interval, move |mut timer| {
+                    let heartbeat = match heartbeat.upgrade() {
+                        Some(heartbeat) => heartbeat,
+                        None => return,
+                    };
+
+                    let tick = heartbeat.borrow_mut().tick();
+                    match tick {
+                        Tick::Ping => {
+                            let heartbeat = Rc::downgrade(&heartbeat);
+                            let subscription = subscription.clone();
+                            ctx.borrow().introspect().get_server_info(move |_| {
+                                let ev = heartbeat.upgrade().and_then(|h| h.borrow_mut().pong());
+                                if let (Some(ev), Some(subscription)) = (ev, subscription.upgrade())
+                                {
+                                    subscription.borrow().send(ev).ignore();
+                                }
+                            });
+                        }
+                        Tick::Unresponsive(ev) => {
+                            if let Some(subscription) = subscription.upgrade() {
+                                subscription.borrow().send(ev).ignore();
+                            }
+                        }
+                        Tick::Wait => {}
+                    }
+
+                    timer.restart_rt(MonotonicTs::now() + interval);
+                });
+
+            self.heartbeat.borrow_mut().timer = timer;
+        }
+
+        self.tx.send(PAResponse::OpComplete).ignore();
+    }
+
+    /// Updates the activity of a sink (`running` is `None` if it was removed), emitting any derived
+    /// events.
+    fn update_sink_activity(
+        ctx: &Ctx,
+        handle: &LoopHandle,
+        subscription: &Rc<RefCell<Subscription>>,
+        index: u32,
+        running: impl Into<Option<bool>>,
+    ) {
+        {
+            let mut subscription = subscription.borrow_mut();
+            if let Some(ev) = subscription.sinks.update(index, running.into()) {
+                subscription.send(ev).ignore();
+            }
+        }
+
+        Self::arm_idle_timer(ctx, handle, subscription);
+    }
+
+    /// If all sinks are idle and an idle timeout is set, starts a timer to emit `AllSinksIdle`.
+    fn arm_idle_timer(ctx: &Ctx, handle: &LoopHandle, subscription: &Rc<RefCell<Subscription>>) {
+        let remaining = match subscription.borrow().sinks.idle_remaining() {
+            Some(remaining) => remaining,
+            None => return,
+        };
+
+        // hold a weak reference, since the timer itself is stored in the subscription
+        let weak = Rc::downgrade(subscription);
+        let timer = handle.timer(&ctx.borrow(), remaining, move |_| {
+            if let Some(subscription) = weak.upgrade() {
+                let subscription = subscription.borrow();
+                if let Some(idle_for) = subscription.sinks.idle_for() {
+                    subscription
+                        .send(PAEvent::AllSinksIdle { r#for: idle_for })
+                        .ignore();
+                }
+            }
+        });
+
+        subscription.borrow_mut().sinks.timer = timer;
+    }
+
+    /*
+     * Sinks
+     */
+
+    fn get_sink_info(&self, ident: PAIdent) {
+        let tx = self.tx.clone();
+        self.with_sink_info(ident, move |_, _, info| {
+            tx.send(PAResponse::SinkInfo(info.into())).ignore();
+            Ok(())
         });
     }
 
@@ -591,15 +1490,13 @@ impl PulseAudioLoop {
     }
 
     fn set_sink_mute(&self, ident: PAIdent, mute: bool) {
-        let mut introspector = self.ctx.borrow_mut().introspect();
-        let tx = self.tx.clone();
-        let ctx = self.ctx.clone();
+        let mut introspector = self.introspect();
         match ident {
             PAIdent::Index(idx) => {
-                introspector.set_sink_mute_by_index(idx, mute, Some(Self::success_cb(ctx, tx)))
+                introspector.set_sink_mute_by_index(idx, mute, Some(Self::success_cb(&self.reply)))
             }
             PAIdent::Name(ref name) => {
-                introspector.set_sink_mute_by_name(name, mute, Some(Self::success_cb(ctx, tx)))
+                introspector.set_sink_mute_by_name(name, mute, Some(Self::success_cb(&self.reply)))
             }
         };
     }
@@ -619,19 +1516,63 @@ impl PulseAudioLoop {
         });
     }
 
-    fn set_sink_volume(&self, ident: PAIdent, volume_spec: VolumeSpec) {
-        let tx = self.tx.clone();
+    fn set_sink_mute_if(&self, ident: PAIdent, expected: bool, mute: bool) {
+        let reply = self.reply.clone();
         self.with_sink_info(ident, move |ident, ctx, info| {
+            if info.mute != expected {
+                reply
+                    .tx
+                    .send(PAResponse::OpConflict(format!(
+                        "The mute of sink {} has changed",
+                        ident
+                    )))
+                    .ignore();
+                return Ok(());
+            }
+
+            let cb = Self::success_cb(&reply);
             let mut introspector = ctx.borrow_mut().introspect();
-            let cv = updated_channel_volumes(info.volume, &volume_spec);
-            let tx = tx.clone();
-            let ctx = ctx.clone();
+            introspector.set_sink_mute_by_index(info.index, mute, Some(cb));
+            Ok(())
+        });
+    }
+
+    /// Sets the volume of a sink; if `expected` is given the volume is only set if it still matches
+    fn set_sink_volume(
+        &self,
+        ident: PAIdent,
+        volume_spec: VolumeSpec,
+        expected: Option<VolumeReadings>,
+    ) {
+        let reply = self.reply.clone();
+        let limit = self.volume_limit;
+        self.with_sink_info(ident, move |ident, ctx, info| {
+            if let Some(ref expected) = expected {
+                if !expected.matches(&info.volume) {
+                    reply
+                        .tx
+                        .send(PAResponse::OpConflict(format!(
+                            "The volume of sink {} has changed",
+                            ident
+                        )))
+                        .ignore();
+                    return Ok(());
+                }
+            }
+
+            let mut introspector = ctx.borrow_mut().introspect();
+            let cv = limit_channel_volumes(
+                updated_channel_volumes(info.volume, &info.channel_map, &volume_spec).map_err(
+                    |e| e.with_device(format!("sink {}", ident), info.channel_map.print()),
+                )?,
+                limit,
+            );
             match ident {
                 PAIdent::Index(idx) => {
-                    introspector.set_sink_volume_by_index(idx, &cv, Some(Self::success_cb(ctx, tx)))
+                    introspector.set_sink_volume_by_index(idx, &cv, Some(Self::success_cb(&reply)))
                 }
                 PAIdent::Name(ref name) => {
-                    introspector.set_sink_volume_by_name(name, &cv, Some(Self::success_cb(ctx, tx)))
+                    introspector.set_sink_volume_by_name(name, &cv, Some(Self::success_cb(&reply)))
                 }
             };
 
@@ -640,33 +1581,193 @@ impl PulseAudioLoop {
     }
 
     fn set_sink_port(&self, ident: PAIdent, port: &String) {
-        let mut introspector = self.ctx.borrow_mut().introspect();
-        let tx = self.tx.clone();
-        let ctx = self.ctx.clone();
+        let mut introspector = self.introspect();
         match ident {
             PAIdent::Index(idx) => {
-                introspector.set_sink_port_by_index(idx, port, Some(Self::success_cb(ctx, tx)));
+                introspector.set_sink_port_by_index(idx, port, Some(Self::success_cb(&self.reply)));
             }
             PAIdent::Name(ref name) => {
-                introspector.set_sink_port_by_name(name, port, Some(Self::success_cb(ctx, tx)));
+                introspector.set_sink_port_by_name(name, port, Some(Self::success_cb(&self.reply)));
             }
         }
     }
 
     fn suspend_sink(&self, ident: PAIdent, suspend: bool) {
-        let mut introspector = self.ctx.borrow_mut().introspect();
-        let tx = self.tx.clone();
-        let ctx = self.ctx.clone();
+        let mut introspector = self.introspect();
         match ident {
             PAIdent::Index(idx) => {
-                introspector.suspend_sink_by_index(idx, suspend, Some(Self::success_cb(ctx, tx)));
+                introspector.suspend_sink_by_index(
+                    idx,
+                    suspend,
+                    Some(Self::success_cb(&self.reply)),
+                );
             }
             PAIdent::Name(ref name) => {
-                introspector.suspend_sink_by_name(name, suspend, Some(Self::success_cb(ctx, tx)));
+                introspector.suspend_sink_by_name(
+                    name,
+                    suspend,
+                    Some(Self::success_cb(&self.reply)),
+                );
             }
         }
     }
 
+    fn rename_sink(&self, ident: PAIdent, description: &str) {
+        let reply = self.reply.clone();
+        let description = description.to_string();
+        self.with_sink_info(ident, move |_, _, info| {
+            let name = info.name.as_deref().ok_or("The sink has no name")?;
+            let device = format!("sink:{}", name);
+            Self::set_device_description(&reply, device, description.clone());
+            Ok(())
+        });
+    }
+
+    fn fade_sink_volume(
+        &self,
+        ident: PAIdent,
+        volume_spec: VolumeSpec,
+        duration: Duration,
+        curve: FadeCurve,
+    ) {
+        let tx = self.tx.clone();
+        let handle = self.handle.clone();
+        let fades = self.fades.clone();
+        let limit = self.volume_limit;
+        self.with_sink_info(ident, move |ident, ctx, info| {
+            let to = limit_channel_volumes(
+                updated_channel_volumes(info.volume, &info.channel_map, &volume_spec).map_err(
+                    |e| e.with_device(format!("sink {}", ident), info.channel_map.print()),
+                )?,
+                limit,
+            );
+            let fade = Fade::new(info.index, info.volume, to, duration, curve);
+            let id = fades.borrow_mut().insert(fade, &tx);
+
+            // hold a weak reference, since the timer itself is stored in the fade
+            let weak = Rc::downgrade(&fades);
+            let timer = {
+                let timer_ctx = ctx.clone();
+                let tx = tx.clone();
+                handle.timer(&ctx.borrow(), Duration::ZERO, move |mut timer| {
+                    if let Some(fades) = weak.upgrade() {
+                        Self::step_fade(&timer_ctx, &fades, &tx, id, &mut timer);
+                    }
+                })
+            };
+
+            match timer {
+                Some(timer) => {
+                    if let Some(fade) = fades.borrow_mut().get_mut(id) {
+                        fade.timer = Some(timer);
+                    }
+                    tx.send(PAResponse::FadeStarted(id))?;
+                }
+                None => {
+                    let err = "Failed to create a timer for the fade".to_string();
+                    fades.borrow_mut().finish(id, Err(err.clone()), &tx);
+                    return Err(err.into());
+                }
+            }
+
+            Ok(())
+        });
+    }
+
+    /// Called by a fade's timer, sets the next volume and re-arms the timer if the fade isn't done.
+    fn step_fade(
+        ctx: &Ctx,
+        fades: &Rc<RefCell<Fades>>,
+        tx: &Sender<PAResponse>,
+        id: u32,
+        timer: &mut TimerRef,
+    ) {
+        let (sink, volumes, done) = match fades.borrow_mut().running(id, tx) {
+            Some(fade) => {
+                let (volumes, done) = fade.step();
+                (fade.sink, volumes, done)
+            }
+            // the fade was cancelled or superseded
+            None => return,
+        };
+
+        let callback = {
+            let ctx = ctx.clone();
+            let tx = tx.clone();
+            let weak = Rc::downgrade(fades);
+            move |success: bool| {
+                if let Some(fades) = weak.upgrade() {
+                    if !success {
+                        let err = ctx.borrow_mut().errno().to_string();
+                        let err = format!(
+                            "Operation failed: {}",
+                            err.unwrap_or("An unknown error occurred".into())
+                        );
+                        fades.borrow_mut().finish(id, Err(err), &tx);
+                    } else if done {
+                        fades.borrow_mut().finish(id, Ok(()), &tx);
+                    }
+                }
+            }
+        };
+
+        let mut introspector = ctx.borrow_mut().introspect();
+        introspector.set_sink_volume_by_index(sink, &volumes, Some(Box::new(callback)));
+
+        if !done {
+            timer.restart_rt(MonotonicTs::now() + FADE_STEP);
+        }
+    }
+
+    fn mute_sink_for(&self, ident: PAIdent, duration: Duration) {
+        let reply = self.reply.clone();
+        let handle = self.handle.clone();
+        let restores = self.restores.clone();
+        self.with_sink_info(ident, move |_, ctx, info| {
+            let target = Target::Sink(info.index);
+            let (current, temporary) = (Saved::Mute(info.mute), Saved::Mute(true));
+            Self::change_temporarily(
+                &ctx, &handle, &restores, &reply, target, current, temporary, duration,
+            );
+            Ok(())
+        });
+    }
+
+    fn duck_sink(&self, ident: PAIdent, pct: f64, duration: Duration) {
+        let reply = self.reply.clone();
+        let handle = self.handle.clone();
+        let restores = self.restores.clone();
+        self.with_sink_info(ident, move |_, ctx, info| {
+            let target = Target::Sink(info.index);
+            // if already ducked, duck relative to the original volume
+            let original = restores
+                .borrow()
+                .saved_volume(target)
+                .unwrap_or(info.volume);
+            let (current, temporary) = (
+                Saved::Volume(info.volume),
+                Saved::Volume(scale_volumes(original, pct)),
+            );
+            Self::change_temporarily(
+                &ctx, &handle, &restores, &reply, target, current, temporary, duration,
+            );
+            Ok(())
+        });
+    }
+
+    fn cancel_fade(&self, id: u32) {
+        match self.fades.borrow_mut().cancel(id, &self.tx) {
+            Ok(()) => self.tx.send(PAResponse::OpComplete).ignore(),
+            Err(e) => self.tx.send(PAResponse::OpError(e.into())).ignore(),
+        }
+    }
+
+    /// Cancels every fade which was started before the `CancelToken` was last cancelled
+    fn cancel_pending(&self) {
+        self.fades.borrow_mut().cancel_stale(&self.tx);
+        self.tx.send(PAResponse::OpComplete).ignore();
+    }
+
     /*
      * Sources
      */
@@ -688,16 +1789,18 @@ impl PulseAudioLoop {
     }
 
     fn set_source_mute(&self, ident: PAIdent, mute: bool) {
-        let mut introspector = self.ctx.borrow_mut().introspect();
-        let tx = self.tx.clone();
-        let ctx = self.ctx.clone();
+        let mut introspector = self.introspect();
         match ident {
-            PAIdent::Index(idx) => {
-                introspector.set_source_mute_by_index(idx, mute, Some(Self::success_cb(ctx, tx)))
-            }
-            PAIdent::Name(ref name) => {
-                introspector.set_source_mute_by_name(name, mute, Some(Self::success_cb(ctx, tx)))
-            }
+            PAIdent::Index(idx) => introspector.set_source_mute_by_index(
+                idx,
+                mute,
+                Some(Self::success_cb(&self.reply)),
+            ),
+            PAIdent::Name(ref name) => introspector.set_source_mute_by_name(
+                name,
+                mute,
+                Some(Self::success_cb(&self.reply)),
+            ),
         };
     }
 
@@ -717,23 +1820,67 @@ impl PulseAudioLoop {
         });
     }
 
-    fn set_source_volume(&self, ident: PAIdent, volume_spec: VolumeSpec) {
-        let tx = self.tx.clone();
+    fn set_source_mute_if(&self, ident: PAIdent, expected: bool, mute: bool) {
+        let reply = self.reply.clone();
         self.with_source_info(ident, move |ident, ctx, info| {
+            if info.mute != expected {
+                reply
+                    .tx
+                    .send(PAResponse::OpConflict(format!(
+                        "The mute of source {} has changed",
+                        ident
+                    )))
+                    .ignore();
+                return Ok(());
+            }
+
+            let cb = Self::success_cb(&reply);
             let mut introspector = ctx.borrow_mut().introspect();
-            let cv = updated_channel_volumes(info.volume, &volume_spec);
-            let tx = tx.clone();
-            let ctx = ctx.clone();
+            introspector.set_source_mute_by_index(info.index, mute, Some(cb));
+            Ok(())
+        });
+    }
+
+    /// Sets the volume of a source; if `expected` is given the volume is only set if it still matches
+    fn set_source_volume(
+        &self,
+        ident: PAIdent,
+        volume_spec: VolumeSpec,
+        expected: Option<VolumeReadings>,
+    ) {
+        let reply = self.reply.clone();
+        let limit = self.volume_limit;
+        self.with_source_info(ident, move |ident, ctx, info| {
+            if let Some(ref expected) = expected {
+                if !expected.matches(&info.volume) {
+                    reply
+                        .tx
+                        .send(PAResponse::OpConflict(format!(
+                            "The volume of source {} has changed",
+                            ident
+                        )))
+                        .ignore();
+                    return Ok(());
+                }
+            }
+
+            let mut introspector = ctx.borrow_mut().introspect();
+            let cv = limit_channel_volumes(
+                updated_channel_volumes(info.volume, &info.channel_map, &volume_spec).map_err(
+                    |e| e.with_device(format!("source {}", ident), info.channel_map.print()),
+                )?,
+                limit,
+            );
             match ident {
                 PAIdent::Index(idx) => introspector.set_source_volume_by_index(
                     idx,
                     &cv,
-                    Some(Self::success_cb(ctx, tx)),
+                    Some(Self::success_cb(&reply)),
                 ),
                 PAIdent::Name(ref name) => introspector.set_source_volume_by_name(
                     name,
                     &cv,
-                    Some(Self::success_cb(ctx, tx)),
+                    Some(Self::success_cb(&reply)),
                 ),
             };
 
@@ -742,37 +1889,79 @@ impl PulseAudioLoop {
     }
 
     fn set_source_port(&self, ident: PAIdent, port: &String) {
-        let mut introspector = self.ctx.borrow_mut().introspect();
-        let tx = self.tx.clone();
-        let ctx = self.ctx.clone();
+        let mut introspector = self.introspect();
         match ident {
             PAIdent::Index(idx) => {
-                introspector.set_source_port_by_index(idx, port, Some(Self::success_cb(ctx, tx)));
+                introspector.set_source_port_by_index(
+                    idx,
+                    port,
+                    Some(Self::success_cb(&self.reply)),
+                );
             }
             PAIdent::Name(ref name) => {
-                introspector.set_source_port_by_name(name, port, Some(Self::success_cb(ctx, tx)));
+                introspector.set_source_port_by_name(
+                    name,
+                    port,
+                    Some(Self::success_cb(&self.reply)),
+                );
             }
         }
     }
 
     fn suspend_source(&self, ident: PAIdent, suspend: bool) {
-        let mut introspector = self.ctx.borrow_mut().introspect();
-        let tx = self.tx.clone();
-        let ctx = self.ctx.clone();
+        let mut introspector = self.introspect();
         match ident {
             PAIdent::Index(idx) => {
-                introspector.suspend_source_by_index(idx, suspend, Some(Self::success_cb(ctx, tx)));
+                introspector.suspend_source_by_index(
+                    idx,
+                    suspend,
+                    Some(Self::success_cb(&self.reply)),
+                );
             }
             PAIdent::Name(ref name) => {
-                introspector.suspend_source_by_name(name, suspend, Some(Self::success_cb(ctx, tx)));
+                introspector.suspend_source_by_name(
+                    name,
+                    suspend,
+                    Some(Self::success_cb(&self.reply)),
+                );
             }
         }
     }
 
+    fn rename_source(&self, ident: PAIdent, description: &str) {
+        let reply = self.reply.clone();
+        let description = description.to_string();
+        self.with_source_info(ident, move |_, _, info| {
+            let name = info.name.as_deref().ok_or("The source has no name")?;
+            let device = format!("source:{}", name);
+            Self::set_device_description(&reply, device, description.clone());
+            Ok(())
+        });
+    }
+
     /*
      * Sink Inputs
      */
 
+    fn get_sink_input_info_list_filtered(&self, filter: StreamFilter) {
+        let introspector = self.introspect();
+        let tx = self.tx.clone();
+        let ctx = self.ctx.clone();
+        let mut v: Vec<PASinkInputInfo> = vec![];
+        introspector.get_sink_input_info_list(move |result| match result {
+            ListResult::Item(info) => {
+                let app = info.proplist.get_str(properties::APPLICATION_NAME);
+                if filter.matches(info.corked, app.as_deref()) {
+                    v.push(PASinkInputInfo::from(info));
+                }
+            }
+            ListResult::End => tx
+                .send(PAResponse::SinkInputInfoList(std::mem::take(&mut v)))
+                .ignore(),
+            ListResult::Error => Self::handle_error(&ctx, &tx),
+        });
+    }
+
     fn get_sink_input_info(&self, idx: u32) {
         let tx = self.tx.clone();
         self.with_sink_input_info(idx, move |_, _, info| {
@@ -806,44 +1995,132 @@ impl PulseAudioLoop {
     }
 
     fn set_sink_input_mute(&self, idx: u32, mute: bool) {
-        let mut introspector = self.ctx.borrow_mut().introspect();
-        let tx = self.tx.clone();
-        let ctx = self.ctx.clone();
-        introspector.set_sink_input_mute(idx, mute, Some(Self::success_cb(ctx, tx)));
+        let mut introspector = self.introspect();
+        introspector.set_sink_input_mute(idx, mute, Some(Self::success_cb(&self.reply)));
     }
 
     fn set_sink_input_volume(&self, idx: u32, volume_spec: VolumeSpec) {
-        let tx = self.tx.clone();
-        self.with_sink_input_info(idx, move |_, ctx, info| {
+        if let VolumeSpec::RelativeToDevice(pct) = volume_spec {
+            return self.set_sink_input_volume_relative(idx, pct);
+        }
+
+        let reply = self.reply.clone();
+        let limit = self.volume_limit;
+        self.with_sink_input_info(idx, move |ident, ctx, info| {
             let mut introspector = ctx.borrow_mut().introspect();
-            let cv = updated_channel_volumes(info.volume, &volume_spec);
-            let tx = tx.clone();
-            let ctx = ctx.clone();
-            introspector.set_sink_input_volume(idx, &cv, Some(Self::success_cb(ctx, tx)));
+            let cv = limit_channel_volumes(
+                updated_channel_volumes(info.volume, &info.channel_map, &volume_spec).map_err(
+                    |e| e.with_device(format!("sink input {}", ident), info.channel_map.print()),
+                )?,
+                limit,
+            );
+            introspector.set_sink_input_volume(idx, &cv, Some(Self::success_cb(&reply)));
 
             Ok(())
         });
     }
 
-    fn move_sink_input(&self, idx: u32, sink: PAIdent) {
-        let mut introspector = self.ctx.borrow_mut().introspect();
-        let tx = self.tx.clone();
-        let ctx = self.ctx.clone();
-        match sink {
-            PAIdent::Index(sink_idx) => introspector.move_sink_input_by_index(
-                idx,
-                sink_idx,
-                Some(Self::success_cb(ctx, tx)),
-            ),
-            PAIdent::Name(ref name) => {
-                introspector.move_sink_input_by_name(idx, name, Some(Self::success_cb(ctx, tx)))
-            }
+    fn set_sink_input_volume_relative(&self, idx: u32, pct: f64) {
+        let reply = self.reply.clone();
+        let limit = self.volume_limit;
+        self.with_sink_input_info(idx, move |_, ctx, info| {
+            let stream_map = info.channel_map;
+            let introspector = ctx.borrow_mut().introspect();
+            let reply = reply.clone();
+            introspector.get_sink_info_by_index(info.sink, move |result| match result {
+                ListResult::Item(sink) => {
+                    let cv = relative_channel_volumes(
+                        sink.volume,
+                        &sink.channel_map,
+                        sink.flags.contains(SinkFlagSet::FLAT_VOLUME),
+                        &stream_map,
+                        pct,
+                    );
+                    let cv = limit_channel_volumes(cv, limit);
+                    let cb = Self::success_cb(&reply);
+                    ctx.borrow_mut()
+                        .introspect()
+                        .set_sink_input_volume(idx, &cv, Some(cb));
+                }
+                ListResult::End => {}
+                ListResult::Error => reply.error(),
+            });
+
+            Ok(())
+        });
+    }
+
+    fn move_sink_input(&self, stream: PAStreamIdent, sink: PAIdent) {
+        let move_one = move |ctx: &Ctx, idx: u32, cb: Box<dyn FnMut(bool)>| {
+            let mut introspector = ctx.borrow_mut().introspect();
+            match sink {
+                PAIdent::Index(sink_idx) => {
+                    introspector.move_sink_input_by_index(idx, sink_idx, Some(cb))
+                }
+                PAIdent::Name(ref name) => {
+                    introspector.move_sink_input_by_name(idx, name, Some(cb))
+                }
+            };
         };
+
+        if let PAStreamIdent::Index(idx) = stream {
+            return move_one(&self.ctx, idx, Self::success_cb(&self.reply));
+        }
+
+        // resolve the streams here rather than in the caller, so they can't change in between
+        let mut indices = vec![];
+        let reply = self.reply.clone();
+        let introspector = self.introspect();
+        introspector.get_sink_input_info_list(move |result| match result {
+            ListResult::Item(info) => {
+                if stream.matches_app(&PAProplist::from(&info.proplist)) {
+                    indices.push(info.index);
+                }
+            }
+            ListResult::End => Self::for_each_stream(&reply, &stream, &indices, &move_one),
+            ListResult::Error => reply.error(),
+        });
     }
 
     fn kill_sink_input(&self, idx: u32) {
-        let mut introspector = self.ctx.borrow_mut().introspect();
-        introspector.kill_sink_input(idx, Self::success_cb(self.ctx.clone(), self.tx.clone()));
+        let mut introspector = self.introspect();
+        introspector.kill_sink_input(idx, Self::success_cb(&self.reply));
+    }
+
+    fn mute_sink_input_for(&self, idx: u32, duration: Duration) {
+        let reply = self.reply.clone();
+        let handle = self.handle.clone();
+        let restores = self.restores.clone();
+        self.with_sink_input_info(idx, move |_, ctx, info| {
+            let target = Target::SinkInput(info.index);
+            let (current, temporary) = (Saved::Mute(info.mute), Saved::Mute(true));
+            Self::change_temporarily(
+                &ctx, &handle, &restores, &reply, target, current, temporary, duration,
+            );
+            Ok(())
+        });
+    }
+
+    fn duck_sink_input(&self, idx: u32, pct: f64, duration: Duration) {
+        let reply = self.reply.clone();
+        let handle = self.handle.clone();
+        let restores = self.restores.clone();
+        self.with_sink_input_info(idx, move |_, ctx, info| {
+            let target = Target::SinkInput(info.index);
+            // if already ducked, duck relative to the original volume
+            let original = restores
+                .borrow()
+                .saved_volume(target)
+                .unwrap_or(info.volume);
+            let (current, temporary) = (
+                Saved::Volume(info.volume),
+                Saved::Volume(scale_volumes(original, pct)),
+            );
+            Self::change_temporarily(
+                &ctx, &handle, &restores, &reply, target, current, temporary, duration,
+            );
+            Ok(())
+        });
     }
 
     /*
@@ -883,76 +2160,1014 @@ impl PulseAudioLoop {
     }
 
     fn set_source_output_mute(&self, idx: u32, mute: bool) {
-        let mut introspector = self.ctx.borrow_mut().introspect();
-        let tx = self.tx.clone();
-        let ctx = self.ctx.clone();
-        introspector.set_source_output_mute(idx, mute, Some(Self::success_cb(ctx, tx)));
+        let mut introspector = self.introspect();
+        introspector.set_source_output_mute(idx, mute, Some(Self::success_cb(&self.reply)));
     }
 
     fn set_source_output_volume(&self, idx: u32, volume_spec: VolumeSpec) {
-        let tx = self.tx.clone();
-        self.with_source_output_info(idx, move |_, ctx, info| {
+        if let VolumeSpec::RelativeToDevice(pct) = volume_spec {
+            return self.set_source_output_volume_relative(idx, pct);
+        }
+
+        let reply = self.reply.clone();
+        let limit = self.volume_limit;
+        self.with_source_output_info(idx, move |ident, ctx, info| {
             let mut introspector = ctx.borrow_mut().introspect();
-            let cv = updated_channel_volumes(info.volume, &volume_spec);
-            let tx = tx.clone();
-            let ctx = ctx.clone();
-            introspector.set_source_output_volume(idx, &cv, Some(Self::success_cb(ctx, tx)));
+            let cv = limit_channel_volumes(
+                updated_channel_volumes(info.volume, &info.channel_map, &volume_spec).map_err(
+                    |e| e.with_device(format!("source output {}", ident), info.channel_map.print()),
+                )?,
+                limit,
+            );
+            introspector.set_source_output_volume(idx, &cv, Some(Self::success_cb(&reply)));
 
             Ok(())
         });
     }
 
-    fn move_source_output(&self, idx: u32, source: PAIdent) {
-        let mut introspector = self.ctx.borrow_mut().introspect();
+    fn set_source_output_volume_relative(&self, idx: u32, pct: f64) {
+        let reply = self.reply.clone();
+        let limit = self.volume_limit;
+        self.with_source_output_info(idx, move |_, ctx, info| {
+            let stream_map = info.channel_map;
+            let introspector = ctx.borrow_mut().introspect();
+            let reply = reply.clone();
+            introspector.get_source_info_by_index(info.source, move |result| match result {
+                ListResult::Item(source) => {
+                    let cv = relative_channel_volumes(
+                        source.volume,
+                        &source.channel_map,
+                        source.flags.contains(SourceFlagSet::FLAT_VOLUME),
+                        &stream_map,
+                        pct,
+                    );
+                    let cv = limit_channel_volumes(cv, limit);
+                    let cb = Self::success_cb(&reply);
+                    ctx.borrow_mut()
+                        .introspect()
+                        .set_source_output_volume(idx, &cv, Some(cb));
+                }
+                ListResult::End => {}
+                ListResult::Error => reply.error(),
+            });
+
+            Ok(())
+        });
+    }
+
+    fn move_source_output(&self, stream: PAStreamIdent, source: PAIdent) {
+        let move_one = move |ctx: &Ctx, idx: u32, cb: Box<dyn FnMut(bool)>| {
+            let mut introspector = ctx.borrow_mut().introspect();
+            match source {
+                PAIdent::Index(source_idx) => {
+                    introspector.move_source_output_by_index(idx, source_idx, Some(cb))
+                }
+                PAIdent::Name(ref name) => {
+                    introspector.move_source_output_by_name(idx, name, Some(cb))
+                }
+            };
+        };
+
+        if let PAStreamIdent::Index(idx) = stream {
+            return move_one(&self.ctx, idx, Self::success_cb(&self.reply));
+        }
+
+        // resolve the streams here rather than in the caller, so they can't change in between
+        let mut indices = vec![];
+        let reply = self.reply.clone();
+        let introspector = self.introspect();
+        introspector.get_source_output_info_list(move |result| match result {
+            ListResult::Item(info) => {
+                if stream.matches_app(&PAProplist::from(&info.proplist)) {
+                    indices.push(info.index);
+                }
+            }
+            ListResult::End => Self::for_each_stream(&reply, &stream, &indices, &move_one),
+            ListResult::Error => reply.error(),
+        });
+    }
+
+    fn kill_source_output(&self, idx: u32) {
+        let mut introspector = self.introspect();
+        introspector.kill_source_output(idx, Self::success_cb(&self.reply));
+    }
+
+    /*
+     * Batches
+     */
+
+    fn mute_all(&self, mask: PAMask, mute: bool, except: Option<PAIdent>) {
+        let kinds = [
+            PAFacility::Sink,
+            PAFacility::Source,
+            PAFacility::SinkInput,
+            PAFacility::SourceOutput,
+        ]
+        .into_iter()
+        .filter(|kind| mask.contains(kind.mask()))
+        .collect::<Vec<_>>();
+
+        let mute_one = move |ctx: &Ctx,
+                             (kind, idx): (PAFacility, u32),
+                             cb: Box<dyn FnMut(bool)>| {
+            let mut introspector = ctx.borrow_mut().introspect();
+            match kind {
+                PAFacility::Sink => introspector.set_sink_mute_by_index(idx, mute, Some(cb)),
+                PAFacility::Source => introspector.set_source_mute_by_index(idx, mute, Some(cb)),
+                PAFacility::SinkInput => introspector.set_sink_input_mute(idx, mute, Some(cb)),
+                PAFacility::SourceOutput => {
+                    introspector.set_source_output_mute(idx, mute, Some(cb))
+                }
+                _ => unreachable!("only devices and streams are muted"),
+            };
+        };
+
+        // collect every target first, and only mute them once all the lists have been received
+        let targets: Rc<RefCell<Vec<(PAFacility, u32)>>> = Rc::default();
+        let pending = Rc::new(Cell::new(kinds.len()));
+        let finish = Rc::new({
+            let (reply, targets) = (self.reply.clone(), targets.clone());
+            move |success: bool| match (pending.get(), success) {
+                // already responded, since another list failed
+                (0, _) => {}
+                (_, false) => {
+                    pending.set(0);
+                    reply.error();
+                }
+                (n, true) => {
+                    pending.set(n - 1);
+                    if n == 1 {
+                        Self::for_each(&reply, &targets.borrow(), &mute_one);
+                    }
+                }
+            }
+        });
+
+        if kinds.is_empty() {
+            self.tx.send(PAResponse::OpComplete).ignore();
+            return;
+        }
+
+        let introspector = self.introspect();
+        for kind in kinds {
+            let finish = finish.clone();
+            let (targets, except) = (targets.clone(), except.clone());
+            let push = move |idx: u32, name: Option<&str>| {
+                let excepted = match except {
+                    Some(PAIdent::Index(i)) => i == idx,
+                    Some(PAIdent::Name(ref n)) => name == Some(n.as_str()),
+                    None => false,
+                };
+                if !excepted {
+                    targets.borrow_mut().push((kind, idx));
+                }
+            };
+
+            match kind {
+                PAFacility::Sink => {
+                    introspector.get_sink_info_list(move |result| match result {
+                        ListResult::Item(info) => push(info.index, info.name.as_deref()),
+                        ListResult::End => finish(true),
+                        ListResult::Error => finish(false),
+                    });
+                }
+                PAFacility::Source => {
+                    introspector.get_source_info_list(move |result| match result {
+                        ListResult::Item(info) => push(info.index, info.name.as_deref()),
+                        ListResult::End => finish(true),
+                        ListResult::Error => finish(false),
+                    });
+                }
+                PAFacility::SinkInput => {
+                    introspector.get_sink_input_info_list(move |result| match result {
+                        ListResult::Item(info) => push(info.index, None),
+                        ListResult::End => finish(true),
+                        ListResult::Error => finish(false),
+                    });
+                }
+                PAFacility::SourceOutput => {
+                    introspector.get_source_output_info_list(move |result| match result {
+                        ListResult::Item(info) => push(info.index, None),
+                        ListResult::End => finish(true),
+                        ListResult::Error => finish(false),
+                    });
+                }
+                _ => unreachable!("only devices and streams are muted"),
+            }
+        }
+    }
+
+    /*
+     * Raw streams
+     */
+
+    fn play_raw(&self, options: RawStreamOptions, data: Receiver<Vec<u8>>) {
+        let (stream, spec) = match self.new_raw_stream("Playback", &options.spec) {
+            Ok((stream, spec)) => (Rc::new(RefCell::new(stream)), spec),
+            Err(e) => {
+                self.tx.send(PAResponse::OpError(e)).ignore();
+                return;
+            }
+        };
+
+        let feed = Feed::new(data, spec.frame_size());
+        #[cfg(feature = "resample")]
+        let feed = match options
+            .input
+            .map(|input| Resampler::new(input, options.spec))
+        {
+            Some(Ok(resampler)) if !resampler.is_noop() => feed.resample(resampler),
+            Some(Err(message)) => {
+                let code = Some(Code::Invalid as i32);
+                self.tx
+                    .send(PAResponse::OpError(OpError {
+                        message,
+                        code,
+                        channel_count: None,
+                    }))
+                    .ignore();
+                return;
+            }
+            _ => feed,
+        };
+        let id = self
+            .raw_streams
+            .borrow_mut()
+            .insert(RawStream::playback(stream.clone(), feed));
+        self.watch_raw_stream(id, &stream);
+        self.forward_raw_stream_events(id, &stream, PAMask::SINK_INPUT);
+
+        let volume = options.volume.map(|vol| {
+            let mut cv = ChannelVolumes::default();
+            cv.set(spec.channels, vol.into());
+            cv
+        });
+        let connected = stream.borrow_mut().connect_playback(
+            options.device.as_deref(),
+            options.buffer.buffer_attr(&spec, true).as_ref(),
+            options.flags(),
+            volume.as_ref(),
+            None,
+        );
+        if let Err(e) = connected {
+            let err = OpError::from_errno(e);
+            self.raw_streams.borrow_mut().finish(id, Err(err), &self.tx);
+            return;
+        }
+
+        // data is written from a timer rather than the write callback, since the callback isn't
+        // called again if there's nothing to write when the server asks for more
+        let timer = {
+            let reply = self.reply.clone();
+            let weak = Rc::downgrade(&self.raw_streams);
+            self.handle
+                .timer(&self.ctx.borrow(), RAW_POLL, move |mut timer| {
+                    if let Some(streams) = weak.upgrade() {
+                        if Self::feed_raw_stream(&reply, &streams, id) {
+                            timer.restart_rt(MonotonicTs::now() + RAW_POLL);
+                        }
+                    }
+                })
+        };
+
+        let mut streams = self.raw_streams.borrow_mut();
+        match (timer, streams.get_mut(id)) {
+            (Some(timer), Some(raw)) => raw.timer = Some(timer),
+            (None, Some(_)) => {
+                stream.borrow_mut().disconnect().ignore();
+                let err = "Failed to create a timer for the stream".into();
+                streams.finish(id, Err(err), &self.tx);
+            }
+            // the stream failed while connecting
+            (_, None) => {}
+        }
+    }
+
+    /// Called by a playback stream's timer, writes as much data as the stream can take. Once there's
+    /// no more data, the stream is drained and disconnected. Returns whether the timer should be
+    /// re-armed.
+    fn feed_raw_stream(reply: &Rc<Reply>, streams: &Rc<RefCell<RawStreams>>, id: u32) -> bool {
+        let (stream, chunk, done, drain) = {
+            let mut streams = streams.borrow_mut();
+            let raw = match streams.get_mut(id) {
+                Some(raw) => raw,
+                None => return false,
+            };
+
+            let stream = raw.stream.clone();
+            let writable = match stream.borrow().get_state() {
+                StreamState::Ready => stream.borrow().writable_size().unwrap_or(0),
+                StreamState::Unconnected | StreamState::Creating => return true,
+                // the state callback will have responded
+                StreamState::Failed | StreamState::Terminated => return false,
+            };
+
+            let (chunk, done, empty) = match raw.feed.as_mut() {
+                Some(feed) => (feed.take(writable), feed.is_done(), feed.is_empty()),
+                None => return false,
+            };
+
+            // a `PACommand::DrainStream` is answered by the drain operation once everything has
+            // been written, unless the stream is about to finish anyway
+            let drain = raw.draining && empty && !done;
+            if drain {
+                raw.draining = false;
+            }
+
+            (stream, chunk, done, drain)
+        };
+
+        let mut s = stream.borrow_mut();
+        if !chunk.is_empty() {
+            if let Err(e) = s.write_copy(&chunk, 0, SeekMode::Relative) {
+                s.disconnect().ignore();
+                streams
+                    .borrow_mut()
+                    .finish(id, Err(OpError::from_errno(e)), &reply.tx);
+                return false;
+            }
+        }
+
+        if drain {
+            s.drain(Some(Self::success_cb(reply)));
+        }
+
+        if !done {
+            return true;
+        }
+
+        // there's no more data, so finish once everything which was written has been played
+        let reply = reply.clone();
+        let weak_streams = Rc::downgrade(streams);
+        let weak_stream = Rc::downgrade(&stream);
+        s.drain(Some(Box::new(move |success| {
+            let result = match success {
+                true => Ok(()),
+                false => Err(OpError::from_errno(reply.ctx.borrow_mut().errno())),
+            };
+            if let Some(stream) = weak_stream.upgrade() {
+                stream.borrow_mut().disconnect().ignore();
+            }
+            if let Some(streams) = weak_streams.upgrade() {
+                streams.borrow_mut().finish(id, result, &reply.tx);
+            }
+        })));
+
+        false
+    }
+
+    fn record_raw(&self, options: RawStreamOptions, data: Sender<Vec<u8>>) {
+        let (stream, spec) = match self.new_raw_stream("Recording", &options.spec) {
+            Ok((stream, spec)) => (Rc::new(RefCell::new(stream)), spec),
+            Err(e) => {
+                self.tx.send(PAResponse::OpError(e)).ignore();
+                return;
+            }
+        };
+
+        let id = self
+            .raw_streams
+            .borrow_mut()
+            .insert(RawStream::recording(stream.clone(), data));
+        self.watch_raw_stream(id, &stream);
+        self.forward_raw_stream_events(id, &stream, PAMask::SOURCE_OUTPUT);
+
         let tx = self.tx.clone();
+        let weak_streams = Rc::downgrade(&self.raw_streams);
+        let weak_stream = Rc::downgrade(&stream);
+        stream
+            .borrow_mut()
+            .set_read_callback(Some(Box::new(move |_| {
+                let (streams, stream) = match (weak_streams.upgrade(), weak_stream.upgrade()) {
+                    (Some(streams), Some(stream)) => (streams, stream),
+                    _ => return,
+                };
+                let mut s = match stream.try_borrow_mut() {
+                    Ok(s) => s,
+                    Err(_) => return,
+                };
+
+                let data = match s.peek() {
+                    Ok(PeekResult::Data(data)) => Some(data.to_vec()),
+                    // a gap in the recording (such as after an overflow), it only needs discarding
+                    Ok(PeekResult::Hole(_)) => None,
+                    Ok(PeekResult::Empty) | Err(_) => return,
+                };
+                s.discard().ignore();
+
+                if let Some(data) = data {
+                    let sent = streams
+                        .borrow_mut()
+                        .get_mut(id)
+                        .and_then(|raw| raw.output.as_ref())
+                        .map_or(false, |output| output.send(data).is_ok());
+
+                    // stop recording once the receiver has been dropped
+                    if !sent {
+                        s.disconnect().ignore();
+                        streams.borrow_mut().finish(id, Ok(()), &tx);
+                    }
+                }
+            })));
+
+        if let Some(idx) = options.monitor_stream {
+            if let Err(e) = stream.borrow_mut().set_monitor_stream(idx) {
+                let err = OpError::from_errno(e);
+                self.raw_streams.borrow_mut().finish(id, Err(err), &self.tx);
+                return;
+            }
+        }
+
+        let connected = stream.borrow_mut().connect_record(
+            options.device.as_deref(),
+            options.buffer.buffer_attr(&spec, false).as_ref(),
+            options.flags(),
+        );
+        if let Err(e) = connected {
+            let err = OpError::from_errno(e);
+            self.raw_streams.borrow_mut().finish(id, Err(err), &self.tx);
+        }
+    }
+
+    /// Creates a stream for raw audio, which isn't connected yet
+    fn new_raw_stream(&self, name: &str, spec: &RawSpec) -> Result<(Stream, Spec), OpError> {
+        let spec = spec.sample_spec().map_err(|message| OpError {
+            message,
+            code: Some(Code::Invalid as i32),
+            channel_count: None,
+        })?;
+
+        let stream = Stream::new(&mut self.ctx.borrow_mut(), name, &spec, None);
+        match stream {
+            Some(stream) => Ok((stream, spec)),
+            None => Err(OpError::from_errno(self.ctx.borrow_mut().errno())),
+        }
+    }
+
+    /// Responds once a stream is ready, and finishes it if it fails (such as when its device is
+    /// removed) or is killed.
+    fn watch_raw_stream(&self, id: u32, stream: &Rc<RefCell<Stream>>) {
         let ctx = self.ctx.clone();
-        match source {
-            PAIdent::Index(source_idx) => introspector.move_source_output_by_index(
-                idx,
-                source_idx,
-                Some(Self::success_cb(ctx, tx)),
-            ),
-            PAIdent::Name(ref name) => {
-                introspector.move_source_output_by_name(idx, name, Some(Self::success_cb(ctx, tx)))
+        let tx = self.tx.clone();
+        let weak_streams = Rc::downgrade(&self.raw_streams);
+        let weak_stream = Rc::downgrade(stream);
+        stream
+            .borrow_mut()
+            .set_state_callback(Some(Box::new(move || {
+                let (streams, stream) = match (weak_streams.upgrade(), weak_stream.upgrade()) {
+                    (Some(streams), Some(stream)) => (streams, stream),
+                    _ => return,
+                };
+                // the stream is borrowed whenever its state is changed from here, such as when
+                // it's connected or disconnected, which is handled there
+                let state = match stream.try_borrow() {
+                    Ok(s) => s.get_state(),
+                    Err(_) => return,
+                };
+
+                let mut streams = streams.borrow_mut();
+                match state {
+                    StreamState::Ready => streams.started(id, &tx),
+                    StreamState::Failed => {
+                        let err = OpError::from_errno(ctx.borrow_mut().errno());
+                        streams.finish(id, Err(err), &tx);
+                    }
+                    StreamState::Terminated => {
+                        streams.finish(id, Err("The stream was terminated".into()), &tx);
+                    }
+                    StreamState::Unconnected | StreamState::Creating => {}
+                }
+            })));
+    }
+
+    /// Sends events for a stream's underflows, overflows, suspends and moves to the subscription, if
+    /// it includes the stream's facility.
+    fn forward_raw_stream_events(&self, id: u32, stream: &Rc<RefCell<Stream>>, mask: PAMask) {
+        let send = {
+            let subscription = self.subscription.clone();
+            Rc::new(move |ev: PAEvent| {
+                let subscription = subscription.borrow();
+                if subscription.mask.contains(mask) {
+                    subscription.send(ev).ignore();
+                }
+            })
+        };
+
+        let mut s = stream.borrow_mut();
+        let f = send.clone();
+        s.set_underflow_callback(Some(Box::new(move || f(PAEvent::StreamUnderflow(id)))));
+        let f = send.clone();
+        s.set_overflow_callback(Some(Box::new(move || f(PAEvent::StreamOverflow(id)))));
+
+        let (f, weak) = (send.clone(), Rc::downgrade(stream));
+        s.set_suspended_callback(Some(Box::new(move || {
+            let suspended = weak
+                .upgrade()
+                .and_then(|stream| stream.try_borrow().ok()?.is_suspended().ok());
+            if let Some(suspended) = suspended {
+                f(PAEvent::StreamSuspended {
+                    stream: id,
+                    suspended,
+                });
+            }
+        })));
+
+        let (f, weak) = (send, Rc::downgrade(stream));
+        s.set_moved_callback(Some(Box::new(move || {
+            let device = weak.upgrade().and_then(|stream| {
+                let s = stream.try_borrow().ok()?;
+                s.get_device_name().map(|name| name.to_string())
+            });
+            f(PAEvent::StreamMoved { stream: id, device });
+        })));
+    }
+
+    /// Pauses (or resumes) a stream
+    fn cork_stream(&self, id: u32, cork: bool) {
+        let stream = match self.raw_streams.borrow_mut().get_mut(id) {
+            Some(raw) => raw.stream.clone(),
+            None => {
+                let err = RawStreams::not_found(id);
+                self.tx.send(PAResponse::OpError(err)).ignore();
+                return;
             }
         };
+
+        let cb = Self::success_cb(&self.reply);
+        match cork {
+            true => stream.borrow_mut().cork(Some(cb)),
+            false => stream.borrow_mut().uncork(Some(cb)),
+        };
     }
 
-    fn kill_source_output(&self, idx: u32) {
-        let mut introspector = self.ctx.borrow_mut().introspect();
-        introspector.kill_source_output(idx, Self::success_cb(self.ctx.clone(), self.tx.clone()));
+    /// Drops the data which hasn't been played (or read) yet
+    fn flush_stream(&self, id: u32) {
+        let stream = match self.raw_streams.borrow_mut().get_mut(id) {
+            Some(raw) => {
+                if let Some(feed) = raw.feed.as_mut() {
+                    feed.clear();
+                }
+                raw.stream.clone()
+            }
+            None => {
+                let err = RawStreams::not_found(id);
+                self.tx.send(PAResponse::OpError(err)).ignore();
+                return;
+            }
+        };
+
+        let cb = Self::success_cb(&self.reply);
+        stream.borrow_mut().flush(Some(cb));
+    }
+
+    fn get_stream_latency(&self, id: u32) {
+        let (stream, recording) = match self.raw_streams.borrow_mut().get_mut(id) {
+            Some(raw) => (raw.stream.clone(), raw.output.is_some()),
+            None => {
+                let err = RawStreams::not_found(id);
+                self.tx.send(PAResponse::OpError(err)).ignore();
+                return;
+            }
+        };
+
+        // make sure the timing info is current, rather than from the last automatic update
+        let ctx = self.ctx.clone();
+        let tx = self.tx.clone();
+        let weak = Rc::downgrade(&stream);
+        stream
+            .borrow_mut()
+            .update_timing_info(Some(Box::new(move |success| {
+                let stream = match (success, weak.upgrade()) {
+                    (true, Some(stream)) => stream,
+                    _ => return Self::handle_error(&ctx, &tx),
+                };
+                let mut s = stream.borrow_mut();
+
+                let latency = match s.get_latency() {
+                    Ok(Latency::None) => 0,
+                    Ok(Latency::Positive(usec)) => usec.0 as i64,
+                    Ok(Latency::Negative(usec)) => -(usec.0 as i64),
+                    Err(e) => {
+                        tx.send(PAResponse::OpError(OpError::from_errno(e)))
+                            .ignore();
+                        return;
+                    }
+                };
+                let buffer = s
+                    .get_buffer_attr()
+                    .map(PABufferAttr::from)
+                    .unwrap_or_default();
+                let response = match s.get_timing_info() {
+                    Some(timing) => PAResponse::StreamLatency(RawStreamLatency {
+                        latency,
+                        device_latency: match recording {
+                            true => timing.source_usec.0,
+                            false => timing.sink_usec.0,
+                        },
+                        transport: timing.transport_usec.0,
+                        playing: timing.playing != 0,
+                        buffer,
+                    }),
+                    None => PAResponse::OpError("No timing info is available".into()),
+                };
+
+                tx.send(response).ignore();
+            })));
+    }
+
+    /// Waits for everything sent to a playback stream so far to be played. The stream's timer
+    /// responds, once it's written everything.
+    fn drain_stream(&self, id: u32) {
+        let recording = self
+            .raw_streams
+            .borrow_mut()
+            .get_mut(id)
+            .map_or(false, |raw| raw.feed.is_none());
+        if recording {
+            let err = "Only playback streams can be drained".into();
+            self.tx.send(PAResponse::OpError(err)).ignore();
+            return;
+        }
+
+        self.raw_streams.borrow_mut().drain(id, &self.tx);
+    }
+
+    /*
+     * Properties
+     */
+
+    fn update_proplist(
+        &self,
+        target: ProplistTarget,
+        props: &BTreeMap<String, String>,
+        mode: ProplistMode,
+    ) {
+        let mut proplist = match Self::new_proplist(props) {
+            Ok(proplist) => proplist,
+            Err(e) => {
+                self.tx.send(PAResponse::OpError(e)).ignore();
+                return;
+            }
+        };
+
+        let cb = Self::success_cb(&self.reply);
+        match target {
+            ProplistTarget::Client => {
+                self.ctx
+                    .borrow_mut()
+                    .proplist_update(mode.into(), &proplist, cb);
+            }
+            ProplistTarget::Stream(id) => {
+                let stream = match self.raw_streams.borrow_mut().get_mut(id) {
+                    Some(raw) => raw.stream.clone(),
+                    None => {
+                        let err = RawStreams::not_found(id);
+                        self.tx.send(PAResponse::OpError(err)).ignore();
+                        return;
+                    }
+                };
+                stream
+                    .borrow_mut()
+                    .update_proplist(mode.into(), &mut proplist, cb);
+            }
+            ProplistTarget::Sink(_) | ProplistTarget::Source(_) => {
+                let err = OpError {
+                    message: "The properties of sinks and sources can only be changed by modules \
+                              on the server"
+                        .into(),
+                    code: Some(Code::NotSupported as i32),
+                    channel_count: None,
+                };
+                self.tx.send(PAResponse::OpError(err)).ignore();
+            }
+        }
+    }
+
+    fn new_proplist(props: &BTreeMap<String, String>) -> Result<Proplist, OpError> {
+        let mut proplist = Proplist::new().ok_or("Failed to create PulseAudio Proplist")?;
+        for (key, value) in props {
+            proplist.set_str(key, value).map_err(|_| OpError {
+                message: format!("Invalid property: {}", key),
+                code: Some(Code::Invalid as i32),
+                channel_count: None,
+            })?;
+        }
+
+        Ok(proplist)
+    }
+
+    /*
+     * Stream restore
+     */
+
+    fn get_app_volumes(&self) {
+        Self::with_stream_restore(&self.reply, |reply| {
+            let mut volumes = vec![];
+            let mut restore = reply.ctx.borrow().stream_restore();
+            let reply = reply.clone();
+            restore.read(move |result| match result {
+                ListResult::Item(info) => {
+                    let app = info
+                        .name
+                        .as_deref()
+                        .and_then(|n| n.strip_prefix(AppVolume::PREFIX));
+                    if let Some(app) = app {
+                        let valid = info.channel_map.is_valid() && info.volume.is_valid();
+                        volumes.push(AppVolume {
+                            application: app.to_string(),
+                            volume: valid.then(|| PAVolume::from(info.volume.avg()).percentage()),
+                            mute: info.mute,
+                        });
+                    }
+                }
+                ListResult::End => {
+                    let volumes = std::mem::take(&mut volumes);
+                    reply.tx.send(PAResponse::AppVolumes(volumes)).ignore();
+                }
+                ListResult::Error => reply.error(),
+            });
+        });
+    }
+
+    fn save_app_volumes(&self, volumes: &[AppVolume]) {
+        if let Some(app) = volumes.iter().find(|v| v.application.contains('\0')) {
+            let err = OpError {
+                message: format!("Invalid application name: {:?}", app.application),
+                code: Some(Code::Invalid as i32),
+                channel_count: None,
+            };
+            self.tx.send(PAResponse::OpError(err)).ignore();
+            return;
+        }
+
+        let limit = self.volume_limit;
+        let volumes = volumes.to_vec();
+        Self::with_stream_restore(&self.reply, move |reply| {
+            // saved with a single channel, which the module remaps onto the channels of each stream
+            let mut mono = Map::default();
+            mono.init_mono();
+            let infos = volumes
+                .iter()
+                .map(|v| {
+                    let (channel_map, volume) = match v.volume {
+                        Some(pct) => {
+                            let cv = new_channel_volumes(vec![PAVol::Percentage(pct).into()]);
+                            (mono, limit_channel_volumes(cv, limit))
+                        }
+                        // an invalid volume means only the mute is saved
+                        None => (Map::default(), ChannelVolumes::default()),
+                    };
+                    StreamRestoreInfo {
+                        name: Some(AppVolume::entry_name(&v.application).into()),
+                        channel_map,
+                        volume,
+                        device: None,
+                        mute: v.mute,
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            let infos = infos.iter().collect::<Vec<_>>();
+            let cb = Self::success_cb(reply);
+            reply
+                .ctx
+                .borrow()
+                .stream_restore()
+                .write(UpdateMode::Replace, &infos, true, cb);
+        });
+    }
+
+    fn delete_app_volumes(&self, apps: &[String]) {
+        let names = apps
+            .iter()
+            .map(|app| AppVolume::entry_name(app))
+            .collect::<Vec<_>>();
+        Self::with_stream_restore(&self.reply, move |reply| {
+            let names = names.iter().map(|n| n.as_str()).collect::<Vec<_>>();
+            let cb = Self::success_cb(reply);
+            reply.ctx.borrow().stream_restore().delete(&names, cb);
+        });
+    }
+
+    /// Runs `f` if module-stream-restore is loaded, otherwise responds with an error
+    fn with_stream_restore(reply: &Rc<Reply>, f: impl FnOnce(&Rc<Reply>) + 'static) {
+        let mut f = Some(f);
+        let mut restore = reply.ctx.borrow().stream_restore();
+        let reply = reply.clone();
+        restore.test(move |version| {
+            if version == PA_INVALID_INDEX {
+                let err = OpError {
+                    message: "Saving application volumes requires module-stream-restore, which \
+                              isn't loaded (see `pactl load-module module-stream-restore`)"
+                        .into(),
+                    code: Some(Code::NotSupported as i32),
+                    channel_count: None,
+                };
+                reply.tx.send(PAResponse::OpError(err)).ignore();
+                return;
+            }
+
+            if let Some(f) = f.take() {
+                f(&reply);
+            }
+        });
+    }
+
+    /*
+     * Temporary changes
+     */
+
+    /// Applies `temporary` to the target, and restores `current` once `duration` has elapsed.
+    /// If the same state of the target was already temporarily changed, the originally saved state
+    /// is kept and the restore is pushed back.
+    #[allow(clippy::too_many_arguments)]
+    fn change_temporarily(
+        ctx: &Ctx,
+        handle: &LoopHandle,
+        restores: &Rc<RefCell<Restores>>,
+        reply: &Rc<Reply>,
+        target: Target,
+        current: Saved,
+        temporary: Saved,
+        duration: Duration,
+    ) {
+        Self::apply_saved(ctx, target, temporary, Some(Self::success_cb(reply)));
+
+        if restores.borrow_mut().extend(target, &current, duration) {
+            return;
+        }
+
+        // hold a weak reference, since the timer itself is stored in the restores
+        let weak = Rc::downgrade(restores);
+        let timer_ctx = ctx.clone();
+        let is_mute = current.is_mute();
+        let timer = handle.timer(&ctx.borrow(), duration, move |_| {
+            if let Some(restores) = weak.upgrade() {
+                let saved = restores.borrow_mut().take(target, is_mute);
+                if let Some(saved) = saved {
+                    Self::apply_saved(&timer_ctx, target, saved, None);
+                }
+            }
+        });
+
+        restores.borrow_mut().insert(target, current, timer);
+    }
+
+    fn apply_saved(
+        ctx: &Ctx,
+        target: Target,
+        saved: Saved,
+        cb: Option<Box<dyn FnMut(bool) + 'static>>,
+    ) {
+        let mut introspector = ctx.borrow_mut().introspect();
+        match (target, saved) {
+            (Target::Sink(idx), Saved::Mute(mute)) => {
+                introspector.set_sink_mute_by_index(idx, mute, cb)
+            }
+            (Target::Sink(idx), Saved::Volume(cv)) => {
+                introspector.set_sink_volume_by_index(idx, &cv, cb)
+            }
+            (Target::SinkInput(idx), Saved::Mute(mute)) => {
+                introspector.set_sink_input_mute(idx, mute, cb)
+            }
+            (Target::SinkInput(idx), Saved::Volume(cv)) => {
+                introspector.set_sink_input_volume(idx, &cv, cb)
+            }
+        };
     }
 
     /*
      * Util
      */
 
+    /// Sets the description of a device (such as "sink:<name>") with module-device-manager, which
+    /// also renames it for other clients. The native protocol can't otherwise change a device's
+    /// properties, so this fails if the module isn't loaded.
+    fn set_device_description(reply: &Rc<Reply>, device: String, description: String) {
+        if description.contains('\0') {
+            let err = OpError {
+                message: "The description can't contain a NUL character".into(),
+                code: Some(Code::Invalid as i32),
+                channel_count: None,
+            };
+            reply.tx.send(PAResponse::OpError(err)).ignore();
+            return;
+        }
+
+        let mut manager = reply.ctx.borrow().device_manager();
+        let reply = reply.clone();
+        manager.test(move |version| {
+            if version == PA_INVALID_INDEX {
+                let err = OpError {
+                    message: "Renaming devices requires module-device-manager, which isn't loaded \
+                              (see `pactl load-module module-device-manager`)"
+                        .into(),
+                    code: Some(Code::NotSupported as i32),
+                    channel_count: None,
+                };
+                reply.tx.send(PAResponse::OpError(err)).ignore();
+                return;
+            }
+
+            let cb = Self::success_cb(&reply);
+            let mut manager = reply.ctx.borrow().device_manager();
+            manager.set_device_description(&device, &description, cb);
+        });
+    }
+
     fn read_volumes<'a>(
         channels: impl Iterator<Item = &'a Position>,
         volumes: impl Iterator<Item = &'a Volume>,
     ) -> VolumeReadings {
         channels
             .zip(volumes)
-            .map(|(chan, vol)| VolumeReading::new(chan, vol))
+            .map(|(chan, vol)| VolumeReading::new((*chan).into(), (*vol).into()))
             .collect()
     }
 
-    fn success_cb(ctx: Ctx, tx: Sender<PAResponse>) -> Box<impl FnMut(bool)> {
-        Box::new(move |success: bool| {
-            if !success {
-                Self::handle_error(&ctx, &tx)
-            } else {
-                tx.send(PAResponse::OpComplete).ignore();
-            }
-        })
+    fn success_cb(reply: &Rc<Reply>) -> Box<impl FnMut(bool)> {
+        let reply = reply.clone();
+        Box::new(move |success: bool| reply.done(success))
+    }
+
+    /// Runs an operation on each of the resolved streams, responding once all of them have
+    /// succeeded, or as soon as one fails. Fails if no streams matched.
+    fn for_each_stream<F>(reply: &Rc<Reply>, stream: &PAStreamIdent, indices: &[u32], f: &F)
+    where
+        F: Fn(&Ctx, u32, Box<dyn FnMut(bool)>),
+    {
+        if indices.is_empty() {
+            let err = OpError {
+                message: format!("No streams found for {}", stream),
+                code: Some(Code::NoEntity as i32),
+                channel_count: None,
+            };
+            reply.tx.send(PAResponse::OpError(err)).ignore();
+            return;
+        }
+
+        Self::for_each(reply, indices, f);
+    }
+
+    /// Runs an operation on each item, responding once all of them have succeeded (or immediately
+    /// if there are none), or as soon as one fails.
+    fn for_each<T, F>(reply: &Rc<Reply>, items: &[T], f: &F)
+    where
+        T: Copy,
+        F: Fn(&Ctx, T, Box<dyn FnMut(bool)>),
+    {
+        if items.is_empty() {
+            reply.tx.send(PAResponse::OpComplete).ignore();
+            return;
+        }
+
+        // every callback shares the one countdown
+        let countdown = Rc::new(Countdown {
+            reply: reply.clone(),
+            remaining: Cell::new(items.len()),
+        });
+        for item in items {
+            let countdown = countdown.clone();
+            f(
+                &reply.ctx,
+                *item,
+                Box::new(move |success| countdown.done(success)),
+            );
+        }
+    }
+
+    fn introspect(&self) -> RefMut<'_, Introspector> {
+        self.introspector.borrow_mut()
+    }
+
+    fn lookup<F>(&self, ident: PAIdent, f: F) -> Lookup<F> {
+        Lookup {
+            ident,
+            f: Rc::new(RefCell::new(f)),
+            ctx: self.ctx.clone(),
+            introspector: self.introspector.clone(),
+            tx: self.tx.clone(),
+            handle: self.handle.clone(),
+            retries: self.retries.clone(),
+            deadline: self.retries.borrow().deadline(),
+        }
+    }
+
+    fn set_lookup_retry(&self, timeout: Option<Duration>) {
+        self.retries.borrow_mut().timeout = timeout;
+        self.tx.send(PAResponse::OpComplete).ignore();
     }
 
     fn handle_error(ctx: &Ctx, tx: &Sender<PAResponse>) {
-        let err = ctx.borrow_mut().errno().to_string();
-        tx.send(PAResponse::OpError(format!(
-            "Operation failed: {}",
-            err.unwrap_or("An unknown error occurred".into())
-        )))
-        .ignore();
+        let err = ctx.borrow_mut().errno();
+        tx.send(PAResponse::OpError(OpError::from_errno(err)))
+            .ignore();
     }
 }
+
+/// A random number for naming temporary files, from the random keys std uses to seed hashers
+fn random_id() -> u64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u32(process::id());
+    hasher.finish()
+}
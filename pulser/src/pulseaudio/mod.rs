@@ -1,3 +1,26 @@
 pub mod api;
+#[cfg(feature = "backend")]
+pub mod decode;
+#[cfg(feature = "backend")]
+pub mod embedded;
+#[cfg(feature = "backend")]
+mod fade;
+#[cfg(feature = "backend")]
+mod heartbeat;
+#[cfg(feature = "backend")]
 pub mod mainloop;
+#[cfg(feature = "backend")]
+mod raw;
+#[cfg(feature = "resample")]
+pub mod resample;
+#[cfg(feature = "backend")]
+mod restore;
+#[cfg(feature = "backend")]
+mod retry;
+#[cfg(feature = "backend")]
+mod subscription;
+#[cfg(feature = "backend")]
+mod timer;
+#[cfg(feature = "backend")]
 pub mod util;
+pub mod volume;
@@ -0,0 +1,237 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::Duration;
+
+use crossbeam_channel::{Receiver, Sender, TryRecvError};
+use libpulse_binding::stream::Stream;
+
+use super::api::{OpError, PAResponse};
+#[cfg(feature = "resample")]
+use super::resample::Resampler;
+use super::timer::Timer;
+use crate::ignore::Ignore;
+
+/// How often a playback stream checks for more data to write
+pub const RAW_POLL: Duration = Duration::from_millis(10);
+
+/// Raw audio which is waiting to be written to a playback stream
+pub struct Feed {
+    data: Receiver<Vec<u8>>,
+    pending: Vec<u8>,
+    /// Whether the sender has been dropped, meaning there's no more data
+    eof: bool,
+    frame_size: usize,
+    /// Converts the data as it's received, if it doesn't match the stream's sample spec
+    #[cfg(feature = "resample")]
+    resampler: Option<Resampler>,
+}
+
+impl Feed {
+    pub fn new(data: Receiver<Vec<u8>>, frame_size: usize) -> Feed {
+        Feed {
+            data,
+            pending: vec![],
+            eof: false,
+            frame_size: frame_size.max(1),
+            #[cfg(feature = "resample")]
+            resampler: None,
+        }
+    }
+
+    /// Converts the data with the given resampler before it's played
+    #[cfg(feature = "resample")]
+    pub fn resample(mut self, resampler: Resampler) -> Feed {
+        self.resampler = Some(resampler);
+        self
+    }
+
+    /// Takes up to `max` bytes of whole frames, receiving more data if there isn't enough pending.
+    pub fn take(&mut self, max: usize) -> Vec<u8> {
+        while self.pending.len() < max && !self.eof {
+            match self.data.try_recv() {
+                Ok(chunk) => {
+                    let chunk = self.convert(chunk);
+                    self.pending.extend(chunk);
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => self.eof = true,
+            }
+        }
+
+        let n = max.min(self.pending.len());
+        self.pending.drain(..n - n % self.frame_size).collect()
+    }
+
+    #[cfg(feature = "resample")]
+    fn convert(&mut self, chunk: Vec<u8>) -> Vec<u8> {
+        match &mut self.resampler {
+            Some(resampler) => resampler.process(&chunk),
+            None => chunk,
+        }
+    }
+
+    #[cfg(not(feature = "resample"))]
+    fn convert(&mut self, chunk: Vec<u8>) -> Vec<u8> {
+        chunk
+    }
+
+    /// Drops all the data which hasn't been taken yet, including anything waiting in the channel
+    pub fn clear(&mut self) {
+        self.pending.clear();
+        while self.data.try_recv().is_ok() {}
+    }
+
+    /// Whether everything received so far has been taken (a trailing partial frame can't be)
+    pub fn is_empty(&self) -> bool {
+        self.pending.len() < self.frame_size
+    }
+
+    /// Whether all the data has been taken, and no more will be received
+    pub fn is_done(&self) -> bool {
+        self.eof && self.is_empty()
+    }
+}
+
+#[derive(Debug, Clone)]
+enum RawState {
+    Running,
+    Done,
+    Failed(OpError),
+}
+
+pub struct RawStream {
+    pub stream: Rc<RefCell<Stream>>,
+    /// The data to play, for playback streams (until the stream is finished)
+    pub feed: Option<Feed>,
+    /// Where recorded data is sent, for recording streams (until the stream is finished)
+    pub output: Option<Sender<Vec<u8>>>,
+    /// The timer which feeds a playback stream; kept alive here until the stream is purged
+    pub timer: Option<Timer>,
+    /// Whether a `PACommand::DrainStream` is waiting for everything received so far to be played
+    pub draining: bool,
+    state: RawState,
+    /// Whether the command which created the stream is waiting on a response
+    starting: bool,
+}
+
+impl RawStream {
+    pub fn playback(stream: Rc<RefCell<Stream>>, feed: Feed) -> RawStream {
+        RawStream::new(stream, Some(feed), None)
+    }
+
+    pub fn recording(stream: Rc<RefCell<Stream>>, output: Sender<Vec<u8>>) -> RawStream {
+        RawStream::new(stream, None, Some(output))
+    }
+
+    fn new(
+        stream: Rc<RefCell<Stream>>,
+        feed: Option<Feed>,
+        output: Option<Sender<Vec<u8>>>,
+    ) -> RawStream {
+        RawStream {
+            stream,
+            feed,
+            output,
+            timer: None,
+            draining: false,
+            state: RawState::Running,
+            starting: true,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        matches!(self.state, RawState::Running)
+    }
+}
+
+/// The playback and recording streams started with `PACommand::PlayRaw` and `PACommand::RecordRaw`
+#[derive(Default)]
+pub struct RawStreams {
+    next_id: u32,
+    inner: HashMap<u32, RawStream>,
+}
+
+impl RawStreams {
+    /// Adds a new stream, dropping any which have finished.
+    /// Must not be called from within a stream's callbacks, since finished streams are dropped here.
+    pub fn insert(&mut self, stream: RawStream) -> u32 {
+        self.inner.retain(|_, s| s.is_running());
+
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+        self.inner.insert(id, stream);
+        id
+    }
+
+    /// Returns a stream if it's still running
+    pub fn get_mut(&mut self, id: u32) -> Option<&mut RawStream> {
+        self.inner.get_mut(&id).filter(|s| s.is_running())
+    }
+
+    /// Responds to the command which created the stream with its id, once the stream is ready.
+    pub fn started(&mut self, id: u32, tx: &Sender<PAResponse>) {
+        if let Some(stream) = self.inner.get_mut(&id) {
+            if stream.starting {
+                stream.starting = false;
+                tx.send(PAResponse::StreamStarted(id)).ignore();
+            }
+        }
+    }
+
+    /// Marks a stream as finished (it should already be disconnected), and responds to the command
+    /// which created it or to a `PACommand::DrainStream`, if either is waiting. Any data which
+    /// hasn't been played is dropped, and the receiver of recorded data is disconnected.
+    pub fn finish(&mut self, id: u32, result: Result<(), OpError>, tx: &Sender<PAResponse>) {
+        if let Some(stream) = self.inner.get_mut(&id) {
+            if !stream.is_running() {
+                return;
+            }
+
+            stream.state = match result {
+                Ok(()) => RawState::Done,
+                Err(e) => RawState::Failed(e),
+            };
+            stream.feed = None;
+            stream.output = None;
+
+            let response = match (&stream.state, stream.starting) {
+                (RawState::Done, true) => Some(PAResponse::StreamStarted(id)),
+                (_, true) => Some(Self::response(&stream.state)),
+                (_, false) if stream.draining => Some(Self::response(&stream.state)),
+                (_, false) => None,
+            };
+
+            stream.starting = false;
+            stream.draining = false;
+            if let Some(response) = response {
+                tx.send(response).ignore();
+            }
+        }
+    }
+
+    /// Responds to a `PACommand::DrainStream`: immediately if the stream has finished, otherwise
+    /// once everything received so far has been played (see `RawStream::draining`).
+    pub fn drain(&mut self, id: u32, tx: &Sender<PAResponse>) {
+        match self.inner.get_mut(&id) {
+            Some(stream) if stream.is_running() => stream.draining = true,
+            Some(stream) => tx.send(Self::response(&stream.state)).ignore(),
+            None => tx.send(PAResponse::OpError(Self::not_found(id))).ignore(),
+        }
+    }
+
+    pub fn not_found(id: u32) -> OpError {
+        OpError {
+            message: format!("No stream found with id: {}", id),
+            code: Some(libpulse_binding::error::Code::NoEntity as i32),
+            channel_count: None,
+        }
+    }
+
+    fn response(state: &RawState) -> PAResponse {
+        match state {
+            RawState::Running | RawState::Done => PAResponse::OpComplete,
+            RawState::Failed(e) => PAResponse::OpError(e.clone()),
+        }
+    }
+}
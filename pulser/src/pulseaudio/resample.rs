@@ -0,0 +1,159 @@
+use super::api::{RawFormat, RawSpec};
+
+/// Converts raw audio from one layout to another as it's played: the sample format, the number of
+/// channels and the rate can all differ. Data can be passed in chunks of any size, since partial
+/// frames are kept until the rest arrives.
+///
+/// Rates are converted with linear interpolation, which is cheap and good enough for things like
+/// notification sounds, but does let some aliasing through when downsampling.
+pub struct Resampler {
+    from: RawSpec,
+    to: RawSpec,
+    /// How far the input advances for each output frame
+    step: f64,
+    /// The position of the next output frame, relative to `prev`
+    pos: f64,
+    /// The last input frame, already mapped to the output's channels
+    prev: Option<Vec<f32>>,
+    /// A trailing partial frame from the last chunk
+    partial: Vec<u8>,
+}
+
+impl Resampler {
+    pub fn new(from: RawSpec, to: RawSpec) -> Result<Resampler, String> {
+        for spec in [&from, &to] {
+            if spec.rate == 0 || spec.channels == 0 {
+                return Err(format!(
+                    "Can't convert audio with {}Hz and {} channels",
+                    spec.rate, spec.channels
+                ));
+            }
+        }
+
+        Ok(Resampler {
+            step: from.rate as f64 / to.rate as f64,
+            pos: 0.0,
+            prev: None,
+            partial: vec![],
+            from,
+            to,
+        })
+    }
+
+    /// Whether the conversion does nothing, in which case there's no need for a resampler
+    pub fn is_noop(&self) -> bool {
+        self.from.format == self.to.format
+            && self.from.rate == self.to.rate
+            && self.from.channels == self.to.channels
+    }
+
+    /// Converts the next chunk of audio
+    pub fn process(&mut self, chunk: &[u8]) -> Vec<u8> {
+        let in_size = frame_size(&self.from);
+        self.partial.extend_from_slice(chunk);
+        let whole = self.partial.len() - self.partial.len() % in_size;
+        let input = self.partial.drain(..whole).collect::<Vec<_>>();
+
+        let mut output = vec![];
+        for frame in input.chunks_exact(in_size) {
+            let samples = frame
+                .chunks_exact(sample_size(self.from.format))
+                .map(|s| decode(self.from.format, s))
+                .collect::<Vec<_>>();
+            let cur = self.map_channels(&samples);
+
+            if self.from.rate == self.to.rate {
+                self.encode_frame(&cur, &mut output);
+                continue;
+            }
+
+            let prev = self.prev.take().unwrap_or_else(|| cur.clone());
+            while self.pos < 1.0 {
+                let t = self.pos as f32;
+                let frame = prev
+                    .iter()
+                    .zip(&cur)
+                    .map(|(a, b)| a + (b - a) * t)
+                    .collect::<Vec<_>>();
+                self.encode_frame(&frame, &mut output);
+                self.pos += self.step;
+            }
+            self.pos -= 1.0;
+            self.prev = Some(cur);
+        }
+
+        output
+    }
+
+    /// Maps a frame onto the output's channels: mono is copied to every channel, everything is
+    /// mixed down to mono, and otherwise the shared channels are kept and any extra ones are silent.
+    fn map_channels(&self, samples: &[f32]) -> Vec<f32> {
+        let to = self.to.channels as usize;
+        match (samples.len(), to) {
+            (from, to) if from == to => samples.to_vec(),
+            (1, to) => vec![samples[0]; to],
+            (from, 1) => vec![samples.iter().sum::<f32>() / from as f32],
+            (_, to) => (0..to)
+                .map(|c| samples.get(c).copied().unwrap_or(0.0))
+                .collect(),
+        }
+    }
+
+    fn encode_frame(&self, frame: &[f32], output: &mut Vec<u8>) {
+        for sample in frame {
+            encode(self.to.format, *sample, output);
+        }
+    }
+}
+
+fn sample_size(format: RawFormat) -> usize {
+    match format {
+        RawFormat::U8 => 1,
+        RawFormat::S16le | RawFormat::S16be => 2,
+        RawFormat::S24le | RawFormat::S24be => 3,
+        RawFormat::S32le | RawFormat::S32be | RawFormat::F32le | RawFormat::F32be => 4,
+    }
+}
+
+fn frame_size(spec: &RawSpec) -> usize {
+    sample_size(spec.format) * spec.channels as usize
+}
+
+/// Decodes a single sample into the range -1.0 to 1.0
+fn decode(format: RawFormat, s: &[u8]) -> f32 {
+    match format {
+        RawFormat::U8 => (s[0] as f32 - 128.0) / 128.0,
+        RawFormat::S16le => i16::from_le_bytes([s[0], s[1]]) as f32 / 32768.0,
+        RawFormat::S16be => i16::from_be_bytes([s[0], s[1]]) as f32 / 32768.0,
+        // shift the 24 bits to the top of an i32 so the sign is kept, then scale them back down
+        RawFormat::S24le => (i32::from_le_bytes([0, s[0], s[1], s[2]]) >> 8) as f32 / 8388608.0,
+        RawFormat::S24be => (i32::from_be_bytes([s[0], s[1], s[2], 0]) >> 8) as f32 / 8388608.0,
+        RawFormat::S32le => i32::from_le_bytes([s[0], s[1], s[2], s[3]]) as f32 / 2147483648.0,
+        RawFormat::S32be => i32::from_be_bytes([s[0], s[1], s[2], s[3]]) as f32 / 2147483648.0,
+        RawFormat::F32le => f32::from_le_bytes([s[0], s[1], s[2], s[3]]),
+        RawFormat::F32be => f32::from_be_bytes([s[0], s[1], s[2], s[3]]),
+    }
+}
+
+/// Encodes a single sample, clipping it if it's out of range
+fn encode(format: RawFormat, sample: f32, output: &mut Vec<u8>) {
+    let sample = sample.clamp(-1.0, 1.0);
+    // `as` saturates, so a full-scale positive sample doesn't wrap around
+    match format {
+        RawFormat::U8 => output.push((sample * 128.0 + 128.0).min(255.0) as u8),
+        RawFormat::S16le => output.extend(((sample * 32768.0) as i16).to_le_bytes()),
+        RawFormat::S16be => output.extend(((sample * 32768.0) as i16).to_be_bytes()),
+        RawFormat::S24le => {
+            let v = ((sample * 8388608.0) as i32).clamp(-8388608, 8388607);
+            output.extend(&v.to_le_bytes()[..3]);
+        }
+        RawFormat::S24be => {
+            let v = ((sample * 8388608.0) as i32).clamp(-8388608, 8388607);
+            output.extend(&v.to_be_bytes()[1..]);
+        }
+        RawFormat::S32le => output.extend(((sample as f64 * 2147483648.0) as i32).to_le_bytes()),
+        RawFormat::S32be => output.extend(((sample as f64 * 2147483648.0) as i32).to_be_bytes()),
+        RawFormat::F32le => output.extend(sample.to_le_bytes()),
+        RawFormat::F32be => output.extend(sample.to_be_bytes()),
+    }
+}
@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use libpulse_binding::time::MonotonicTs;
+use libpulse_binding::volume::{ChannelVolumes, Volume};
+
+use super::timer::Timer;
+
+/// An object whose state can be temporarily changed
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Target {
+    Sink(u32),
+    SinkInput(u32),
+}
+
+/// A piece of state which is restored once a temporary change expires
+#[derive(Debug, Copy, Clone)]
+pub enum Saved {
+    Mute(bool),
+    Volume(ChannelVolumes),
+}
+
+impl Saved {
+    pub fn is_mute(&self) -> bool {
+        matches!(self, Saved::Mute(_))
+    }
+}
+
+type Key = (Target, bool);
+
+struct Restore {
+    saved: Saved,
+    /// Set once the state has been restored, the entry is purged the next time a change is made
+    restored: bool,
+    timer: Option<Timer>,
+}
+
+/// Pending restores of temporarily changed state. They're also applied when the connection is
+/// closed, but not if the process is killed or the connection is lost, in which case the state
+/// stays changed.
+#[derive(Default)]
+pub struct Restores {
+    inner: HashMap<Key, Restore>,
+}
+
+impl Restores {
+    /// Volume saved for this target, if it has a pending restore.
+    pub fn saved_volume(&self, target: Target) -> Option<ChannelVolumes> {
+        match self.inner.get(&(target, false)) {
+            Some(Restore {
+                saved: Saved::Volume(cv),
+                restored: false,
+                ..
+            }) => Some(*cv),
+            _ => None,
+        }
+    }
+
+    /// If there's already a pending restore for this state, push it back so it expires after
+    /// `duration` and return `true`. The originally saved state is kept.
+    pub fn extend(&mut self, target: Target, saved: &Saved, duration: Duration) -> bool {
+        match self.inner.get_mut(&(target, saved.is_mute())) {
+            Some(Restore {
+                restored: false,
+                timer: Some(timer),
+                ..
+            }) => {
+                timer.restart_rt(MonotonicTs::now() + duration);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Adds a pending restore, must not be called from within a restore's timer callback.
+    pub fn insert(&mut self, target: Target, saved: Saved, timer: Option<Timer>) {
+        self.inner.retain(|_, r| !r.restored);
+        let key = (target, saved.is_mute());
+        self.inner.insert(
+            key,
+            Restore {
+                saved,
+                restored: false,
+                timer,
+            },
+        );
+    }
+
+    /// Takes every saved state which hasn't been restored yet, cancelling their timers.
+    pub fn drain(&mut self) -> Vec<(Target, Saved)> {
+        self.inner
+            .drain()
+            .filter(|(_, restore)| !restore.restored)
+            .map(|((target, _), restore)| (target, restore.saved))
+            .collect()
+    }
+
+    /// Takes the saved state once a restore's timer has fired.
+    pub fn take(&mut self, target: Target, mute: bool) -> Option<Saved> {
+        match self.inner.get_mut(&(target, mute)) {
+            Some(restore) if !restore.restored => {
+                restore.restored = true;
+                Some(restore.saved)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Scales each channel's volume to `pct` percent of its current value.
+pub fn scale_volumes(mut cv: ChannelVolumes, pct: f64) -> ChannelVolumes {
+    let factor = (pct / 100.0).max(0.0);
+    for vol in cv.get_mut() {
+        *vol = Volume((vol.0 as f64 * factor).round() as u32);
+    }
+
+    cv
+}
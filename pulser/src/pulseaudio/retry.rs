@@ -0,0 +1,51 @@
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use libpulse_binding::context::Context;
+
+use super::timer::{LoopHandle, Timer};
+
+/// How long to wait between attempts when retrying a lookup
+pub const RETRY_DELAY: Duration = Duration::from_millis(50);
+
+/// Lookups of objects which don't exist (yet) can optionally be retried, since objects are
+/// sometimes not visible immediately after their `SubscriptionNew` event.
+#[derive(Default)]
+pub struct Retries {
+    /// How long to keep retrying for, `None` disables retries
+    pub timeout: Option<Duration>,
+    /// Timers for retries, along with whether they've fired
+    timers: Vec<(Timer, Rc<Cell<bool>>)>,
+}
+
+impl Retries {
+    /// The deadline for a lookup which is starting now, if retries are enabled.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.timeout.map(|timeout| Instant::now() + timeout)
+    }
+
+    /// Calls `f` once `RETRY_DELAY` has elapsed.
+    ///
+    /// This must not be called from within a retry's callback, since timers which have already fired
+    /// are dropped here (and a timer can't be dropped inside its own callback).
+    pub fn schedule<F>(&mut self, ctx: &Context, handle: &LoopHandle, mut f: F)
+    where
+        F: FnMut() + 'static,
+    {
+        self.timers.retain(|(_, fired)| !fired.get());
+
+        let fired = Rc::new(Cell::new(false));
+        let timer = handle.timer(ctx, RETRY_DELAY, {
+            let fired = fired.clone();
+            move |_| {
+                fired.set(true);
+                f();
+            }
+        });
+
+        if let Some(timer) = timer {
+            self.timers.push((timer, fired));
+        }
+    }
+}
@@ -0,0 +1,227 @@
+use std::collections::HashMap;
+use std::sync::mpsc::SendError;
+use std::time::{Duration, Instant};
+
+use super::api::{EventFilter, PAEvent, PAIdent, PAMask, PAServerInfo};
+use super::timer::Timer;
+use crate::sender::EventSender;
+
+/// State of the current subscription, shared between the subscribe callback and any callbacks used
+/// to compute derived events.
+pub struct Subscription {
+    tx: Option<Box<dyn EventSender>>,
+    /// Events which don't pass are dropped rather than sent
+    filter: Option<EventFilter>,
+    pub mask: PAMask,
+    pub sinks: SinkActivity,
+    pub corks: StreamCorks,
+    pub recordings: Recordings,
+    pub defaults: Defaults,
+}
+
+impl Default for Subscription {
+    fn default() -> Self {
+        Subscription {
+            tx: None,
+            filter: None,
+            mask: PAMask::empty(),
+            sinks: SinkActivity::default(),
+            corks: StreamCorks::default(),
+            recordings: Recordings::default(),
+            defaults: Defaults::default(),
+        }
+    }
+}
+
+impl Subscription {
+    pub fn start(&mut self, mask: PAMask, filter: Option<EventFilter>, tx: Box<dyn EventSender>) {
+        self.tx = Some(tx);
+        self.filter = filter;
+        self.mask = mask;
+        self.sinks.reset();
+        self.corks = StreamCorks::default();
+        self.recordings = Recordings::default();
+        self.defaults = Defaults::default();
+    }
+
+    pub fn stop(&mut self) {
+        self.tx = None;
+        self.filter = None;
+        self.mask = PAMask::empty();
+        self.sinks.reset();
+        self.corks = StreamCorks::default();
+        self.recordings = Recordings::default();
+        self.defaults = Defaults::default();
+    }
+
+    /// Sends an event, unless it's filtered out (which isn't an error). This means a subscriber
+    /// which has gone away isn't noticed until an event passes the filter.
+    pub fn send(&self, ev: PAEvent) -> Result<(), SendError<PAEvent>> {
+        match (&self.tx, &self.filter) {
+            (Some(_), Some(filter)) if !filter.matches(&ev) => Ok(()),
+            (Some(tx), _) => tx.send(ev),
+            (None, _) => Err(SendError(ev)),
+        }
+    }
+}
+
+/// Tracks which sinks are active (running, i.e.: have at least one uncorked stream) so that
+/// `SinkBecameActive`, `SinkBecameIdle` and `AllSinksIdle` events can be derived from sink changes.
+#[derive(Default)]
+pub struct SinkActivity {
+    /// Whether each known sink (by index) is currently running
+    running: HashMap<u32, bool>,
+    /// When the last sink stopped running, if no sinks are running
+    idle_since: Option<Instant>,
+    /// How long all sinks must be idle before an `AllSinksIdle` event is emitted
+    pub idle_timeout: Option<Duration>,
+    /// Armed while all sinks are idle and an `idle_timeout` is set
+    pub timer: Option<Timer>,
+}
+
+impl SinkActivity {
+    fn reset(&mut self) {
+        // no sinks are known to be running yet
+        self.running.clear();
+        self.idle_since = Some(Instant::now());
+        self.timer = None;
+    }
+
+    /// Records the state of a sink, `None` means the sink was removed. Returns the derived event, if
+    /// the sink's activity changed.
+    pub fn update(&mut self, index: u32, running: Option<bool>) -> Option<PAEvent> {
+        let was_running = match running {
+            Some(running) => self.running.insert(index, running),
+            None => self.running.remove(&index),
+        }
+        .unwrap_or(false);
+
+        let ev = match (was_running, running.unwrap_or(false)) {
+            (false, true) => Some(PAEvent::SinkBecameActive(PAIdent::Index(index))),
+            (true, false) => Some(PAEvent::SinkBecameIdle(PAIdent::Index(index))),
+            _ => None,
+        };
+
+        if self.running.values().any(|running| *running) {
+            self.idle_since = None;
+            self.timer = None;
+        } else if self.idle_since.is_none() {
+            self.idle_since = Some(Instant::now());
+        }
+
+        ev
+    }
+
+    /// How long all sinks have been idle for, or `None` if any sink is running.
+    pub fn idle_for(&self) -> Option<Duration> {
+        self.idle_since.map(|since| since.elapsed())
+    }
+
+    /// Whether an idle timer should be armed, and if so, how long until it should fire.
+    pub fn idle_remaining(&self) -> Option<Duration> {
+        match (self.idle_timeout, self.idle_for(), &self.timer) {
+            (Some(timeout), Some(idle_for), None) => Some(timeout.saturating_sub(idle_for)),
+            _ => None,
+        }
+    }
+}
+
+/// Tracks which sink inputs are corked (paused), so that `StreamCorked` and `StreamUncorked` events
+/// can be derived from sink input changes.
+#[derive(Default)]
+pub struct StreamCorks {
+    /// Whether each known sink input (by index) is corked
+    corked: HashMap<u32, bool>,
+}
+
+impl StreamCorks {
+    /// Records the state of a sink input, `None` means it was removed. Returns the derived event if
+    /// the stream was already known and its state changed; the first sighting only seeds the cache.
+    pub fn update(&mut self, index: u32, corked: Option<bool>) -> Option<PAEvent> {
+        let was_corked = match corked {
+            Some(corked) => self.corked.insert(index, corked),
+            None => self.corked.remove(&index),
+        };
+
+        match (was_corked, corked) {
+            (Some(false), Some(true)) => Some(PAEvent::StreamCorked(PAIdent::Index(index))),
+            (Some(true), Some(false)) => Some(PAEvent::StreamUncorked(PAIdent::Index(index))),
+            _ => None,
+        }
+    }
+}
+
+/// Tracks which source outputs are recording, so that `RecordingStarted` and `RecordingStopped`
+/// events can be derived from source output changes.
+#[derive(Default)]
+pub struct Recordings {
+    /// The application of each source output (by index) which is recording
+    recording: HashMap<u32, Option<String>>,
+}
+
+impl Recordings {
+    /// Records the state of a source output, `None` means it isn't recording (it was removed, it's
+    /// corked or it isn't recording from a microphone). `Some` holds its application and source.
+    /// Returns the derived event if it started or stopped recording.
+    pub fn update(
+        &mut self,
+        index: u32,
+        recording: Option<(Option<String>, u32)>,
+    ) -> Option<PAEvent> {
+        match recording {
+            Some((app, source)) => match self.recording.insert(index, app.clone()) {
+                Some(_) => None,
+                None => Some(PAEvent::RecordingStarted {
+                    id: PAIdent::Index(index),
+                    app,
+                    source: PAIdent::Index(source),
+                }),
+            },
+            None => self
+                .recording
+                .remove(&index)
+                .map(|app| PAEvent::RecordingStopped {
+                    id: PAIdent::Index(index),
+                    app,
+                }),
+        }
+    }
+}
+
+/// Caches the default sink and source so that `DefaultSinkChanged` and `DefaultSourceChanged`
+/// events can be derived from server changes.
+#[derive(Default)]
+pub struct Defaults {
+    /// Whether the defaults have been read from the server yet
+    seeded: bool,
+    sink: Option<String>,
+    source: Option<String>,
+}
+
+impl Defaults {
+    /// Records the current server info, returning an event for each default which changed. The
+    /// first call only seeds the cache.
+    pub fn update(&mut self, info: &PAServerInfo) -> Vec<PAEvent> {
+        let sink = std::mem::replace(&mut self.sink, info.default_sink_name.clone());
+        let source = std::mem::replace(&mut self.source, info.default_source_name.clone());
+        if !std::mem::replace(&mut self.seeded, true) {
+            return vec![];
+        }
+
+        let mut events = vec![];
+        if sink != self.sink {
+            events.push(PAEvent::DefaultSinkChanged {
+                old: sink.map(PAIdent::Name),
+                new: self.sink.clone().map(PAIdent::Name),
+            });
+        }
+        if source != self.source {
+            events.push(PAEvent::DefaultSourceChanged {
+                old: source.map(PAIdent::Name),
+                new: self.source.clone().map(PAIdent::Name),
+            });
+        }
+
+        events
+    }
+}
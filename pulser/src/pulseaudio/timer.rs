@@ -0,0 +1,106 @@
+use std::rc::Rc;
+use std::time::Duration;
+
+use libpulse_binding::context::Context;
+use libpulse_binding::mainloop::api::{Mainloop as MainloopTrait, MainloopInner};
+use libpulse_binding::mainloop::events::timer::{TimeEvent, TimeEventRef};
+use libpulse_binding::mainloop::{standard, threaded};
+use libpulse_binding::time::MonotonicTs;
+
+type ThreadedInner = MainloopInner<threaded::MainloopInternal>;
+type StandardInner = MainloopInner<standard::MainloopInternal>;
+
+/// A timer event source; the timer is destroyed when this is dropped.
+pub enum Timer {
+    Threaded(TimeEvent<ThreadedInner>),
+    Standard(TimeEvent<StandardInner>),
+}
+
+impl Timer {
+    /// Re-arms the timer to fire at `t`.
+    pub fn restart_rt(&mut self, t: MonotonicTs) {
+        match self {
+            Timer::Threaded(timer) => timer.restart_rt(t),
+            Timer::Standard(timer) => timer.restart_rt(t),
+        }
+    }
+}
+
+/// Reference to a timer passed into its own callback, can be used to re-arm it.
+pub enum TimerRef {
+    Threaded(TimeEventRef<ThreadedInner>),
+    Standard(TimeEventRef<StandardInner>),
+}
+
+impl TimerRef {
+    /// Re-arms the timer to fire at `t`.
+    pub fn restart_rt(&mut self, t: MonotonicTs) {
+        match self {
+            TimerRef::Threaded(timer) => timer.restart_rt(t),
+            TimerRef::Standard(timer) => timer.restart_rt(t),
+        }
+    }
+}
+
+/// A cheap handle to the mainloop, used to create timers.
+///
+/// Timers are usually created from within PulseAudio callbacks, where the `RefCell` wrapping the
+/// `Mainloop` can't be borrowed (the command thread may be holding it while waiting on the lock,
+/// or the caller may be holding it while iterating the loop). This only holds onto the inner
+/// mainloop, which is all that's required to create timer events.
+#[derive(Clone)]
+pub enum LoopHandle {
+    Threaded(Rc<ThreadedInner>),
+    Standard(Rc<StandardInner>),
+}
+
+impl LoopHandle {
+    pub fn threaded(mainloop: &threaded::Mainloop) -> LoopHandle {
+        LoopHandle::Threaded(mainloop.inner())
+    }
+
+    pub fn standard(mainloop: &standard::Mainloop) -> LoopHandle {
+        LoopHandle::Standard(mainloop.inner())
+    }
+
+    /// Creates a timer which will call `f` once `after` has elapsed. The callback runs on the
+    /// mainloop's thread (with the mainloop lock held, if it's threaded), just like any other
+    /// PulseAudio callback.
+    pub fn timer<F>(&self, ctx: &Context, after: Duration, mut f: F) -> Option<Timer>
+    where
+        F: FnMut(TimerRef) + 'static,
+    {
+        let at = MonotonicTs::now() + after;
+        match self {
+            LoopHandle::Threaded(inner) => ctx
+                .rttime_new::<Handle<ThreadedInner>, _>(&Handle(inner.clone()), at, move |t| {
+                    f(TimerRef::Threaded(t))
+                })
+                .map(Timer::Threaded),
+            LoopHandle::Standard(inner) => ctx
+                .rttime_new::<Handle<StandardInner>, _>(&Handle(inner.clone()), at, move |t| {
+                    f(TimerRef::Standard(t))
+                })
+                .map(Timer::Standard),
+        }
+    }
+}
+
+/// Used to pass the inner mainloop to `Context::rttime_new`, which expects a `Mainloop`.
+struct Handle<T>(Rc<T>);
+
+impl MainloopTrait for Handle<ThreadedInner> {
+    type MI = ThreadedInner;
+
+    fn inner(&self) -> Rc<ThreadedInner> {
+        self.0.clone()
+    }
+}
+
+impl MainloopTrait for Handle<StandardInner> {
+    type MI = StandardInner;
+
+    fn inner(&self) -> Rc<StandardInner> {
+        self.0.clone()
+    }
+}
@@ -1,57 +1,138 @@
 use libpulse_binding::channelmap::{Map, Position};
 use libpulse_binding::volume::{ChannelVolumes, Volume};
-use libpulse_sys::{pa_channel_map, pa_cvolume};
 
-use super::api::{VolumeReadings, VolumeSpec};
-
-pub fn new_channel_volumes(volumes: Vec<Volume>) -> ChannelVolumes {
-    let mut inner = pa_cvolume::default();
-    inner.channels = volumes.len() as u8;
-    for (i, vol) in volumes.into_iter().enumerate() {
-        inner.values[i] = vol.0;
-    }
-
-    // is this really the only way to create a `ChannelVolumes`?
-    inner.into()
-}
+use super::api::{InvalidChannelCount, PAPosition, PAVol, VolumeReadings, VolumeSpec};
+use super::volume::new_channel_volumes;
 
 pub fn updated_channel_volumes(
     current: ChannelVolumes,
+    map: &Map,
     volume_spec: &VolumeSpec,
-) -> ChannelVolumes {
+) -> Result<ChannelVolumes, InvalidChannelCount> {
     match volume_spec {
         VolumeSpec::All(vol) => {
             let mut cv = current.clone();
             cv.set(current.len(), (*vol).into());
-            cv
+            Ok(cv)
         }
         VolumeSpec::Channels(vols) => {
             let volumes: Vec<Volume> = vols.into_iter().map(|v| (*v).into()).collect();
-            // TODO: return an error here, rather than asserting
-            assert!(
-                volumes.len() as u8 == current.len(),
-                "Failed to set volumes. Provided channel count: {}, actual count: {}",
-                volumes.len(),
-                current.len()
-            );
-            new_channel_volumes(volumes)
+            if volumes.len() != current.len() as usize {
+                return Err(InvalidChannelCount::new(current.len(), volumes.len()));
+            }
+
+            Ok(new_channel_volumes(volumes))
+        }
+        VolumeSpec::Mapped(from, vols) => {
+            let volumes: Vec<Volume> = vols.iter().map(|v| (*v).into()).collect();
+            if volumes.len() != from.0.len() {
+                return Err(InvalidChannelCount::new(from.0.len() as u8, volumes.len()));
+            }
+
+            let mut cv = remap_volumes(new_channel_volumes(volumes), &from.to_map(), map);
+
+            // the LFE channel isn't on either side, so libpulse would set it to the average of all
+            // the channels; leave it alone instead, since it's usually balanced separately
+            if !from.0.contains(&PAPosition::Lfe) {
+                for (i, pos) in map.get().iter().enumerate() {
+                    if *pos == Position::Lfe {
+                        cv.get_mut()[i] = current.get()[i];
+                    }
+                }
+            }
+
+            Ok(cv)
+        }
+        VolumeSpec::RelativeToDevice(pct) => {
+            updated_channel_volumes(current, map, &VolumeSpec::All(PAVol::Percentage(*pct)))
         }
     }
 }
 
-pub fn new_channel_map(channels: Vec<Position>) -> Map {
-    let mut inner = pa_channel_map::default();
-    inner.channels = channels.len() as u8;
-    for (i, chan) in channels.into_iter().enumerate() {
-        inner.map[i] = chan.into();
+/// The volumes a stream needs so that its output is `pct`% of its device's volume. With flat
+/// volumes, stream volumes are absolute, so the device's volumes are scaled (and remapped onto the
+/// stream's channels); otherwise stream volumes are already relative to the device.
+pub fn relative_channel_volumes(
+    device: ChannelVolumes,
+    device_map: &Map,
+    flat_volume: bool,
+    stream_map: &Map,
+    pct: f64,
+) -> ChannelVolumes {
+    let scale: Volume = PAVol::Percentage(pct).into();
+    match flat_volume {
+        true => {
+            let mut cv = remap_volumes(device, device_map, stream_map);
+            cv.sw_multiply_scalar(scale);
+            cv
+        }
+        false => {
+            let mut cv = ChannelVolumes::default();
+            cv.set(stream_map.len(), scale);
+            cv
+        }
+    }
+}
+
+/// Remaps volumes from one channel map onto another, such as stereo volumes onto a 5.1 device. Each
+/// channel of `to` takes the average volume of the channels in `from` on the same side (left, right
+/// or center), or the average of all of them if there are none.
+pub fn remap_volumes(mut cv: ChannelVolumes, from: &Map, to: &Map) -> ChannelVolumes {
+    cv.remap(from, to);
+    cv
+}
+
+/// Lowers any volumes which are above `limit` down to it
+pub fn limit_channel_volumes(mut cv: ChannelVolumes, limit: Option<Volume>) -> ChannelVolumes {
+    if let Some(limit) = limit {
+        for vol in cv.get_mut() {
+            if *vol > limit {
+                *vol = limit;
+            }
+        }
     }
 
-    // is this really the only way to create a `Map`?
-    inner.into()
+    cv
 }
 
 impl From<VolumeReadings> for ChannelVolumes {
     fn from(value: VolumeReadings) -> Self {
-        new_channel_volumes(value.inner.into_iter().map(|v| v.volume.0).collect())
+        new_channel_volumes(value.inner.into_iter().map(|v| v.volume.into()).collect())
+    }
+}
+
+/// Splits a list of parameters from PulseAudio's message API (such as `{a}{b}{{c}{d}}`) into the
+/// contents of each top-level group (`a`, `b` and `{c}{d}`). Returns `None` if it's malformed.
+pub fn split_message_params(params: &str) -> Option<Vec<&str>> {
+    let mut groups = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+    let mut escaped = false;
+    for (i, c) in params.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '{' => {
+                if depth == 0 {
+                    start = i + 1;
+                }
+                depth += 1;
+            }
+            '}' => {
+                depth -= 1;
+                match depth {
+                    0 => groups.push(&params[start..i]),
+                    d if d < 0 => return None,
+                    _ => {}
+                }
+            }
+            c if depth == 0 && !c.is_whitespace() => return None,
+            _ => {}
+        }
+    }
+
+    match depth {
+        0 => Some(groups),
+        _ => None,
     }
 }
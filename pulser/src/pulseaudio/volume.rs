@@ -0,0 +1,75 @@
+//! Helpers for building libpulse's volume types, and converting volumes between the raw values
+//! PulseAudio uses (`pa_volume_t`) and percentages, decibels or linear factors. These are what
+//! `PAVol` and `PAVolume` use, so a UI which shows volumes gets the same numbers as the CLI.
+//!
+//! The conversions are the same as libpulse's (`pa_sw_volume_to_dB` and friends), but don't call
+//! into it, so they're available without the `backend` feature.
+
+#[cfg(feature = "backend")]
+use libpulse_binding::channelmap::{Map, Position};
+#[cfg(feature = "backend")]
+use libpulse_binding::volume::{ChannelVolumes, Volume};
+
+use crate::api::PAVolume;
+
+/// Creates channel volumes with a volume for each channel, in order. Panics if there are more
+/// than `libpulse_binding::sample::CHANNELS_MAX` volumes.
+#[cfg(feature = "backend")]
+pub fn new_channel_volumes(volumes: Vec<Volume>) -> ChannelVolumes {
+    let mut cv = ChannelVolumes::default();
+    cv.set_len(volumes.len() as u8);
+    cv.get_mut().copy_from_slice(&volumes);
+    cv
+}
+
+/// Creates a channel map with the given positions, in order. Panics if there are more than
+/// `libpulse_binding::sample::CHANNELS_MAX` positions.
+#[cfg(feature = "backend")]
+pub fn new_channel_map(channels: Vec<Position>) -> Map {
+    let mut map = Map::default();
+    map.set_len(channels.len() as u8);
+    map.get_mut().copy_from_slice(&channels);
+    map
+}
+
+/// Converts a percentage (where `100.0` is `PAVolume::NORMAL`) to a volume. Negative percentages
+/// are treated as muted.
+pub fn percentage_to_volume(pct: f64) -> PAVolume {
+    // libpulse doesn't seem to offer a way to calculate percentages...
+    PAVolume((PAVolume::NORMAL.0 as f64 * (pct / 100.0)) as u32)
+}
+
+/// Converts a volume to a percentage, where `PAVolume::NORMAL` is `100.0`
+pub fn volume_to_percentage(vol: PAVolume) -> f64 {
+    (vol.0 as f64 / (PAVolume::NORMAL.0 as f64)) * 100.0
+}
+
+/// Converts decibels (where `0.0` is `PAVolume::NORMAL`) to a volume, using PulseAudio's cubic
+/// software volume curve
+pub fn decibels_to_volume(db: f64) -> PAVolume {
+    linear_to_volume(10.0_f64.powf(db / 20.0))
+}
+
+/// Converts a volume to decibels, which is `f64::NEG_INFINITY` when it's muted
+pub fn volume_to_decibels(vol: PAVolume) -> f64 {
+    match vol.0 {
+        0 => f64::NEG_INFINITY,
+        _ => 20.0 * volume_to_linear(vol).log10(),
+    }
+}
+
+/// Converts a linear factor (where `1.0` is `PAVolume::NORMAL`) to a volume
+pub fn linear_to_volume(lin: f64) -> PAVolume {
+    if lin <= 0.0 {
+        return PAVolume::MUTED;
+    }
+
+    let value = (lin.cbrt() * PAVolume::NORMAL.0 as f64).round();
+    PAVolume(value.min(PAVolume::MAX.0 as f64) as u32)
+}
+
+/// Converts a volume to a linear factor, where `PAVolume::NORMAL` is `1.0`
+pub fn volume_to_linear(vol: PAVolume) -> f64 {
+    let f = vol.0 as f64 / PAVolume::NORMAL.0 as f64;
+    f * f * f
+}
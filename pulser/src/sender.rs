@@ -13,3 +13,9 @@ impl EventSender for Sender<PAEvent> {
         self.send(ev)
     }
 }
+
+impl EventSender for crossbeam_channel::Sender<PAEvent> {
+    fn send(&self, ev: PAEvent) -> Result<(), SendError<PAEvent>> {
+        self.send(ev).map_err(|e| SendError(e.0))
+    }
+}
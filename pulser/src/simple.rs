@@ -1,48 +1,232 @@
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
 use std::error::Error;
-use std::fmt::Display;
-use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender};
-use std::time::Duration;
-
+use std::fmt::{Debug, Display};
+use std::fs::File;
+use std::io::{self, BufReader, ErrorKind, Read};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::{self as channel, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use libpulse_binding::volume::Volume;
 use serde::Serialize;
 
 use crate::api::*;
-use crate::mainloop::PulseAudioLoop;
+use crate::decode::decode;
+use crate::ignore::Ignore;
+use crate::mainloop::{CancelToken, ConnectOptions, Cookie, PulseAudioLoop, RetryPolicy};
+#[cfg(feature = "pa_v15")]
+use crate::pulseaudio::util::split_message_params;
 use crate::sender::EventSender;
 
-macro_rules! assume_variant {
-    ($event:expr, $pattern:pat => $mapping:expr) => {
-        match $event {
-            $pattern => Ok($mapping),
-            PAResponse::OpError(s) => Err((OperationResult::Failure { error: s }).into()),
-            ev => Err(format!("Expected {} but received {:?}", stringify!($pattern), ev).into()),
-        }
-    };
-}
+/// How much raw audio is read at a time when playing
+const RAW_CHUNK_SIZE: usize = 4096;
+/// How many chunks of raw audio can be waiting to be played, before reading blocks
+const RAW_BUFFERED_CHUNKS: usize = 16;
+/// How often levels are reported by `PulseAudio::monitor_stream_levels`
+const LEVEL_INTERVAL: Duration = Duration::from_millis(40);
+/// The spec of the streams used to read peak levels, one peak for each interval
+const LEVEL_SPEC: RawSpec = RawSpec {
+    format: RawFormat::F32le,
+    rate: 25,
+    channels: 1,
+};
 
 #[derive(Debug, Serialize)]
 #[serde(tag = "type", rename_all = "lowercase")]
 pub enum OperationResult {
     Success,
-    Failure { error: String },
+    /// The operation succeeded, and this is the state the server ended up with. Only returned when
+    /// connected with `Builder::confirm`.
+    Confirmed {
+        state: ConfirmedState,
+    },
+    Failure {
+        error: String,
+        /// The libpulse error code, if the server rejected the operation
+        #[serde(skip_serializing_if = "Option::is_none")]
+        code: Option<i32>,
+        /// Set if the volumes provided didn't match the number of channels of the object, so
+        /// which object (and its channel map) caused it can be reported
+        #[serde(skip_serializing_if = "Option::is_none")]
+        channel_count: Option<InvalidChannelCount>,
+    },
+    /// A conditional operation wasn't performed, since the current value didn't match
+    Conflict {
+        error: String,
+    },
 }
 
 impl Display for OperationResult {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             OperationResult::Success => f.write_str("success"),
-            OperationResult::Failure { error } => f.write_str(error),
+            OperationResult::Confirmed { .. } => f.write_str("success"),
+            OperationResult::Failure { error, .. } => f.write_str(error),
+            OperationResult::Conflict { error } => f.write_str(error),
         }
     }
 }
 
 impl Error for OperationResult {}
 
+impl OperationResult {
+    pub fn is_success(&self) -> bool {
+        matches!(
+            self,
+            OperationResult::Success | OperationResult::Confirmed { .. }
+        )
+    }
+
+    /// Why the operation failed, or `None` if it didn't (a conflict isn't a failure)
+    pub fn error_kind(&self) -> Option<crate::api::ErrorKind> {
+        match self {
+            OperationResult::Failure {
+                channel_count: Some(e),
+                ..
+            } => Some(crate::api::ErrorKind::InvalidChannelCount(e.clone())),
+            OperationResult::Failure { code, .. } => Some(crate::api::ErrorKind::from_code(*code)),
+            _ => None,
+        }
+    }
+}
+
+/// The state of an object after a setter succeeded, see `OperationResult::Confirmed`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfirmedState {
+    /// The volume which was applied, which PulseAudio may have rounded or limited
+    Volume(VolumeReadings),
+    Mute(bool),
+}
+
+impl From<OpError> for OperationResult {
+    fn from(value: OpError) -> Self {
+        OperationResult::Failure {
+            error: value.message,
+            code: value.code,
+            channel_count: value.channel_count,
+        }
+    }
+}
+
+/// How long to wait for the connection to shut down, if no timeout was set when connecting
+const DISCONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+
 pub type Result<T> = std::result::Result<T, Box<dyn Error>>;
 
 // TODO: docs on when disconnect occurs
 pub struct PulseAudio {
     tx: Sender<PACommand>,
     rx: Receiver<PAResponse>,
+    /// The request (as it's debug formatted, so it includes the arguments) which a non-blocking
+    /// getter is waiting for a response to, if any
+    pending: RefCell<Option<String>>,
+    /// How long to wait for a response before giving up
+    timeout: Option<Duration>,
+    /// How many commands timed out before their response was received. Their responses are
+    /// dropped when they arrive (responses arrive in order), so they aren't mistaken for the
+    /// responses to later commands. This counts on every command being answered by exactly one
+    /// response, so a command sent with `send` which has none (or several) throws it off.
+    late: Cell<usize>,
+    /// Whether `disconnect` has been called
+    disconnected: Cell<bool>,
+    /// When the last command was sent, used to log how long responses take
+    sent_at: Cell<Option<Instant>>,
+    /// How much to step volumes by, for devices which support arbitrary volumes
+    volume_step: f64,
+    /// Whether volume and mute setters fetch the resulting state after succeeding
+    confirm: bool,
+    /// Shared with the mainloop, see `PulseAudio::cancel_pending`
+    cancel: CancelToken,
+}
+
+/// Used to configure a connection, see `PulseAudio::builder`
+#[derive(Debug, Clone)]
+pub struct Builder {
+    options: ConnectOptions,
+    timeout: Option<Duration>,
+    volume_step: f64,
+    confirm: bool,
+}
+
+impl Builder {
+    /// The name of the application, defaults to `PulseAudio::DEFAULT_NAME`
+    pub fn name(mut self, name: impl Into<String>) -> Builder {
+        self.options.name = name.into();
+        self
+    }
+
+    /// The server to connect to (such as "tcp:host:port"), otherwise the default server is used
+    pub fn server(mut self, server: impl Into<String>) -> Builder {
+        self.options.server = Some(server.into());
+        self
+    }
+
+    /// The file containing the authentication cookie, such as a copy of the remote server's
+    /// `~/.config/pulse/cookie`. Otherwise libpulse looks for one in the usual places.
+    pub fn cookie_path(mut self, path: impl Into<PathBuf>) -> Builder {
+        self.options.cookie = Some(Cookie::Path(path.into()));
+        self
+    }
+
+    /// The authentication cookie itself, which must be `COOKIE_LENGTH` (256) bytes
+    pub fn cookie(mut self, cookie: impl Into<Vec<u8>>) -> Builder {
+        self.options.cookie = Some(Cookie::Bytes(cookie.into()));
+        self
+    }
+
+    /// How long to wait for each response before failing, otherwise calls wait indefinitely
+    pub fn timeout(mut self, timeout: Duration) -> Builder {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// How to retry if connecting fails, otherwise only one attempt is made
+    pub fn retry(mut self, policy: RetryPolicy) -> Builder {
+        self.options.retry = policy;
+        self
+    }
+
+    /// The maximum volume which can be set, where `1.0` is 100%. Volumes above this are lowered to
+    /// the limit when they're set.
+    pub fn volume_limit(mut self, limit: f64) -> Builder {
+        self.options.volume_limit = Some(limit);
+        self
+    }
+
+    /// How much `step_*_volume` changes the volume by for each step, where `1.0` is 100%. This is
+    /// only used for devices which support arbitrary volumes, otherwise the device's own steps are
+    /// used. Defaults to `0.05` (5%).
+    pub fn volume_step(mut self, step: f64) -> Builder {
+        self.volume_step = step;
+        self
+    }
+
+    /// Whether the volume and mute setters (such as `set_sink_volume`) fetch the state after
+    /// they succeed, and return it as `OperationResult::Confirmed`. This costs an extra request,
+    /// but shows the volume PulseAudio actually applied. Defaults to `false`.
+    pub fn confirm(mut self, confirm: bool) -> Builder {
+        self.confirm = confirm;
+        self
+    }
+
+    pub fn connect(self) -> PulseAudio {
+        let cancel = self.options.cancel.clone();
+        let (tx, rx) = PulseAudioLoop::start_with(self.options);
+        PulseAudio {
+            tx,
+            rx,
+            pending: RefCell::new(None),
+            timeout: self.timeout,
+            late: Cell::new(0),
+            disconnected: Cell::new(false),
+            sent_at: Cell::new(None),
+            volume_step: self.volume_step,
+            confirm: self.confirm,
+            cancel,
+        }
+    }
 }
 
 macro_rules! impl_find {
@@ -61,6 +245,15 @@ macro_rules! impl_find {
     };
 }
 
+macro_rules! impl_try_get {
+    ($name:ident($($arg:ident: $ty:ty),*) -> $ret:ty, $req:expr) => {
+        /// Non-blocking version of the getter without the `try_` prefix, see `try_request`.
+        pub fn $name(&self, $($arg: $ty),*) -> Result<$ret> {
+            self.try_request($req)
+        }
+    };
+}
+
 impl PulseAudio {
     pub const DEFAULT_NAME: &str = "Pulser";
 
@@ -69,14 +262,66 @@ impl PulseAudio {
     impl_find!(SinkInputInfo);
     impl_find!(SourceOutputInfo);
 
+    pub fn builder() -> Builder {
+        Builder {
+            options: ConnectOptions::new(Self::DEFAULT_NAME),
+            timeout: None,
+            volume_step: 0.05,
+            confirm: false,
+        }
+    }
+
+    /// Connects with the default options, and the given name. See `PulseAudio::builder` for more
+    /// options.
     pub fn connect(name: Option<&str>) -> PulseAudio {
-        let name = name
-            .map(|s| s.as_ref())
-            .unwrap_or(Self::DEFAULT_NAME)
-            .to_owned();
+        Self::builder()
+            .name(name.unwrap_or(Self::DEFAULT_NAME))
+            .connect()
+    }
+
+    /// Whether the connection to PulseAudio is still up. If the connection is lost, no other calls
+    /// will succeed and a new connection will need to be made.
+    pub fn is_connected(&self) -> bool {
+        if self.disconnected.get() || self.send(PACommand::IsConnected).is_err() {
+            return false;
+        }
+
+        matches!(self.recv(), Ok(PAResponse::Connected(true)))
+    }
+
+    /// Disconnects from PulseAudio, waiting until the connection has been shut down. This also
+    /// happens when `PulseAudio` is dropped, but any errors are ignored there.
+    pub fn disconnect(self) -> Result<()> {
+        self.shutdown()
+    }
+
+    fn shutdown(&self) -> Result<()> {
+        if self.disconnected.replace(true) {
+            return Ok(());
+        }
+
+        // the response to a pending non-blocking getter is skipped below, with any others
+        self.pending.borrow_mut().take();
+        // if this fails, the loop has already stopped (after a `PAResponse::Fatal`)
+        if self.send(PACommand::Disconnect).is_err() {
+            return Ok(());
+        }
 
-        let (tx, rx) = PulseAudioLoop::start(name);
-        PulseAudio { tx, rx }
+        loop {
+            // skip any responses which haven't been received yet (from `send`, etc)
+            match self
+                .rx
+                .recv_timeout(self.timeout.unwrap_or(DISCONNECT_TIMEOUT))
+            {
+                Ok(PAResponse::Disconnected) => return Ok(()),
+                Ok(_) => continue,
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err("Timed out while disconnecting".into())
+                }
+                // the loop stopped before it received the command
+                Err(RecvTimeoutError::Disconnected) => return Ok(()),
+            }
+        }
     }
 
     /*
@@ -84,27 +329,148 @@ impl PulseAudio {
      */
 
     pub fn get_server_info(&self) -> Result<PAServerInfo> {
-        self.tx.send(PACommand::GetServerInfo)?;
-        assume_variant!(self.rx.recv()?, PAResponse::ServerInfo(x) => x)
+        self.request(request::GetServerInfo)
+    }
+
+    /// Returns the protocol versions of the library and the server, along with other details
+    /// about the connection.
+    pub fn get_protocol_info(&self) -> Result<ProtocolInfo> {
+        self.request(request::GetProtocolInfo)
+    }
+
+    /// Returns what the server supports, as probed when connecting.
+    pub fn capabilities(&self) -> Result<Capabilities> {
+        self.request(request::GetCapabilities)
+    }
+
+    /// Inspects the server's info and protocol version to determine what it is (such as
+    /// pipewire-pulse) and what it supports.
+    pub fn get_server_capabilities(&self) -> Result<ServerCapabilities> {
+        let info = self.get_server_info()?;
+        let protocol = self.get_protocol_info()?;
+        Ok(ServerCapabilities::new(&info, Some(&protocol)))
+    }
+
+    /// Sends a message to an object which has registered a message handler, returning its response.
+    /// Use `"/core"` with `"list-handlers"` to list the objects which can receive messages.
+    #[cfg(feature = "pa_v15")]
+    pub fn send_message(
+        &self,
+        recipient: impl Into<String>,
+        message: impl Into<String>,
+        params: impl Into<String>,
+    ) -> Result<Option<String>> {
+        self.request(request::SendMessage(
+            recipient.into(),
+            message.into(),
+            params.into(),
+        ))
     }
 
     pub fn get_default_sink(&self) -> Result<Option<PAIdent>> {
-        self.tx.send(PACommand::GetDefaultSink)?;
-        assume_variant!(self.rx.recv()?, PAResponse::DefaultSink(x) => x)
+        self.request(request::GetDefaultSink)
     }
 
     pub fn set_default_sink(&self, id: PAIdent) -> Result<OperationResult> {
-        self.tx.send(PACommand::SetDefaultSink(id))?;
+        self.send(PACommand::SetDefaultSink(id))?;
         self.operation_result()
     }
 
+    /// Sets the default sink to the most preferred available sink, moving all streams to it if the
+    /// preference says so. Returns the chosen sink, or `None` if no preferred sink is available (in
+    /// which case nothing is changed).
+    pub fn apply_sink_preference(&self, pref: &SinkPreference) -> Result<Option<PASinkInfo>> {
+        let sinks = self.get_sink_info_list()?;
+        let sink = match pref.choose(&sinks) {
+            Some(sink) => sink.clone(),
+            None => return Ok(None),
+        };
+
+        match self.set_default_sink(PAIdent::Index(sink.index))? {
+            OperationResult::Success => {}
+            result => return Err(result.into()),
+        }
+
+        if pref.move_streams {
+            self.move_sink_inputs_to(sink.index)?;
+        }
+
+        Ok(Some(sink))
+    }
+
+    /// Sets whichever of the two sinks isn't the default as the default sink (or the first, if
+    /// neither is), optionally moving all streams to it. Each sink is matched by index or name, or
+    /// else by its description (case-insensitively). Returns the new default sink.
+    pub fn toggle_default_sink(
+        &self,
+        a: PAIdent,
+        b: PAIdent,
+        move_streams: bool,
+    ) -> Result<PASinkInfo> {
+        let sinks = self.get_sink_info_list()?;
+        let a = Self::resolve_sink(&sinks, &a)?;
+        let b = Self::resolve_sink(&sinks, &b)?;
+
+        let a_is_default = match self.get_default_sink()? {
+            Some(PAIdent::Index(idx)) => idx == a.index,
+            Some(PAIdent::Name(name)) => a.name.as_ref() == Some(&name),
+            None => false,
+        };
+        let sink = if a_is_default { b } else { a };
+
+        match self.set_default_sink(PAIdent::Index(sink.index))? {
+            OperationResult::Success => {}
+            result => return Err(result.into()),
+        }
+
+        if move_streams {
+            self.move_sink_inputs_to(sink.index)?;
+        }
+
+        Ok(sink.clone())
+    }
+
+    fn resolve_sink<'a>(sinks: &'a [PASinkInfo], id: &PAIdent) -> Result<&'a PASinkInfo> {
+        let found = match id {
+            PAIdent::Index(idx) => sinks.iter().find(|sink| sink.index == *idx),
+            PAIdent::Name(name) => sinks
+                .iter()
+                .find(|sink| sink.name.as_ref() == Some(name))
+                .or_else(|| {
+                    sinks.iter().find(|sink| {
+                        sink.description
+                            .as_deref()
+                            .map_or(false, |d| d.eq_ignore_ascii_case(name))
+                    })
+                }),
+        };
+
+        found.ok_or_else(|| format!("No sink found matching: {}", id).into())
+    }
+
+    /// Moves every sink input which isn't already playing to the sink. Stops early if
+    /// `cancel_pending` is called in the meantime.
+    fn move_sink_inputs_to(&self, sink: u32) -> Result<()> {
+        let generation = self.cancel.generation();
+        for input in self.get_sink_input_info_list()? {
+            if self.cancel.generation() != generation {
+                return Err("Moving the sink inputs was cancelled".into());
+            }
+            if input.sink != sink {
+                // the stream may have gone away in the meantime, which is fine
+                self.move_sink_input(PAIdent::Index(input.index), PAIdent::Index(sink))?;
+            }
+        }
+
+        Ok(())
+    }
+
     pub fn get_default_source(&self) -> Result<Option<PAIdent>> {
-        self.tx.send(PACommand::GetDefaultSource)?;
-        assume_variant!(self.rx.recv()?, PAResponse::DefaultSource(x) => x)
+        self.request(request::GetDefaultSource)
     }
 
     pub fn set_default_source(&self, id: PAIdent) -> Result<OperationResult> {
-        self.tx.send(PACommand::SetDefaultSource(id))?;
+        self.send(PACommand::SetDefaultSource(id))?;
         self.operation_result()
     }
 
@@ -113,7 +479,83 @@ impl PulseAudio {
      */
 
     pub fn subscribe(&self, mask: PAMask, tx: Box<dyn EventSender>) -> Result<OperationResult> {
-        self.tx.send(PACommand::Subscribe(mask, tx))?;
+        self.send(PACommand::Subscribe(mask, tx))?;
+        self.operation_result()
+    }
+
+    /// Like `subscribe`, but only events which pass the filter are sent. The rest are dropped
+    /// before they're sent, so they don't wake up the receiver.
+    pub fn subscribe_filtered(
+        &self,
+        mask: PAMask,
+        filter: impl Into<EventFilter>,
+        tx: Box<dyn EventSender>,
+    ) -> Result<OperationResult> {
+        self.send(PACommand::SubscribeFiltered(mask, filter.into(), tx))?;
+        self.operation_result()
+    }
+
+    /// Subscribes to events, returning an iterator which blocks until the next event is received.
+    /// Dropping the iterator ends the subscription (once the next event is received).
+    pub fn events(&self, mask: PAMask) -> Result<impl Iterator<Item = PAEvent>> {
+        let (tx, rx) = channel::unbounded();
+        match self.subscribe(mask, Box::new(tx))? {
+            OperationResult::Success => Ok(rx.into_iter()),
+            result => Err(result.into()),
+        }
+    }
+
+    /// Ends the current subscription (such as one started with `subscribe` or `events`)
+    pub fn unsubscribe(&self) -> Result<OperationResult> {
+        self.send(PACommand::Unsubscribe)?;
+        self.operation_result()
+    }
+
+    /// Subscribes to events only while `f` runs, which keeps short-lived waits simple (such as
+    /// waiting up to a few seconds for a device to appear, with `Receiver::recv_timeout`).
+    ///
+    /// The subscription is ended once `f` returns (or panics), and any events which haven't been
+    /// received are dropped. Since there's only one subscription at a time, this replaces any
+    /// existing subscription.
+    pub fn with_subscription<T>(
+        &self,
+        mask: PAMask,
+        f: impl FnOnce(&Receiver<PAEvent>) -> T,
+    ) -> Result<T> {
+        let (tx, rx) = channel::unbounded();
+        match self.subscribe(mask, Box::new(tx))? {
+            OperationResult::Success => {}
+            result => return Err(result.into()),
+        }
+
+        let _guard = SubscriptionGuard(self);
+        Ok(f(&rx))
+    }
+
+    /// Once all sinks have been idle for `timeout`, a `PAEvent::AllSinksIdle` event will be sent to
+    /// the current subscription (which must include `PAMask::SINK`). Pass `None` to disable it.
+    pub fn set_idle_timeout(&self, timeout: Option<Duration>) -> Result<OperationResult> {
+        self.send(PACommand::SetIdleTimeout(timeout))?;
+        self.operation_result()
+    }
+
+    /// Pings the server every `interval`. If a ping isn't answered before the next one is due, a
+    /// `PAEvent::ServerUnresponsive` event is sent to the current subscription (followed by
+    /// `PAEvent::ServerResponsive` once it answers). Pass `None` to disable it (the default).
+    pub fn set_heartbeat(&self, interval: Option<Duration>) -> Result<OperationResult> {
+        self.send(PACommand::SetHeartbeat(interval))?;
+        self.operation_result()
+    }
+
+    /*
+     * Lookups
+     */
+
+    /// When set, operations on an object which doesn't exist keep retrying until it does or until
+    /// `timeout` has elapsed. Useful when reacting to `PAEvent::SubscriptionNew` events, since the
+    /// new object isn't always visible straight away. Pass `None` to disable it (the default).
+    pub fn set_lookup_retry(&self, timeout: Option<Duration>) -> Result<OperationResult> {
+        self.send(PACommand::SetLookupRetry(timeout))?;
         self.operation_result()
     }
 
@@ -122,15 +564,58 @@ impl PulseAudio {
      */
 
     pub fn get_card_info(&self, id: PAIdent) -> Result<PACardInfo> {
-        self.tx.send(PACommand::GetCardInfo(id))?;
-        assume_variant!(self.rx.recv()?, PAResponse::CardInfo(x) => x)
+        self.request(request::GetCardInfo(id))
     }
 
     pub fn set_card_profile(&self, id: PAIdent, profile: String) -> Result<OperationResult> {
-        self.tx.send(PACommand::SetCardProfile(id, profile))?;
+        self.send(PACommand::SetCardProfile(id, profile))?;
         self.operation_result()
     }
 
+    /// Switches the card to the profile after its active one in `profiles` (wrapping around), so
+    /// with two profiles this toggles between them. If the active profile isn't listed, the first
+    /// profile is used.
+    pub fn toggle_card_profile(&self, id: PAIdent, profiles: &[String]) -> Result<OperationResult> {
+        let card = self.get_card_info(id)?;
+        let active = card.active_profile.and_then(|p| p.name);
+        let next = match profiles.iter().position(|p| Some(p) == active.as_ref()) {
+            Some(i) => profiles.get((i + 1) % profiles.len()),
+            None => profiles.first(),
+        }
+        .ok_or("No profiles to toggle between")?;
+
+        self.set_card_profile(PAIdent::Index(card.index), next.clone())
+    }
+
+    /// Sets the card's profile from the first rule which matches it, unless it's already active.
+    /// Returns `None` if no rules match the card.
+    pub fn apply_card_profile_rules(
+        &self,
+        rules: &[CardProfileRule],
+        id: PAIdent,
+    ) -> Result<Option<OperationResult>> {
+        let card = self.get_card_info(id)?;
+        let profile = match CardProfileRule::profile_for(rules, &card) {
+            Some(profile) => profile,
+            None => return Ok(None),
+        };
+
+        let active = card.active_profile.as_ref().and_then(|p| p.name.as_deref());
+        if active == Some(profile) {
+            return Ok(Some(OperationResult::Success));
+        }
+
+        self.set_card_profile(PAIdent::Index(card.index), profile.to_string())
+            .map(Some)
+    }
+
+    /// Returns the latency offset of a card's port, resolving them the same way as
+    /// `set_port_latency_offset`
+    pub fn get_port_latency_offset(&self, card_id: PAIdent, port_id: PAIdent) -> Result<i64> {
+        let card = self.get_card_info(card_id)?;
+        Ok(find_card_port(&card, &port_id)?.latency_offset)
+    }
+
     pub fn set_port_latency_offset(
         &self,
         card_id: PAIdent,
@@ -138,51 +623,61 @@ impl PulseAudio {
         offset: i64,
     ) -> Result<OperationResult> {
         let card = self.get_card_info(card_id.clone())?;
-        let port = card.ports.into_iter().enumerate().find_map(|(i, p)| {
-            let found = match port_id {
-                PAIdent::Index(idx) => idx == i as u32,
-                PAIdent::Name(ref name) => p.name.as_ref() == Some(name),
-            };
-
-            if found {
-                Some(p)
-            } else {
-                None
+        let port = match find_card_port(&card, &port_id)?.name {
+            Some(ref name) => name.clone(),
+            None => {
+                return Err(format!(
+                    "Found port with id: {}, but it has no name and one is required",
+                    port_id
+                )
+                .into())
             }
-        });
+        };
         let card = match card.name {
             Some(name) => name,
             None => return Err(format!("No card found with id: {}", card_id).into()),
         };
-        let port = match port {
-            Some(port) => match port.name {
-                Some(name) => name,
-                None => {
-                    return Err(format!(
-                        "Found port with id: {}, but it has no name and one is required",
-                        port_id
-                    )
-                    .into())
-                }
-            },
-            None => return Err(format!("No port found with id: {}", port_id).into()),
-        };
 
-        self.tx
-            .send(PACommand::SetPortLatencyOffset(card, port, offset))?;
+        self.send(PACommand::SetPortLatencyOffset(card, port, offset))?;
         self.operation_result()
     }
 
+    /// Gathers the latency of every sink, source and stream into a single report.
+    pub fn get_latency_report(&self) -> Result<LatencyReport> {
+        Ok(LatencyReport::new(
+            &self.get_sink_info_list()?,
+            &self.get_source_info_list()?,
+            &self.get_sink_input_info_list()?,
+            &self.get_source_output_info_list()?,
+        ))
+    }
+
+    /// Returns the card which a sink belongs to, or `None` if the sink isn't backed by a card
+    /// (such as a null sink or a network sink).
+    pub fn get_card_for_sink(&self, id: PAIdent) -> Result<Option<PACardInfo>> {
+        match self.get_sink_info(id)?.card {
+            Some(idx) => Ok(Some(self.get_card_info(PAIdent::Index(idx))?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns all the sinks which belong to a card.
+    pub fn get_sinks_for_card(&self, id: PAIdent) -> Result<Vec<PASinkInfo>> {
+        let card = self.get_card_info(id)?;
+        Ok(self
+            .get_sink_info_list()?
+            .into_iter()
+            .filter(|sink| sink.card == Some(card.index))
+            .collect())
+    }
+
     /*
      * Clients
      */
 
     pub fn get_client_info(&self, id: PAIdent) -> Result<PAClientInfo> {
         match id {
-            PAIdent::Index(idx) => {
-                self.tx.send(PACommand::GetClientInfo(idx))?;
-                assume_variant!(self.rx.recv()?, PAResponse::ClientInfo(x) => x)
-            }
+            PAIdent::Index(idx) => self.request(request::GetClientInfo(idx)),
             PAIdent::Name(ref name) => {
                 let client = self.find_client_info_by_name(name)?;
                 self.get_client_info(PAIdent::Index(client.index))
@@ -193,7 +688,7 @@ impl PulseAudio {
     pub fn kill_client(&self, id: PAIdent) -> Result<OperationResult> {
         match id {
             PAIdent::Index(idx) => {
-                self.tx.send(PACommand::KillClient(idx))?;
+                self.send(PACommand::KillClient(idx))?;
                 self.operation_result()
             }
             PAIdent::Name(ref name) => {
@@ -209,10 +704,7 @@ impl PulseAudio {
 
     pub fn get_module_info(&self, id: PAIdent) -> Result<PAModuleInfo> {
         match id {
-            PAIdent::Index(idx) => {
-                self.tx.send(PACommand::GetModuleInfo(idx))?;
-                assume_variant!(self.rx.recv()?, PAResponse::ModuleInfo(x) => x)
-            }
+            PAIdent::Index(idx) => self.request(request::GetModuleInfo(idx)),
             PAIdent::Name(ref name) => {
                 let module = self.find_module_info_by_name(name)?;
                 self.get_module_info(PAIdent::Index(module.index))
@@ -220,15 +712,30 @@ impl PulseAudio {
         }
     }
 
+    /// Returns the modules whose argument contains the given string (case-insensitively), such as
+    /// "source=alsa_input.usb" to find a loopback module loaded for that source.
+    pub fn find_modules_by_argument(&self, argument: &str) -> Result<Vec<PAModuleInfo>> {
+        let argument = argument.to_lowercase();
+        Ok(self
+            .get_module_info_list()?
+            .into_iter()
+            .filter(|module| {
+                module
+                    .argument
+                    .as_ref()
+                    .map_or(false, |a| a.to_lowercase().contains(&argument))
+            })
+            .collect())
+    }
+
     pub fn load_module(&self, name: String, args: String) -> Result<u32> {
-        self.tx.send(PACommand::LoadModule(name, args))?;
-        assume_variant!(self.rx.recv()?, PAResponse::ModuleLoaded(x) => x)
+        self.request(request::LoadModule(name, args))
     }
 
     pub fn unload_module(&self, id: PAIdent) -> Result<OperationResult> {
         match id {
             PAIdent::Index(idx) => {
-                self.tx.send(PACommand::UnloadModule(idx))?;
+                self.send(PACommand::UnloadModule(idx))?;
                 self.operation_result()
             }
             PAIdent::Name(ref name) => {
@@ -239,137 +746,552 @@ impl PulseAudio {
     }
 
     /*
-     * Lists
+     * Raw streams
      */
 
-    pub fn get_card_info_list(&self) -> Result<Vec<PACardInfo>> {
-        self.tx.send(PACommand::GetCardInfoList)?;
-        assume_variant!(self.rx.recv()?, PAResponse::CardInfoList(x) => x)
+    /// Opens a stream which plays raw audio on the given sink (or the default sink), see
+    /// `PAStream::write`. Playback ends once the stream is closed (or dropped) and everything has
+    /// been played.
+    pub fn open_playback(&self, options: RawStreamOptions) -> Result<PAStream> {
+        let (data_tx, data_rx) = channel::bounded(RAW_BUFFERED_CHUNKS);
+        let id = self.request(request::PlayRaw(options, data_rx))?;
+        Ok(PAStream {
+            pa: self,
+            id,
+            writer: Some(data_tx),
+            reader: None,
+        })
     }
 
-    pub fn get_client_info_list(&self) -> Result<Vec<PAClientInfo>> {
-        self.tx.send(PACommand::GetClientInfoList)?;
-        assume_variant!(self.rx.recv()?, PAResponse::ClientInfoList(x) => x)
+    /// Opens a stream which records raw audio from the given source (or the default source), see
+    /// `PAStream::read`. Recording stops once the stream is dropped, or if the source goes away.
+    pub fn open_recording(&self, options: RawStreamOptions) -> Result<PAStream> {
+        let (data_tx, data_rx) = channel::unbounded();
+        let id = self.request(request::RecordRaw(options, data_tx))?;
+        Ok(PAStream {
+            pa: self,
+            id,
+            writer: None,
+            reader: Some(data_rx),
+        })
     }
 
-    pub fn get_module_info_list(&self) -> Result<Vec<PAModuleInfo>> {
-        self.tx.send(PACommand::GetModuleInfoList)?;
-        assume_variant!(self.rx.recv()?, PAResponse::ModuleInfoList(x) => x)
-    }
+    /// Records what's playing on a sink, by recording from its monitor source. The stream isn't
+    /// moved if the sink's streams are, so it always captures this sink.
+    pub fn record_sink_monitor(&self, sink: PAIdent, spec: RawSpec) -> Result<PAStream> {
+        let sink = self.get_sink_info(sink)?;
+        let monitor = match sink.monitor_source_name {
+            Some(name) => name,
+            None => return Err(format!("Sink {} has no monitor source", sink.index).into()),
+        };
 
-    pub fn get_sample_info_list(&self) -> Result<Vec<PASampleInfo>> {
-        self.tx.send(PACommand::GetSampleInfoList)?;
-        assume_variant!(self.rx.recv()?, PAResponse::SampleInfoList(x) => x)
+        self.open_recording(RawStreamOptions {
+            dont_move: true,
+            ..RawStreamOptions::new(Some(monitor), spec)
+        })
     }
 
-    pub fn get_sink_info_list(&self) -> Result<Vec<PASinkInfo>> {
-        self.tx.send(PACommand::GetSinkInfoList)?;
-        assume_variant!(self.rx.recv()?, PAResponse::SinkInfoList(x) => x)
+    /// Reports the peak level of every sink input, every 40ms, until `f` returns `false` or all of
+    /// the sink inputs have finished. Sink inputs which start afterwards aren't included.
+    pub fn monitor_stream_levels(&self, mut f: impl FnMut(&[StreamLevel]) -> bool) -> Result<()> {
+        let sinks = self.get_sink_info_list()?;
+        let mut meters = vec![];
+        for input in self.get_sink_input_info_list()? {
+            let monitor = sinks
+                .iter()
+                .find(|sink| sink.index == input.sink)
+                .and_then(|sink| sink.monitor_source_name.clone());
+            let options = RawStreamOptions {
+                monitor_stream: Some(input.index),
+                peak_detect: true,
+                ..RawStreamOptions::new(monitor, LEVEL_SPEC)
+            };
+
+            match self.open_recording(options) {
+                Ok(stream) => meters.push((StreamLevel::new(&input), stream)),
+                // the stream may have gone away in the meantime, which is fine
+                Err(e) if e.is::<OperationResult>() => continue,
+                Err(e) => return Err(e),
+            }
+        }
+
+        let ticker = channel::tick(LEVEL_INTERVAL);
+        while !meters.is_empty() {
+            ticker.recv()?;
+            meters.retain_mut(|(level, stream)| {
+                level.peak = 0.0;
+                let reader = match stream.reader {
+                    Some(ref reader) => reader,
+                    None => return false,
+                };
+                loop {
+                    match reader.try_recv() {
+                        Ok(chunk) => level.peak = level.peak.max(peak(&chunk)),
+                        Err(TryRecvError::Empty) => return true,
+                        Err(TryRecvError::Disconnected) => return false,
+                    }
+                }
+            });
+
+            let levels = meters
+                .iter()
+                .map(|(level, _)| level.clone())
+                .collect::<Vec<_>>();
+            if !f(&levels) {
+                break;
+            }
+        }
+
+        Ok(())
     }
 
-    pub fn get_sink_input_info_list(&self) -> Result<Vec<PASinkInputInfo>> {
-        self.tx.send(PACommand::GetSinkInputInfoList)?;
-        assume_variant!(self.rx.recv()?, PAResponse::SinkInputInfoList(x) => x)
+    /// Like `monitor_stream_levels`, but the sink inputs are grouped by their application, which
+    /// is what mixers usually show.
+    pub fn monitor_app_levels(&self, mut f: impl FnMut(&[AppLevel]) -> bool) -> Result<()> {
+        self.monitor_stream_levels(|levels| f(&AppLevel::group(levels)))
     }
 
-    pub fn get_source_info_list(&self) -> Result<Vec<PASourceInfo>> {
-        self.tx.send(PACommand::GetSourceInfoList)?;
-        assume_variant!(self.rx.recv()?, PAResponse::SourceInfoList(x) => x)
+    /// Plays raw audio read from `data` until it ends, on the given sink (or the default sink).
+    /// Returns once everything has been played, so the timeout (if any) must allow for the time it
+    /// takes to play the last of the stream's buffer.
+    pub fn play_raw(
+        &self,
+        options: RawStreamOptions,
+        mut data: impl Read,
+    ) -> Result<OperationResult> {
+        let stream = self.open_playback(options)?;
+
+        let mut buf = vec![0; RAW_CHUNK_SIZE];
+        loop {
+            let n = match data.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            };
+
+            // the stream has failed, and closing it returns the error
+            if stream.write(&buf[..n]).is_err() {
+                break;
+            }
+        }
+
+        stream.close()
     }
 
-    pub fn get_source_output_info_list(&self) -> Result<Vec<PASourceOutputInfo>> {
-        self.tx.send(PACommand::GetSourceOutputInfoList)?;
-        assume_variant!(self.rx.recv()?, PAResponse::SourceOutputInfoList(x) => x)
+    /// Plays an audio file until it ends, see `decode::decode` for the supported formats. The
+    /// stream's sample spec is taken from the file, rather than `options`. Returns once everything
+    /// has been played.
+    pub fn play_file(
+        &self,
+        path: impl AsRef<Path>,
+        options: RawStreamOptions,
+    ) -> Result<OperationResult> {
+        let decoded = decode(BufReader::new(File::open(path)?))?;
+        let options = RawStreamOptions {
+            spec: decoded.spec,
+            ..options
+        };
+        self.play_raw(options, decoded.data)
     }
 
-    /*
-     * Sinks
-     */
+    /// Plays an audio file (such as a notification sound) on the given sink (or the default sink)
+    /// at the given volume (or the server's choice of volume). Returns once the sound has finished
+    /// playing, after which its stream has been removed.
+    pub fn play_file_once(
+        &self,
+        path: impl AsRef<Path>,
+        sink: Option<PAIdent>,
+        volume: Option<PAVol>,
+    ) -> Result<OperationResult> {
+        let decoded = decode(BufReader::new(File::open(path)?))?;
+        let device = sink.as_ref().map(|sink| match sink {
+            PAIdent::Index(idx) => idx.to_string(),
+            PAIdent::Name(name) => name.clone(),
+        });
 
-    pub fn get_sink_info(&self, id: PAIdent) -> Result<PASinkInfo> {
-        self.tx.send(PACommand::GetSinkInfo(id))?;
-        assume_variant!(self.rx.recv()?, PAResponse::SinkInfo(x) => x)
+        let options = RawStreamOptions {
+            volume,
+            ..RawStreamOptions::new(device, decoded.spec)
+        };
+
+        // play at the sink's own sample spec, so the server doesn't have to convert the sound (or
+        // switch the sink's rate just for it)
+        #[cfg(feature = "resample")]
+        let options = match self.native_spec(sink) {
+            Some(spec) => RawStreamOptions {
+                spec,
+                input: Some(decoded.spec),
+                ..options
+            },
+            None => options,
+        };
+
+        self.play_raw(options, decoded.data)
     }
 
-    pub fn get_sink_mute(&self, id: PAIdent) -> Result<bool> {
-        self.tx.send(PACommand::GetSinkMute(id))?;
-        assume_variant!(self.rx.recv()?, PAResponse::Mute(_, x) => x)
+    /// The sample spec of the given sink (or the default sink), if it can be played to directly
+    #[cfg(feature = "resample")]
+    fn native_spec(&self, sink: Option<PAIdent>) -> Option<RawSpec> {
+        let sink = match sink {
+            Some(sink) => sink,
+            None => self.get_default_sink().ok()??,
+        };
+
+        let info = self.get_sink_info(sink).ok()?;
+        RawSpec::try_from(&info.sample_spec).ok()
     }
 
-    pub fn get_sink_volume(&self, id: PAIdent) -> Result<VolumeReadings> {
-        self.tx.send(PACommand::GetSinkVolume(id))?;
-        assume_variant!(self.rx.recv()?, PAResponse::Volume(_, x) => x)
+    /// Pauses (`true`) or resumes (`false`) a stream opened with `open_playback` or
+    /// `open_recording`.
+    pub fn cork_stream(&self, stream_id: u32, cork: bool) -> Result<OperationResult> {
+        self.send(PACommand::CorkStream(stream_id, cork))?;
+        self.operation_result()
     }
 
-    pub fn set_sink_mute(&self, id: PAIdent, mute: bool) -> Result<OperationResult> {
-        self.tx.send(PACommand::SetSinkMute(id, mute))?;
+    /// Updates the properties of our own client or one of our streams, such as to tag them for rules
+    /// to match on later. The native protocol can't change the properties of sinks or sources, so
+    /// those fail with `Code::NotSupported`.
+    pub fn update_proplist(
+        &self,
+        target: ProplistTarget,
+        props: BTreeMap<String, String>,
+        mode: ProplistMode,
+    ) -> Result<OperationResult> {
+        self.send(PACommand::UpdateDeviceProplist(target, props, mode))?;
         self.operation_result()
     }
 
-    pub fn set_sink_volume(&self, id: PAIdent, vol: VolumeSpec) -> Result<OperationResult> {
-        self.tx.send(PACommand::SetSinkVolume(id, vol))?;
+    /// Drops the data in a stream's buffer which hasn't been played (or read) yet, such as when a
+    /// player stops or seeks.
+    pub fn flush_stream(&self, stream_id: u32) -> Result<OperationResult> {
+        self.send(PACommand::FlushStream(stream_id))?;
         self.operation_result()
     }
 
-    pub fn set_sink_port(&self, id: PAIdent, port: String) -> Result<OperationResult> {
-        self.tx.send(PACommand::SetSinkPort(id, port))?;
+    /// Waits for everything written to a playback stream so far to be played.
+    pub fn drain_stream(&self, stream_id: u32) -> Result<OperationResult> {
+        self.send(PACommand::DrainStream(stream_id))?;
         self.operation_result()
     }
 
-    pub fn suspend_sink(&self, id: PAIdent, suspend: bool) -> Result<OperationResult> {
-        self.tx.send(PACommand::SuspendSink(id, suspend))?;
+    /// Returns the current latency of a stream, and the buffer attributes the server chose for it.
+    pub fn get_stream_latency(&self, stream_id: u32) -> Result<RawStreamLatency> {
+        self.request(request::GetStreamLatency(stream_id))
+    }
+
+    /*
+     * Batches
+     */
+
+    /// Mutes (or unmutes) every sink, source, sink input and source output in the mask at once.
+    /// The `except` object is left alone: devices are matched by name or index, streams by index.
+    pub fn mute_all(
+        &self,
+        mask: PAMask,
+        mute: bool,
+        except: Option<PAIdent>,
+    ) -> Result<OperationResult> {
+        self.send(PACommand::MuteAll(mask, mute, except))?;
         self.operation_result()
     }
 
     /*
-     * Sources
+     * Lists
      */
 
-    pub fn get_source_info(&self, id: PAIdent) -> Result<PASourceInfo> {
-        self.tx.send(PACommand::GetSourceInfo(id))?;
-        assume_variant!(self.rx.recv()?, PAResponse::SourceInfo(x) => x)
+    pub fn get_card_info_list(&self) -> Result<Vec<PACardInfo>> {
+        self.request(request::GetCardInfoList)
     }
 
-    pub fn get_source_mute(&self, id: PAIdent) -> Result<bool> {
-        self.tx.send(PACommand::GetSourceMute(id))?;
-        assume_variant!(self.rx.recv()?, PAResponse::Mute(_, x) => x)
+    pub fn get_client_info_list(&self) -> Result<Vec<PAClientInfo>> {
+        self.request(request::GetClientInfoList)
     }
 
-    pub fn get_source_volume(&self, id: PAIdent) -> Result<VolumeReadings> {
-        self.tx.send(PACommand::GetSourceVolume(id))?;
-        assume_variant!(self.rx.recv()?, PAResponse::Volume(_, x) => x)
+    pub fn get_module_info_list(&self) -> Result<Vec<PAModuleInfo>> {
+        self.request(request::GetModuleInfoList)
     }
 
-    pub fn set_source_mute(&self, id: PAIdent, mute: bool) -> Result<OperationResult> {
-        self.tx.send(PACommand::SetSourceMute(id, mute))?;
-        self.operation_result()
+    pub fn get_sample_info_list(&self) -> Result<Vec<PASampleInfo>> {
+        self.request(request::GetSampleInfoList)
     }
 
-    pub fn set_source_volume(&self, id: PAIdent, vol: VolumeSpec) -> Result<OperationResult> {
-        self.tx.send(PACommand::SetSourceVolume(id, vol))?;
-        self.operation_result()
+    pub fn get_sink_info_list(&self) -> Result<Vec<PASinkInfo>> {
+        self.request(request::GetSinkInfoList)
     }
 
-    pub fn set_source_port(&self, id: PAIdent, port: String) -> Result<OperationResult> {
-        self.tx.send(PACommand::SetSourcePort(id, port))?;
-        self.operation_result()
+    pub fn get_sink_input_info_list(&self) -> Result<Vec<PASinkInputInfo>> {
+        self.request(request::GetSinkInputInfoList)
     }
 
-    pub fn suspend_source(&self, id: PAIdent, suspend: bool) -> Result<OperationResult> {
-        self.tx.send(PACommand::SuspendSource(id, suspend))?;
-        self.operation_result()
+    /// Returns only the sink inputs which match the filter, which is cheaper than filtering the full
+    /// list when there are many streams.
+    pub fn get_sink_input_info_list_filtered(
+        &self,
+        filter: StreamFilter,
+    ) -> Result<Vec<PASinkInputInfo>> {
+        self.request(request::GetSinkInputInfoListFiltered(filter))
     }
 
-    /*
-     * Sink Inputs
-     */
+    pub fn get_source_info_list(&self) -> Result<Vec<PASourceInfo>> {
+        self.request(request::GetSourceInfoList)
+    }
 
-    pub fn get_sink_input_info(&self, id: PAIdent) -> Result<PASinkInputInfo> {
+    pub fn get_source_output_info_list(&self) -> Result<Vec<PASourceOutputInfo>> {
+        self.request(request::GetSourceOutputInfoList)
+    }
+
+    /*
+     * Sinks
+     */
+
+    pub fn get_sink_info(&self, id: PAIdent) -> Result<PASinkInfo> {
+        self.request(request::GetSinkInfo(id))
+    }
+
+    pub fn get_sink_mute(&self, id: PAIdent) -> Result<bool> {
+        self.request(request::GetSinkMute(id))
+    }
+
+    pub fn get_sink_volume(&self, id: PAIdent) -> Result<VolumeReadings> {
+        self.request(request::GetSinkVolume(id))
+    }
+
+    pub fn set_sink_mute(&self, id: PAIdent, mute: bool) -> Result<OperationResult> {
+        self.send(PACommand::SetSinkMute(id.clone(), mute))?;
+        let result = self.operation_result()?;
+        self.confirmed(result, || Ok(ConfirmedState::Mute(self.get_sink_mute(id)?)))
+    }
+
+    pub fn set_sink_volume(&self, id: PAIdent, vol: VolumeSpec) -> Result<OperationResult> {
+        self.send(PACommand::SetSinkVolume(id.clone(), vol))?;
+        let result = self.operation_result()?;
+        self.confirmed(result, || {
+            Ok(ConfirmedState::Volume(self.get_sink_volume(id)?))
+        })
+    }
+
+    /// Mutes or unmutes a sink, but only if its mute is still `expected`. Returns
+    /// `OperationResult::Conflict` if it has changed.
+    pub fn set_sink_mute_if(
+        &self,
+        id: PAIdent,
+        expected: bool,
+        mute: bool,
+    ) -> Result<OperationResult> {
+        self.send(PACommand::SetSinkMuteIf(id, expected, mute))?;
+        self.operation_result()
+    }
+
+    /// Sets the volume(s) of a sink, but only if they still match `expected` (such as a previous
+    /// reading from `get_sink_volume`). Returns `OperationResult::Conflict` if they have changed.
+    pub fn set_sink_volume_if(
+        &self,
+        id: PAIdent,
+        expected: VolumeReadings,
+        vol: VolumeSpec,
+    ) -> Result<OperationResult> {
+        self.send(PACommand::SetSinkVolumeIf(id, expected, vol))?;
+        self.operation_result()
+    }
+
+    /// Raises (or lowers, if `steps` is negative) the volume of a sink by a number of steps. If the
+    /// sink only supports a limited number of volumes, its own steps are used, otherwise each step
+    /// is the amount set with `Builder::volume_step`.
+    pub fn step_sink_volume(&self, id: PAIdent, steps: i32) -> Result<OperationResult> {
+        let info = self.get_sink_info(id.clone())?;
+        let step = self.volume_step_size(&info.base_volume, info.n_volume_steps);
+        self.set_sink_volume(id, stepped_volumes(&info.volume, step, steps))
+    }
+
+    /// Whether a sink accepts the given encoding, such as `PAEncoding::Ac3` for passthrough.
+    pub fn sink_supports_format(&self, id: PAIdent, encoding: PAEncoding) -> Result<bool> {
+        Ok(self.get_sink_info(id)?.supports_encoding(encoding))
+    }
+
+    pub fn set_sink_port(&self, id: PAIdent, port: String) -> Result<OperationResult> {
+        self.send(PACommand::SetSinkPort(id, port))?;
+        self.operation_result()
+    }
+
+    pub fn suspend_sink(&self, id: PAIdent, suspend: bool) -> Result<OperationResult> {
+        self.send(PACommand::SuspendSink(id, suspend))?;
+        self.operation_result()
+    }
+
+    /// Sets the description of a sink, which is the name users see (such as "Desk Speakers").
+    /// This requires module-device-manager, which remembers the description for next time.
+    pub fn rename_sink(
+        &self,
+        id: PAIdent,
+        description: impl Into<String>,
+    ) -> Result<OperationResult> {
+        self.send(PACommand::RenameSink(id, description.into()))?;
+        self.operation_result()
+    }
+
+    /// Mutes a sink, and unmutes it once `duration` has elapsed. Calling this again before then
+    /// pushes back the unmute. If the connection is closed (or this is dropped) first, it's
+    /// unmuted then; if the process is killed, it stays muted.
+    pub fn mute_sink_for(&self, id: PAIdent, duration: Duration) -> Result<OperationResult> {
+        self.send(PACommand::MuteSinkFor(id, duration))?;
+        self.operation_result()
+    }
+
+    /// Lowers the volume of a sink to `pct` percent of its current volume, and restores it once
+    /// `duration` has elapsed. Calling this again before then pushes back the restore. If the
+    /// connection is closed (or this is dropped) first, it's restored then; if the process is
+    /// killed, it stays lowered.
+    pub fn duck_sink(&self, id: PAIdent, pct: f64, duration: Duration) -> Result<OperationResult> {
+        self.send(PACommand::DuckSink(id, pct, duration))?;
+        self.operation_result()
+    }
+
+    /// Starts gradually changing the volume of a sink, returning the id of the fade.
+    /// Starting another fade on the same sink cancels this one.
+    pub fn fade_sink_volume(
+        &self,
+        id: PAIdent,
+        vol: VolumeSpec,
+        duration: Duration,
+        curve: FadeCurve,
+    ) -> Result<u32> {
+        self.request(request::FadeSinkVolume(id, vol, duration, curve))
+    }
+
+    /// Stops a running fade, leaving the volume where it currently is.
+    pub fn cancel_fade(&self, fade_id: u32) -> Result<OperationResult> {
+        self.send(PACommand::CancelFade(fade_id))?;
+        self.operation_result()
+    }
+
+    /// Blocks until a fade has finished. Fails if the fade was cancelled.
+    pub fn wait_fade(&self, fade_id: u32) -> Result<OperationResult> {
+        self.send(PACommand::WaitFade(fade_id))?;
+        self.operation_result()
+    }
+
+    /// Cancels every multi-step operation which is still running, such as fades (individual fades
+    /// can be cancelled with `cancel_fade`). Operations started afterwards aren't affected.
+    pub fn cancel_pending(&self) -> Result<OperationResult> {
+        self.cancel.cancel();
+        self.send(PACommand::CancelPending)?;
+        self.operation_result()
+    }
+
+    /// Merges volume changes which are made in quick succession, such as from a slider or a scroll
+    /// wheel, so at most one is sent for each object per `window`. See `VolumeThrottle`.
+    pub fn throttle_volumes(&self, window: Duration) -> VolumeThrottle<'_> {
+        VolumeThrottle {
+            pa: self,
+            window,
+            targets: vec![],
+        }
+    }
+
+    /// A handle which cancels the same operations as `cancel_pending`, but can be used from another
+    /// thread, such as while this one is blocked in `wait_fade`. Fades stop at their next step.
+    pub fn cancel_token(&self) -> CancelToken {
+        self.cancel.clone()
+    }
+
+    /*
+     * Sources
+     */
+
+    pub fn get_source_info(&self, id: PAIdent) -> Result<PASourceInfo> {
+        self.request(request::GetSourceInfo(id))
+    }
+
+    pub fn get_source_mute(&self, id: PAIdent) -> Result<bool> {
+        self.request(request::GetSourceMute(id))
+    }
+
+    pub fn get_source_volume(&self, id: PAIdent) -> Result<VolumeReadings> {
+        self.request(request::GetSourceVolume(id))
+    }
+
+    pub fn set_source_mute(&self, id: PAIdent, mute: bool) -> Result<OperationResult> {
+        self.send(PACommand::SetSourceMute(id.clone(), mute))?;
+        let result = self.operation_result()?;
+        self.confirmed(result, || {
+            Ok(ConfirmedState::Mute(self.get_source_mute(id)?))
+        })
+    }
+
+    pub fn set_source_volume(&self, id: PAIdent, vol: VolumeSpec) -> Result<OperationResult> {
+        self.send(PACommand::SetSourceVolume(id.clone(), vol))?;
+        let result = self.operation_result()?;
+        self.confirmed(result, || {
+            Ok(ConfirmedState::Volume(self.get_source_volume(id)?))
+        })
+    }
+
+    /// Mutes or unmutes a source, but only if its mute is still `expected`. Returns
+    /// `OperationResult::Conflict` if it has changed.
+    pub fn set_source_mute_if(
+        &self,
+        id: PAIdent,
+        expected: bool,
+        mute: bool,
+    ) -> Result<OperationResult> {
+        self.send(PACommand::SetSourceMuteIf(id, expected, mute))?;
+        self.operation_result()
+    }
+
+    /// Sets the volume(s) of a source, but only if they still match `expected` (such as a previous
+    /// reading from `get_source_volume`). Returns `OperationResult::Conflict` if they have changed.
+    pub fn set_source_volume_if(
+        &self,
+        id: PAIdent,
+        expected: VolumeReadings,
+        vol: VolumeSpec,
+    ) -> Result<OperationResult> {
+        self.send(PACommand::SetSourceVolumeIf(id, expected, vol))?;
+        self.operation_result()
+    }
+
+    /// Raises (or lowers, if `steps` is negative) the volume of a source by a number of steps, see
+    /// `step_sink_volume`.
+    pub fn step_source_volume(&self, id: PAIdent, steps: i32) -> Result<OperationResult> {
+        let info = self.get_source_info(id.clone())?;
+        let step = self.volume_step_size(&info.base_volume, info.n_volume_steps);
+        self.set_source_volume(id, stepped_volumes(&info.volume, step, steps))
+    }
+
+    /// Whether a source accepts the given encoding.
+    pub fn source_supports_format(&self, id: PAIdent, encoding: PAEncoding) -> Result<bool> {
+        Ok(self.get_source_info(id)?.supports_encoding(encoding))
+    }
+
+    pub fn set_source_port(&self, id: PAIdent, port: String) -> Result<OperationResult> {
+        self.send(PACommand::SetSourcePort(id, port))?;
+        self.operation_result()
+    }
+
+    pub fn suspend_source(&self, id: PAIdent, suspend: bool) -> Result<OperationResult> {
+        self.send(PACommand::SuspendSource(id, suspend))?;
+        self.operation_result()
+    }
+
+    /// Sets the description of a source, which is the name users see (such as "Desk Speakers").
+    /// This requires module-device-manager, which remembers the description for next time.
+    pub fn rename_source(
+        &self,
+        id: PAIdent,
+        description: impl Into<String>,
+    ) -> Result<OperationResult> {
+        self.send(PACommand::RenameSource(id, description.into()))?;
+        self.operation_result()
+    }
+
+    /*
+     * Sink Inputs
+     */
+
+    pub fn get_sink_input_info(&self, id: PAIdent) -> Result<PASinkInputInfo> {
         match id {
-            PAIdent::Index(idx) => {
-                self.tx.send(PACommand::GetSinkInputInfo(idx))?;
-                assume_variant!(self.rx.recv()?, PAResponse::SinkInputInfo(x) => x)
-            }
+            PAIdent::Index(idx) => self.request(request::GetSinkInputInfo(idx)),
             PAIdent::Name(ref name) => {
                 let si = self.find_sink_input_info_by_name(name)?;
                 self.get_sink_input_info(PAIdent::Index(si.index))
@@ -379,10 +1301,7 @@ impl PulseAudio {
 
     pub fn get_sink_input_mute(&self, id: PAIdent) -> Result<bool> {
         match id {
-            PAIdent::Index(idx) => {
-                self.tx.send(PACommand::GetSinkInputMute(idx))?;
-                assume_variant!(self.rx.recv()?, PAResponse::Mute(_, x) => x)
-            }
+            PAIdent::Index(idx) => self.request(request::GetSinkInputMute(idx)),
             PAIdent::Name(ref name) => {
                 let si = self.find_sink_input_info_by_name(name)?;
                 self.get_sink_input_mute(PAIdent::Index(si.index))
@@ -392,10 +1311,7 @@ impl PulseAudio {
 
     pub fn get_sink_input_volume(&self, id: PAIdent) -> Result<VolumeReadings> {
         match id {
-            PAIdent::Index(idx) => {
-                self.tx.send(PACommand::GetSinkInputVolume(idx))?;
-                assume_variant!(self.rx.recv()?, PAResponse::Volume(_, x) => x)
-            }
+            PAIdent::Index(idx) => self.request(request::GetSinkInputVolume(idx)),
             PAIdent::Name(ref name) => {
                 let si = self.find_sink_input_info_by_name(name)?;
                 self.get_sink_input_volume(PAIdent::Index(si.index))
@@ -406,8 +1322,11 @@ impl PulseAudio {
     pub fn set_sink_input_mute(&self, id: PAIdent, mute: bool) -> Result<OperationResult> {
         match id {
             PAIdent::Index(idx) => {
-                self.tx.send(PACommand::SetSinkInputMute(idx, mute))?;
-                self.operation_result()
+                self.send(PACommand::SetSinkInputMute(idx, mute))?;
+                let result = self.operation_result()?;
+                self.confirmed(result, || {
+                    Ok(ConfirmedState::Mute(self.get_sink_input_mute(id)?))
+                })
             }
             PAIdent::Name(ref name) => {
                 let si = self.find_sink_input_info_by_name(name)?;
@@ -419,8 +1338,11 @@ impl PulseAudio {
     pub fn set_sink_input_volume(&self, id: PAIdent, vol: VolumeSpec) -> Result<OperationResult> {
         match id {
             PAIdent::Index(idx) => {
-                self.tx.send(PACommand::SetSinkInputVolume(idx, vol))?;
-                self.operation_result()
+                self.send(PACommand::SetSinkInputVolume(idx, vol))?;
+                let result = self.operation_result()?;
+                self.confirmed(result, || {
+                    Ok(ConfirmedState::Volume(self.get_sink_input_volume(id)?))
+                })
             }
             PAIdent::Name(ref name) => {
                 let si = self.find_sink_input_info_by_name(name)?;
@@ -432,7 +1354,7 @@ impl PulseAudio {
     pub fn move_sink_input(&self, id: PAIdent, sink: PAIdent) -> Result<OperationResult> {
         match id {
             PAIdent::Index(idx) => {
-                self.tx.send(PACommand::MoveSinkInput(idx, sink))?;
+                self.send(PACommand::MoveSinkInput(idx.into(), sink))?;
                 self.operation_result()
             }
             PAIdent::Name(ref name) => {
@@ -442,10 +1364,61 @@ impl PulseAudio {
         }
     }
 
+    /// Moves every sink input of the given application (matched case-insensitively against its
+    /// `application.name`) to the sink. The sink inputs are resolved by the server connection, so
+    /// there's no race between finding them and moving them.
+    pub fn move_sink_inputs_by_app(&self, app: &str, sink: PAIdent) -> Result<OperationResult> {
+        let stream = PAStreamIdent::App(app.to_string());
+        self.send(PACommand::MoveSinkInput(stream, sink))?;
+        self.operation_result()
+    }
+
+    /// Lists the volumes which are restored for each application's streams, see
+    /// `save_app_volumes`. Requires module-stream-restore.
+    pub fn get_app_volumes(&self) -> Result<Vec<AppVolume>> {
+        self.request(request::GetAppVolumes)
+    }
+
+    /// Saves volumes which are restored for each application's streams when they appear, and
+    /// applies them to the streams which are already playing. Requires module-stream-restore.
+    pub fn save_app_volumes(&self, volumes: Vec<AppVolume>) -> Result<OperationResult> {
+        self.send(PACommand::SaveAppVolumes(volumes))?;
+        self.operation_result()
+    }
+
+    /// Forgets the saved volumes of the given applications, see `save_app_volumes`
+    pub fn delete_app_volumes(&self, apps: Vec<String>) -> Result<OperationResult> {
+        self.send(PACommand::DeleteAppVolumes(apps))?;
+        self.operation_result()
+    }
+
+    /// The current volume of each application which is playing, taken from its first sink input.
+    /// Pass these to `save_app_volumes` to keep the current levels.
+    pub fn current_app_volumes(&self) -> Result<Vec<AppVolume>> {
+        let mut volumes: Vec<AppVolume> = vec![];
+        for input in self.get_sink_input_info_list()? {
+            let app = match input.proplist.get_str("application.name") {
+                Some(app) => app,
+                None => continue,
+            };
+            if volumes.iter().any(|v| v.application == app) {
+                continue;
+            }
+
+            volumes.push(AppVolume {
+                application: app,
+                volume: Some(input.volume.avg().percentage()),
+                mute: input.mute,
+            });
+        }
+
+        Ok(volumes)
+    }
+
     pub fn kill_sink_input(&self, id: PAIdent) -> Result<OperationResult> {
         match id {
             PAIdent::Index(idx) => {
-                self.tx.send(PACommand::KillSinkInput(idx))?;
+                self.send(PACommand::KillSinkInput(idx))?;
                 self.operation_result()
             }
             PAIdent::Name(ref name) => {
@@ -455,16 +1428,51 @@ impl PulseAudio {
         }
     }
 
+    /// Mutes a sink input, and unmutes it once `duration` has elapsed. Calling this again before
+    /// then pushes back the unmute. If the connection is closed (or this is dropped) first, it's
+    /// unmuted then; if the process is killed, it stays muted.
+    pub fn mute_sink_input_for(&self, id: PAIdent, duration: Duration) -> Result<OperationResult> {
+        match id {
+            PAIdent::Index(idx) => {
+                self.send(PACommand::MuteSinkInputFor(idx, duration))?;
+                self.operation_result()
+            }
+            PAIdent::Name(ref name) => {
+                let si = self.find_sink_input_info_by_name(name)?;
+                self.mute_sink_input_for(PAIdent::Index(si.index), duration)
+            }
+        }
+    }
+
+    /// Lowers the volume of a sink input to `pct` percent of its current volume, and restores it
+    /// once `duration` has elapsed. Calling this again before then pushes back the restore. If the
+    /// connection is closed (or this is dropped) first, it's restored then; if the process is
+    /// killed, it stays lowered.
+    pub fn duck_sink_input(
+        &self,
+        id: PAIdent,
+        pct: f64,
+        duration: Duration,
+    ) -> Result<OperationResult> {
+        match id {
+            PAIdent::Index(idx) => {
+                self.send(PACommand::DuckSinkInput(idx, pct, duration))?;
+                self.operation_result()
+            }
+            PAIdent::Name(ref name) => {
+                let si = self.find_sink_input_info_by_name(name)?;
+                self.duck_sink_input(PAIdent::Index(si.index), pct, duration)
+            }
+        }
+    }
+
     /*
      * Source Outputs
      */
 
     pub fn get_source_output_info(&self, id: PAIdent) -> Result<PASourceOutputInfo> {
         match id {
-            PAIdent::Index(idx) => {
-                self.tx.send(PACommand::GetSourceOutputInfo(idx))?;
-                assume_variant!(self.rx.recv()?, PAResponse::SourceOutputInfo(x) => x)
-            }
+            PAIdent::Index(idx) => self.request(request::GetSourceOutputInfo(idx)),
             PAIdent::Name(ref name) => {
                 let si = self.find_source_output_info_by_name(name)?;
                 self.get_source_output_info(PAIdent::Index(si.index))
@@ -474,10 +1482,7 @@ impl PulseAudio {
 
     pub fn get_source_output_mute(&self, id: PAIdent) -> Result<bool> {
         match id {
-            PAIdent::Index(idx) => {
-                self.tx.send(PACommand::GetSinkInputMute(idx))?;
-                assume_variant!(self.rx.recv()?, PAResponse::Mute(_, x) => x)
-            }
+            PAIdent::Index(idx) => self.request(request::GetSinkInputMute(idx)),
             PAIdent::Name(ref name) => {
                 let si = self.find_source_output_info_by_name(name)?;
                 self.get_source_output_mute(PAIdent::Index(si.index))
@@ -487,10 +1492,7 @@ impl PulseAudio {
 
     pub fn get_source_output_volume(&self, id: PAIdent) -> Result<VolumeReadings> {
         match id {
-            PAIdent::Index(idx) => {
-                self.tx.send(PACommand::GetSinkInputVolume(idx))?;
-                assume_variant!(self.rx.recv()?, PAResponse::Volume(_, x) => x)
-            }
+            PAIdent::Index(idx) => self.request(request::GetSinkInputVolume(idx)),
             PAIdent::Name(ref name) => {
                 let si = self.find_source_output_info_by_name(name)?;
                 self.get_source_output_volume(PAIdent::Index(si.index))
@@ -501,8 +1503,11 @@ impl PulseAudio {
     pub fn set_source_output_mute(&self, id: PAIdent, mute: bool) -> Result<OperationResult> {
         match id {
             PAIdent::Index(idx) => {
-                self.tx.send(PACommand::SetSinkInputMute(idx, mute))?;
-                self.operation_result()
+                self.send(PACommand::SetSinkInputMute(idx, mute))?;
+                let result = self.operation_result()?;
+                self.confirmed(result, || {
+                    Ok(ConfirmedState::Mute(self.get_source_output_mute(id)?))
+                })
             }
             PAIdent::Name(ref name) => {
                 let si = self.find_source_output_info_by_name(name)?;
@@ -518,8 +1523,11 @@ impl PulseAudio {
     ) -> Result<OperationResult> {
         match id {
             PAIdent::Index(idx) => {
-                self.tx.send(PACommand::SetSinkInputVolume(idx, vol))?;
-                self.operation_result()
+                self.send(PACommand::SetSinkInputVolume(idx, vol))?;
+                let result = self.operation_result()?;
+                self.confirmed(result, || {
+                    Ok(ConfirmedState::Volume(self.get_source_output_volume(id)?))
+                })
             }
             PAIdent::Name(ref name) => {
                 let si = self.find_source_output_info_by_name(name)?;
@@ -531,7 +1539,7 @@ impl PulseAudio {
     pub fn move_source_output(&self, id: PAIdent, source: PAIdent) -> Result<OperationResult> {
         match id {
             PAIdent::Index(idx) => {
-                self.tx.send(PACommand::MoveSourceOutput(idx, source))?;
+                self.send(PACommand::MoveSourceOutput(idx.into(), source))?;
                 self.operation_result()
             }
             PAIdent::Name(ref name) => {
@@ -541,10 +1549,41 @@ impl PulseAudio {
         }
     }
 
+    /// The source outputs which are recording from a microphone (or another source which isn't a
+    /// monitor), the same ones `PAEvent::RecordingStarted` is emitted for. Corked streams and peak
+    /// meters aren't included.
+    pub fn get_recording_streams(&self) -> Result<Vec<PASourceOutputInfo>> {
+        let monitors = self
+            .get_source_info_list()?
+            .into_iter()
+            .filter(|source| source.monitor_of_sink.is_some())
+            .map(|source| source.index)
+            .collect::<Vec<_>>();
+
+        Ok(self
+            .get_source_output_info_list()?
+            .into_iter()
+            .filter(|so| !so.corked && so.resample_method.as_deref() != Some("peaks"))
+            .filter(|so| !monitors.contains(&so.source))
+            .collect())
+    }
+
+    /// Moves every source output of the given application (matched case-insensitively against its
+    /// `application.name`) to the source, like `move_sink_inputs_by_app`.
+    pub fn move_source_outputs_by_app(
+        &self,
+        app: &str,
+        source: PAIdent,
+    ) -> Result<OperationResult> {
+        let stream = PAStreamIdent::App(app.to_string());
+        self.send(PACommand::MoveSourceOutput(stream, source))?;
+        self.operation_result()
+    }
+
     pub fn kill_source_output(&self, id: PAIdent) -> Result<OperationResult> {
         match id {
             PAIdent::Index(idx) => {
-                self.tx.send(PACommand::KillSinkInput(idx))?;
+                self.send(PACommand::KillSinkInput(idx))?;
                 self.operation_result()
             }
             PAIdent::Name(ref name) => {
@@ -558,24 +1597,705 @@ impl PulseAudio {
      * Util
      */
 
+    /*
+     * Non-blocking
+     */
+
+    /// Sends a command without waiting for its response, which can be received with
+    /// `try_recv_event`. Don't mix this with blocking calls until the response has been received.
+    ///
+    /// Fails while a non-blocking getter is waiting for a response, since the responses would be
+    /// mixed up.
+    pub fn send(&self, cmd: PACommand) -> Result<()> {
+        if let Some(pending) = self.pending.borrow().as_ref() {
+            return Err(format!("Still waiting for a response to {}", pending).into());
+        }
+
+        log::debug!("sending {:?}", cmd);
+        self.sent_at.set(Some(Instant::now()));
+        Ok(self.tx.send(cmd)?)
+    }
+
+    /// Receives the next response without blocking. Returns an `std::io::Error` with the kind
+    /// `ErrorKind::WouldBlock` if no response has been received yet.
+    pub fn try_recv_event(&self) -> Result<PAResponse> {
+        loop {
+            match self.rx.try_recv() {
+                Ok(res) if self.skip_late(&res) => continue,
+                Ok(res) => return Ok(self.log_response(res)),
+                Err(TryRecvError::Empty) => {
+                    return Err(io::Error::from(ErrorKind::WouldBlock).into())
+                }
+                Err(e @ TryRecvError::Disconnected) => return Err(e.into()),
+            }
+        }
+    }
+
+    /// Receives the next response, waiting until `deadline` at the latest (regardless of the
+    /// connection's timeout). Returns an `std::io::Error` with the kind `ErrorKind::TimedOut` if no
+    /// response was received in time, in which case the response is dropped when it arrives.
+    pub fn recv_deadline(&self, deadline: Instant) -> Result<PAResponse> {
+        match self.recv_until(Some(deadline)) {
+            Ok(res) => Ok(res),
+            Err(RecvTimeoutError::Timeout) => Err(io::Error::from(ErrorKind::TimedOut).into()),
+            Err(e @ RecvTimeoutError::Disconnected) => Err(e.into()),
+        }
+    }
+
+    /// The receiver of responses to commands sent with `send`, so they can be waited for alongside
+    /// other channels with `crossbeam_channel::select!`. Responses received from this directly
+    /// aren't checked against commands which timed out, so don't mix it with timeouts.
+    pub fn responses(&self) -> &Receiver<PAResponse> {
+        &self.rx
+    }
+
+    /// Used by the `try_get_*` getters: the first call sends the request, and each call returns an
+    /// `std::io::Error` with the kind `ErrorKind::WouldBlock` until the response has been received.
+    /// Only one request can be waiting for a response at a time, so calling a getter with other
+    /// arguments (or any other method which sends a command) fails until it has been received.
+    fn try_request<R: PARequest + Debug>(&self, req: R) -> Result<R::Response> {
+        let key = format!("{:?}", req);
+        let pending = self.pending.borrow().clone();
+        match pending {
+            Some(pending) if pending != key => {
+                return Err(format!("Still waiting for a response to {}", pending).into())
+            }
+            Some(_) => {}
+            None => {
+                self.send(req.into_command())?;
+                *self.pending.borrow_mut() = Some(key);
+            }
+        }
+
+        let res = self.try_recv_event()?;
+        *self.pending.borrow_mut() = None;
+        response::<R>(res)
+    }
+
+    impl_try_get!(
+        try_get_server_info() -> PAServerInfo,
+        request::GetServerInfo
+    );
+    impl_try_get!(
+        try_get_default_sink() -> Option<PAIdent>,
+        request::GetDefaultSink
+    );
+    impl_try_get!(
+        try_get_default_source() -> Option<PAIdent>,
+        request::GetDefaultSource
+    );
+    impl_try_get!(
+        try_get_card_info(id: PAIdent) -> PACardInfo,
+        request::GetCardInfo(id)
+    );
+    impl_try_get!(
+        try_get_sink_info(id: PAIdent) -> PASinkInfo,
+        request::GetSinkInfo(id)
+    );
+    impl_try_get!(
+        try_get_sink_mute(id: PAIdent) -> bool,
+        request::GetSinkMute(id)
+    );
+    impl_try_get!(
+        try_get_sink_volume(id: PAIdent) -> VolumeReadings,
+        request::GetSinkVolume(id)
+    );
+    impl_try_get!(
+        try_get_source_info(id: PAIdent) -> PASourceInfo,
+        request::GetSourceInfo(id)
+    );
+    impl_try_get!(
+        try_get_source_mute(id: PAIdent) -> bool,
+        request::GetSourceMute(id)
+    );
+    impl_try_get!(
+        try_get_source_volume(id: PAIdent) -> VolumeReadings,
+        request::GetSourceVolume(id)
+    );
+    impl_try_get!(
+        try_get_card_info_list() -> Vec<PACardInfo>,
+        request::GetCardInfoList
+    );
+    impl_try_get!(
+        try_get_client_info_list() -> Vec<PAClientInfo>,
+        request::GetClientInfoList
+    );
+    impl_try_get!(
+        try_get_module_info_list() -> Vec<PAModuleInfo>,
+        request::GetModuleInfoList
+    );
+    impl_try_get!(
+        try_get_sink_info_list() -> Vec<PASinkInfo>,
+        request::GetSinkInfoList
+    );
+    impl_try_get!(
+        try_get_sink_input_info_list() -> Vec<PASinkInputInfo>,
+        request::GetSinkInputInfoList
+    );
+    impl_try_get!(
+        try_get_source_info_list() -> Vec<PASourceInfo>,
+        request::GetSourceInfoList
+    );
+    impl_try_get!(
+        try_get_source_output_info_list() -> Vec<PASourceOutputInfo>,
+        request::GetSourceOutputInfoList
+    );
+
+    /*
+     * Idempotent helpers, these only make a change if required and return whether one was made
+     */
+
+    pub fn ensure_default_sink(&self, id: PAIdent) -> Result<bool> {
+        let sink = self.get_sink_info(id)?;
+        if let (Some(PAIdent::Name(default)), Some(name)) = (self.get_default_sink()?, &sink.name) {
+            if default == *name {
+                return Ok(false);
+            }
+        }
+
+        changed(self.set_default_sink(PAIdent::Index(sink.index))?)
+    }
+
+    pub fn ensure_default_source(&self, id: PAIdent) -> Result<bool> {
+        let source = self.get_source_info(id)?;
+        if let (Some(PAIdent::Name(default)), Some(name)) =
+            (self.get_default_source()?, &source.name)
+        {
+            if default == *name {
+                return Ok(false);
+            }
+        }
+
+        changed(self.set_default_source(PAIdent::Index(source.index))?)
+    }
+
+    pub fn ensure_sink_muted(&self, id: PAIdent, mute: bool) -> Result<bool> {
+        let sink = self.get_sink_info(id)?;
+        if sink.mute == mute {
+            return Ok(false);
+        }
+
+        changed(self.set_sink_mute(PAIdent::Index(sink.index), mute)?)
+    }
+
+    pub fn ensure_source_muted(&self, id: PAIdent, mute: bool) -> Result<bool> {
+        let source = self.get_source_info(id)?;
+        if source.mute == mute {
+            return Ok(false);
+        }
+
+        changed(self.set_source_mute(PAIdent::Index(source.index), mute)?)
+    }
+
+    pub fn ensure_sink_input_muted(&self, id: PAIdent, mute: bool) -> Result<bool> {
+        let si = self.get_sink_input_info(id)?;
+        if si.mute == mute {
+            return Ok(false);
+        }
+
+        changed(self.set_sink_input_mute(PAIdent::Index(si.index), mute)?)
+    }
+
+    pub fn ensure_source_output_muted(&self, id: PAIdent, mute: bool) -> Result<bool> {
+        let so = self.get_source_output_info(id)?;
+        if so.mute == mute {
+            return Ok(false);
+        }
+
+        changed(self.set_source_output_mute(PAIdent::Index(so.index), mute)?)
+    }
+
+    pub fn ensure_sink_port(&self, id: PAIdent, port: String) -> Result<bool> {
+        let sink = self.get_sink_info(id)?;
+        if sink.active_port.and_then(|p| p.name).as_ref() == Some(&port) {
+            return Ok(false);
+        }
+
+        changed(self.set_sink_port(PAIdent::Index(sink.index), port)?)
+    }
+
+    pub fn ensure_source_port(&self, id: PAIdent, port: String) -> Result<bool> {
+        let source = self.get_source_info(id)?;
+        if source.active_port.and_then(|p| p.name).as_ref() == Some(&port) {
+            return Ok(false);
+        }
+
+        changed(self.set_source_port(PAIdent::Index(source.index), port)?)
+    }
+
+    /// Sends a request and waits for its response, see `PARequest`. Returns an error if the
+    /// operation failed, or if the response doesn't belong to the request.
+    pub fn request<R: PARequest>(&self, req: R) -> Result<R::Response> {
+        self.send(req.into_command())?;
+        response::<R>(self.recv()?)
+    }
+
+    /// Like `request`, but waits until `deadline` at the latest rather than for the connection's
+    /// timeout. Returns an `std::io::Error` with the kind `ErrorKind::TimedOut` if no response was
+    /// received in time. The command isn't cancelled on the server, but its response is dropped
+    /// when it arrives, so a single slow request doesn't hold up the ones after it. This assumes
+    /// every command sent before it is answered by exactly one response (as the blocking methods
+    /// here expect), so mixing it with commands sent with `send` which aren't can drop the wrong
+    /// response.
+    pub fn request_deadline<R: PARequest>(&self, req: R, deadline: Instant) -> Result<R::Response> {
+        self.send(req.into_command())?;
+        response::<R>(self.recv_deadline(deadline)?)
+    }
+
+    fn recv(&self) -> Result<PAResponse> {
+        let deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+        match self.recv_until(deadline) {
+            Ok(res) => Ok(res),
+            Err(RecvTimeoutError::Timeout) => Err("Timed out waiting for a response".into()),
+            Err(e @ RecvTimeoutError::Disconnected) => Err(e.into()),
+        }
+    }
+
+    /// Receives the next response, skipping those to commands which timed out. If this times out
+    /// too, the response is expected to arrive later and will be skipped.
+    fn recv_until(
+        &self,
+        deadline: Option<Instant>,
+    ) -> std::result::Result<PAResponse, RecvTimeoutError> {
+        loop {
+            let res = match deadline {
+                Some(deadline) => self.rx.recv_deadline(deadline),
+                None => self.rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+
+            match res {
+                Ok(res) if self.skip_late(&res) => continue,
+                Ok(res) => return Ok(self.log_response(res)),
+                Err(RecvTimeoutError::Timeout) => {
+                    self.late.set(self.late.get() + 1);
+                    return Err(RecvTimeoutError::Timeout);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Whether a response should be dropped, since it's the response to a command which timed out.
+    /// Fatal errors and disconnections are never dropped, since they aren't answering a command and
+    /// are usually why the command timed out in the first place.
+    fn skip_late(&self, res: &PAResponse) -> bool {
+        match (self.late.get(), res) {
+            (0, _) | (_, PAResponse::Fatal(_) | PAResponse::Disconnected) => false,
+            (late, _) => {
+                self.late.set(late - 1);
+                log::debug!(
+                    "dropping a response which arrived after its timeout: {:?}",
+                    res
+                );
+                true
+            }
+        }
+    }
+
+    /// Logs how long a response took since the last command was sent, and (at the trace level) the
+    /// response itself
+    fn log_response(&self, res: PAResponse) -> PAResponse {
+        match self.sent_at.take() {
+            Some(sent_at) => log::debug!("received a response after {:?}", sent_at.elapsed()),
+            None => log::debug!("received a response"),
+        }
+        log::trace!("response {:?}", res);
+        res
+    }
+
+    /// The size of a single volume step for a device
+    fn volume_step_size(&self, base_volume: &PAVolume, n_volume_steps: u32) -> u32 {
+        // devices which support arbitrary volumes report `Volume::NORMAL + 1` steps
+        let steps = n_volume_steps.saturating_sub(1);
+        match base_volume.value().checked_div(steps) {
+            Some(step) if steps < Volume::NORMAL.0 && step > 0 => step,
+            _ => (Volume::NORMAL.0 as f64 * self.volume_step).round() as u32,
+        }
+    }
+
+    /// Replaces a successful result with the state fetched by `get`, if `Builder::confirm` is set.
+    /// The setter has completed by now, so the state includes its change.
+    fn confirmed(
+        &self,
+        result: OperationResult,
+        get: impl FnOnce() -> Result<ConfirmedState>,
+    ) -> Result<OperationResult> {
+        match result {
+            OperationResult::Success if self.confirm => {
+                Ok(OperationResult::Confirmed { state: get()? })
+            }
+            result => Ok(result),
+        }
+    }
+
     fn operation_result(&self) -> Result<OperationResult> {
-        match self.rx.recv()? {
+        match self.recv()? {
             PAResponse::OpComplete => Ok(OperationResult::Success),
-            PAResponse::OpError(e) => Ok(OperationResult::Failure { error: e }),
+            PAResponse::OpError(e) => Ok(e.into()),
+            PAResponse::OpConflict(e) => Ok(OperationResult::Conflict { error: e }),
+            PAResponse::Fatal(e) => Err(e.into()),
             ev => Err(format!("Unexpected response received {:?}", ev).into()),
         }
     }
+
+    /*
+     * Bluetooth
+     */
+
+    /// Lists the codecs supported by a Bluetooth card. Requires a server which implements the
+    /// message API (see `get_server_capabilities`).
+    #[cfg(feature = "pa_v15")]
+    pub fn get_bluetooth_codecs(&self, card: PAIdent) -> Result<Vec<BluetoothCodec>> {
+        let card = self.get_bluetooth_card(card)?;
+        let response = self
+            .send_message(bluetooth_message_path(&card)?, "list-codecs", "")?
+            .unwrap_or_default();
+
+        let malformed = || format!("Unexpected response when listing codecs: {}", response);
+        let list = split_message_params(&response).ok_or_else(malformed)?;
+        let codecs = match list.first() {
+            Some(codecs) => split_message_params(codecs).ok_or_else(malformed)?,
+            None => vec![],
+        };
+
+        codecs
+            .into_iter()
+            .map(|codec| match split_message_params(codec).as_deref() {
+                Some([name, description]) => Ok(BluetoothCodec {
+                    name: name.to_string(),
+                    description: description.to_string(),
+                }),
+                _ => Err(malformed().into()),
+            })
+            .collect()
+    }
+
+    /// Switches a Bluetooth card to the given profile, and optionally to the given codec (which
+    /// only applies to A2DP).
+    ///
+    /// On pipewire-pulse, codecs may be exposed as separate profiles (such as "a2dp-sink-ldac"), in
+    /// which case that profile is used. Otherwise the codec is switched with the message API, which
+    /// requires the `pa_v15` feature.
+    pub fn set_bluetooth_profile(
+        &self,
+        card: PAIdent,
+        profile: BluetoothProfile,
+        codec: Option<&str>,
+    ) -> Result<OperationResult> {
+        let card = self.get_bluetooth_card(card)?;
+        let name = profile.profile_name();
+        let available = |profile: &str| {
+            card.profiles
+                .iter()
+                .any(|p| p.available && p.name.as_deref() == Some(profile))
+        };
+
+        let (target, codec) = match codec {
+            Some(codec) if available(&format!("{}-{}", name, codec)) => {
+                (format!("{}-{}", name, codec), None)
+            }
+            codec if available(name) => (name.to_string(), codec),
+            _ => {
+                return Err(format!(
+                    "Card {} has no available {} profile",
+                    card.index,
+                    codec.map_or(name.to_string(), |codec| format!("{}-{}", name, codec))
+                )
+                .into())
+            }
+        };
+
+        let result = self.set_card_profile(PAIdent::Index(card.index), target)?;
+        match (result, codec) {
+            (OperationResult::Success, Some(codec)) => self.set_bluetooth_codec(&card, codec),
+            (result, _) => Ok(result),
+        }
+    }
+
+    #[cfg(feature = "pa_v15")]
+    fn set_bluetooth_codec(&self, card: &PACardInfo, codec: &str) -> Result<OperationResult> {
+        let path = bluetooth_message_path(card)?;
+        match self.send_message(path, "switch-codec", format!("{{{}}}", codec)) {
+            Ok(_) => Ok(OperationResult::Success),
+            Err(e) => Ok(OperationResult::Failure {
+                error: e.to_string(),
+                code: None,
+                channel_count: None,
+            }),
+        }
+    }
+
+    #[cfg(not(feature = "pa_v15"))]
+    fn set_bluetooth_codec(&self, _: &PACardInfo, _: &str) -> Result<OperationResult> {
+        Err("Switching codecs requires the `pa_v15` feature".into())
+    }
+
+    fn get_bluetooth_card(&self, card: PAIdent) -> Result<PACardInfo> {
+        let card = self.get_card_info(card)?;
+        match card.proplist.get_str("device.bus").as_deref() {
+            Some("bluetooth") => Ok(card),
+            _ => Err(format!("Card {} is not a Bluetooth card", card.index).into()),
+        }
+    }
+}
+
+/// A playback or recording stream of raw audio, see `PulseAudio::open_playback` and
+/// `PulseAudio::open_recording`.
+pub struct PAStream<'a> {
+    pa: &'a PulseAudio,
+    id: u32,
+    writer: Option<Sender<Vec<u8>>>,
+    reader: Option<Receiver<Vec<u8>>>,
+}
+
+impl<'a> PAStream<'a> {
+    /// The id of the stream, used by `PulseAudio::cork_stream` and friends
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Queues raw audio to be played, blocking if too much is already waiting. Fails if this is a
+    /// recording stream, or if the stream has failed (`close` returns the reason).
+    pub fn write(&self, data: &[u8]) -> Result<()> {
+        match self.writer {
+            Some(ref writer) => writer
+                .send(data.to_vec())
+                .map_err(|_| "The stream has finished".into()),
+            None => Err("Only playback streams can be written to".into()),
+        }
+    }
+
+    /// Waits for the next chunk of recorded audio, returning `None` once recording has stopped (or
+    /// if this is a playback stream).
+    pub fn read(&self) -> Option<Vec<u8>> {
+        self.reader.as_ref().and_then(|reader| reader.recv().ok())
+    }
+
+    pub fn cork(&self) -> Result<OperationResult> {
+        self.pa.cork_stream(self.id, true)
+    }
+
+    pub fn uncork(&self) -> Result<OperationResult> {
+        self.pa.cork_stream(self.id, false)
+    }
+
+    /// Waits for everything written so far to be played
+    pub fn drain(&self) -> Result<OperationResult> {
+        self.pa.drain_stream(self.id)
+    }
+
+    /// Drops everything which hasn't been played (or read) yet
+    pub fn flush(&self) -> Result<OperationResult> {
+        self.pa.flush_stream(self.id)
+    }
+
+    pub fn latency(&self) -> Result<RawStreamLatency> {
+        self.pa.get_stream_latency(self.id)
+    }
+
+    /// Ends playback once everything written has been played, returning whether the stream
+    /// finished successfully. Recording streams are stopped.
+    pub fn close(mut self) -> Result<OperationResult> {
+        self.reader = None;
+        match self.writer.take() {
+            Some(writer) => {
+                drop(writer);
+                self.pa.drain_stream(self.id)
+            }
+            None => Ok(OperationResult::Success),
+        }
+    }
+}
+
+/// An object whose volume is set through a `VolumeThrottle`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VolumeTarget {
+    Sink(PAIdent),
+    Source(PAIdent),
+    SinkInput(PAIdent),
+    SourceOutput(PAIdent),
+}
+
+struct Throttled {
+    target: VolumeTarget,
+    /// When a volume was last sent for the target
+    sent_at: Instant,
+    /// The latest volume which hasn't been sent yet, replacing any earlier ones
+    pending: Option<VolumeSpec>,
+}
+
+/// Limits how often volumes are sent to PulseAudio, see `PulseAudio::throttle_volumes`. The first
+/// change for an object is sent straight away, and any made within the window after it are merged
+/// into the latest one, which is sent once `flush` is called after the window has passed. Pending
+/// volumes are sent when the throttle is dropped.
+pub struct VolumeThrottle<'a> {
+    pa: &'a PulseAudio,
+    window: Duration,
+    targets: Vec<Throttled>,
+}
+
+impl<'a> VolumeThrottle<'a> {
+    /// Sets the volume, or returns `None` if it was held back to be merged with later changes
+    pub fn set(
+        &mut self,
+        target: VolumeTarget,
+        vol: VolumeSpec,
+    ) -> Result<Option<OperationResult>> {
+        let now = Instant::now();
+        let window = self.window;
+        self.targets
+            .retain(|t| t.pending.is_some() || now < t.sent_at + window);
+
+        match self.targets.iter_mut().find(|t| t.target == target) {
+            Some(t) if now < t.sent_at + window => {
+                t.pending = Some(vol);
+                return Ok(None);
+            }
+            Some(t) => t.sent_at = now,
+            None => self.targets.push(Throttled {
+                target: target.clone(),
+                sent_at: now,
+                pending: None,
+            }),
+        }
+
+        self.send(&target, vol).map(Some)
+    }
+
+    /// When the next pending volume is due to be sent by `flush`, if there are any
+    pub fn deadline(&self) -> Option<Instant> {
+        self.targets
+            .iter()
+            .filter(|t| t.pending.is_some())
+            .map(|t| t.sent_at + self.window)
+            .min()
+    }
+
+    /// Sends the pending volumes whose window has passed
+    pub fn flush(&mut self) -> Result<Vec<OperationResult>> {
+        self.flush_before(Instant::now())
+    }
+
+    /// Sends every pending volume, whether or not its window has passed
+    pub fn flush_all(&mut self) -> Result<Vec<OperationResult>> {
+        self.flush_before(Instant::now() + self.window)
+    }
+
+    fn flush_before(&mut self, deadline: Instant) -> Result<Vec<OperationResult>> {
+        let mut due = vec![];
+        for t in self.targets.iter_mut() {
+            if t.sent_at + self.window <= deadline {
+                if let Some(vol) = t.pending.take() {
+                    t.sent_at = Instant::now();
+                    due.push((t.target.clone(), vol));
+                }
+            }
+        }
+
+        due.into_iter()
+            .map(|(target, vol)| self.send(&target, vol))
+            .collect()
+    }
+
+    fn send(&self, target: &VolumeTarget, vol: VolumeSpec) -> Result<OperationResult> {
+        match target {
+            VolumeTarget::Sink(id) => self.pa.set_sink_volume(id.clone(), vol),
+            VolumeTarget::Source(id) => self.pa.set_source_volume(id.clone(), vol),
+            VolumeTarget::SinkInput(id) => self.pa.set_sink_input_volume(id.clone(), vol),
+            VolumeTarget::SourceOutput(id) => self.pa.set_source_output_volume(id.clone(), vol),
+        }
+    }
+}
+
+impl Drop for VolumeThrottle<'_> {
+    fn drop(&mut self) {
+        self.flush_all().ignore();
+    }
+}
+
+/// Moves each channel's volume by `steps` multiples of `step`
+fn stepped_volumes(cv: &PAChannelVolumes, step: u32, steps: i32) -> VolumeSpec {
+    VolumeSpec::Channels(
+        cv.0.iter()
+            .map(|vol| {
+                let vol = vol.value() as i64 + step as i64 * steps as i64;
+                PAVol::Value(vol.clamp(Volume::MUTED.0 as i64, Volume::MAX.0 as i64) as u32)
+            })
+            .collect(),
+    )
+}
+
+/// Finds a card's port, by either its name or its position in the card's list of ports
+fn find_card_port<'a>(card: &'a PACardInfo, port_id: &PAIdent) -> Result<&'a PACardPortInfo> {
+    card.ports
+        .iter()
+        .enumerate()
+        .find_map(|(i, p)| {
+            let found = match port_id {
+                PAIdent::Index(idx) => *idx == i as u32,
+                PAIdent::Name(ref name) => p.name.as_ref() == Some(name),
+            };
+
+            if found {
+                Some(p)
+            } else {
+                None
+            }
+        })
+        .ok_or_else(|| format!("No port found with id: {}", port_id).into())
+}
+
+/// The path of the message handler for a Bluetooth card
+#[cfg(feature = "pa_v15")]
+fn bluetooth_message_path(card: &PACardInfo) -> Result<String> {
+    match card.name {
+        Some(ref name) => Ok(format!("/card/{}/bluez", name)),
+        None => Err(format!("Card {} has no name", card.index).into()),
+    }
+}
+
+/// The loudest sample in a chunk recorded with `LEVEL_SPEC`
+fn peak(chunk: &[u8]) -> f32 {
+    chunk
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]).abs())
+        .fold(0.0, f32::max)
+}
+
+/// Takes the value out of the response to a request, turning failures into errors
+fn response<R: PARequest>(res: PAResponse) -> Result<R::Response> {
+    match R::from_response(res) {
+        Ok(x) => Ok(x),
+        Err(PAResponse::OpError(e)) => Err(OperationResult::from(e).into()),
+        Err(PAResponse::Fatal(e)) => Err(e.into()),
+        Err(res) => Err(format!(
+            "Unexpected response to {}: {:?}",
+            std::any::type_name::<R>(),
+            res
+        )
+        .into()),
+    }
+}
+
+/// Turns the result of an operation made by an `ensure_*` helper into whether a change was made
+fn changed(result: OperationResult) -> Result<bool> {
+    match result {
+        result if result.is_success() => Ok(true),
+        result => Err(result.into()),
+    }
+}
+
+/// Ends the subscription started by `PulseAudio::with_subscription` when it's dropped
+struct SubscriptionGuard<'a>(&'a PulseAudio);
+
+impl Drop for SubscriptionGuard<'_> {
+    fn drop(&mut self) {
+        self.0.unsubscribe().ignore();
+    }
 }
 
 impl Drop for PulseAudio {
     fn drop(&mut self) {
-        // TODO: handle unwraps gracefully
-        self.tx.send(PACommand::Disconnect).unwrap();
-        match self.rx.recv_timeout(Duration::from_secs(3)) {
-            Ok(PAResponse::Disconnected) => {}
-            Ok(ev) => unreachable!("Unexpected event: {:?}", ev),
-            Err(RecvTimeoutError::Disconnected) => todo!("handle sender dropped"),
-            Err(RecvTimeoutError::Timeout) => todo!("response timed out"),
-        }
+        self.shutdown().ignore();
     }
 }